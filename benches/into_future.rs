@@ -0,0 +1,316 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use criterion::{
+    criterion_group, criterion_main,
+    measurement::{Measurement, ValueFormatter},
+    Criterion, Throughput,
+};
+use futures::future;
+use pyo3::{prelude::*, wrap_pyfunction};
+use pyo3_asyncio::TaskLocals;
+
+const ITERATIONS: usize = 100;
+
+/// The event loop thread benchmark converts its coroutines in a single batch, so it runs the full
+/// 100k of them per iteration
+const BATCH: usize = 100_000;
+
+const DRIVER_CODE: &str = r#"
+import asyncio
+import contextvars
+
+async def drive(bridge_all, n):
+    await bridge_all([asyncio.sleep(0) for _ in range(n)], None)
+
+async def drive_deferred(bridge_all, n):
+    # the context of the locals is entered, so every Task is created by the event loop later on
+    # with call_soon_threadsafe, like before conversions on the event loop thread were shortcut
+    context = contextvars.copy_context()
+    await context.run(bridge_all, [asyncio.sleep(0) for _ in range(n)], context)
+"#;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts the allocations made through the Rust global allocator
+///
+/// Python objects, the pyclass callbacks of a conversion included, come from Python's own allocator
+/// and aren't counted.
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Measures the number of allocations made by a benchmark instead of its wall time
+struct Allocations;
+
+impl Measurement for Allocations {
+    type Intermediate = usize;
+    type Value = usize;
+
+    fn start(&self) -> usize {
+        ALLOCATIONS.load(Ordering::SeqCst)
+    }
+
+    fn end(&self, start: usize) -> usize {
+        ALLOCATIONS.load(Ordering::SeqCst) - start
+    }
+
+    fn add(&self, v1: &usize, v2: &usize) -> usize {
+        v1 + v2
+    }
+
+    fn zero(&self) -> usize {
+        0
+    }
+
+    fn to_f64(&self, value: &usize) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+impl ValueFormatter for Allocations {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        let (n, unit) = match throughput {
+            Throughput::Bytes(n) => (*n, "allocs/byte"),
+            Throughput::Elements(n) => (*n, "allocs/elem"),
+        };
+        for value in values {
+            *value /= n as f64;
+        }
+        unit
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+}
+
+/// Run a fresh asyncio event loop forever on a background thread
+fn spawn_event_loop() -> PyObject {
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        Python::with_gil(|py| -> PyResult<()> {
+            let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+            tx.send(PyObject::from(event_loop)).unwrap();
+            event_loop.call_method0("run_forever")?;
+            Ok(())
+        })
+        .unwrap();
+    });
+
+    rx.recv().unwrap()
+}
+
+/// Stop an event loop started with [`spawn_event_loop`]
+fn stop_event_loop(event_loop: PyObject) {
+    Python::with_gil(|py| {
+        event_loop
+            .as_ref(py)
+            .call_method1(
+                "call_soon_threadsafe",
+                (event_loop.getattr(py, "stop").unwrap(),),
+            )
+            .unwrap();
+    });
+}
+
+fn sleep_zero(py: Python) -> PyResult<&PyAny> {
+    py.import("asyncio")?.call_method1("sleep", (0,))
+}
+
+/// Await `awaitables` from Rust, converting them on the thread running the event loop
+#[pyfunction]
+fn bridge_all<'p>(
+    py: Python<'p>,
+    awaitables: Vec<&'p PyAny>,
+    context: Option<&'p PyAny>,
+) -> PyResult<&'p PyAny> {
+    let locals = match context {
+        Some(context) => TaskLocals::with_running_loop(py)?.with_context(context),
+        None => TaskLocals::with_running_loop(py)?,
+    };
+    let futs = awaitables
+        .into_iter()
+        .map(|awaitable| pyo3_asyncio::into_future_with_locals(&locals, awaitable))
+        .collect::<PyResult<Vec<_>>>()?;
+
+    pyo3_asyncio::tokio::future_into_py_with_locals(py, locals, async move {
+        future::try_join_all(futs).await?;
+        Ok(())
+    })
+}
+
+fn bench_into_future(c: &mut Criterion) {
+    pyo3::prepare_freethreaded_python();
+
+    let event_loop = spawn_event_loop();
+    let locals = Python::with_gil(|py| TaskLocals::new(event_loop.as_ref(py)));
+    let rt = pyo3_asyncio::tokio::get_runtime();
+
+    let mut group = c.benchmark_group("asyncio.sleep(0)");
+
+    group.bench_function("into_future_with_locals", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                for _ in 0..ITERATIONS {
+                    Python::with_gil(|py| {
+                        pyo3_asyncio::into_future_with_locals(&locals, sleep_zero(py)?)
+                    })
+                    .unwrap()
+                    .await
+                    .unwrap();
+                }
+            })
+        })
+    });
+
+    group.bench_function("into_future_ignore_result_with_locals", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                for _ in 0..ITERATIONS {
+                    Python::with_gil(|py| {
+                        pyo3_asyncio::into_future_ignore_result_with_locals(
+                            &locals,
+                            sleep_zero(py)?,
+                        )
+                    })
+                    .unwrap()
+                    .await
+                    .unwrap();
+                }
+            })
+        })
+    });
+
+    group.finish();
+
+    // with 100k coroutines per iteration, criterion's default of 100 samples would take minutes
+    let mut group = c.benchmark_group("100k asyncio.sleep(0)");
+    group.sample_size(10);
+
+    // the Task created right away on the event loop thread, then the call_soon_threadsafe hop
+    // that every conversion used to take as the baseline
+    for (name, driver) in [
+        ("into_future_with_locals (event loop thread)", "drive"),
+        (
+            "into_future_with_locals (call_soon_threadsafe)",
+            "drive_deferred",
+        ),
+    ]
+    .iter()
+    {
+        group.bench_function(*name, |b| {
+            Python::with_gil(|py| -> PyResult<()> {
+                let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+                let drive = PyModule::from_code(py, DRIVER_CODE, "driver.py", "driver")?
+                    .getattr(*driver)?;
+                let bridge_all = wrap_pyfunction!(bridge_all, py)?;
+
+                b.iter(|| {
+                    event_loop
+                        .call_method1(
+                            "run_until_complete",
+                            (drive.call1((bridge_all, BATCH)).unwrap(),),
+                        )
+                        .unwrap()
+                });
+
+                event_loop.call_method0("close")?;
+                Ok(())
+            })
+            .unwrap()
+        });
+    }
+
+    group.finish();
+
+    stop_event_loop(event_loop);
+}
+
+/// The Rust allocations of a conversion, i.e. its channel, `TaskHandle` and boxed callbacks, which
+/// a pool could save at best
+fn bench_allocations(c: &mut Criterion<Allocations>) {
+    pyo3::prepare_freethreaded_python();
+
+    let event_loop = spawn_event_loop();
+    let locals = Python::with_gil(|py| TaskLocals::new(event_loop.as_ref(py)));
+    let rt = pyo3_asyncio::tokio::get_runtime();
+
+    let mut group = c.benchmark_group("allocations per conversion");
+
+    group.bench_function("into_future_with_locals", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                Python::with_gil(|py| {
+                    pyo3_asyncio::into_future_with_locals(&locals, sleep_zero(py)?)
+                })
+                .unwrap()
+                .await
+                .unwrap();
+            })
+        })
+    });
+
+    group.bench_function("into_future_ignore_result_with_locals", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                Python::with_gil(|py| {
+                    pyo3_asyncio::into_future_ignore_result_with_locals(&locals, sleep_zero(py)?)
+                })
+                .unwrap()
+                .await
+                .unwrap();
+            })
+        })
+    });
+
+    group.finish();
+
+    stop_event_loop(event_loop);
+}
+
+criterion_group!(benches, bench_into_future);
+
+fn allocations() {
+    // the counts barely vary between samples, which the plots can't scale to, so they are disabled
+    // after the command line, which would enable them again
+    let mut criterion = Criterion::default()
+        .with_measurement(Allocations)
+        .configure_from_args()
+        .without_plots();
+    bench_allocations(&mut criterion);
+}
+
+criterion_main!(benches, allocations);
@@ -101,6 +101,228 @@ pub fn tokio_main(args: TokenStream, item: TokenStream) -> TokenStream {
     tokio::main(args, item, true)
 }
 
+/// Provide the test function's parameters through `pyo3_asyncio::testing::Fixture`
+///
+/// The first `case_len` parameters are left to the test's cases (see [`parse_cases`]) and bound to
+/// `case_0`, `case_1`, etc. by [`register_test`].
+///
+/// Returns the statement that binds the fixtures, to be used within the test's task, along with the
+/// arguments to call the test function with.
+fn provide_fixtures(
+    sig: &syn::Signature,
+    get_current_locals: proc_macro2::TokenStream,
+    case_len: usize,
+) -> syn::Result<(proc_macro2::TokenStream, Vec<syn::Ident>)> {
+    let types = sig
+        .inputs
+        .iter()
+        .map(|input| match input {
+            syn::FnArg::Typed(arg) => Ok(&arg.ty),
+            syn::FnArg::Receiver(receiver) => Err(syn::Error::new_spanned(
+                receiver,
+                "test functions cannot take self",
+            )),
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    if types.len() < case_len {
+        return Err(syn::Error::new_spanned(
+            &sig.inputs,
+            format!(
+                "each case provides {} parameter(s), but the test function only takes {}",
+                case_len,
+                types.len()
+            ),
+        ));
+    }
+
+    let cases: Vec<_> = (0..case_len)
+        .map(|i| quote::format_ident!("case_{}", i))
+        .collect();
+    let types = &types[case_len..];
+    let fixtures: Vec<_> = (0..types.len())
+        .map(|i| quote::format_ident!("fixture_{}", i))
+        .collect();
+
+    let provide = if types.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            let (#(#fixtures,)*) = pyo3::Python::with_gil(|py| -> pyo3::PyResult<_> {
+                let locals = #get_current_locals(py)?;
+                Ok((#(<#types as pyo3_asyncio::testing::Fixture>::provide(py, &locals)?,)*))
+            })?;
+        }
+    };
+
+    Ok((provide, cases.into_iter().chain(fixtures).collect()))
+}
+
+/// A `name = value` argument of a test attribute
+struct TestArg {
+    name: syn::Ident,
+    value: syn::Expr,
+}
+
+impl syn::parse::Parse for TestArg {
+    fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<syn::Token![=]>()?;
+        let value = input.parse()?;
+
+        Ok(Self { name, value })
+    }
+}
+
+/// Parse the arguments of a test attribute
+///
+/// Unlike `syn::AttributeArgs`, the values may be any expression, which `cases` requires.
+fn parse_test_args(attr: TokenStream) -> syn::Result<Vec<TestArg>> {
+    use syn::parse::Parser;
+
+    syn::punctuated::Punctuated::<TestArg, syn::Token![,]>::parse_terminated
+        .parse(attr)
+        .map(|args| args.into_iter().collect())
+}
+
+/// Parse the value of the `cases` argument of a test attribute
+///
+/// `cases` is an array with one element per case. A tuple element provides the values of the
+/// test function's leading parameters, any other element provides the value of its first
+/// parameter. Every case must provide the same number of values.
+fn parse_cases(value: &syn::Expr) -> syn::Result<Vec<Vec<syn::Expr>>> {
+    let array = match value {
+        syn::Expr::Array(array) => array,
+        other => {
+            return Err(syn::Error::new_spanned(
+                other,
+                "`cases` must be an array, i.e. `cases = [(1, 2), (3, 4)]`",
+            ))
+        }
+    };
+
+    if array.elems.is_empty() {
+        return Err(syn::Error::new_spanned(array, "`cases` may not be empty."));
+    }
+
+    let cases: Vec<Vec<syn::Expr>> = array
+        .elems
+        .iter()
+        .map(|case| match case {
+            syn::Expr::Tuple(tuple) => tuple.elems.iter().cloned().collect(),
+            other => vec![other.clone()],
+        })
+        .collect();
+
+    for (case, expr) in cases.iter().zip(array.elems.iter()) {
+        if case.len() != cases[0].len() {
+            return Err(syn::Error::new_spanned(
+                expr,
+                format!(
+                    "every case must provide {} value(s), like the first case",
+                    cases[0].len()
+                ),
+            ));
+        }
+    }
+
+    Ok(cases)
+}
+
+/// Generate the function that creates a test's task, along with its registration in the harness
+///
+/// `task` is the body of the closure that creates the task and `scope` the runtime's `scope`
+/// function used by `pyo3_asyncio::testing::isolate`. With `cases`, the generated function takes
+/// the index of the case to run, the case's values are bound before `task`, and a test named
+/// `name[caseN]` is registered for each case.
+fn register_test(
+    input: &syn::ItemFn,
+    task: proc_macro2::TokenStream,
+    scope: proc_macro2::TokenStream,
+    cases: Option<&[Vec<syn::Expr>]>,
+) -> proc_macro2::TokenStream {
+    let sig = &input.sig;
+    let name = &input.sig.ident;
+    let body = &input.block;
+    let vis = &input.vis;
+
+    let cases = match cases {
+        Some(cases) => cases,
+        None => {
+            return quote! {
+                #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
+                    #sig {
+                        #body
+                    }
+
+                    pyo3_asyncio::testing::isolate(
+                        || -> pyo3_asyncio::testing::TestTask {
+                            #task
+                        },
+                        #scope,
+                    )
+                }
+
+                pyo3_asyncio::inventory::submit! {
+                    #![crate = pyo3_asyncio] {
+                        pyo3_asyncio::testing::Test {
+                            name: format!("{}::{}", std::module_path!(), stringify!(#name)),
+                            test_fn: &#name
+                        }
+                    }
+                }
+            };
+        }
+    };
+
+    let args: Vec<_> = (0..cases[0].len())
+        .map(|i| quote::format_ident!("case_{}", i))
+        .collect();
+    let indices: Vec<_> = (0..cases.len()).collect();
+    let values = cases.iter().map(|case| quote! { (#(#case,)*) });
+
+    quote! {
+        #vis fn #name(case: usize) -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
+            #sig {
+                #body
+            }
+
+            pyo3_asyncio::testing::isolate(
+                move || -> pyo3_asyncio::testing::TestTask {
+                    let (#(#args,)*) = match case {
+                        #(#indices => #values,)*
+                        _ => unreachable!("{} has no case {}", stringify!(#name), case),
+                    };
+
+                    #task
+                },
+                #scope,
+            )
+        }
+
+        #(
+            pyo3_asyncio::inventory::submit! {
+                #![crate = pyo3_asyncio] {
+                    pyo3_asyncio::testing::Test {
+                        name: format!(
+                            "{}::{}[case{}]",
+                            std::module_path!(),
+                            stringify!(#name),
+                            #indices
+                        ),
+                        test_fn: {
+                            fn case() -> pyo3_asyncio::testing::TestTask {
+                                #name(#indices)
+                            }
+                            &case
+                        }
+                    }
+                }
+            }
+        )*
+    }
+}
+
 /// Registers an `async-std` test with the `pyo3-asyncio` test harness.
 ///
 /// This attribute is meant to mirror the `#[test]` attribute and allow you to mark a function for
@@ -127,77 +349,114 @@ pub fn tokio_main(args: TokenStream, item: TokenStream) -> TokenStream {
 ///     Ok(())
 /// }
 ///
-/// // blocking test functions can optionally accept an event_loop parameter
+/// // test functions can take parameters that the harness injects, i.e. the event loop
 /// #[pyo3_asyncio::async_std::test]
 /// fn test_blocking_sleep_with_event_loop(event_loop: PyObject) -> PyResult<()> {
 ///     thread::sleep(Duration::from_secs(1));
 ///     Ok(())
 /// }
+///
+/// // or the task locals, see pyo3_asyncio::testing::Fixture
+/// #[pyo3_asyncio::async_std::test]
+/// async fn test_async_sleep_with_locals(locals: pyo3_asyncio::TaskLocals) -> PyResult<()> {
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::into_future_with_locals(
+///             &locals,
+///             py.import("asyncio")?.call_method1("sleep", (1,))?,
+///         )
+///     })?
+///     .await?;
+///     Ok(())
+/// }
+///
+/// // registers `test_add[case0]` and `test_add[case1]`
+/// #[pyo3_asyncio::async_std::test(cases = [(1, 2, 3), (2, 2, 4)])]
+/// async fn test_add(a: i32, b: i32, sum: i32) -> PyResult<()> {
+///     assert_eq!(a + b, sum);
+///     Ok(())
+/// }
 /// ```
+///
+/// # Arguments
+/// * `cases` - run the test once per element of this array, each appearing as a separate test named
+///   `test_name[caseN]`. A tuple provides the values of the test function's leading parameters,
+///   any other value provides its first parameter. The remaining parameters are fixtures.
 #[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
 #[proc_macro_attribute]
-pub fn async_std_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn async_std_test(attr: TokenStream, item: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(item as syn::ItemFn);
 
+    let cases = match parse_async_std_test_args(attr) {
+        Ok(cases) => cases,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
     let sig = &input.sig;
     let name = &input.sig.ident;
-    let body = &input.block;
-    let vis = &input.vis;
 
-    let fn_impl = if input.sig.asyncness.is_none() {
-        // Optionally pass an event_loop parameter to blocking tasks
-        let task = if sig.inputs.is_empty() {
-            quote! {
-                Box::pin(pyo3_asyncio::async_std::re_exports::spawn_blocking(move || {
-                    #name()
-                }))
-            }
-        } else {
-            quote! {
-                let event_loop = Python::with_gil(|py| {
-                    pyo3_asyncio::async_std::get_current_loop(py).unwrap().into()
-                });
-                Box::pin(pyo3_asyncio::async_std::re_exports::spawn_blocking(move || {
-                    #name(event_loop)
-                }))
-            }
-        };
+    let (provide, args) = match provide_fixtures(
+        sig,
+        quote! { pyo3_asyncio::async_std::get_current_locals },
+        cases.as_ref().map_or(0, |cases| cases[0].len()),
+    ) {
+        Ok(fixtures) => fixtures,
+        Err(e) => return e.to_compile_error().into(),
+    };
 
+    let task = if input.sig.asyncness.is_none() {
         quote! {
-            #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
-                #sig {
-                    #body
-                }
-
-                #task
-            }
+            Box::pin(async move {
+                #provide
+                pyo3_asyncio::async_std::re_exports::spawn_blocking(move || {
+                    #name(#(#args),*)
+                })
+                .await
+            })
         }
     } else {
         quote! {
-            #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
-                #sig {
-                    #body
-                }
-
-                Box::pin(#name())
-            }
+            Box::pin(async move {
+                #provide
+                #name(#(#args),*).await
+            })
         }
     };
 
-    let result = quote! {
-        #fn_impl
+    register_test(
+        &input,
+        task,
+        quote! { pyo3_asyncio::async_std::scope },
+        cases.as_deref(),
+    )
+    .into()
+}
+
+/// Parse the arguments of `#[pyo3_asyncio::async_std::test]`, returning its cases if any
+fn parse_async_std_test_args(attr: TokenStream) -> syn::Result<Option<Vec<Vec<syn::Expr>>>> {
+    let mut cases = None;
 
-        pyo3_asyncio::inventory::submit! {
-            #![crate = pyo3_asyncio] {
-                pyo3_asyncio::testing::Test {
-                    name: format!("{}::{}", std::module_path!(), stringify!(#name)),
-                    test_fn: &#name
+    for arg in parse_test_args(attr)? {
+        match arg.name.to_string().as_str() {
+            "cases" => {
+                if cases.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        arg.name,
+                        "`cases` set multiple times.",
+                    ));
                 }
+                cases = Some(parse_cases(&arg.value)?);
+            }
+            name => {
+                let msg = format!(
+                    "Unknown attribute {} is specified; expected one of: `cases`",
+                    name
+                );
+                return Err(syn::Error::new_spanned(arg.name, msg));
             }
         }
-    };
+    }
 
-    result.into()
+    Ok(cases)
 }
 
 /// Registers a `tokio` test with the `pyo3-asyncio` test harness.
@@ -226,87 +485,129 @@ pub fn async_std_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
 ///     Ok(())
 /// }
 ///
-/// // blocking test functions can optionally accept an event_loop parameter
+/// // test functions can take parameters that the harness injects, i.e. the event loop
 /// #[pyo3_asyncio::tokio::test]
 /// fn test_blocking_sleep_with_event_loop(event_loop: PyObject) -> PyResult<()> {
 ///     thread::sleep(Duration::from_secs(1));
 ///     Ok(())
 /// }
+///
+/// // or the task locals, see pyo3_asyncio::testing::Fixture
+/// #[pyo3_asyncio::tokio::test]
+/// async fn test_async_sleep_with_locals(locals: pyo3_asyncio::TaskLocals) -> PyResult<()> {
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::into_future_with_locals(
+///             &locals,
+///             py.import("asyncio")?.call_method1("sleep", (1,))?,
+///         )
+///     })?
+///     .await?;
+///     Ok(())
+/// }
+///
+/// // tests that take longer than `timeout_ms` fail with a `TimeoutError`
+/// #[pyo3_asyncio::tokio::test(timeout_ms = 5000)]
+/// async fn test_async_sleep_with_timeout() -> PyResult<()> {
+///     tokio::time::sleep(Duration::from_secs(1)).await;
+///     Ok(())
+/// }
+///
+/// // registers `test_add[case0]` and `test_add[case1]`
+/// #[pyo3_asyncio::tokio::test(cases = [(1, 2, 3), (2, 2, 4)])]
+/// async fn test_add(a: i32, b: i32, sum: i32) -> PyResult<()> {
+///     assert_eq!(a + b, sum);
+///     Ok(())
+/// }
 /// ```
+///
+/// # Arguments
+/// * `timeout_ms` - fail the test if it does not complete within this many milliseconds. Tests
+///   without a `timeout_ms` use the
+///   [`pyo3_asyncio::testing::default_timeout`](https://docs.rs/pyo3-asyncio/latest/pyo3_asyncio/testing/fn.default_timeout.html)
+///   instead.
+/// * `cases` - run the test once per element of this array, each appearing as a separate test named
+///   `test_name[caseN]`. A tuple provides the values of the test function's leading parameters,
+///   any other value provides its first parameter. The remaining parameters are fixtures.
+///
+/// > Blocking tests are run with `spawn_blocking`, so a timeout will fail the test but it cannot
+/// > interrupt the blocking thread.
 #[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
 #[proc_macro_attribute]
-pub fn tokio_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn tokio_test(attr: TokenStream, item: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(item as syn::ItemFn);
 
+    let config = match parse_test_args(attr).and_then(tokio::parse_test_args) {
+        Ok(config) => config,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
     let sig = &input.sig;
     let name = &input.sig.ident;
-    let body = &input.block;
-    let vis = &input.vis;
 
-    let fn_impl = if input.sig.asyncness.is_none() {
-        // Optionally pass an event_loop parameter to blocking tasks
-        let task = if sig.inputs.is_empty() {
-            quote! {
-                Box::pin(async move {
-                    match pyo3_asyncio::tokio::get_runtime().spawn_blocking(move || #name()).await {
-                        Ok(result) => result,
-                        Err(e) => {
-                            assert!(e.is_panic());
-                            Err(pyo3::exceptions::PyException::new_err("rust future panicked"))
-                        }
-                    }
-                })
-            }
-        } else {
-            quote! {
-                let event_loop = Python::with_gil(|py| {
-                    pyo3_asyncio::tokio::get_current_loop(py).unwrap().into()
-                });
-                Box::pin(async move {
-                    match pyo3_asyncio::tokio::get_runtime().spawn_blocking(move || #name(event_loop)).await {
-                        Ok(result) => result,
-                        Err(e) => {
-                            assert!(e.is_panic());
-                            Err(pyo3::exceptions::PyException::new_err("rust future panicked"))
-                        }
-                    }
-                })
-            }
-        };
+    let timeout = match config.timeout_ms {
+        Some(timeout_ms) => quote! { Some(std::time::Duration::from_millis(#timeout_ms)) },
+        None => quote! { pyo3_asyncio::testing::default_timeout() },
+    };
 
-        quote! {
-            #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
-                #sig {
-                    #body
+    // Wrap the test task in a timeout so a hung test fails instead of blocking the harness
+    let with_timeout = quote! {
+        match #timeout {
+            Some(timeout) => Box::pin(async move {
+                match pyo3_asyncio::tokio::re_exports::timeout(timeout, task).await {
+                    Ok(result) => result,
+                    Err(_) => Err(pyo3::exceptions::PyTimeoutError::new_err(format!(
+                        "test timed out after {}ms",
+                        timeout.as_millis()
+                    ))),
                 }
-
-                #task
-            }
-        }
-    } else {
-        quote! {
-            #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
-                #sig {
-                    #body
-                }
-
-                Box::pin(#name())
-            }
+            }),
+            None => task,
         }
     };
 
-    let result = quote! {
-        #fn_impl
+    let (provide, args) = match provide_fixtures(
+        sig,
+        quote! { pyo3_asyncio::tokio::get_current_locals },
+        config.cases.as_ref().map_or(0, |cases| cases[0].len()),
+    ) {
+        Ok(fixtures) => fixtures,
+        Err(e) => return e.to_compile_error().into(),
+    };
 
-        pyo3_asyncio::inventory::submit! {
-            #![crate = pyo3_asyncio] {
-                pyo3_asyncio::testing::Test {
-                    name: format!("{}::{}", std::module_path!(), stringify!(#name)),
-                    test_fn: &#name
+    let task = if input.sig.asyncness.is_none() {
+        quote! {
+            Box::pin(async move {
+                #provide
+                match pyo3_asyncio::tokio::get_runtime()
+                    .spawn_blocking(move || #name(#(#args),*))
+                    .await
+                {
+                    Ok(result) => result,
+                    Err(e) => {
+                        assert!(e.is_panic());
+                        Err(pyo3::exceptions::PyException::new_err("rust future panicked"))
+                    }
                 }
-            }
+            })
+        }
+    } else {
+        quote! {
+            Box::pin(async move {
+                #provide
+                #name(#(#args),*).await
+            })
         }
     };
 
-    result.into()
+    register_test(
+        &input,
+        quote! {
+            let task: pyo3_asyncio::testing::TestTask = #task;
+
+            #with_timeout
+        },
+        quote! { pyo3_asyncio::tokio::scope },
+        config.cases.as_deref(),
+    )
+    .into()
 }
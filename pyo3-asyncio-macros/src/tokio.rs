@@ -107,6 +107,69 @@ impl Configuration {
     }
 }
 
+pub(crate) struct TestConfig {
+    pub(crate) timeout_ms: Option<u64>,
+    pub(crate) cases: Option<Vec<Vec<syn::Expr>>>,
+}
+
+pub(crate) fn parse_test_args(args: Vec<crate::TestArg>) -> Result<TestConfig, syn::Error> {
+    let mut config = TestConfig {
+        timeout_ms: None,
+        cases: None,
+    };
+
+    for arg in args {
+        match arg.name.to_string().to_lowercase().as_str() {
+            "timeout_ms" => {
+                if config.timeout_ms.is_some() {
+                    return Err(syn::Error::new(
+                        arg.name.span(),
+                        "`timeout_ms` set multiple times.",
+                    ));
+                }
+
+                let timeout_ms = match arg.value {
+                    syn::Expr::Lit(syn::ExprLit { lit, .. }) => {
+                        parse_int(lit, arg.name.span(), "timeout_ms")?
+                    }
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "Failed to parse timeout_ms as integer.",
+                        ))
+                    }
+                };
+                if timeout_ms == 0 {
+                    return Err(syn::Error::new(
+                        arg.name.span(),
+                        "`timeout_ms` may not be 0.",
+                    ));
+                }
+                config.timeout_ms = Some(timeout_ms as u64);
+            }
+            "cases" => {
+                if config.cases.is_some() {
+                    return Err(syn::Error::new(
+                        arg.name.span(),
+                        "`cases` set multiple times.",
+                    ));
+                }
+
+                config.cases = Some(crate::parse_cases(&arg.value)?);
+            }
+            name => {
+                let msg = format!(
+                    "Unknown attribute {} is specified; expected one of: `timeout_ms`, `cases`",
+                    name
+                );
+                return Err(syn::Error::new_spanned(arg.name, msg));
+            }
+        }
+    }
+
+    Ok(config)
+}
+
 fn parse_int(int: syn::Lit, span: Span, field: &str) -> Result<usize, syn::Error> {
     match int {
         syn::Lit::Int(lit) => match lit.base10_parse::<usize>() {
@@ -216,6 +216,32 @@ fn test_local_cancel(event_loop: PyObject) -> PyResult<()> {
     }))
 }
 
+#[pyo3_asyncio::async_std::test]
+fn test_run_local(event_loop: PyObject) -> PyResult<()> {
+    let locals = Python::with_gil(|py| -> PyResult<TaskLocals> {
+        Ok(TaskLocals::new(event_loop.as_ref(py)).copy_context(py)?)
+    })?;
+    let thread = std::thread::current().id();
+
+    pyo3_asyncio::async_std::run_local(locals, async move {
+        let non_send_thread = Rc::new(thread);
+
+        Python::with_gil(|py| {
+            pyo3_asyncio::async_std::into_future(pyo3_asyncio::async_std::local_future_into_py(
+                py,
+                async move {
+                    async_std::task::sleep(Duration::from_millis(100)).await;
+                    assert_eq!(std::thread::current().id(), *non_send_thread);
+                    Ok(())
+                },
+            )?)
+        })?
+        .await?;
+
+        Ok(())
+    })
+}
+
 /// This module is implemented in Rust.
 #[pymodule]
 fn test_mod(_py: Python, m: &PyModule) -> PyResult<()> {
@@ -0,0 +1,139 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use futures::StreamExt;
+use pyo3::{exceptions::asyncio::CancelledError, prelude::*};
+use pyo3_asyncio::TaskLocals;
+
+fn dump_err(py: Python, e: PyErr) {
+    // We can't display Python exceptions via std::fmt::Display,
+    // so print the error here manually.
+    e.print_and_set_sys_last_vars(py);
+}
+
+struct SetOnDrop(Arc<AtomicBool>);
+
+impl Drop for SetOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+const TICKS_CODE: &str = r#"
+import asyncio
+
+async def ticks():
+    for i in range(10):
+        yield i
+    await asyncio.sleep(60)
+    yield 10
+"#;
+
+async fn test_cancel_all_bridges(locals: TaskLocals) -> PyResult<()> {
+    let mut stream = Python::with_gil(|py| {
+        let ticks = PyModule::from_code(py, TICKS_CODE, "ticks.py", "ticks")?;
+        pyo3_asyncio::into_stream_with_locals(locals.clone(), ticks.call_method0("ticks")?)
+    })?
+    .boxed();
+
+    for i in 0..10 {
+        let item = stream.next().await.unwrap()?;
+        assert_eq!(Python::with_gil(|py| item.extract::<i32>(py))?, i);
+    }
+    // every __anext__ shares the bridge of the stream
+    assert_eq!(pyo3_asyncio::live_bridge_count(), 1);
+
+    let next = tokio::spawn(async move { stream.next().await.unwrap() });
+
+    let sleeping = Python::with_gil(|py| {
+        pyo3_asyncio::into_future_with_locals(
+            &locals,
+            py.import("asyncio")?.call_method1("sleep", (60,))?,
+        )
+    })?;
+    let sleeping = tokio::spawn(sleeping);
+
+    let dropped = Arc::new(AtomicBool::new(false));
+    let set_on_drop = SetOnDrop(Arc::clone(&dropped));
+    let pending: PyObject = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::future_into_py_with_locals(py, locals.clone(), async move {
+            let _set_on_drop = set_on_drop;
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(())
+        })
+        .map(Into::into)
+    })?;
+
+    // let the event loop pick up the awaitables
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(pyo3_asyncio::live_bridge_count(), 3);
+    assert_eq!(Python::with_gil(pyo3_asyncio::cancel_all_bridges), 3);
+
+    // the in-flight bridges resolve to a CancelledError
+    let next_err = next.await.unwrap().unwrap_err();
+    let sleeping_err = sleeping.await.unwrap().unwrap_err();
+    Python::with_gil(|py| {
+        assert!(next_err.is_instance_of::<CancelledError>(py));
+        assert!(sleeping_err.is_instance_of::<CancelledError>(py));
+    });
+
+    // the Rust future is dropped once its asyncio.Future is cancelled, which releases its bridge
+    for _ in 0..100 {
+        if dropped.load(Ordering::SeqCst) && pyo3_asyncio::live_bridge_count() == 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(dropped.load(Ordering::SeqCst));
+    assert_eq!(pyo3_asyncio::live_bridge_count(), 0);
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(pending.as_ref(py).call_method0("cancelled")?.is_true()?);
+        Ok(())
+    })
+}
+
+fn main() {
+    pyo3::prepare_freethreaded_python();
+
+    let (event_loop, locals) = Python::with_gil(|py| -> PyResult<_> {
+        let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+        let locals = TaskLocals::new(event_loop).copy_context(py)?;
+        Ok((PyObject::from(event_loop), locals))
+    })
+    .map_err(|e| Python::with_gil(|py| dump_err(py, e)))
+    .unwrap();
+
+    // the event loop runs on its own thread, so that cancelling every bridge doesn't cancel the
+    // test itself
+    let loop_thread = {
+        let event_loop = Python::with_gil(|py| event_loop.clone_ref(py));
+        thread::spawn(move || {
+            Python::with_gil(|py| event_loop.as_ref(py).call_method0("run_forever").map(drop))
+        })
+    };
+
+    let result = pyo3_asyncio::tokio::get_runtime().block_on(test_cancel_all_bridges(locals));
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let event_loop = event_loop.as_ref(py);
+        event_loop.call_method1("call_soon_threadsafe", (event_loop.getattr("stop")?,))?;
+        Ok(())
+    })
+    .unwrap();
+    loop_thread
+        .join()
+        .unwrap()
+        .and(result)
+        .and_then(|()| Python::with_gil(|py| event_loop.as_ref(py).call_method0("close").map(drop)))
+        .map_err(|e| Python::with_gil(|py| dump_err(py, e)))
+        .unwrap();
+
+    println!("test test_cancel_all_bridges ... ok");
+}
@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use pyo3::prelude::*;
+
+fn dump_err(py: Python, e: PyErr) {
+    // We can't display Python exceptions via std::fmt::Display,
+    // so print the error here manually.
+    e.print_and_set_sys_last_vars(py);
+}
+
+/// Await a Python coroutine that awaits a Rust future, all from the async-std runtime
+async fn async_std_round_trip() -> PyResult<i32> {
+    let result = Python::with_gil(|py| {
+        let answer = pyo3_asyncio::async_std::future_into_py(py, async {
+            async_std::task::sleep(Duration::from_millis(10)).await;
+            Ok(21)
+        })?;
+        let doubled = PyModule::from_code(
+            py,
+            "async def double(answer):\n    return 2 * await answer\n",
+            "async_std_round_trip.py",
+            "async_std_round_trip",
+        )?
+        .call_method1("double", (answer,))?;
+
+        pyo3_asyncio::async_std::into_future(doubled)
+    })?
+    .await?;
+
+    Python::with_gil(|py| result.extract(py))
+}
+
+/// Await a Python coroutine that awaits a Rust future, all from the tokio runtime
+async fn tokio_round_trip() -> PyResult<i32> {
+    let result = Python::with_gil(|py| {
+        let answer = pyo3_asyncio::tokio::future_into_py(py, async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Ok(21)
+        })?;
+        let doubled = PyModule::from_code(
+            py,
+            "async def double(answer):\n    return 2 * await answer\n",
+            "tokio_round_trip.py",
+            "tokio_round_trip",
+        )?
+        .call_method1("double", (answer,))?;
+
+        pyo3_asyncio::tokio::into_future(doubled)
+    })?
+    .await?;
+
+    Python::with_gil(|py| result.extract(py))
+}
+
+fn test_async_std_on_tokio_loop(py: Python) -> PyResult<()> {
+    pyo3_asyncio::tokio::run(py, async move {
+        let locals = Python::with_gil(pyo3_asyncio::tokio::get_current_locals)?;
+
+        let answer = async_std::task::spawn(pyo3_asyncio::async_std::scope(
+            locals.clone(),
+            async_std_round_trip(),
+        ))
+        .await?;
+        assert_eq!(answer, 42);
+
+        // the *_with_locals functions don't need the task locals to be set at all
+        let answer = async_std::task::spawn(async move {
+            let value = Python::with_gil(|py| {
+                pyo3_asyncio::into_future_with_locals(
+                    &locals,
+                    py.import("asyncio")?.call_method1("sleep", (0.01, 42))?,
+                )
+            })?
+            .await?;
+
+            Python::with_gil(|py| value.extract::<i32>(py))
+        })
+        .await?;
+        assert_eq!(answer, 42);
+
+        Ok(())
+    })
+}
+
+fn test_tokio_on_async_std_loop(py: Python) -> PyResult<()> {
+    pyo3_asyncio::async_std::run(py, async move {
+        let locals = Python::with_gil(pyo3_asyncio::async_std::get_current_locals)?;
+
+        let answer = pyo3_asyncio::tokio::get_runtime()
+            .spawn(pyo3_asyncio::tokio::scope(
+                locals.clone(),
+                tokio_round_trip(),
+            ))
+            .await
+            .unwrap()?;
+        assert_eq!(answer, 42);
+
+        let answer = pyo3_asyncio::tokio::get_runtime()
+            .spawn(async move {
+                let value = Python::with_gil(|py| {
+                    pyo3_asyncio::into_future_with_locals(
+                        &locals,
+                        py.import("asyncio")?.call_method1("sleep", (0.01, 42))?,
+                    )
+                })?
+                .await?;
+
+                Python::with_gil(|py| value.extract::<i32>(py))
+            })
+            .await
+            .unwrap()?;
+        assert_eq!(answer, 42);
+
+        Ok(())
+    })
+}
+
+fn main() {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| -> PyResult<()> {
+        test_async_std_on_tokio_loop(py)?;
+        println!("test test_async_std_on_tokio_loop ... ok");
+
+        test_tokio_on_async_std_loop(py)?;
+        println!("test test_tokio_on_async_std_loop ... ok");
+
+        Ok(())
+    })
+    .map_err(|e| Python::with_gil(|py| dump_err(py, e)))
+    .unwrap()
+}
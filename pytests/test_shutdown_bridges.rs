@@ -0,0 +1,82 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use pyo3::prelude::*;
+
+fn dump_err(py: Python, e: PyErr) {
+    // We can't display Python exceptions via std::fmt::Display,
+    // so print the error here manually.
+    e.print_and_set_sys_last_vars(py);
+}
+
+struct SetOnDrop(Arc<AtomicBool>);
+
+impl Drop for SetOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+const AWAIT_CODE: &str = r#"
+async def await_pending(fut):
+    try:
+        await fut
+    except RuntimeError as e:
+        return str(e)
+"#;
+
+fn main() {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let asyncio = py.import("asyncio")?;
+        let event_loop = asyncio.call_method0("new_event_loop")?;
+        let locals = pyo3_asyncio::TaskLocals::new(event_loop).copy_context(py)?;
+
+        let dropped = Arc::new(AtomicBool::new(false));
+        let set_on_drop = SetOnDrop(Arc::clone(&dropped));
+
+        let pending = pyo3_asyncio::tokio::future_into_py_with_locals(py, locals, async move {
+            let _set_on_drop = set_on_drop;
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(())
+        })?;
+
+        // the runtime is going away while the awaitable is still pending
+        thread::spawn(|| {
+            thread::sleep(Duration::from_millis(100));
+            Python::with_gil(|py| assert_eq!(pyo3_asyncio::shutdown_bridges(py), 1));
+        });
+
+        let message: String = event_loop
+            .call_method1(
+                "run_until_complete",
+                (
+                    PyModule::from_code(py, AWAIT_CODE, "test_shutdown.py", "test_shutdown")?
+                        .call_method1("await_pending", (pending,))?,
+                ),
+            )?
+            .extract()?;
+        assert_eq!(message, "event loop / runtime shut down");
+
+        // the Rust future is dropped after the awaitable fails
+        event_loop.call_method1(
+            "run_until_complete",
+            (asyncio.call_method1("sleep", (0.1,))?,),
+        )?;
+        assert!(dropped.load(Ordering::SeqCst));
+        assert_eq!(pyo3_asyncio::live_bridge_count(), 0);
+
+        event_loop.call_method0("close")?;
+        println!("test test_shutdown_bridges ... ok");
+        Ok(())
+    })
+    .map_err(|e| Python::with_gil(|py| dump_err(py, e)))
+    .unwrap()
+}
@@ -0,0 +1,40 @@
+// `run_forever_until_signal` is only available on unix
+#[cfg(unix)]
+use std::{thread, time::Duration};
+
+#[cfg(unix)]
+use pyo3::prelude::*;
+#[cfg(unix)]
+use signal_hook::{
+    consts::{SIGINT, SIGTERM},
+    low_level::raise,
+};
+
+#[cfg(not(unix))]
+fn main() {}
+
+#[cfg(unix)]
+fn main() {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+
+        // the handlers are unregistered on return, so each call only sees its own signal
+        for sig in [SIGINT, SIGTERM] {
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(100));
+                raise(sig).unwrap();
+            });
+
+            pyo3_asyncio::run_forever_until_signal(event_loop)?;
+            assert!(!event_loop.call_method0("is_running")?.is_true()?);
+        }
+
+        pyo3_asyncio::try_close(event_loop)?;
+        println!("test test_run_forever_until_signal ... ok");
+        Ok(())
+    })
+    .map_err(|e| Python::with_gil(|py| e.print_and_set_sys_last_vars(py)))
+    .unwrap();
+}
@@ -0,0 +1,107 @@
+use std::{rc::Rc, thread, time::Duration};
+
+use pyo3::prelude::*;
+
+fn dump_err(py: Python, e: PyErr) {
+    // We can't display Python exceptions via std::fmt::Display,
+    // so print the error here manually.
+    e.print_and_set_sys_last_vars(py);
+}
+
+/// Await a Python coroutine that awaits a Rust future, all from the smol runtime
+fn test_round_trip(py: Python) -> PyResult<()> {
+    let answer = pyo3_asyncio::smol::run(py, async move {
+        let result = Python::with_gil(|py| {
+            let answer = pyo3_asyncio::smol::future_into_py(py, async {
+                smol::Timer::after(Duration::from_millis(10)).await;
+                Ok(21)
+            })?;
+            let doubled = PyModule::from_code(
+                py,
+                "async def double(answer):\n    return 2 * await answer\n",
+                "smol_round_trip.py",
+                "smol_round_trip",
+            )?
+            .call_method1("double", (answer,))?;
+
+            pyo3_asyncio::smol::into_future(doubled)
+        })?
+        .await?;
+
+        Python::with_gil(|py| result.extract::<i32>(py))
+    })?;
+
+    assert_eq!(answer, 42);
+    println!("test test_round_trip ... ok");
+    Ok(())
+}
+
+fn test_panic(py: Python) -> PyResult<()> {
+    let err = pyo3_asyncio::smol::run(py, async move {
+        Python::with_gil(|py| {
+            pyo3_asyncio::smol::into_future(pyo3_asyncio::smol::future_into_py::<_, ()>(
+                py,
+                async {
+                    panic!("this panic was intentional!");
+                },
+            )?)
+        })?
+        .await
+    })
+    .unwrap_err();
+
+    assert!(err.is_instance_of::<pyo3_asyncio::err::RustPanic>(py));
+    assert_eq!(
+        err.value(py).to_string(),
+        "rust future panicked: this panic was intentional!"
+    );
+    println!("test test_panic ... ok");
+    Ok(())
+}
+
+fn test_run_local(py: Python) -> PyResult<()> {
+    pyo3_asyncio::smol::run(py, async move {
+        let locals = Python::with_gil(pyo3_asyncio::smol::get_current_locals)?;
+
+        // the event loop is busy on this thread, so the !Send work happens on another one
+        let worker = thread::spawn(move || {
+            let thread = thread::current().id();
+
+            pyo3_asyncio::smol::run_local(locals, async move {
+                let non_send_thread = Rc::new(thread);
+
+                Python::with_gil(|py| {
+                    pyo3_asyncio::smol::into_future(pyo3_asyncio::smol::local_future_into_py(
+                        py,
+                        async move {
+                            smol::Timer::after(Duration::from_millis(10)).await;
+                            assert_eq!(thread::current().id(), *non_send_thread);
+                            Ok(())
+                        },
+                    )?)
+                })?
+                .await?;
+
+                PyResult::Ok(())
+            })
+        });
+
+        smol::unblock(move || worker.join().unwrap()).await
+    })?;
+
+    println!("test test_run_local ... ok");
+    Ok(())
+}
+
+fn main() {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| -> PyResult<()> {
+        test_round_trip(py)?;
+        test_panic(py)?;
+        test_run_local(py)?;
+        Ok(())
+    })
+    .map_err(|e| Python::with_gil(|py| dump_err(py, e)))
+    .unwrap()
+}
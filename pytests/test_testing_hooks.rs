@@ -0,0 +1,70 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use pyo3::{exceptions::PyAssertionError, prelude::*};
+use pyo3_asyncio::testing::{self, Args, Test, TestTask};
+
+static SET_UP: AtomicBool = AtomicBool::new(false);
+static TORN_DOWN: AtomicUsize = AtomicUsize::new(0);
+
+fn test_setup_ran() -> TestTask {
+    Box::pin(async {
+        if !SET_UP.load(Ordering::SeqCst) {
+            return Err(PyAssertionError::new_err(
+                "setup did not run before the test",
+            ));
+        }
+        Ok(())
+    })
+}
+
+fn test_panic() -> TestTask {
+    Box::pin(async { panic!("this panic was intentional!") })
+}
+
+fn main() -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    testing::set_setup(|| async {
+        SET_UP.store(true, Ordering::SeqCst);
+        Ok(())
+    });
+    testing::set_teardown(|| async {
+        SET_UP.store(false, Ordering::SeqCst);
+        TORN_DOWN.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    });
+
+    Python::with_gil(|py| {
+        pyo3_asyncio::tokio::run(py, async move {
+            testing::test_harness(
+                vec![Test {
+                    name: "test_testing_hooks::test_setup_ran".into(),
+                    test_fn: &test_setup_ran,
+                }],
+                Args::default(),
+            )
+            .await?;
+            assert_eq!(TORN_DOWN.load(Ordering::SeqCst), 1);
+
+            // the teardown still runs when the test panics
+            let panicked = pyo3_asyncio::tokio::get_runtime()
+                .spawn(async {
+                    testing::test_harness(
+                        vec![Test {
+                            name: "test_testing_hooks::test_panic".into(),
+                            test_fn: &test_panic,
+                        }],
+                        Args::default(),
+                    )
+                    .await
+                })
+                .await;
+            assert!(panicked.unwrap_err().is_panic());
+            assert_eq!(TORN_DOWN.load(Ordering::SeqCst), 2);
+            assert!(!SET_UP.load(Ordering::SeqCst));
+
+            println!("test test_testing_hooks ... ok");
+            Ok(())
+        })
+    })
+}
@@ -0,0 +1,86 @@
+use std::future::Future;
+
+use pyo3::{prelude::*, wrap_pyfunction};
+use tracing::{info_span, Instrument, Span};
+use tracing_subscriber::{registry::LookupSpan, Registry};
+
+/// The names of the current span and its ancestors, innermost first
+fn current_span_path() -> Vec<&'static str> {
+    tracing::dispatcher::get_default(|dispatch| {
+        let registry = dispatch.downcast_ref::<Registry>().unwrap();
+        Span::current()
+            .id()
+            .and_then(|id| registry.span(&id))
+            .map(|span| span.scope().map(|span| span.name()).collect())
+            .unwrap_or_default()
+    })
+}
+
+#[pyfunction]
+fn span_path<'p>(py: Python<'p>) -> PyResult<&'p PyAny> {
+    pyo3_asyncio::tokio::future_into_py(py, async { Ok(current_span_path()) })
+}
+
+#[pyfunction]
+fn explicit_span_path<'p>(py: Python<'p>) -> PyResult<&'p PyAny> {
+    pyo3_asyncio::tokio::future_into_py_instrumented(py, info_span!("explicit"), async {
+        Ok(current_span_path())
+    })
+}
+
+const CALL_CODE: &str = r#"
+async def call(f):
+    return await f()
+"#;
+
+/// Await `f()` from a Python coroutine
+fn call_from_python(f: &PyAny) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send> {
+    let call_mod = PyModule::from_code(f.py(), CALL_CODE, "call.py", "call")?;
+    pyo3_asyncio::tokio::into_future(call_mod.call_method1("call", (f,))?)
+}
+
+fn main() {
+    tracing::subscriber::set_global_default(Registry::default()).unwrap();
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        pyo3_asyncio::tokio::run(py, async move {
+            // the Rust future converted by Python code becomes a child of the `py_await` span
+            let path = async {
+                Python::with_gil(|py| call_from_python(wrap_pyfunction!(span_path, py)?))?.await
+            }
+            .instrument(info_span!("root"))
+            .await?;
+            let path: Vec<String> = Python::with_gil(|py| path.extract(py))?;
+            assert_eq!(path, ["py_await", "root"]);
+
+            // an explicit span replaces the default one
+            let path =
+                Python::with_gil(|py| call_from_python(wrap_pyfunction!(explicit_span_path, py)?))?
+                    .await?;
+            let path: Vec<String> = Python::with_gil(|py| path.extract(py))?;
+            assert_eq!(path, ["explicit"]);
+
+            // without a Python boundary, the future inherits the current span
+            let path = async {
+                Python::with_gil(|py| {
+                    pyo3_asyncio::tokio::into_future(pyo3_asyncio::tokio::future_into_py(
+                        py,
+                        async { Ok(current_span_path()) },
+                    )?)
+                })?
+                .await
+            }
+            .instrument(info_span!("outer"))
+            .await?;
+            let path: Vec<String> = Python::with_gil(|py| path.extract(py))?;
+            assert_eq!(path, ["outer"]);
+
+            Ok(())
+        })
+    })
+    .map_err(|e| Python::with_gil(|py| e.print_and_set_sys_last_vars(py)))
+    .unwrap();
+
+    println!("test test_tracing_spans ... ok");
+}
@@ -1,16 +1,20 @@
 use std::{
+    future::Future,
+    pin::Pin,
     rc::Rc,
     sync::{Arc, Mutex},
+    task::{Context, Poll},
     time::Duration,
 };
 
+use futures::StreamExt;
 use pyo3::{
     prelude::*,
     proc_macro::pymodule,
-    types::{IntoPyDict, PyType},
+    types::{IntoPyDict, PyList, PyType},
     wrap_pyfunction, wrap_pymodule,
 };
-use pyo3_asyncio::TaskLocals;
+use pyo3_asyncio::{generic::Cancellable, TaskLocals};
 
 use crate::common;
 
@@ -48,6 +52,39 @@ async fn test_future_into_py() -> PyResult<()> {
     Ok(())
 }
 
+#[pyo3_asyncio::tokio::test]
+async fn test_async_fn_into_py() -> PyResult<()> {
+    let async_fn = Python::with_gil(|py| -> PyResult<PyObject> {
+        Ok(
+            pyo3_asyncio::tokio::async_fn_into_py(py, |(x,): (u64,)| async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Ok(x * 2)
+            })?
+            .into(),
+        )
+    })?;
+
+    for x in 1..3u64 {
+        let result = Python::with_gil(|py| {
+            pyo3_asyncio::tokio::into_future(async_fn.as_ref(py).call1((x,))?)
+        })?
+        .await?;
+
+        Python::with_gil(|py| -> PyResult<()> {
+            assert_eq!(result.extract::<u64>(py)?, x * 2);
+            Ok(())
+        })?;
+    }
+
+    // the wrong number of arguments raises a TypeError, like it would for a Python function
+    Python::with_gil(|py| {
+        let err = async_fn.as_ref(py).call1((1u64, 2u64)).unwrap_err();
+        assert!(err.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+    });
+
+    Ok(())
+}
+
 #[pyo3_asyncio::tokio::test]
 async fn test_async_sleep() -> PyResult<()> {
     let asyncio =
@@ -68,6 +105,12 @@ fn test_blocking_sleep() -> PyResult<()> {
     common::test_blocking_sleep()
 }
 
+#[pyo3_asyncio::tokio::test(timeout_ms = 5000)]
+async fn test_async_sleep_with_timeout() -> PyResult<()> {
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    Ok(())
+}
+
 #[pyo3_asyncio::tokio::test]
 async fn test_into_future() -> PyResult<()> {
     common::test_into_future(Python::with_gil(|py| {
@@ -130,6 +173,144 @@ async fn test_panic() -> PyResult<()> {
     }
 }
 
+#[pyo3_asyncio::tokio::test]
+async fn test_into_future_abortable() -> PyResult<()> {
+    let (fut, registration) = Python::with_gil(|py| -> PyResult<_> {
+        let event_loop = pyo3_asyncio::tokio::get_current_loop(py)?;
+        let py_future = event_loop.call_method0("create_future")?;
+
+        let (fut, registration) = pyo3_asyncio::tokio::into_future_abortable(py_future)?;
+        event_loop.call_method1("call_soon_threadsafe", (py_future.getattr("cancel")?,))?;
+
+        Ok((fut, registration))
+    })?;
+
+    assert!(fut.await.is_err());
+    assert!(
+        futures::future::Abortable::new(futures::future::pending::<()>(), registration)
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_spawn_blocking_rust_panic() -> PyResult<()> {
+    let fut = Python::with_gil(|py| -> PyResult<_> {
+        pyo3_asyncio::tokio::into_future(pyo3_asyncio::tokio::future_into_py(py, async {
+            pyo3_asyncio::tokio::spawn_blocking_rust(|| -> u64 {
+                panic!("this panic was intentional!")
+            })
+            .await
+        })?)
+    })?;
+
+    match fut.await {
+        Ok(_) => panic!("blocking task should panic"),
+        Err(e) => Python::with_gil(|py| {
+            if e.is_instance::<pyo3_asyncio::err::RustPanic>(py) {
+                Ok(())
+            } else {
+                panic!("expected RustPanic err")
+            }
+        }),
+    }
+}
+
+const CONTEXT_MANAGER_CODE: &str = r#"
+async def use_resource(resource):
+    async with resource as value:
+        assert value == "acquired"
+
+    try:
+        async with resource:
+            raise ValueError("raised in block")
+    except ValueError:
+        return
+    raise AssertionError("exception was suppressed")
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_async_context_manager_into_py() -> PyResult<()> {
+    let exits = Arc::new(Mutex::new(Vec::new()));
+
+    let fut = Python::with_gil(|py| -> PyResult<_> {
+        let exits = Arc::clone(&exits);
+        let resource = pyo3_asyncio::tokio::async_context_manager_into_py(
+            py,
+            || async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Ok("acquired")
+            },
+            move |exc| {
+                exits.lock().unwrap().push(exc.is_some());
+                async move { Ok(false) }
+            },
+        )?;
+
+        let test_mod = PyModule::from_code(
+            py,
+            CONTEXT_MANAGER_CODE,
+            "test_async_context_manager.py",
+            "test_async_context_manager",
+        )?;
+
+        pyo3_asyncio::tokio::into_future(test_mod.call_method1("use_resource", (resource,))?)
+    })?;
+
+    fut.await?;
+    assert_eq!(*exits.lock().unwrap(), vec![false, true]);
+
+    Ok(())
+}
+
+const CONCURRENCY_CODE: &str = r#"
+import asyncio
+
+in_flight = 0
+peak = 0
+
+async def work(i):
+    global in_flight, peak
+    in_flight += 1
+    peak = max(peak, in_flight)
+    await asyncio.sleep(0.01)
+    in_flight -= 1
+    return i
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_for_each_concurrent() -> PyResult<()> {
+    let (test_mod, awaitables) = Python::with_gil(|py| -> PyResult<_> {
+        let test_mod = PyModule::from_code(
+            py,
+            CONCURRENCY_CODE,
+            "test_for_each_concurrent.py",
+            "test_for_each_concurrent",
+        )?;
+
+        let awaitables = (0..100u64)
+            .map(|i| Ok(test_mod.call_method1("work", (i,))?.into()))
+            .collect::<PyResult<Vec<PyObject>>>()?;
+
+        Ok((PyObject::from(test_mod), awaitables))
+    })?;
+
+    let mut total = 0;
+    pyo3_asyncio::tokio::for_each_concurrent(awaitables, 4, |result| {
+        total += Python::with_gil(|py| result?.extract::<u64>(py))?;
+        Ok(())
+    })
+    .await?;
+
+    assert_eq!(total, (0..100u64).sum::<u64>());
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(test_mod.getattr(py, "peak")?.extract::<u64>(py)?, 4);
+        Ok(())
+    })
+}
+
 #[pyo3_asyncio::tokio::test]
 async fn test_cancel() -> PyResult<()> {
     let completed = Arc::new(Mutex::new(false));
@@ -172,6 +353,104 @@ async fn test_cancel() -> PyResult<()> {
     Ok(())
 }
 
+const SLOW_CLEANUP_CODE: &str = r#"
+import asyncio
+
+cleaned_up = False
+
+async def worker():
+    global cleaned_up
+    try:
+        await asyncio.sleep(100)
+    finally:
+        await asyncio.sleep(0.2)
+        cleaned_up = True
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_cancel_and_wait() -> PyResult<()> {
+    let (test_mod, task) = Python::with_gil(|py| -> PyResult<(PyObject, PyObject)> {
+        let test_mod = PyModule::from_code(
+            py,
+            SLOW_CLEANUP_CODE,
+            "test_cancel_and_wait.py",
+            "test_cancel_and_wait",
+        )?;
+        let task = pyo3_asyncio::tokio::get_current_loop(py)?
+            .call_method1("create_task", (test_mod.call_method0("worker")?,))?;
+
+        Ok((test_mod.into(), task.into()))
+    })?;
+
+    // give the worker a chance to start before it is cancelled
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let stopped = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::cancel_and_wait(task.as_ref(py), Some(Duration::from_secs(5)))
+    })?
+    .await?;
+
+    assert!(stopped);
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(test_mod.getattr(py, "cleaned_up")?.extract::<bool>(py)?);
+        Ok(())
+    })
+}
+
+struct CleanupOnCancel {
+    step: Pin<Box<dyn Future<Output = PyResult<()>> + Send>>,
+    cleaned_up: Arc<Mutex<bool>>,
+}
+
+impl Future for CleanupOnCancel {
+    type Output = PyResult<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.step.as_mut().poll(cx)
+    }
+}
+
+impl Cancellable for CleanupOnCancel {
+    fn cancel(mut self: Pin<&mut Self>) -> bool {
+        let cleaned_up = Arc::clone(&self.cleaned_up);
+        self.step = Box::pin(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            *cleaned_up.lock().unwrap() = true;
+            Ok(())
+        });
+        true
+    }
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_structured_cancel() -> PyResult<()> {
+    let cleaned_up = Arc::new(Mutex::new(false));
+
+    let fut = Python::with_gil(|py| -> PyResult<_> {
+        let py_future = pyo3_asyncio::tokio::structured_future_into_py(
+            py,
+            CleanupOnCancel {
+                step: Box::pin(async move {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Ok(())
+                }),
+                cleaned_up: Arc::clone(&cleaned_up),
+            },
+        )?;
+
+        pyo3_asyncio::tokio::get_current_loop(py)?
+            .call_method1("call_later", (0.1, py_future.getattr("cancel")?))?;
+        pyo3_asyncio::tokio::into_future(py_future)
+    })?;
+
+    assert!(fut.await.is_err());
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(*cleaned_up.lock().unwrap());
+
+    Ok(())
+}
+
 #[pyo3_asyncio::tokio::test]
 fn test_local_cancel(event_loop: PyObject) -> PyResult<()> {
     let locals = Python::with_gil(|py| -> PyResult<TaskLocals> {
@@ -268,6 +547,48 @@ fn test_multiple_asyncio_run() -> PyResult<()> {
     })
 }
 
+#[pyo3_asyncio::tokio::test]
+fn test_warmup() -> PyResult<()> {
+    Python::with_gil(|py| {
+        pyo3_asyncio::tokio::warmup(py)?;
+        // warming up again is harmless
+        pyo3_asyncio::tokio::warmup(py)?;
+
+        // the temporary event loop is gone
+        assert!(pyo3_asyncio::get_running_loop(py).is_err());
+
+        pyo3_asyncio::tokio::run(py, async move {
+            Python::with_gil(|py| {
+                pyo3_asyncio::tokio::into_future(py.import("asyncio")?.call_method1("sleep", (0,))?)
+            })?
+            .await?;
+            Ok(())
+        })
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+fn test_run_until_complete_all() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+
+        for _ in 0..2 {
+            let results = pyo3_asyncio::tokio::run_until_complete_all(
+                event_loop,
+                (0..3u64).map(|i| async move {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    Ok(i)
+                }),
+            )?;
+
+            assert_eq!(results, vec![0, 1, 2]);
+        }
+
+        event_loop.call_method0("close")?;
+        Ok(())
+    })
+}
+
 #[pymodule]
 fn cvars_mod(_py: Python, m: &PyModule) -> PyResult<()> {
     #![allow(deprecated)]
@@ -318,3 +639,2509 @@ fn test_contextvars() -> PyResult<()> {
         Ok(())
     })
 }
+
+#[pyo3_asyncio::tokio::test(cases = [(1, 2, 3), (2, 2, 4), (-1, 1, 0)])]
+async fn test_parameterized(a: i32, b: i32, sum: i32, locals: TaskLocals) -> PyResult<()> {
+    let result = Python::with_gil(|py| {
+        pyo3_asyncio::into_future_with_locals(
+            &locals,
+            py.import("asyncio")?.call_method1("sleep", (0, a + b))?,
+        )
+    })?
+    .await?;
+
+    Python::with_gil(|py| {
+        assert_eq!(result.extract::<i32>(py)?, sum);
+        Ok(())
+    })
+}
+
+#[derive(Debug)]
+struct LookupError(String);
+
+#[pyo3_asyncio::tokio::test]
+async fn test_future_into_py_mapped() -> PyResult<()> {
+    pyo3_asyncio::err::register_error_mapper(|_py, e: LookupError| {
+        pyo3::exceptions::PyKeyError::new_err(e.0)
+    });
+
+    let fut = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future(pyo3_asyncio::tokio::future_into_py_mapped(
+            py,
+            async move { Err::<(), _>(LookupError("missing".into())) },
+        )?)
+    })?;
+
+    let err = fut.await.unwrap_err();
+    Python::with_gil(|py| {
+        assert!(err.is_instance_of::<pyo3::exceptions::PyKeyError>(py));
+        assert_eq!(err.value(py).to_string(), "'missing'");
+    });
+
+    Ok(())
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_future_into_py_with_error_map() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future(pyo3_asyncio::tokio::future_into_py_with_error_map(
+            py,
+            async move { Err::<(), _>(LookupError("timed out".into())) },
+            |e| pyo3::exceptions::PyTimeoutError::new_err(e.0),
+        )?)
+    })?;
+
+    let err = fut.await.unwrap_err();
+    Python::with_gil(|py| {
+        assert!(err.is_instance_of::<pyo3::exceptions::PyTimeoutError>(py));
+        assert_eq!(err.value(py).to_string(), "timed out");
+    });
+
+    Ok(())
+}
+
+const RATE_LIMIT_CODE: &str = r#"
+import asyncio
+
+running = 0
+peak = 0
+
+async def work(fail=False):
+    global running, peak
+    running += 1
+    peak = max(peak, running)
+    try:
+        await asyncio.sleep(0.05)
+        if fail:
+            raise ValueError("request failed")
+    finally:
+        running -= 1
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_into_future_rate_limited() -> PyResult<()> {
+    let bridge = pyo3_asyncio::RateLimitedBridge::new(2);
+
+    let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+        Ok(PyModule::from_code(py, RATE_LIMIT_CODE, "rate_limit.py", "rate_limit")?.into())
+    })?;
+
+    let futs = Python::with_gil(|py| {
+        (0..6)
+            .map(|i| {
+                let coro = test_mod.as_ref(py).call_method1("work", (i == 3,))?;
+                pyo3_asyncio::tokio::into_future_rate_limited(&bridge, coro)
+            })
+            .collect::<PyResult<Vec<_>>>()
+    })?;
+
+    let results = futures::future::join_all(futs).await;
+    assert_eq!(results.iter().filter(|r| r.is_err()).count(), 1);
+
+    // a bridged future dropped while it is still running gives its slot back
+    let fut = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future_rate_limited(
+            &bridge,
+            test_mod.as_ref(py).call_method0("work")?,
+        )
+    })?;
+    assert!(tokio::time::timeout(Duration::from_millis(10), fut)
+        .await
+        .is_err());
+
+    assert_eq!(bridge.available_permits(), 2);
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(test_mod.getattr(py, "peak")?.extract::<usize>(py)?, 2);
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+const AWAITABLES_CODE: &str = r#"
+import asyncio
+
+class CustomAwaitable:
+    def __await__(self):
+        return asyncio.sleep(0).__await__()
+
+def awaitables(loop):
+    fut = loop.create_future()
+    fut.set_result(None)
+    return [asyncio.sleep(0), loop.create_task(asyncio.sleep(0)), fut, CustomAwaitable()]
+"#;
+
+struct StrictGuard;
+
+impl Drop for StrictGuard {
+    fn drop(&mut self) {
+        pyo3_asyncio::set_strict(false);
+    }
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_strict_mode() -> PyResult<()> {
+    pyo3_asyncio::set_strict(true);
+    let strict = StrictGuard;
+
+    let futs = Python::with_gil(|py| -> PyResult<_> {
+        let locals = pyo3_asyncio::tokio::get_current_locals(py)?;
+        let awaitables = PyModule::from_code(py, AWAITABLES_CODE, "awaitables.py", "awaitables")?
+            .call_method1("awaitables", (locals.event_loop(py),))?;
+        let err = pyo3_asyncio::into_future_ignore_result_with_locals(
+            &locals,
+            "foo".to_object(py).as_ref(py),
+        )
+        .err()
+        .unwrap();
+        assert!(err.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+
+        awaitables
+            .iter()?
+            .map(|awaitable| {
+                pyo3_asyncio::into_future_ignore_result_with_locals(&locals, awaitable?)
+            })
+            .collect::<PyResult<Vec<_>>>()
+    });
+    drop(strict);
+
+    for fut in futs? {
+        fut.await?;
+    }
+
+    Ok(())
+}
+
+#[pyfunction]
+fn await_pinned<'p>(py: Python<'p>, awaitable: &'p PyAny) -> PyResult<&'p PyAny> {
+    let fut = pyo3_asyncio::into_future_pinned(awaitable)?;
+    pyo3_asyncio::tokio::future_into_py(py, async move { fut.await })
+}
+
+const PINNED_CODE: &str = r#"
+import asyncio
+import threading
+
+async def thread_id():
+    return threading.get_ident()
+
+async def main(await_pinned):
+    return threading.get_ident(), await await_pinned(thread_id())
+
+def run(await_pinned):
+    event_loop = asyncio.new_event_loop()
+    try:
+        return event_loop.run_until_complete(main(await_pinned))
+    finally:
+        event_loop.close()
+"#;
+
+#[pyo3_asyncio::tokio::test]
+fn test_into_future_pinned() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let (caller, awaited): (u64, u64) =
+            PyModule::from_code(py, PINNED_CODE, "pinned.py", "pinned")?
+                .call_method1("run", (wrap_pyfunction!(await_pinned, py)?,))?
+                .extract()?;
+
+        assert_eq!(caller, awaited);
+        Ok(())
+    })
+}
+
+#[pyfunction]
+fn await_in<'p>(
+    py: Python<'p>,
+    context: Option<&'p PyAny>,
+    awaitable: &'p PyAny,
+) -> PyResult<&'p PyAny> {
+    let locals = TaskLocals::with_running_loop(py)?;
+    let locals = match context {
+        Some(context) => locals.with_context(context),
+        None => locals.copy_context(py)?,
+    };
+
+    let fut = pyo3_asyncio::into_future_with_locals(&locals, awaitable)?;
+    pyo3_asyncio::tokio::future_into_py(py, async move { fut.await })
+}
+
+const IMMEDIATE_TASK_CODE: &str = r#"
+import asyncio
+import contextvars
+
+cx = contextvars.ContextVar("cx")
+
+async def get_cx():
+    return cx.get()
+
+def schedule(await_in, context):
+    before = len(asyncio.all_tasks())
+    fut = await_in(context, get_cx())
+    return len(asyncio.all_tasks()) - before, fut
+
+async def main(await_in):
+    cx.set("foobar")
+
+    # the task is created right away, in a copy of the current context
+    created, fut = schedule(await_in, None)
+    copied = await fut
+
+    # the context is already entered, so the task is created by the event loop later on
+    context = contextvars.copy_context()
+    deferred, fut = context.run(schedule, await_in, context)
+    entered = await fut
+
+    return created, copied, deferred, entered
+
+def run(await_in):
+    event_loop = asyncio.new_event_loop()
+    try:
+        return event_loop.run_until_complete(main(await_in))
+    finally:
+        event_loop.close()
+"#;
+
+#[pyo3_asyncio::tokio::test]
+fn test_into_future_on_loop_thread() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let (created, copied, deferred, entered): (usize, String, usize, String) =
+            PyModule::from_code(
+                py,
+                IMMEDIATE_TASK_CODE,
+                "immediate_task.py",
+                "immediate_task",
+            )?
+            .call_method1("run", (wrap_pyfunction!(await_in, py)?,))?
+            .extract()?;
+
+        assert_eq!(created, 1);
+        assert_eq!(copied, "foobar");
+        assert_eq!(deferred, 0);
+        assert_eq!(entered, "foobar");
+        Ok(())
+    })
+}
+
+const QUEUE_CONSUMER_CODE: &str = r#"
+async def consume(queue):
+    return [value async for value in queue]
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_channel_to_py_queue() -> PyResult<()> {
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    let sent = Arc::new(Mutex::new(0));
+
+    let producer = {
+        let sent = Arc::clone(&sent);
+        tokio::spawn(async move {
+            for i in 0..10 {
+                tx.send(i).await.unwrap();
+                *sent.lock().unwrap() += 1;
+            }
+        })
+    };
+
+    let queue = Python::with_gil(|py| -> PyResult<PyObject> {
+        Ok(pyo3_asyncio::tokio::channel_to_py_queue(py, rx)?.into())
+    })?;
+
+    // nothing consumes the queue yet, so the producer is held back by the channel and the queue
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(*sent.lock().unwrap() <= 3);
+
+    let values = Python::with_gil(|py| {
+        let consumer = PyModule::from_code(
+            py,
+            QUEUE_CONSUMER_CODE,
+            "queue_consumer.py",
+            "queue_consumer",
+        )?;
+        pyo3_asyncio::tokio::into_future(consumer.call_method1("consume", (queue,))?)
+    })?
+    .await?;
+
+    producer.await.unwrap();
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(values.extract::<Vec<i32>>(py)?, (0..10).collect::<Vec<_>>());
+        Ok(())
+    })
+}
+
+const QUEUE_PRODUCER_CODE: &str = r#"
+import asyncio
+
+async def produce(count):
+    queue = asyncio.Queue()
+
+    async def producer():
+        for i in range(count):
+            await queue.put(i)
+            await asyncio.sleep(0.01)
+        await queue.put(None)
+
+    task = asyncio.ensure_future(producer())
+    return queue, task
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_py_queue_to_stream() -> PyResult<()> {
+    let (queue, task) = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future(
+            PyModule::from_code(
+                py,
+                QUEUE_PRODUCER_CODE,
+                "queue_producer.py",
+                "queue_producer",
+            )?
+            .call_method1("produce", (5,))?,
+        )
+    })?
+    .await
+    .and_then(|produced| Python::with_gil(|py| produced.extract::<(PyObject, PyObject)>(py)))?;
+
+    // without a sentinel, the stream only ends when the consumer stops
+    let first =
+        Python::with_gil(|py| pyo3_asyncio::tokio::py_queue_to_stream(queue.as_ref(py), None))?
+            .take(2)
+            .collect::<Vec<_>>()
+            .await;
+
+    let rest = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::py_queue_to_stream(queue.as_ref(py), Some(py.None().into_ref(py)))
+    })?
+    .collect::<Vec<_>>()
+    .await;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let extract = |items: Vec<PyResult<PyObject>>| -> PyResult<Vec<i32>> {
+            items.into_iter().map(|item| item?.extract(py)).collect()
+        };
+
+        assert_eq!(extract(first)?, vec![0, 1]);
+        assert_eq!(extract(rest)?, vec![2, 3, 4]);
+        assert!(task.call_method0(py, "done")?.extract::<bool>(py)?);
+        Ok(())
+    })
+}
+
+const EVENT_CODE: &str = r#"
+import asyncio
+
+async def start():
+    ready = asyncio.Event()
+    done = asyncio.Event()
+
+    async def worker():
+        ready.set()
+        await done.wait()
+        return "finished"
+
+    return ready, done, asyncio.ensure_future(worker())
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_py_event() -> PyResult<()> {
+    let (ready, done, worker) = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future(
+            PyModule::from_code(py, EVENT_CODE, "event.py", "event")?.call_method0("start")?,
+        )
+    })?
+    .await
+    .and_then(|started| {
+        Python::with_gil(|py| started.extract::<(PyObject, PyObject, PyObject)>(py))
+    })?;
+
+    Python::with_gil(|py| pyo3_asyncio::tokio::sync::await_py_event(ready.as_ref(py)))?.await?;
+
+    // set the event from a thread that knows nothing about the event loop
+    let locals = Python::with_gil(pyo3_asyncio::tokio::get_current_locals)?;
+    std::thread::spawn(move || {
+        Python::with_gil(|py| {
+            pyo3_asyncio::set_py_event_threadsafe_with_locals(&locals, done.as_ref(py))
+        })
+    })
+    .join()
+    .unwrap()?;
+
+    let result =
+        Python::with_gil(|py| pyo3_asyncio::tokio::into_future(worker.as_ref(py)))?.await?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(result.extract::<&str>(py)?, "finished");
+
+        let not_an_event = py.import("threading")?.call_method0("Event")?;
+        let err = pyo3_asyncio::tokio::sync::await_py_event(not_an_event)
+            .err()
+            .unwrap();
+        assert!(err.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+        let err = pyo3_asyncio::tokio::sync::set_py_event_threadsafe(not_an_event).unwrap_err();
+        assert!(err.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+        Ok(())
+    })
+}
+
+#[pyclass]
+#[derive(Clone)]
+struct Point {
+    #[pyo3(get)]
+    x: i32,
+    #[pyo3(get)]
+    y: i32,
+}
+
+const ROUND_TRIP_CODE: &str = r#"
+async def round_trip(point, pair, mapping, point_cls):
+    point, pair, mapping = await point, await pair, await mapping
+
+    assert isinstance(point, point_cls), type(point)
+    assert (point.x, point.y) == (1, 2)
+    assert pair == ("answer", 42), pair
+    assert mapping == {"a": [1, 2], "b": []}, mapping
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_future_into_py_preserves_types() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let point = pyo3_asyncio::tokio::future_into_py(py, async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Ok(Point { x: 1, y: 2 })
+        })?;
+        let pair =
+            pyo3_asyncio::tokio::future_into_py(py, async move { Ok(("answer".to_string(), 42)) })?;
+        let mapping = pyo3_asyncio::tokio::future_into_py(py, async move {
+            Ok(std::collections::HashMap::from([
+                ("a".to_string(), vec![1, 2]),
+                ("b".to_string(), vec![]),
+            ]))
+        })?;
+
+        pyo3_asyncio::tokio::into_future(
+            PyModule::from_code(py, ROUND_TRIP_CODE, "round_trip.py", "round_trip")?
+                .getattr("round_trip")?
+                .call1((point, pair, mapping, py.get_type::<Point>()))?,
+        )
+    })?
+    .await?;
+
+    // the same goes for a value converted back into Rust
+    let point = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future(pyo3_asyncio::tokio::future_into_py(py, async move {
+            Ok(Point { x: 3, y: 4 })
+        })?)
+    })?
+    .await?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let point: Point = point.extract(py)?;
+        assert_eq!((point.x, point.y), (3, 4));
+        Ok(())
+    })
+}
+
+const DRAIN_CODE: &str = r#"
+async def worker(fut, cleaned_up):
+    try:
+        await fut
+    finally:
+        cleaned_up.append(True)
+"#;
+
+#[pyo3_asyncio::tokio::test]
+fn test_try_close_graceful() -> PyResult<()> {
+    let dropped = Arc::new(Mutex::new(false));
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let asyncio = py.import("asyncio")?;
+        let event_loop = asyncio.call_method0("new_event_loop")?;
+        let drain_mod = PyModule::from_code(py, DRAIN_CODE, "drain.py", "drain")?;
+
+        let flag = DropFlag(Arc::clone(&dropped));
+        let fut = pyo3_asyncio::tokio::future_into_py_with_locals(
+            py,
+            TaskLocals::new(event_loop),
+            async move {
+                let _flag = flag;
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok(())
+            },
+        )?;
+
+        let cleaned_up = PyList::empty(py);
+        let task = event_loop.call_method1(
+            "create_task",
+            (drain_mod.call_method1("worker", (fut, cleaned_up))?,),
+        )?;
+        event_loop.call_method1(
+            "run_until_complete",
+            (asyncio.call_method1("sleep", (0,))?,),
+        )?;
+
+        let start = std::time::Instant::now();
+        pyo3_asyncio::try_close_graceful(event_loop, Duration::from_secs(5))?;
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // the task was cancelled and ran its finally block before the event loop closed
+        assert!(task.call_method0("cancelled")?.is_true()?);
+        assert_eq!(cleaned_up.len(), 1);
+        assert!(event_loop.call_method0("is_closed")?.is_true()?);
+        Ok(())
+    })?;
+
+    // the Rust future awaited by the task is dropped too
+    for _ in 0..100 {
+        if *dropped.lock().unwrap() {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("the Rust future was not dropped");
+}
+
+#[pyfunction]
+fn named_sleep<'p>(py: Python<'p>, name: &str, secs: u64) -> PyResult<&'p PyAny> {
+    pyo3_asyncio::tokio::future_into_py_named(py, name, async move {
+        tokio::time::sleep(Duration::from_millis(secs)).await;
+        Ok(secs)
+    })
+}
+
+const NAMED_TASK_CODE: &str = r#"
+import asyncio
+
+async def main(named_sleep):
+    assert await named_sleep("short_sleep", 10) == 10
+
+    task = named_sleep("long_sleep", 60_000)
+    assert task in asyncio.all_tasks()
+    assert task.get_name() == "long_sleep"
+    assert "long_sleep" in repr(task)
+
+    task.cancel()
+    try:
+        await task
+    except asyncio.CancelledError:
+        pass
+    else:
+        raise AssertionError("expected CancelledError")
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_future_into_py_named() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let named_mod = PyModule::from_code(py, NAMED_TASK_CODE, "named.py", "named")?;
+
+        pyo3_asyncio::tokio::into_future(
+            named_mod.call_method1("main", (wrap_pyfunction!(named_sleep, py)?,))?,
+        )
+    })?
+    .await?;
+
+    // loop.create_task isn't thread-safe, so it can't be called from a tokio thread
+    Python::with_gil(|py| {
+        let err = pyo3_asyncio::tokio::future_into_py_named(py, "off_loop", async { Ok(()) })
+            .unwrap_err();
+        assert!(err.is_instance_of::<pyo3::exceptions::PyRuntimeError>(py));
+    });
+
+    Ok(())
+}
+
+#[pyfunction]
+fn on_same_thread<'p>(py: Python<'p>) -> PyResult<&'p PyAny> {
+    let thread = Rc::new(std::thread::current().id());
+
+    pyo3_asyncio::tokio::local_future_into_py(py, async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        Ok(std::thread::current().id() == *thread)
+    })
+}
+
+const CURRENT_THREAD_CODE: &str = r#"
+import asyncio
+
+async def main(on_same_thread):
+    results = await asyncio.gather(*(on_same_thread() for _ in range(10)))
+    assert all(results), results
+"#;
+
+#[pyo3_asyncio::tokio::test]
+fn test_run_current_thread() -> PyResult<()> {
+    let thread = std::thread::current().id();
+
+    Python::with_gil(|py| {
+        let value = pyo3_asyncio::tokio::run_current_thread(py, async move {
+            let non_send_thread = Rc::new(thread);
+
+            // local futures can be converted by Python code too
+            Python::with_gil(|py| {
+                let current_thread_mod = PyModule::from_code(
+                    py,
+                    CURRENT_THREAD_CODE,
+                    "current_thread.py",
+                    "current_thread",
+                )?;
+                pyo3_asyncio::tokio::into_future(
+                    current_thread_mod
+                        .call_method1("main", (wrap_pyfunction!(on_same_thread, py)?,))?,
+                )
+            })?
+            .await?;
+
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            assert_eq!(std::thread::current().id(), *non_send_thread);
+
+            // the runtime built for the call is entered, so tasks can be spawned on it
+            Ok(tokio::spawn(async { 42 }).await.unwrap())
+        })?;
+        assert_eq!(value, 42);
+
+        let err = pyo3_asyncio::tokio::run_current_thread::<_, ()>(py, async {
+            panic!("this panic was intentional!")
+        })
+        .unwrap_err();
+        assert!(err.is_instance_of::<pyo3_asyncio::err::RustPanic>(py));
+
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_future_into_py_with_handle() -> PyResult<()> {
+    let dropped = Arc::new(Mutex::new(false));
+    let flag = DropFlag(Arc::clone(&dropped));
+
+    let (py_future, handle) = Python::with_gil(|py| -> PyResult<_> {
+        let (py_future, handle) =
+            pyo3_asyncio::tokio::future_into_py_with_handle(py, async move {
+                let _flag = flag;
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok(())
+            })?;
+        Ok((PyObject::from(py_future), handle))
+    })?;
+
+    handle.abort();
+    for _ in 0..100 {
+        if *dropped.lock().unwrap() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(*dropped.lock().unwrap());
+
+    // aborting the Rust future leaves the awaitable pending until it is cancelled
+    let err = Python::with_gil(|py| -> PyResult<_> {
+        let py_future = py_future.as_ref(py);
+        assert!(!py_future.call_method0("done")?.is_true()?);
+
+        py_future
+            .call_method0("get_loop")?
+            .call_method1("call_soon_threadsafe", (py_future.getattr("cancel")?,))?;
+        pyo3_asyncio::tokio::into_future(py_future)
+    })?
+    .await
+    .unwrap_err();
+
+    Python::with_gil(|py| {
+        assert!(err.is_instance_of::<pyo3::exceptions::asyncio::CancelledError>(py));
+    });
+
+    Ok(())
+}
+
+const ASYNC_WITH_CODE: &str = r#"
+class Resource:
+    def __init__(self, suppress):
+        self.suppress = suppress
+        self.entered = 0
+        self.exited_with = []
+
+    def __aenter__(self):
+        self.entered += 1
+        return self.enter()
+
+    async def enter(self):
+        return "resource"
+
+    async def __aexit__(self, exc_type, exc, tb):
+        self.exited_with.append(exc_type and exc_type.__name__)
+        return self.suppress
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_async_with() -> PyResult<()> {
+    let (ok_cm, err_cm, suppress_cm) = Python::with_gil(|py| -> PyResult<_> {
+        let module = PyModule::from_code(py, ASYNC_WITH_CODE, "async_with.py", "async_with")?;
+        let resource = |suppress: bool| -> PyResult<PyObject> {
+            Ok(module.call_method1("Resource", (suppress,))?.into())
+        };
+        Ok((resource(false)?, resource(false)?, resource(true)?))
+    })?;
+
+    // the context manager isn't entered until the future is polled
+    Python::with_gil(|py| -> PyResult<()> {
+        drop(pyo3_asyncio::tokio::async_with(
+            ok_cm.as_ref(py),
+            |_| async move { Ok(()) },
+        )?);
+        assert_eq!(ok_cm.getattr(py, "entered")?.extract::<usize>(py)?, 0);
+        Ok(())
+    })?;
+
+    // the body gets the entered value and __aexit__ gets no exception
+    let value = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::async_with(ok_cm.as_ref(py), |value| async move {
+            Python::with_gil(|py| value.extract::<String>(py))
+        })
+    })?
+    .await?;
+    assert_eq!(value.as_deref(), Some("resource"));
+
+    // the exception raised by the body is passed to __aexit__ and propagated
+    let err = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::async_with(err_cm.as_ref(py), |_| async move {
+            Err::<(), _>(pyo3::exceptions::PyValueError::new_err("body failed"))
+        })
+    })?
+    .await
+    .unwrap_err();
+
+    // __aexit__ returning True suppresses the exception
+    let value = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::async_with(suppress_cm.as_ref(py), |_| async move {
+            Err::<(), _>(pyo3::exceptions::PyValueError::new_err("body failed"))
+        })
+    })?
+    .await?;
+    assert!(value.is_none());
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+
+        let exited_with = |cm: &PyObject| -> PyResult<Vec<Option<String>>> {
+            cm.as_ref(py).getattr("exited_with")?.extract()
+        };
+        assert_eq!(exited_with(&ok_cm)?, [None]);
+        assert_eq!(exited_with(&err_cm)?, [Some("ValueError".into())]);
+        assert_eq!(exited_with(&suppress_cm)?, [Some("ValueError".into())]);
+        Ok(())
+    })
+}
+
+const LOOP_EXIT_CODE: &str = r#"
+import sys
+
+def exit(code):
+    sys.exit(code)
+"#;
+
+#[pyo3_asyncio::tokio::test]
+fn test_run_forever_with_handler() -> PyResult<()> {
+    Python::with_gil(|py| -> PyResult<()> {
+        let exit = PyModule::from_code(py, LOOP_EXIT_CODE, "loop_exit.py", "loop_exit")?
+            .getattr("exit")?;
+        let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+        event_loop.call_method1("call_soon", (exit, 1))?;
+        event_loop.call_method1("call_soon", (exit, 2))?;
+
+        // the first exception restarts the event loop, the second one is propagated
+        let mut codes = Vec::new();
+        let err = pyo3_asyncio::run_forever_with_handler(event_loop, |py, e| {
+            codes.push(
+                e.value(py)
+                    .getattr("code")
+                    .unwrap()
+                    .extract::<i32>()
+                    .unwrap(),
+            );
+            if codes.len() < 2 {
+                pyo3_asyncio::LoopControl::Resume
+            } else {
+                pyo3_asyncio::LoopControl::Propagate(e)
+            }
+        })
+        .unwrap_err();
+
+        assert_eq!(codes, [1, 2]);
+        assert!(err.is_instance_of::<pyo3::exceptions::PySystemExit>(py));
+
+        event_loop.call_method0("close")?;
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+fn test_run_until_complete_with_cancel() -> PyResult<()> {
+    Python::with_gil(|py| -> PyResult<()> {
+        let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+        let token = pyo3_asyncio::CancellationToken::new();
+
+        // the token can be reused as long as it isn't cancelled
+        for i in 0..3 {
+            let result = pyo3_asyncio::tokio::run_until_complete_with_cancel(
+                event_loop,
+                async move { Ok(i) },
+                token.clone(),
+            )?;
+            assert_eq!(result, i);
+        }
+
+        // cancelling the token stops the running event loop and drops the future
+        let dropped = Arc::new(Mutex::new(false));
+        let flag = DropFlag(Arc::clone(&dropped));
+        let canceller = token.clone();
+        let cancel_thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            canceller.cancel();
+        });
+
+        let err = pyo3_asyncio::tokio::run_until_complete_with_cancel(
+            event_loop,
+            async move {
+                let _flag = flag;
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok(())
+            },
+            token,
+        )
+        .unwrap_err();
+        cancel_thread.join().unwrap();
+
+        assert!(err.is_instance_of::<pyo3::exceptions::asyncio::CancelledError>(py));
+        assert!(*dropped.lock().unwrap());
+
+        // the event loop can still be used
+        pyo3_asyncio::tokio::run_until_complete(event_loop, async { Ok(()) })?;
+        event_loop.call_method0("close")?;
+        Ok(())
+    })
+}
+
+struct DebugGuard;
+
+impl Drop for DebugGuard {
+    fn drop(&mut self) {
+        pyo3_asyncio::set_debug(false);
+    }
+}
+
+#[pyfunction]
+fn future_on_loop<'p>(py: Python<'p>, event_loop: &'p PyAny) -> PyResult<&'p PyAny> {
+    pyo3_asyncio::tokio::future_into_py_with_locals(py, TaskLocals::new(event_loop), async {
+        Ok(())
+    })
+}
+
+const WRONG_LOOP_CODE: &str = r#"
+import asyncio
+
+async def main(future_on_loop):
+    other_loop = asyncio.new_event_loop()
+    try:
+        future_on_loop(other_loop)
+    finally:
+        other_loop.close()
+"#;
+
+#[pyo3_asyncio::tokio::test]
+fn test_debug_wrong_loop() -> PyResult<()> {
+    pyo3_asyncio::set_debug(true);
+    let _guard = DebugGuard;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let test_mod = PyModule::from_code(py, WRONG_LOOP_CODE, "wrong_loop.py", "wrong_loop")?;
+        let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+
+        let err =
+            event_loop
+                .call_method1(
+                    "run_until_complete",
+                    (test_mod
+                        .call_method1("main", (wrap_pyfunction!(future_on_loop, test_mod)?,))?,),
+                )
+                .unwrap_err();
+        event_loop.call_method0("close")?;
+
+        assert!(err.is_instance_of::<pyo3::exceptions::PyRuntimeError>(py));
+        assert!(err.to_string().contains("different event loop"));
+        Ok(())
+    })
+}
+
+const ASYNC_GEN_CODE: &str = r#"
+import asyncio
+
+closed = False
+
+async def gen():
+    global closed
+    try:
+        for i in range(10):
+            await asyncio.sleep(0)
+            yield i
+    finally:
+        closed = True
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_into_stream_closes_on_drop() -> PyResult<()> {
+    let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+        Ok(PyModule::from_code(
+            py,
+            ASYNC_GEN_CODE,
+            "test_into_stream_closes_on_drop.py",
+            "test_into_stream_closes_on_drop",
+        )?
+        .into())
+    })?;
+
+    let mut stream = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_stream(test_mod.call_method0(py, "gen")?.as_ref(py))
+    })?;
+
+    let first = stream.next().await.unwrap()?;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(first.extract::<i32>(py)?, 0);
+        assert!(!test_mod.getattr(py, "closed")?.extract::<bool>(py)?);
+        Ok(())
+    })?;
+
+    drop(stream);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(test_mod.getattr(py, "closed")?.extract::<bool>(py)?);
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_raw_ensure_future_callback() -> PyResult<()> {
+    let rx = Python::with_gil(|py| -> PyResult<_> {
+        let locals = pyo3_asyncio::tokio::get_current_locals(py)?;
+        let (rx, callback) = pyo3_asyncio::raw::ensure_future_callback(
+            py.import("asyncio")?.call_method1("sleep", (0.01, 42))?,
+        )?;
+
+        locals
+            .event_loop(py)
+            .call_method1("call_soon_threadsafe", (callback,))?;
+        Ok(rx)
+    })?;
+
+    let result = rx.await.expect("the completion handler was dropped")?;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(result.extract::<i32>(py)?, 42);
+        Ok(())
+    })
+}
+
+const STREAM_CODE: &str = r#"
+async def collect(items):
+    collected = []
+    try:
+        async for item in items:
+            collected.append(item)
+    except ValueError as e:
+        collected.append(str(e))
+    return collected
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_stream_into_py() -> PyResult<()> {
+    let collect = |items: Vec<PyResult<i32>>| {
+        Python::with_gil(|py| {
+            let test_mod = PyModule::from_code(
+                py,
+                STREAM_CODE,
+                "test_stream_into_py.py",
+                "test_stream_into_py",
+            )?;
+            let stream = futures::stream::iter(items).then(|item| async move {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                item
+            });
+
+            pyo3_asyncio::tokio::into_future(test_mod.call_method1(
+                "collect",
+                (pyo3_asyncio::tokio::stream_into_py(py, stream)?,),
+            )?)
+        })
+    };
+
+    let collected = collect(vec![Ok(1), Ok(2), Ok(3)])?.await?;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(collected.extract::<Vec<i32>>(py)?, vec![1, 2, 3]);
+        Ok(())
+    })?;
+
+    let collected = collect(vec![])?.await?;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(collected.extract::<Vec<i32>>(py)?.is_empty());
+        Ok(())
+    })?;
+
+    let collected = collect(vec![
+        Ok(1),
+        Err(pyo3::exceptions::PyValueError::new_err("boom")),
+    ])?
+    .await?;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(collected.as_ref(py).str()?.to_str()?, "[1, 'boom']");
+        Ok(())
+    })
+}
+
+const ASYNC_GEN_EDGE_CODE: &str = r#"
+async def empty():
+    return
+    yield
+
+async def fails_after_one():
+    yield 1
+    raise ValueError("boom")
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_into_stream_empty_and_failing() -> PyResult<()> {
+    let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+        Ok(PyModule::from_code(
+            py,
+            ASYNC_GEN_EDGE_CODE,
+            "test_into_stream_edges.py",
+            "test_into_stream_edges",
+        )?
+        .into())
+    })?;
+
+    // StopAsyncIteration on the first __anext__ is an empty stream, not an error
+    let empty = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_stream(test_mod.call_method0(py, "empty")?.as_ref(py))
+    })?;
+    assert!(empty.collect::<Vec<_>>().await.is_empty());
+
+    let items = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_stream(test_mod.call_method0(py, "fails_after_one")?.as_ref(py))
+    })?
+    .collect::<Vec<_>>()
+    .await;
+
+    assert_eq!(items.len(), 2);
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(items[0].as_ref().unwrap().extract::<i32>(py)?, 1);
+        assert!(items[1]
+            .as_ref()
+            .unwrap_err()
+            .is_instance_of::<pyo3::exceptions::PyValueError>(py));
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+fn test_run_on_thread() -> PyResult<()> {
+    let handle = pyo3_asyncio::tokio::run_on_thread(async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        Ok(42)
+    });
+
+    assert_ne!(handle.thread().id(), std::thread::current().id());
+    assert_eq!(handle.join().unwrap()?, 42);
+
+    Ok(())
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_task_locals_same_loop() -> PyResult<()> {
+    let locals = Python::with_gil(pyo3_asyncio::tokio::get_current_locals)?;
+    let scoped = pyo3_asyncio::tokio::scope(locals.clone(), async move {
+        Python::with_gil(pyo3_asyncio::tokio::get_current_locals)
+    })
+    .await?;
+
+    assert!(locals.same_loop(&scoped));
+    assert_eq!(locals, scoped);
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let other = TaskLocals::new(py.import("asyncio")?.call_method0("new_event_loop")?);
+        assert!(!locals.same_loop(&other));
+        assert!(format!("{:?}", other).contains("has_context: false"));
+        other.event_loop(py).call_method0("close")?;
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_into_future_with_heartbeat() -> PyResult<()> {
+    let beats = Arc::new(Mutex::new(0));
+
+    let beats_ref = Arc::clone(&beats);
+    Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future_with_heartbeat(
+            py.import("asyncio")?.call_method1("sleep", (0.35,))?,
+            Duration::from_millis(100),
+            move || *beats_ref.lock().unwrap() += 1,
+        )
+    })?
+    .await?;
+
+    // the exact number of beats depends on the scheduling of the timers, so only check that some
+    // were received while the awaitable was pending
+    let received = *beats.lock().unwrap();
+    assert!(received >= 1);
+
+    // no more beats after the awaitable completes
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(*beats.lock().unwrap(), received);
+
+    Ok(())
+}
+
+const COLLECT_CODE: &str = r#"
+async def collect(it):
+    return [x async for x in it]
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_buffer_unordered_into_py() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        let jobs = futures::stream::iter(0..4u64).map(|i| async move {
+            tokio::time::sleep(Duration::from_millis((4 - i) * 50)).await;
+            Ok(i)
+        });
+        let iter = pyo3_asyncio::tokio::buffer_unordered_into_py(py, jobs, 4)?;
+
+        let test_mod = PyModule::from_code(
+            py,
+            COLLECT_CODE,
+            "test_buffer_unordered_into_py.py",
+            "test_buffer_unordered_into_py",
+        )?;
+        pyo3_asyncio::tokio::into_future(test_mod.call_method1("collect", (iter,))?)
+    })?;
+
+    let results = fut.await?;
+    Python::with_gil(|py| -> PyResult<()> {
+        // completion order, not stream order
+        assert_eq!(results.extract::<Vec<u64>>(py)?, vec![3, 2, 1, 0]);
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+fn test_future_into_py_deferred() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+
+        // created outside of any running event loop
+        let deferred = pyo3_asyncio::tokio::future_into_py_deferred(py, async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Ok(42)
+        })?;
+
+        let result = event_loop.call_method1("run_until_complete", (deferred,))?;
+        assert_eq!(result.extract::<i32>()?, 42);
+
+        // only the first await schedules the future
+        let err = event_loop
+            .call_method1("run_until_complete", (deferred,))
+            .unwrap_err();
+        assert!(err.is_instance_of::<pyo3::exceptions::PyRuntimeError>(py));
+
+        event_loop.call_method0("close")?;
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_into_stream_aclose_from_python() -> PyResult<()> {
+    let (test_mod, gen) = Python::with_gil(|py| -> PyResult<(PyObject, PyObject)> {
+        let test_mod = PyModule::from_code(
+            py,
+            ASYNC_GEN_CODE,
+            "test_into_stream_aclose_from_python.py",
+            "test_into_stream_aclose_from_python",
+        )?;
+        let gen = test_mod.call_method0("gen")?;
+
+        Ok((test_mod.into(), gen.into()))
+    })?;
+
+    let mut stream = Python::with_gil(|py| pyo3_asyncio::tokio::into_stream(gen.as_ref(py)))?;
+    assert!(stream.next().await.unwrap().is_ok());
+
+    Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future(gen.as_ref(py).call_method0("aclose")?)
+    })?
+    .await?;
+
+    // closing the generator ends the stream instead of raising an error
+    assert!(stream.next().await.is_none());
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(test_mod.getattr(py, "closed")?.extract::<bool>(py)?);
+        Ok(())
+    })
+}
+
+const ACLOSE_CODE: &str = r#"
+async def consume_and_close(it):
+    first = await it.__anext__()
+    await it.aclose()
+    return first, [x async for x in it]
+"#;
+
+struct DropFlag(Arc<Mutex<bool>>);
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        *self.0.lock().unwrap() = true;
+    }
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_buffer_unordered_into_py_aclose() -> PyResult<()> {
+    let dropped = Arc::new(Mutex::new(false));
+
+    let flag = DropFlag(Arc::clone(&dropped));
+    let fut = Python::with_gil(|py| {
+        let jobs = futures::stream::iter(0..5u64).map(move |i| {
+            let _ = &flag;
+            async move { Ok(i) }
+        });
+        let iter = pyo3_asyncio::tokio::buffer_unordered_into_py(py, jobs, 2)?;
+
+        let test_mod = PyModule::from_code(
+            py,
+            ACLOSE_CODE,
+            "test_buffer_unordered_into_py_aclose.py",
+            "test_buffer_unordered_into_py_aclose",
+        )?;
+        pyo3_asyncio::tokio::into_future(test_mod.call_method1("consume_and_close", (iter,))?)
+    })?;
+
+    let result = fut.await?;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(result.extract::<(u64, Vec<u64>)>(py)?, (0, vec![]));
+        Ok(())
+    })?;
+    assert!(*dropped.lock().unwrap());
+
+    Ok(())
+}
+
+const LOOP_THREAD_CODE: &str = r#"
+import threading
+
+async def await_on_loop(fut):
+    try:
+        result = await fut
+    except ValueError as e:
+        result = str(e)
+    return result, threading.get_ident()
+"#;
+
+#[pyo3_asyncio::tokio::test]
+fn test_local_future_into_py_cross_thread(event_loop: PyObject) -> PyResult<()> {
+    tokio::task::LocalSet::new().block_on(pyo3_asyncio::tokio::get_runtime(), async {
+        let locals = Python::with_gil(|py| TaskLocals::new(event_loop.as_ref(py)));
+
+        for fails in [false, true] {
+            let fut = Python::with_gil(|py| {
+                // !Send state produced on the LocalSet thread
+                let value = Rc::new(42);
+                let py_future = pyo3_asyncio::tokio::local_future_into_py_with_locals(
+                    py,
+                    locals.clone(),
+                    async move {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        if fails {
+                            Err(pyo3::exceptions::PyValueError::new_err("local failure"))
+                        } else {
+                            Ok(*value)
+                        }
+                    },
+                )?;
+
+                let test_mod = PyModule::from_code(
+                    py,
+                    LOOP_THREAD_CODE,
+                    "test_local_future_into_py_cross_thread.py",
+                    "test_local_future_into_py_cross_thread",
+                )?;
+                pyo3_asyncio::into_future_with_locals(
+                    &locals,
+                    test_mod.call_method1("await_on_loop", (py_future,))?,
+                )
+            })?;
+
+            let result = fut.await?;
+            Python::with_gil(|py| -> PyResult<()> {
+                let (value, loop_thread): (PyObject, u64) = result.extract(py)?;
+                let this_thread: u64 = py
+                    .import("threading")?
+                    .call_method0("get_ident")?
+                    .extract()?;
+
+                // resolved on the event loop's thread, not the LocalSet's
+                assert_ne!(loop_thread, this_thread);
+                if fails {
+                    assert_eq!(value.extract::<String>(py)?, "local failure");
+                } else {
+                    assert_eq!(value.extract::<i32>(py)?, 42);
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_into_future_abort() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future(py.import("asyncio")?.call_method1("sleep", (10,))?)
+    })?;
+    assert!(!fut.is_finished());
+
+    fut.abort();
+
+    let err = fut.await.unwrap_err();
+    Python::with_gil(|py| {
+        assert!(err.is_instance_of::<pyo3::exceptions::asyncio::CancelledError>(py));
+    });
+
+    let fut = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future(py.import("asyncio")?.call_method1("sleep", (0,))?)
+    })?;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(fut.is_finished());
+
+    // aborting a finished task does nothing
+    fut.abort();
+    fut.await?;
+
+    Ok(())
+}
+
+#[pyo3_asyncio::tokio::test]
+fn test_register_cleanup() -> PyResult<()> {
+    let cleaned_up = Arc::new(Mutex::new(Vec::new()));
+
+    for i in 0..3 {
+        let cleaned_up = Arc::clone(&cleaned_up);
+        pyo3_asyncio::register_cleanup(move |_py| cleaned_up.lock().unwrap().push(i));
+    }
+
+    pyo3_asyncio::tokio::run_on_thread(async move { Ok(()) })
+        .join()
+        .unwrap()?;
+
+    // cleanups run once, in registration order
+    assert_eq!(*cleaned_up.lock().unwrap(), vec![0, 1, 2]);
+
+    Ok(())
+}
+
+#[pyfunction]
+fn delayed_name<'p>(py: Python<'p>, config: &'p pyo3::types::PyDict) -> PyResult<&'p PyAny> {
+    pyo3_asyncio::tokio::future_into_py_borrowed(py, |_py| {
+        let delay: u64 = config.get_item("delay").unwrap().extract()?;
+        let name: String = config.get_item("name").unwrap().extract()?;
+
+        Ok(async move {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            Ok(name)
+        })
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_future_into_py_borrowed() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        let delayed_name = wrap_pyfunction!(delayed_name, py)?;
+
+        // extraction errors are raised before any awaitable is created
+        let err = delayed_name
+            .call1(([("delay", "soon"), ("name", "x")].into_py_dict(py),))
+            .unwrap_err();
+        assert!(err.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+
+        let config = pyo3::types::PyDict::new(py);
+        config.set_item("delay", 10)?;
+        config.set_item("name", "borrowed")?;
+        pyo3_asyncio::tokio::into_future(delayed_name.call1((config,))?)
+    })?;
+
+    let name = fut.await?;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(name.extract::<String>(py)?, "borrowed");
+        Ok(())
+    })
+}
+
+const FLAKY_CODE: &str = r#"
+attempts = 0
+
+async def flaky(fail_times):
+    global attempts
+    attempts += 1
+    if attempts <= fail_times:
+        raise ValueError(f"attempt {attempts} failed")
+    return attempts
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_supervise() -> PyResult<()> {
+    let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+        Ok(PyModule::from_code(py, FLAKY_CODE, "test_supervise.py", "test_supervise")?.into())
+    })?;
+    let policy = pyo3_asyncio::RestartPolicy::new(2)
+        .with_backoff(Duration::from_millis(10), Duration::from_millis(100));
+
+    // succeeds on the last allowed restart
+    let flaky_mod = test_mod.clone();
+    let result = pyo3_asyncio::tokio::supervise(
+        move |py| flaky_mod.call_method1(py, "flaky", (2,)),
+        policy.clone(),
+    )
+    .await?;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(result.extract::<u32>(py)?, 3);
+        test_mod.setattr(py, "attempts", 0)
+    })?;
+
+    // gives up with the last error
+    let flaky_mod = test_mod.clone();
+    let err = pyo3_asyncio::tokio::supervise(
+        move |py| flaky_mod.call_method1(py, "flaky", (10,)),
+        policy,
+    )
+    .await
+    .unwrap_err();
+    Python::with_gil(|py| {
+        assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+        assert_eq!(err.value(py).to_string(), "attempt 3 failed");
+    });
+
+    Ok(())
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_loop_exception_stream() -> PyResult<()> {
+    let (seen, event_loop, mut exceptions) = Python::with_gil(|py| -> PyResult<_> {
+        let seen = PyList::empty(py);
+        let event_loop = pyo3_asyncio::tokio::get_current_loop(py)?;
+
+        let record = py
+            .eval(
+                "lambda seen: lambda loop, context: seen.append(context['message'])",
+                None,
+                None,
+            )?
+            .call1((seen,))?;
+        event_loop.call_method1("set_exception_handler", (record,))?;
+
+        let exceptions = pyo3_asyncio::tokio::loop_exception_stream(py)?;
+        event_loop.call_method1(
+            "call_exception_handler",
+            ([("message", "boom")].into_py_dict(py),),
+        )?;
+
+        Ok((PyObject::from(seen), PyObject::from(event_loop), exceptions))
+    })?;
+
+    let context = exceptions.next().await.unwrap();
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(
+            context.as_ref(py).get_item("message")?.extract::<&str>()?,
+            "boom"
+        );
+        // the previous handler still receives the exception
+        assert_eq!(seen.extract::<Vec<String>>(py)?, vec!["boom".to_string()]);
+
+        event_loop.call_method1(py, "set_exception_handler", (py.None(),))?;
+        Ok(())
+    })
+}
+
+const CONTEXT_CODE: &str = r#"
+import contextvars
+
+request_id = contextvars.ContextVar("request_id")
+
+async def handle(value):
+    seen = request_id.get(None)
+    request_id.set(value)
+    return seen
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_into_future_in_context() -> PyResult<()> {
+    let (test_mod, context) = Python::with_gil(|py| -> PyResult<_> {
+        let test_mod = PyModule::from_code(py, CONTEXT_CODE, "test_context.py", "test_context")?;
+        let context = py.import("contextvars")?.call_method0("Context")?;
+        context.call_method1(
+            "run",
+            (test_mod.getattr("request_id")?.getattr("set")?, "a"),
+        )?;
+
+        Ok((PyObject::from(test_mod), PyObject::from(context)))
+    })?;
+
+    let seen = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future_in_context(
+            test_mod.call_method1(py, "handle", ("b",))?.as_ref(py),
+            context.as_ref(py),
+        )
+    })?
+    .await?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(seen.extract::<&str>(py)?, "a");
+
+        let request_id = test_mod.getattr(py, "request_id")?;
+        let expected = if py.version_info() >= (3, 11) {
+            "b"
+        } else {
+            "a"
+        };
+        assert_eq!(
+            context
+                .as_ref(py)
+                .get_item(request_id.as_ref(py))?
+                .extract::<&str>()?,
+            expected
+        );
+        assert!(request_id
+            .call_method1(py, "get", (py.None(),))?
+            .is_none(py));
+
+        Ok(())
+    })
+}
+
+#[pyclass]
+struct DelayedAnswer {
+    #[pyo3(get)]
+    value: i32,
+}
+
+#[pymethods]
+impl DelayedAnswer {
+    fn __await__<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let value = self.value;
+        pyo3_asyncio::tokio::future_into_await(py, async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Ok(value)
+        })
+    }
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_future_into_await() -> PyResult<()> {
+    let result = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code(
+            py,
+            "async def twice(answer):\n    return answer.value + await answer + await answer\n",
+            "test_future_into_await.py",
+            "test_future_into_await",
+        )?;
+        let answer = PyCell::new(py, DelayedAnswer { value: 14 })?;
+
+        pyo3_asyncio::tokio::into_future(test_mod.call_method1("twice", (answer,))?)
+    })?
+    .await?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(result.extract::<i32>(py)?, 42);
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_future_into_py_portable() -> PyResult<()> {
+    let (answer, failure) = Python::with_gil(|py| -> PyResult<_> {
+        let answer = pyo3_asyncio::tokio::future_into_py_portable(py, async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Ok(42)
+        })?;
+        let failure = pyo3_asyncio::tokio::future_into_py_portable::<_, ()>(py, async {
+            Err(pyo3::exceptions::PyValueError::new_err("boom"))
+        })?;
+
+        Ok((PyObject::from(answer), PyObject::from(failure)))
+    })?;
+
+    // like an asyncio.Future, it can be awaited more than once
+    for _ in 0..2 {
+        let result =
+            Python::with_gil(|py| pyo3_asyncio::tokio::into_future(answer.as_ref(py)))?.await?;
+        Python::with_gil(|py| -> PyResult<()> {
+            assert_eq!(result.extract::<i32>(py)?, 42);
+            Ok(())
+        })?;
+    }
+
+    let err = Python::with_gil(|py| pyo3_asyncio::tokio::into_future(failure.as_ref(py)))?
+        .await
+        .unwrap_err();
+    Python::with_gil(|py| {
+        assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+    });
+
+    Ok(())
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_oneshot_into_py() -> PyResult<()> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let (dropped_tx, dropped_rx) = tokio::sync::oneshot::channel::<PyResult<u64>>();
+
+    let (answer, dropped) = Python::with_gil(|py| -> PyResult<_> {
+        Ok((
+            pyo3_asyncio::tokio::into_future(pyo3_asyncio::tokio::oneshot_into_py(py, rx)?)?,
+            pyo3_asyncio::tokio::into_future(pyo3_asyncio::tokio::oneshot_into_py(
+                py, dropped_rx,
+            )?)?,
+        ))
+    })?;
+
+    tx.send(Ok(42u64)).unwrap();
+    drop(dropped_tx);
+
+    let answer = answer.await?;
+    let err = dropped.await.unwrap_err();
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(answer.extract::<u64>(py)?, 42);
+        assert!(err.is_instance_of::<pyo3::exceptions::asyncio::CancelledError>(py));
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_into_future_finally() -> PyResult<()> {
+    let outcomes = Arc::new(Mutex::new(Vec::new()));
+
+    let record = |outcomes: &Arc<Mutex<Vec<String>>>| {
+        let outcomes = Arc::clone(outcomes);
+        move |py: Python, result: &PyResult<PyObject>| {
+            outcomes.lock().unwrap().push(match result {
+                Ok(val) => val.as_ref(py).to_string(),
+                Err(e) => e.get_type(py).name().unwrap().to_string(),
+            });
+        }
+    };
+
+    let (completes, fails, dropped) = Python::with_gil(|py| -> PyResult<_> {
+        let asyncio = py.import("asyncio")?;
+        let fails = PyModule::from_code(
+            py,
+            "async def fail():\n    raise ValueError('boom')\n",
+            "test_finally.py",
+            "test_finally",
+        )?;
+
+        Ok((
+            pyo3_asyncio::tokio::into_future_finally(
+                asyncio.call_method1("sleep", (0, 42))?,
+                record(&outcomes),
+            )?,
+            pyo3_asyncio::tokio::into_future_finally(
+                fails.call_method0("fail")?,
+                record(&outcomes),
+            )?,
+            pyo3_asyncio::tokio::into_future_finally(
+                asyncio.call_method1("sleep", (0.1,))?,
+                record(&outcomes),
+            )?,
+        ))
+    })?;
+
+    completes.await?;
+    assert!(fails.await.is_err());
+    drop(dropped);
+
+    assert_eq!(
+        *outcomes.lock().unwrap(),
+        vec!["42", "ValueError", "CancelledError"]
+    );
+
+    Ok(())
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_rust_awaitables_cannot_be_pickled() -> PyResult<()> {
+    Python::with_gil(|py| -> PyResult<()> {
+        let pickle = py.import("pickle")?;
+        let awaitables = [
+            pyo3_asyncio::tokio::future_into_py_portable(py, async { Ok(()) })?,
+            pyo3_asyncio::tokio::future_into_py_deferred(py, async { Ok(()) })?,
+        ];
+
+        for awaitable in awaitables.iter() {
+            let err = pickle.call_method1("dumps", (*awaitable,)).unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+            assert_eq!(
+                err.value(py).to_string(),
+                "Rust-backed awaitables cannot be pickled"
+            );
+        }
+
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_into_future_timed() -> PyResult<()> {
+    let (value, elapsed) = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future_timed(
+            py.import("asyncio")?.call_method1("sleep", (0.1, 42))?,
+        )
+    })?
+    .await?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(value.extract::<i32>(py)?, 42);
+        Ok(())
+    })?;
+    assert!(elapsed >= Duration::from_millis(100));
+
+    Ok(())
+}
+
+const AGEN_CODE: &str = r#"
+async def running_total():
+    total = 0
+    while True:
+        try:
+            value = yield total
+        except ValueError:
+            yield -1
+            continue
+        except KeyError:
+            return
+        total += value
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_agen_send_throw() -> PyResult<()> {
+    let agen: PyObject = Python::with_gil(|py| -> PyResult<_> {
+        let module = PyModule::from_code(py, AGEN_CODE, "test_agen.py", "test_agen")?;
+        Ok(module.call_method0("running_total")?.into())
+    })?;
+
+    let send = |value: Option<i64>| {
+        Python::with_gil(|py| {
+            pyo3_asyncio::tokio::agen_send(agen.as_ref(py), value.into_py(py).as_ref(py))
+        })
+    };
+    let throw =
+        |exc: PyErr| Python::with_gil(|py| pyo3_asyncio::tokio::agen_throw(agen.as_ref(py), exc));
+    let extract = |item: Option<PyObject>| {
+        Python::with_gil(|py| item.map(|item| item.extract::<i64>(py)).transpose())
+    };
+
+    assert_eq!(extract(send(None)?.await?)?, Some(0));
+    assert_eq!(extract(send(Some(2))?.await?)?, Some(2));
+    assert_eq!(extract(send(Some(3))?.await?)?, Some(5));
+
+    // handled by the generator
+    assert_eq!(
+        extract(throw(pyo3::exceptions::PyValueError::new_err("reset"))?.await?)?,
+        Some(-1)
+    );
+    assert_eq!(extract(send(None)?.await?)?, Some(5));
+
+    // the generator returns, so it's finished from now on
+    assert_eq!(
+        extract(throw(pyo3::exceptions::PyKeyError::new_err("stop"))?.await?)?,
+        None
+    );
+    assert_eq!(extract(send(None)?.await?)?, None);
+
+    // unhandled exceptions are propagated
+    let agen: PyObject = Python::with_gil(|py| -> PyResult<_> {
+        let module = PyModule::from_code(py, AGEN_CODE, "test_agen.py", "test_agen")?;
+        Ok(module.call_method0("running_total")?.into())
+    })?;
+    Python::with_gil(|py| pyo3_asyncio::tokio::agen_send(agen.as_ref(py), py.None().as_ref(py)))?
+        .await?;
+
+    let err = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::agen_throw(
+            agen.as_ref(py),
+            pyo3::exceptions::PyTypeError::new_err("unexpected"),
+        )
+    })?
+    .await
+    .unwrap_err();
+    Python::with_gil(|py| assert!(err.is_instance_of::<pyo3::exceptions::PyTypeError>(py)));
+
+    Ok(())
+}
+
+const SINK_CODE: &str = r#"
+import asyncio
+
+async def consume(queue):
+    assert isinstance(queue, asyncio.Queue)
+    items = []
+    try:
+        async for item in queue:
+            items.append(item)
+    except ValueError as e:
+        items.append(str(e))
+    return items
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_future_into_py_with_sink() -> PyResult<()> {
+    let produce = |fail: bool| {
+        Python::with_gil(|py| -> PyResult<_> {
+            let test_mod = PyModule::from_code(py, SINK_CODE, "test_sink.py", "test_sink")?;
+            let queue =
+                pyo3_asyncio::tokio::future_into_py_with_sink(py, move |sink| async move {
+                    for i in 0..3 {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        sink.send(i)?;
+                    }
+                    if fail {
+                        return Err(pyo3::exceptions::PyValueError::new_err("boom"));
+                    }
+                    Ok(())
+                })?;
+
+            pyo3_asyncio::tokio::into_future(test_mod.call_method1("consume", (queue,))?)
+        })
+    };
+
+    let items = produce(false)?.await?;
+    let failed = produce(true)?.await?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(items.extract::<Vec<u32>>(py)?, vec![0, 1, 2]);
+        assert_eq!(failed.as_ref(py).str()?.to_str()?, "[0, 1, 2, 'boom']");
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_check_gil_released() -> PyResult<()> {
+    // a !Send future can keep the GIL across an await point without a compile error
+    let result = std::panic::catch_unwind(|| {
+        let holds_gil = pyo3_asyncio::check_gil_released(async {
+            let gil = Python::acquire_gil();
+            tokio::task::yield_now().await;
+            drop(gil);
+        });
+        futures::FutureExt::now_or_never(Box::pin(holds_gil))
+    });
+    assert!(result.is_err());
+
+    // releasing the GIL before awaiting passes the check
+    pyo3_asyncio::check_gil_released(async {
+        Python::with_gil(|py| py.None());
+        tokio::task::yield_now().await;
+        Python::with_gil(|py| py.None());
+    })
+    .await;
+
+    Ok(())
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_peek_with_timeout() -> PyResult<()> {
+    let slow = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::peek_with_timeout(
+            py.import("asyncio")?.call_method1("sleep", (0.1, "done"))?,
+            Duration::from_millis(10),
+        )
+    })?
+    .await;
+
+    // the deadline doesn't cancel the task, so it can still be awaited
+    let fut = match slow {
+        pyo3_asyncio::Peek::Pending(fut) => fut,
+        pyo3_asyncio::Peek::Ready(_) => panic!("expected the awaitable to be pending"),
+    };
+    assert!(!fut.is_finished());
+
+    let result = match fut.peek(tokio::time::sleep(Duration::from_secs(5))).await {
+        pyo3_asyncio::Peek::Ready(result) => result?,
+        pyo3_asyncio::Peek::Pending(_) => panic!("expected the awaitable to be ready"),
+    };
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(result.extract::<&str>(py)?, "done");
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_reset_globals() -> PyResult<()> {
+    for _ in 0..2 {
+        pyo3_asyncio::testing::reset_globals();
+
+        let result = Python::with_gil(|py| {
+            pyo3_asyncio::tokio::into_future(
+                py.import("asyncio")?
+                    .call_method1("sleep", (0, "cached again"))?,
+            )
+        })?
+        .await?;
+
+        Python::with_gil(|py| -> PyResult<()> {
+            assert_eq!(result.extract::<&str>(py)?, "cached again");
+            Ok(())
+        })?;
+    }
+
+    Ok(())
+}
+
+const TIMEOUT_CODE: &str = r#"
+import asyncio
+
+cleaned_up = False
+
+async def slow():
+    global cleaned_up
+    try:
+        await asyncio.sleep(10)
+    finally:
+        cleaned_up = True
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_into_future_with_asyncio_timeout() -> PyResult<()> {
+    let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+        Ok(PyModule::from_code(py, TIMEOUT_CODE, "test_timeout.py", "test_timeout")?.into())
+    })?;
+
+    let err = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future_with_asyncio_timeout(
+            test_mod.call_method0(py, "slow")?.as_ref(py),
+            Duration::from_millis(10),
+        )
+    })?
+    .await
+    .unwrap_err();
+
+    let fast = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future_with_asyncio_timeout(
+            py.import("asyncio")?.call_method1("sleep", (0, "fast"))?,
+            Duration::from_secs(5),
+        )
+    })?
+    .await?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let timeout_error = py.import("asyncio")?.getattr("TimeoutError")?;
+        assert!(err.matches(py, timeout_error));
+        assert!(test_mod.getattr(py, "cleaned_up")?.extract::<bool>(py)?);
+        assert_eq!(fast.extract::<&str>(py)?, "fast");
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_into_future_with_timeout() -> PyResult<()> {
+    let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+        Ok(PyModule::from_code(
+            py,
+            TIMEOUT_CODE,
+            "test_rust_timeout.py",
+            "test_rust_timeout",
+        )?
+        .into())
+    })?;
+
+    let err = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future_with_timeout(
+            test_mod.call_method0(py, "slow")?.as_ref(py),
+            Duration::from_millis(10),
+        )
+    })?
+    .await
+    .unwrap_err();
+
+    // the task was cancelled, but it unwinds on the event loop
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let fast = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future_with_timeout(
+            py.import("asyncio")?.call_method1("sleep", (0, "fast"))?,
+            Duration::from_secs(5),
+        )
+    })?
+    .await?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let timeout_error = py.import("asyncio")?.getattr("TimeoutError")?;
+        assert!(err.matches(py, timeout_error));
+        assert!(test_mod.getattr(py, "cleaned_up")?.extract::<bool>(py)?);
+        assert_eq!(fast.extract::<&str>(py)?, "fast");
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_into_future_or_timeout() -> PyResult<()> {
+    let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+        Ok(PyModule::from_code(
+            py,
+            TIMEOUT_CODE,
+            "test_rust_or_timeout.py",
+            "test_rust_or_timeout",
+        )?
+        .into())
+    })?;
+
+    let timed_out = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future_or_timeout(
+            test_mod.call_method0(py, "slow")?.as_ref(py),
+            Duration::from_millis(10),
+        )
+    })?
+    .await?;
+
+    // the task was cancelled, but it unwinds on the event loop
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let fast = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future_or_timeout(
+            py.import("asyncio")?.call_method1("sleep", (0, "fast"))?,
+            Duration::from_secs(5),
+        )
+    })?
+    .await?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(timed_out.is_none());
+        assert!(test_mod.getattr(py, "cleaned_up")?.extract::<bool>(py)?);
+        assert_eq!(fast.unwrap().extract::<&str>(py)?, "fast");
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_into_future_cancels_on_drop() -> PyResult<()> {
+    let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+        Ok(PyModule::from_code(py, TIMEOUT_CODE, "test_drop.py", "test_drop")?.into())
+    })?;
+
+    let fut = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future(test_mod.call_method0(py, "slow")?.as_ref(py))
+    })?;
+
+    // a Rust side timeout drops the future without awaiting it to completion
+    assert!(tokio::time::timeout(Duration::from_millis(10), fut)
+        .await
+        .is_err());
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(test_mod.getattr(py, "cleaned_up")?.extract::<bool>(py)?);
+        Ok(())
+    })
+}
+
+const SHARED_TASK_CODE: &str = r#"
+import asyncio
+
+async def shared():
+    await asyncio.sleep(0.1)
+    return "shared"
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_observe_task() -> PyResult<()> {
+    let task = Python::with_gil(|py| -> PyResult<PyObject> {
+        let test_mod = PyModule::from_code(
+            py,
+            SHARED_TASK_CODE,
+            "test_observe_task.py",
+            "test_observe_task",
+        )?;
+        Ok(pyo3_asyncio::tokio::get_current_loop(py)?
+            .call_method1("create_task", (test_mod.call_method0("shared")?,))?
+            .into())
+    })?;
+
+    // an observer giving up early must not cancel the task for the others
+    let impatient = Python::with_gil(|py| pyo3_asyncio::observe_task(task.as_ref(py)))?;
+    assert!(tokio::time::timeout(Duration::from_millis(10), impatient)
+        .await
+        .is_err());
+
+    let result = Python::with_gil(|py| pyo3_asyncio::observe_task(task.as_ref(py)))?.await?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(result.extract::<&str>(py)?, "shared");
+        assert!(!task.call_method0(py, "cancelled")?.is_true(py)?);
+
+        let err = pyo3_asyncio::observe_task(py.None().as_ref(py))
+            .map(|_| ())
+            .unwrap_err();
+        assert!(err.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+        Ok(())
+    })
+}
+
+const ITER_CODE: &str = r#"
+import asyncio
+
+async def consume(it):
+    passes = 0
+
+    async def count_passes():
+        nonlocal passes
+        while True:
+            await asyncio.sleep(0)
+            passes += 1
+
+    counter = asyncio.ensure_future(count_passes())
+    await asyncio.sleep(0)
+    start = passes
+
+    items = []
+    try:
+        async for item in it:
+            items.append(item)
+    except ValueError as e:
+        items.append(str(e))
+
+    counter.cancel()
+    return items, passes - start
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_iter_into_py_async() -> PyResult<()> {
+    fn items(py: Python) -> Vec<PyResult<PyObject>> {
+        (0..6)
+            .map(|i| Ok(i.into_py(py)))
+            .chain(Some(Err(pyo3::exceptions::PyValueError::new_err("boom"))))
+            .collect()
+    }
+
+    let (eager, yielding) = Python::with_gil(|py| -> PyResult<_> {
+        let test_mod = PyModule::from_code(py, ITER_CODE, "test_iter.py", "test_iter")?;
+
+        let eager = pyo3_asyncio::tokio::into_future(test_mod.call_method1(
+            "consume",
+            (pyo3_asyncio::iter_into_py_async(py, items(py))?,),
+        )?)?;
+        let yielding = pyo3_asyncio::tokio::into_future(test_mod.call_method1(
+            "consume",
+            (pyo3_asyncio::iter_into_py_async_with_yield(
+                py,
+                items(py),
+                2,
+            )?,),
+        )?)?;
+
+        Ok((eager, yielding))
+    })?;
+    let (eager, yielding) = (eager.await?, yielding.await?);
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let (items, passes): (Vec<PyObject>, usize) = eager.extract(py)?;
+        assert_eq!(items.len(), 7);
+        assert_eq!(items[6].extract::<&str>(py)?, "boom");
+        // the event loop never ran while the iterator was consumed
+        assert_eq!(passes, 0);
+
+        let (items, passes): (Vec<PyObject>, usize) = yielding.extract(py)?;
+        assert_eq!(items.len(), 7);
+        assert_eq!(passes, 3);
+        Ok(())
+    })
+}
+
+const AS_COMPLETED_CODE: &str = r#"
+import asyncio
+
+async def delayed(secs, value):
+    await asyncio.sleep(secs)
+    if isinstance(value, Exception):
+        raise value
+    return value
+
+def awaitables():
+    return [delayed(0.3, 3), delayed(0.1, 1), delayed(0.2, ValueError("boom"))]
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_as_completed_stream() -> PyResult<()> {
+    let results = Python::with_gil(|py| -> PyResult<_> {
+        let test_mod = PyModule::from_code(
+            py,
+            AS_COMPLETED_CODE,
+            "test_as_completed.py",
+            "test_as_completed",
+        )?;
+
+        assert!(pyo3_asyncio::tokio::as_completed_stream(PyList::new(py, [1])).is_err());
+        pyo3_asyncio::tokio::as_completed_stream(test_mod.call_method0("awaitables")?)
+    })?
+    .collect::<Vec<_>>()
+    .await;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().extract::<i32>(py)?, 1);
+        assert!(results[1]
+            .as_ref()
+            .unwrap_err()
+            .is_instance_of::<pyo3::exceptions::PyValueError>(py));
+        assert_eq!(results[2].as_ref().unwrap().extract::<i32>(py)?, 3);
+        Ok(())
+    })
+}
+
+const EXIT_STACK_CODE: &str = r#"
+import contextlib
+
+log = []
+
+@contextlib.asynccontextmanager
+async def resource(name):
+    log.append("enter " + name)
+    try:
+        yield name
+    finally:
+        log.append("exit " + name)
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_async_exit_stack() -> PyResult<()> {
+    let test_mod: PyObject = Python::with_gil(|py| -> PyResult<_> {
+        Ok(
+            PyModule::from_code(py, EXIT_STACK_CODE, "test_exit_stack.py", "test_exit_stack")?
+                .into(),
+        )
+    })?;
+    let enter = |stack: &pyo3_asyncio::PyAsyncExitStack, name: &'static str| {
+        Python::with_gil(|py| {
+            stack.enter_async_context(test_mod.as_ref(py).call_method1("resource", (name,))?)
+        })
+    };
+    let log = || -> PyResult<Vec<String>> {
+        Python::with_gil(|py| {
+            test_mod
+                .as_ref(py)
+                .getattr("log")?
+                .call_method0("copy")?
+                .extract()
+        })
+    };
+
+    let stack = Python::with_gil(pyo3_asyncio::tokio::async_exit_stack)?;
+    let entered = enter(&stack, "a")?.await?;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(entered.extract::<&str>(py)?, "a");
+        Ok(())
+    })?;
+    enter(&stack, "b")?.await?;
+    Python::with_gil(|py| stack.aclose(py))?.await?;
+    assert_eq!(log()?, ["enter a", "enter b", "exit b", "exit a"]);
+
+    // dropping the stack exits the context managers on the event loop
+    let stack = Python::with_gil(pyo3_asyncio::tokio::async_exit_stack)?;
+    enter(&stack, "c")?.await?;
+    drop(stack);
+    Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future(py.import("asyncio")?.call_method1("sleep", (0.1,))?)
+    })?
+    .await?;
+    assert_eq!(log()?[4..], ["enter c", "exit c"]);
+
+    Ok(())
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_task_group() -> PyResult<()> {
+    let locals = Python::with_gil(pyo3_asyncio::tokio::get_current_locals)?;
+    let completed = Arc::new(Mutex::new(false));
+
+    let mut group = pyo3_asyncio::tokio::TaskGroup::new(locals.clone());
+    let event_loop = locals.clone();
+    group.spawn(async move {
+        // children inherit the locals of the group
+        Python::with_gil(|py| -> PyResult<()> {
+            assert!(pyo3_asyncio::tokio::get_current_loop(py)?.is(event_loop.event_loop(py)));
+            Ok(())
+        })
+    });
+    group.spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        Err(pyo3::exceptions::PyValueError::new_err("boom"))
+    });
+    let sibling = Arc::clone(&completed);
+    group.spawn(async move {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        *sibling.lock().unwrap() = true;
+        Ok(())
+    });
+    assert_eq!(group.len(), 3);
+
+    let err = group.join_all().await.unwrap_err();
+    Python::with_gil(|py| assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py)));
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert!(!*completed.lock().unwrap(), "sibling was not aborted");
+
+    Ok(())
+}
+
+const CALLBACK_CODE: &str = r#"
+import asyncio
+
+def callbacks(loop, fut):
+    def on_result(value):
+        assert asyncio.get_running_loop() is loop
+        fut.set_result(("ok", value))
+    def on_error(exc):
+        fut.set_result(("err", exc))
+    return on_result, on_error
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_future_into_callback() -> PyResult<()> {
+    async fn outcome(result: PyResult<i32>) -> PyResult<(String, PyObject)> {
+        let fut = Python::with_gil(|py| -> PyResult<_> {
+            let test_mod =
+                PyModule::from_code(py, CALLBACK_CODE, "test_callback.py", "test_callback")?;
+            let event_loop = pyo3_asyncio::tokio::get_current_loop(py)?;
+            let fut = event_loop.call_method0("create_future")?;
+            let (on_result, on_error): (&PyAny, &PyAny) = test_mod
+                .call_method1("callbacks", (event_loop, fut))?
+                .extract()?;
+
+            pyo3_asyncio::tokio::future_into_callback(
+                py,
+                async move {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    result
+                },
+                on_result,
+                on_error,
+            )?;
+
+            pyo3_asyncio::tokio::into_future(fut)
+        })?;
+
+        let outcome = fut.await?;
+        Python::with_gil(|py| outcome.extract(py))
+    }
+
+    let (kind, value) = outcome(Ok(42)).await?;
+    assert_eq!(kind, "ok");
+    Python::with_gil(|py| assert_eq!(value.extract::<i32>(py).unwrap(), 42));
+
+    let (kind, value) = outcome(Err(pyo3::exceptions::PyValueError::new_err("boom"))).await?;
+    assert_eq!(kind, "err");
+    Python::with_gil(|py| {
+        assert!(PyErr::from_value(value.as_ref(py))
+            .is_instance_of::<pyo3::exceptions::PyValueError>(py))
+    });
+
+    Ok(())
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_run_on_python_executor() -> PyResult<()> {
+    let thread_name = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::run_on_python_executor(py, async move {
+            Python::with_gil(|py| -> PyResult<String> {
+                py.import("threading")?
+                    .call_method0("current_thread")?
+                    .getattr("name")?
+                    .extract()
+            })
+        })
+    })?
+    .await??;
+    // asyncio names the threads of its default executor after itself
+    assert!(thread_name.starts_with("asyncio"));
+
+    let err = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::run_on_python_executor(py, async move {
+            panic!("this panic was intentional!");
+        })
+    })?
+    .await
+    .unwrap_err();
+    Python::with_gil(|py| assert!(err.is_instance_of::<pyo3_asyncio::err::RustPanic>(py)));
+
+    Ok(())
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_into_future_then() -> PyResult<()> {
+    let len = Python::with_gil(|py| {
+        let body = py
+            .import("asyncio")?
+            .call_method1("sleep", (0.1, "[1, 2, 3]"))?;
+
+        pyo3_asyncio::tokio::into_future_then(body, |py, body| {
+            // the transform runs on the event loop
+            py.import("asyncio")?.call_method0("get_running_loop")?;
+            py.import("json")?.call_method1("loads", (body,))?.len()
+        })
+    })?
+    .await?;
+    assert_eq!(len, 3);
+
+    let err = Python::with_gil(|py| {
+        pyo3_asyncio::tokio::into_future_then(
+            py.import("asyncio")?.call_method1("sleep", (0.1,))?,
+            |_py, _| -> PyResult<()> { Err(pyo3::exceptions::PyValueError::new_err("boom")) },
+        )
+    })?
+    .await
+    .unwrap_err();
+    Python::with_gil(|py| assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py)));
+
+    Ok(())
+}
+
+const TRACEBACK_CODE: &str = r#"
+async def raise_from_python():
+    raise ValueError("boom")
+"#;
+
+#[pyo3_asyncio::tokio::test]
+async fn test_into_future_traceback() -> PyResult<()> {
+    let err = Python::with_gil(|py| {
+        let test_mod =
+            PyModule::from_code(py, TRACEBACK_CODE, "test_traceback.py", "test_traceback")?;
+        pyo3_asyncio::tokio::into_future(test_mod.call_method0("raise_from_python")?)
+    })?
+    .await
+    .unwrap_err();
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let traceback = py
+            .import("traceback")?
+            .call_method1("format_tb", (err.ptraceback(py),))?
+            .str()?
+            .to_str()?
+            .to_owned();
+        assert!(traceback.contains("raise_from_python"), "{}", traceback);
+        Ok(())
+    })
+}
+
+#[pyo3_asyncio::tokio::test]
+async fn test_fixture_locals(locals: TaskLocals, event_loop: PyObject) -> PyResult<()> {
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(locals.event_loop(py).is(event_loop.as_ref(py)));
+        assert!(pyo3_asyncio::tokio::get_current_loop(py)?.is(event_loop.as_ref(py)));
+        Ok(())
+    })
+}
@@ -1,12 +1,18 @@
-use std::{any::Any, cell::RefCell, future::Future, panic::AssertUnwindSafe, pin::Pin};
+use std::{
+    any::Any, cell::RefCell, future::Future, panic::AssertUnwindSafe, pin::Pin, time::Duration,
+};
 
 use async_std::task;
-use futures::prelude::*;
+use futures::{
+    future::{AbortHandle, AbortRegistration},
+    prelude::*,
+};
 use pyo3::prelude::*;
 
 use crate::{
     generic::{self, ContextExt, JoinError, LocalContextExt, Runtime, SpawnLocalExt},
-    TaskLocals,
+    CancellationToken, Peek, PyAsyncExitStack, PyFuture, PyFutureResult, RateLimitedBridge,
+    RestartPolicy, TaskLocals,
 };
 
 /// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>attributes</code></span>
@@ -121,6 +127,63 @@ where
     AsyncStdRuntime::scope_local(locals, fut).await
 }
 
+/// Drive a !Send future to completion on the calling thread
+///
+/// This is the `async-std` counterpart of blocking on a `tokio::task::LocalSet`. The future is run
+/// with [`scope_local`] on async-std's thread-local executor, so it never leaves the calling
+/// thread, and neither do the futures it spawns with [`local_future_into_py`] and friends. Those
+/// futures only make progress while `run_local` is running.
+///
+/// The Python event loop in `locals` keeps running on its own thread. Don't call this from the
+/// thread that runs that event loop (i.e. from Python code), since blocking it would prevent any
+/// Python awaitable from completing.
+///
+/// # Arguments
+/// * `locals` - The task locals for the given future
+/// * `fut` - The !Send future to drive to completion
+///
+/// # Examples
+///
+/// ```
+/// use std::{rc::Rc, time::Duration};
+///
+/// use pyo3::prelude::*;
+///
+/// # #[cfg(all(feature = "async-std-runtime", feature = "attributes"))]
+/// #[pyo3_asyncio::async_std::main]
+/// async fn main() -> PyResult<()> {
+///     let locals = Python::with_gil(|py| pyo3_asyncio::async_std::get_current_locals(py))?;
+///
+///     // the event loop runs on the main thread, so the !Send work happens on another one
+///     async_std::task::spawn_blocking(move || {
+///         pyo3_asyncio::async_std::run_local(locals, async {
+///             let secs = Rc::new(1);
+///
+///             Python::with_gil(|py| {
+///                 pyo3_asyncio::async_std::into_future(
+///                     pyo3_asyncio::async_std::local_future_into_py(py, async move {
+///                         async_std::task::sleep(Duration::from_secs(*secs)).await;
+///                         Ok(())
+///                     })?,
+///                 )
+///             })?
+///             .await?;
+///
+///             Ok(())
+///         })
+///     })
+///     .await
+/// }
+/// # #[cfg(not(all(feature = "async-std-runtime", feature = "attributes")))]
+/// # fn main() {}
+/// ```
+pub fn run_local<F, T>(locals: TaskLocals, fut: F) -> T
+where
+    F: Future<Output = T> + 'static,
+{
+    task::block_on(scope_local(locals, fut))
+}
+
 /// Get the current event loop from either Python or Rust async task local context
 ///
 /// This function first checks if the runtime has a task-local reference to the Python event loop.
@@ -172,6 +235,134 @@ where
     generic::run_until_complete::<AsyncStdRuntime, _, T>(event_loop, fut)
 }
 
+/// Run the event loop until the given Future completes or `token` is cancelled
+///
+/// Cancelling `token` from any thread drops `fut`, stops the event loop gracefully and returns an
+/// `asyncio.CancelledError`. After this function returns, the event loop can be resumed with
+/// [`run_until_complete`].
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop that should run the future
+/// * `fut` - The future to drive to completion
+/// * `token` - Cancels `fut` when cancelled
+///
+/// # Examples
+///
+/// ```
+/// # use std::time::Duration;
+/// #
+/// # use pyo3::prelude::*;
+/// use pyo3_asyncio::CancellationToken;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// # Python::with_gil(|py| -> PyResult<()> {
+/// # let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+/// let token = CancellationToken::new();
+///
+/// let canceller = token.clone();
+/// std::thread::spawn(move || {
+///     std::thread::sleep(Duration::from_millis(100));
+///     canceller.cancel();
+/// });
+///
+/// let result = pyo3_asyncio::async_std::run_until_complete_with_cancel(
+///     event_loop,
+///     async move {
+///         async_std::task::sleep(Duration::from_secs(60)).await;
+///         Ok(())
+///     },
+///     token,
+/// );
+/// assert!(result.is_err());
+/// # Ok(())
+/// # }).unwrap();
+/// ```
+pub fn run_until_complete_with_cancel<F, T>(
+    event_loop: &PyAny,
+    fut: F,
+    token: CancellationToken,
+) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    generic::run_until_complete_with_cancel::<AsyncStdRuntime, _, T>(event_loop, fut, token)
+}
+
+/// Run the event loop until all of the given Futures complete, one after another
+///
+/// The futures are awaited in order within a single call to [`run_until_complete`], so the event
+/// loop is only entered once. If a future fails, the remaining futures are dropped without being
+/// polled and its error is returned.
+///
+/// After this function returns, the event loop can be resumed with [`run_until_complete`] or
+/// [`run_until_complete_all`]
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop that should run the futures
+/// * `futs` - The futures to drive to completion, in order
+///
+/// # Examples
+///
+/// ```
+/// # use std::time::Duration;
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// # pyo3::prepare_freethreaded_python();
+/// # Python::with_gil(|py| -> PyResult<()> {
+/// # let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+/// let results = pyo3_asyncio::async_std::run_until_complete_all(
+///     event_loop,
+///     (1..=3u64).map(|millis| async move {
+///         async_std::task::sleep(Duration::from_millis(millis)).await;
+///         Ok(millis)
+///     }),
+/// )?;
+///
+/// assert_eq!(results, vec![1, 2, 3]);
+/// # Ok(())
+/// # }).unwrap();
+/// ```
+pub fn run_until_complete_all<I, F, T>(event_loop: &PyAny, futs: I) -> PyResult<Vec<T>>
+where
+    I: IntoIterator<Item = F>,
+    I::IntoIter: Send + 'static,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    generic::run_until_complete_all::<AsyncStdRuntime, I, F, T>(event_loop, futs)
+}
+
+/// Pay the one-time costs of the bridge up front
+///
+/// This imports the Python modules used by the bridge, then drives a throwaway round trip
+/// between Rust and Python on a temporary event loop, so that the first real conversion isn't
+/// slower than the following ones. See [`generic::warmup`](`crate::generic::warmup`) for more
+/// details.
+///
+/// This must be called while no event loop is running on the current thread.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| -> PyResult<()> {
+///     // at startup, before serving any request
+///     pyo3_asyncio::async_std::warmup(py)?;
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn warmup(py: Python) -> PyResult<()> {
+    generic::warmup::<AsyncStdRuntime>(py)
+}
+
 /// Run the event loop until the given Future completes
 ///
 /// # Arguments
@@ -209,6 +400,87 @@ where
     generic::run::<AsyncStdRuntime, F, T>(py, fut)
 }
 
+/// Run the event loop until the given Future completes, using `executor` as the event loop's
+/// default executor
+///
+/// See [`generic::run_with_executor`](`crate::generic::run_with_executor`) for more details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `executor` - The `concurrent.futures.Executor` to use as the default executor
+/// * `fut` - The future to drive to completion
+///
+/// # Examples
+///
+/// ```
+/// # use std::time::Duration;
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// fn main() {
+///     pyo3::prepare_freethreaded_python();
+///
+///     Python::with_gil(|py| {
+///         // blocking work offloaded with asyncio.to_thread uses at most 2 threads
+///         let executor = py
+///             .import("concurrent.futures")?
+///             .call_method1("ThreadPoolExecutor", (2,))?;
+///
+///         pyo3_asyncio::async_std::run_with_executor(py, executor, async move {
+///             async_std::task::sleep(Duration::from_millis(10)).await;
+///             Ok(())
+///         })
+///     })
+///     .map_err(|e| Python::with_gil(|py| e.print_and_set_sys_last_vars(py)))
+///     .unwrap();
+/// }
+/// ```
+pub fn run_with_executor<F, T>(py: Python, executor: &PyAny, fut: F) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    generic::run_with_executor::<AsyncStdRuntime, F, T>(py, executor, fut)
+}
+
+/// Run the event loop until the given Future completes on a dedicated thread
+///
+/// The event loop is created and driven on a new thread so that the calling thread stays free
+/// (i.e. for a GUI toolkit's own event loop). See
+/// [`generic::run_on_thread`](`crate::generic::run_on_thread`) for more details, including why
+/// signal handling does not work on that thread.
+///
+/// # Arguments
+/// * `fut` - The future to drive to completion
+///
+/// # Examples
+///
+/// ```
+/// # use std::time::Duration;
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// fn main() {
+///     pyo3::prepare_freethreaded_python();
+///
+///     let handle = pyo3_asyncio::async_std::run_on_thread(async move {
+///         async_std::task::sleep(Duration::from_millis(100)).await;
+///         Ok(42)
+///     });
+///
+///     // the main thread is free to do other work in the meantime
+///
+///     assert_eq!(handle.join().unwrap().unwrap(), 42);
+/// }
+/// ```
+pub fn run_on_thread<F, T>(fut: F) -> std::thread::JoinHandle<PyResult<T>>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    generic::run_on_thread::<AsyncStdRuntime, F, T>(fut)
+}
+
 /// Convert a Rust Future into a Python awaitable
 ///
 /// __This function will be removed in `v0.16`__
@@ -390,16 +662,60 @@ where
     generic::future_into_py::<AsyncStdRuntime, _, T>(py, fut)
 }
 
-/// Convert a Rust Future into a Python awaitable
+/// Convert a Rust Future into the iterator that a Python `__await__` method returns
 ///
-/// __This function was deprecated in favor of [`future_into_py`] in `v0.15` because
-/// it became the default behaviour. In `v0.15`, any calls to this function can be seamlessly
-/// replaced with [`future_into_py`].__
+/// This makes a `#[pyclass]` awaitable while it keeps full control over its other methods and
+/// attributes. See [`generic::future_into_await`](`crate::generic::future_into_await`) for more
+/// details.
 ///
-/// __This function will be removed in `v0.16`__
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future driven by `__await__`
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// A timer that can be awaited and also reports its duration
+/// #[pyclass]
+/// struct Timer {
+///     #[pyo3(get)]
+///     secs: u64,
+/// }
+///
+/// #[pymethods]
+/// impl Timer {
+///     fn __await__<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+///         let secs = self.secs;
+///         pyo3_asyncio::async_std::future_into_await(py, async move {
+///             async_std::task::sleep(Duration::from_secs(secs)).await;
+///             Ok(secs)
+///         })
+///     }
+/// }
+/// ```
+pub fn future_into_await<F, T>(py: Python, fut: F) -> PyResult<&PyAny>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::future_into_await::<AsyncStdRuntime, _, T>(py, fut)
+}
+
+/// Convert a Rust Future into a named Python task
+///
+/// The task shows up in `asyncio.all_tasks()` and its `repr` under `name`, which makes it easier to
+/// tell the futures converted from Rust apart when debugging. It must be created on the thread
+/// running the event loop. See
+/// [`generic::future_into_py_named_with_locals`](`crate::generic::future_into_py_named_with_locals`)
+/// for more details.
 ///
 /// # Arguments
 /// * `py` - The current PyO3 GIL guard
+/// * `name` - The name of the task
 /// * `fut` - The Rust future to be converted
 ///
 /// # Examples
@@ -409,228 +725,244 @@ where
 ///
 /// use pyo3::prelude::*;
 ///
-/// /// Awaitable sleep function
+/// /// Fetch a user, visible as "fetch_user:<id>" in `asyncio.all_tasks()`
 /// #[pyfunction]
-/// fn sleep_for<'p>(py: Python<'p>, secs: &'p PyAny) -> PyResult<&'p PyAny> {
-///     let secs = secs.extract()?;
-///     pyo3_asyncio::async_std::cancellable_future_into_py(py, async move {
-///         async_std::task::sleep(Duration::from_secs(secs)).await;
-///         Python::with_gil(|py| Ok(py.None()))
+/// fn fetch_user<'p>(py: Python<'p>, id: u64) -> PyResult<&'p PyAny> {
+///     pyo3_asyncio::async_std::future_into_py_named(py, &format!("fetch_user:{}", id), async move {
+///         async_std::task::sleep(Duration::from_millis(100)).await;
+///         Ok(id)
 ///     })
 /// }
 /// ```
-#[deprecated(
-    since = "0.15.0",
-    note = "Use pyo3_asyncio::async_std::future_into_py instead"
-)]
-#[allow(deprecated)]
-pub fn cancellable_future_into_py<F>(py: Python, fut: F) -> PyResult<&PyAny>
+pub fn future_into_py_named<'p, F, T>(py: Python<'p>, name: &str, fut: F) -> PyResult<&'p PyAny>
 where
-    F: Future<Output = PyResult<PyObject>> + Send + 'static,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
 {
-    generic::cancellable_future_into_py::<AsyncStdRuntime, _>(py, fut)
+    generic::future_into_py_named::<AsyncStdRuntime, _, T>(py, name, fut)
 }
 
-/// Convert a `!Send` Rust Future into a Python awaitable
+/// Convert a Rust Future into a Python awaitable and a handle that aborts the Rust future
 ///
-/// __This function will be removed in `v0.16`__
+/// Aborting the Rust future does not complete the awaitable, which stays pending unless it is also
+/// cancelled. See
+/// [`generic::future_into_py_with_handle_with_locals`](`crate::generic::future_into_py_with_handle_with_locals`)
+/// for more details.
 ///
 /// # Arguments
-/// * `event_loop` - The Python event loop that the awaitable should be attached to
+/// * `py` - The current PyO3 GIL guard
 /// * `fut` - The Rust future to be converted
 ///
 /// # Examples
 ///
 /// ```
-/// use std::{rc::Rc, time::Duration};
+/// use std::time::Duration;
 ///
+/// use futures::future::AbortHandle;
 /// use pyo3::prelude::*;
 ///
-/// /// Awaitable non-send sleep function
-/// #[pyfunction]
-/// fn sleep_for(py: Python, secs: u64) -> PyResult<&PyAny> {
-///     // Rc is non-send so it cannot be passed into pyo3_asyncio::async_std::future_into_py
-///     let secs = Rc::new(secs);
-///     Ok(pyo3_asyncio::async_std::local_future_into_py_with_loop(
-///         pyo3_asyncio::async_std::get_current_loop(py)?,
-///         async move {
-///             async_std::task::sleep(Duration::from_secs(*secs)).await;
-///             Ok(Python::with_gil(|py| py.None()))
+/// /// Start a long-running poll and keep a way to stop it from Rust
+/// fn start_polling(py: Python) -> PyResult<(&PyAny, AbortHandle)> {
+///     pyo3_asyncio::async_std::future_into_py_with_handle(py, async move {
+///         loop {
+///             async_std::task::sleep(Duration::from_secs(1)).await;
 ///         }
-///     )?.into())
+///         #[allow(unreachable_code)]
+///         Ok(())
+///     })
 /// }
 ///
-/// # #[cfg(all(feature = "async-std-runtime", feature = "attributes"))]
-/// #[pyo3_asyncio::async_std::main]
-/// async fn main() -> PyResult<()> {
-///     Python::with_gil(|py| {
-///         let py_future = sleep_for(py, 1)?;
-///         pyo3_asyncio::async_std::into_future(py_future)
-///     })?
-///     .await?;
-///
+/// fn stop_polling(fut: &PyAny, handle: AbortHandle) -> PyResult<()> {
+///     handle.abort();
+///     // the awaitable stays pending unless it is cancelled as well
+///     let event_loop = fut.call_method0("get_loop")?;
+///     event_loop.call_method1("call_soon_threadsafe", (fut.getattr("cancel")?,))?;
 ///     Ok(())
 /// }
-/// # #[cfg(not(all(feature = "async-std-runtime", feature = "attributes")))]
-/// # fn main() {}
 /// ```
-#[deprecated(
-    since = "0.15.0",
-    note = "Use pyo3_asyncio::async_std::local_future_into_py_with_locals instead"
-)]
-#[allow(deprecated)]
-pub fn local_future_into_py_with_loop<F>(event_loop: &PyAny, fut: F) -> PyResult<&PyAny>
+pub fn future_into_py_with_handle<F, T>(py: Python, fut: F) -> PyResult<(&PyAny, AbortHandle)>
 where
-    F: Future<Output = PyResult<PyObject>> + 'static,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
 {
-    generic::local_future_into_py_with_loop::<AsyncStdRuntime, _>(event_loop, fut)
+    generic::future_into_py_with_handle::<AsyncStdRuntime, _, T>(py, fut)
 }
 
-/// Convert a `!Send` Rust Future into a Python awaitable
-///
-/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
-/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tracing</code></span>
+/// Convert a Rust Future into a Python awaitable instrumented with a `tracing` span
 ///
-/// Python `contextvars` are preserved when calling async Python functions within the Rust future
-/// via [`into_future`] (new behaviour in `v0.15`).
-///
-/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
-/// unfortunately fail to resolve them when called within the Rust future. This is because the
-/// function is being called from a Rust thread, not inside an actual Python coroutine context.
-/// >
-/// > As a workaround, you can get the `contextvars` from the current task locals using
-/// [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
-/// synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
-/// synchronous function, and restore the previous context when it returns or raises an exception.
+/// `span` is entered whenever `fut` is polled. See
+/// [`generic::future_into_py_instrumented_with_locals`](`crate::generic::future_into_py_instrumented_with_locals`)
+/// for more details.
 ///
 /// # Arguments
-/// * `py` - PyO3 GIL guard
-/// * `locals` - The task locals for the given future
+/// * `py` - The current PyO3 GIL guard
+/// * `span` - The span to instrument `fut` with
 /// * `fut` - The Rust future to be converted
 ///
 /// # Examples
 ///
 /// ```
-/// use std::{rc::Rc, time::Duration};
+/// use std::time::Duration;
 ///
 /// use pyo3::prelude::*;
 ///
-/// /// Awaitable non-send sleep function
+/// /// Awaitable sleep function, traced in its own span
 /// #[pyfunction]
-/// fn sleep_for(py: Python, secs: u64) -> PyResult<&PyAny> {
-///     // Rc is non-send so it cannot be passed into pyo3_asyncio::async_std::future_into_py
-///     let secs = Rc::new(secs);
-///     Ok(pyo3_asyncio::async_std::local_future_into_py_with_locals(
-///         py,
-///         pyo3_asyncio::async_std::get_current_locals(py)?,
-///         async move {
-///             async_std::task::sleep(Duration::from_secs(*secs)).await;
-///             Python::with_gil(|py| Ok(py.None()))
-///         }
-///     )?.into())
+/// fn sleep_for<'p>(py: Python<'p>, secs: u64) -> PyResult<&'p PyAny> {
+///     let span = tracing::info_span!("sleep_for", secs);
+///     pyo3_asyncio::async_std::future_into_py_instrumented(py, span, async move {
+///         async_std::task::sleep(Duration::from_secs(secs)).await;
+///         Ok(())
+///     })
 /// }
+/// ```
+#[cfg(feature = "tracing")]
+pub fn future_into_py_instrumented<F, T>(
+    py: Python,
+    span: tracing::Span,
+    fut: F,
+) -> PyResult<&PyAny>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::future_into_py_instrumented::<AsyncStdRuntime, _, T>(py, span, fut)
+}
+
+/// Convert a [`Cancellable`](generic::Cancellable) Rust Future into a Python awaitable with the
+/// given task locals
 ///
-/// # #[cfg(all(feature = "async-std-runtime", feature = "attributes"))]
-/// #[pyo3_asyncio::async_std::main]
-/// async fn main() -> PyResult<()> {
-///     Python::with_gil(|py| {
-///         let py_future = sleep_for(py, 1)?;
-///         pyo3_asyncio::async_std::into_future(py_future)
-///     })?
-///     .await?;
+/// This works just like [`future_into_py_with_locals`], except that
+/// [`Cancellable::cancel`](generic::Cancellable::cancel) is called when the Python awaitable is
+/// cancelled, giving the future a chance to clean up asynchronously before it is dropped.
 ///
-///     Ok(())
-/// }
-/// # #[cfg(not(all(feature = "async-std-runtime", feature = "attributes")))]
-/// # fn main() {}
-/// ```
-pub fn local_future_into_py_with_locals<F, T>(
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `fut` - The Rust future to be converted
+pub fn structured_future_into_py_with_locals<F, T>(
     py: Python,
     locals: TaskLocals,
     fut: F,
 ) -> PyResult<&PyAny>
 where
-    F: Future<Output = PyResult<T>> + 'static,
+    F: generic::Cancellable<Output = PyResult<T>> + Send + 'static,
     T: IntoPy<PyObject>,
 {
-    generic::local_future_into_py_with_locals::<AsyncStdRuntime, _, T>(py, locals, fut)
+    generic::structured_future_into_py_with_locals::<AsyncStdRuntime, F, T>(py, locals, fut)
 }
 
-/// Convert a `!Send` Rust Future into a Python awaitable
-///
-/// __This function was deprecated in favor of [`local_future_into_py_with_locals`] in `v0.15` because
-/// it became the default behaviour. In `v0.15`, any calls to this function should be
-/// replaced with [`local_future_into_py_with_locals`].__
+/// Convert a [`Cancellable`](generic::Cancellable) Rust Future into a Python awaitable
 ///
-/// __This function will be removed in `v0.16`__
+/// This works just like [`future_into_py`], except that
+/// [`Cancellable::cancel`](generic::Cancellable::cancel) is called when the Python awaitable is
+/// cancelled, giving the future a chance to clean up asynchronously before it is dropped.
 ///
 /// # Arguments
-/// * `event_loop` - The Python event loop that the awaitable should be attached to
+/// * `py` - The current PyO3 GIL guard
 /// * `fut` - The Rust future to be converted
 ///
 /// # Examples
 ///
 /// ```
-/// use std::{rc::Rc, time::Duration};
+/// use std::{future::Future, pin::Pin, task::{Context, Poll}, time::Duration};
 ///
 /// use pyo3::prelude::*;
+/// use pyo3_asyncio::generic::Cancellable;
 ///
-/// /// Awaitable non-send sleep function
-/// #[pyfunction]
-/// fn sleep_for(py: Python, secs: u64) -> PyResult<&PyAny> {
-///     // Rc is non-send so it cannot be passed into pyo3_asyncio::async_std::future_into_py
-///     let secs = Rc::new(secs);
-///     Ok(pyo3_asyncio::async_std::local_cancellable_future_into_py_with_loop(
-///         pyo3_asyncio::async_std::get_current_loop(py)?,
-///         async move {
-///             async_std::task::sleep(Duration::from_secs(*secs)).await;
-///             Python::with_gil(|py| Ok(py.None()))
-///         }
-///     )?.into())
+/// struct Transaction {
+///     step: Pin<Box<dyn Future<Output = PyResult<()>> + Send>>,
 /// }
 ///
-/// # #[cfg(all(feature = "async-std-runtime", feature = "attributes"))]
-/// #[pyo3_asyncio::async_std::main]
-/// async fn main() -> PyResult<()> {
-///     Python::with_gil(|py| {
-///         let py_future = sleep_for(py, 1)?;
-///         pyo3_asyncio::async_std::into_future(py_future)
-///     })?
-///     .await?;
+/// impl Future for Transaction {
+///     type Output = PyResult<()>;
 ///
-///     Ok(())
+///     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+///         self.step.as_mut().poll(cx)
+///     }
+/// }
+///
+/// impl Cancellable for Transaction {
+///     fn cancel(mut self: Pin<&mut Self>) -> bool {
+///         // roll back asynchronously instead of just dropping the commit
+///         self.step = Box::pin(async move {
+///             async_std::task::sleep(Duration::from_millis(100)).await;
+///             Ok(())
+///         });
+///         true
+///     }
+/// }
+///
+/// /// Awaitable commit that is rolled back if cancelled
+/// #[pyfunction]
+/// fn commit(py: Python) -> PyResult<&PyAny> {
+///     pyo3_asyncio::async_std::structured_future_into_py(
+///         py,
+///         Transaction {
+///             step: Box::pin(async move {
+///                 async_std::task::sleep(Duration::from_secs(1)).await;
+///                 Ok(())
+///             }),
+///         },
+///     )
 /// }
-/// # #[cfg(not(all(feature = "async-std-runtime", feature = "attributes")))]
-/// # fn main() {}
 /// ```
-#[deprecated(
-    since = "0.15.0",
-    note = "Use pyo3_asyncio::async_std::local_future_into_py_with_locals instead"
-)]
-#[allow(deprecated)]
-pub fn local_cancellable_future_into_py_with_loop<F>(event_loop: &PyAny, fut: F) -> PyResult<&PyAny>
+pub fn structured_future_into_py<F, T>(py: Python, fut: F) -> PyResult<&PyAny>
 where
-    F: Future<Output = PyResult<PyObject>> + 'static,
+    F: generic::Cancellable<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
 {
-    generic::local_cancellable_future_into_py_with_loop::<AsyncStdRuntime, _>(event_loop, fut)
+    generic::structured_future_into_py::<AsyncStdRuntime, F, T>(py, fut)
 }
 
-/// Convert a `!Send` Rust Future into a Python awaitable
+/// Convert a Rust Future built from borrowed Python arguments into a Python awaitable
 ///
-/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
-/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
+/// `build` is called immediately with the GIL held to resolve borrowed arguments into the owned
+/// data the `'static` future needs, which saves converting every argument into a `PyObject` up
+/// front. See [`generic::future_into_py_borrowed`](`crate::generic::future_into_py_borrowed`) for
+/// more details.
 ///
-/// Python `contextvars` are preserved when calling async Python functions within the Rust future
-/// via [`into_future`] (new behaviour in `v0.15`).
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `build` - Creates the Rust future to be converted while the GIL is held
 ///
-/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
-/// unfortunately fail to resolve them when called within the Rust future. This is because the
-/// function is being called from a Rust thread, not inside an actual Python coroutine context.
-/// >
-/// > As a workaround, you can get the `contextvars` from the current task locals using
-/// [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
-/// synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
-/// synchronous function, and restore the previous context when it returns or raises an exception.
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::{prelude::*, types::PyDict};
+///
+/// /// Awaitable that sleeps for `config["delay"]` seconds, then returns `config["name"]`
+/// #[pyfunction]
+/// fn delayed_name<'p>(py: Python<'p>, config: &'p PyDict) -> PyResult<&'p PyAny> {
+///     pyo3_asyncio::async_std::future_into_py_borrowed(py, |_py| {
+///         // borrows of `config` end here, only owned data moves into the future
+///         let delay: u64 = config.get_item("delay").unwrap().extract()?;
+///         let name: String = config.get_item("name").unwrap().extract()?;
+///
+///         Ok(async move {
+///             async_std::task::sleep(Duration::from_secs(delay)).await;
+///             Ok(name)
+///         })
+///     })
+/// }
+/// ```
+pub fn future_into_py_borrowed<'p, B, F, T>(py: Python<'p>, build: B) -> PyResult<&'p PyAny>
+where
+    B: FnOnce(Python<'p>) -> PyResult<F>,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::future_into_py_borrowed::<AsyncStdRuntime, B, F, T>(py, build)
+}
+
+/// Convert a Rust Future that fails with a custom error type into a Python awaitable
+///
+/// Errors returned by `fut` are converted into Python exceptions with the mapper registered for
+/// `E` through [`register_error_mapper`](crate::err::register_error_mapper). Otherwise this
+/// behaves exactly like [`future_into_py`].
 ///
 /// # Arguments
 /// * `py` - The current PyO3 GIL guard
@@ -639,108 +971,101 @@ where
 /// # Examples
 ///
 /// ```
-/// use std::{rc::Rc, time::Duration};
+/// use std::time::Duration;
 ///
-/// use pyo3::prelude::*;
+/// use pyo3::{exceptions::PyKeyError, prelude::*};
 ///
-/// /// Awaitable non-send sleep function
+/// #[derive(Debug)]
+/// struct NotFound(String);
+///
+/// /// Awaitable lookup that raises a `KeyError` for missing keys
 /// #[pyfunction]
-/// fn sleep_for(py: Python, secs: u64) -> PyResult<&PyAny> {
-///     // Rc is non-send so it cannot be passed into pyo3_asyncio::async_std::future_into_py
-///     let secs = Rc::new(secs);
-///     pyo3_asyncio::async_std::local_future_into_py(py, async move {
-///         async_std::task::sleep(Duration::from_secs(*secs)).await;
-///         Ok(())
+/// fn lookup(py: Python, key: String) -> PyResult<&PyAny> {
+///     pyo3_asyncio::async_std::future_into_py_mapped(py, async move {
+///         async_std::task::sleep(Duration::from_millis(10)).await;
+///         Err::<(), _>(NotFound(key))
 ///     })
 /// }
 ///
-/// # #[cfg(all(feature = "async-std-runtime", feature = "attributes"))]
-/// #[pyo3_asyncio::async_std::main]
-/// async fn main() -> PyResult<()> {
-///     Python::with_gil(|py| {
-///         let py_future = sleep_for(py, 1)?;
-///         pyo3_asyncio::async_std::into_future(py_future)
-///     })?
-///     .await?;
-///
+/// #[pymodule]
+/// fn my_module(_py: Python, m: &PyModule) -> PyResult<()> {
+///     pyo3_asyncio::err::register_error_mapper(|_py, e: NotFound| PyKeyError::new_err(e.0));
+///     m.add_function(wrap_pyfunction!(lookup, m)?)?;
 ///     Ok(())
 /// }
-/// # #[cfg(not(all(feature = "async-std-runtime", feature = "attributes")))]
-/// # fn main() {}
 /// ```
-pub fn local_future_into_py<F, T>(py: Python, fut: F) -> PyResult<&PyAny>
+pub fn future_into_py_mapped<F, T, E>(py: Python, fut: F) -> PyResult<&PyAny>
 where
-    F: Future<Output = PyResult<T>> + 'static,
+    F: Future<Output = Result<T, E>> + Send + 'static,
     T: IntoPy<PyObject>,
+    E: std::fmt::Debug + Send + 'static,
 {
-    generic::local_future_into_py::<AsyncStdRuntime, _, T>(py, fut)
+    generic::future_into_py_mapped::<AsyncStdRuntime, F, T, E>(py, fut)
 }
 
-/// Convert a `!Send` Rust Future into a Python awaitable
-///
-/// __This function was deprecated in favor of [`local_future_into_py`] in `v0.15` because
-/// it became the default behaviour. In `v0.15`, any calls to this function can be seamlessly
-/// replaced with [`local_future_into_py`].__
+/// Convert a Rust Future that fails with a custom error type into a Python awaitable, converting
+/// the error with `map_fn`
 ///
-/// __This function will be removed in `v0.16`__
+/// This function simply forwards the future and `map_fn` to
+/// [`generic::future_into_py_with_error_map`](`crate::generic::future_into_py_with_error_map`).
+/// See [`generic::future_into_py_with_error_map`](`crate::generic::future_into_py_with_error_map`)
+/// for more details.
 ///
 /// # Arguments
 /// * `py` - The current PyO3 GIL guard
 /// * `fut` - The Rust future to be converted
+/// * `map_fn` - Converts the error returned by `fut` into a Python exception
 ///
 /// # Examples
 ///
 /// ```
-/// use std::{rc::Rc, time::Duration};
+/// use std::{fmt, time::Duration};
 ///
-/// use pyo3::prelude::*;
+/// use pyo3::{exceptions::PyConnectionError, prelude::*};
 ///
-/// /// Awaitable non-send sleep function
-/// #[pyfunction]
-/// fn sleep_for(py: Python, secs: u64) -> PyResult<&PyAny> {
-///     // Rc is non-send so it cannot be passed into pyo3_asyncio::async_std::future_into_py
-///     let secs = Rc::new(secs);
-///     pyo3_asyncio::async_std::local_cancellable_future_into_py(py, async move {
-///         async_std::task::sleep(Duration::from_secs(*secs)).await;
-///         Python::with_gil(|py| Ok(py.None()))
-///     })
+/// #[derive(Debug)]
+/// struct Unreachable(String);
+///
+/// impl fmt::Display for Unreachable {
+///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+///         write!(f, "{} is unreachable", self.0)
+///     }
 /// }
 ///
-/// # #[cfg(all(feature = "async-std-runtime", feature = "attributes"))]
-/// #[pyo3_asyncio::async_std::main]
-/// async fn main() -> PyResult<()> {
-///     Python::with_gil(|py| {
-///         let py_future = sleep_for(py, 1)?;
-///         pyo3_asyncio::async_std::into_future(py_future)
-///     })?
-///     .await?;
+/// impl std::error::Error for Unreachable {}
 ///
-///     Ok(())
+/// /// Awaitable ping that raises a `ConnectionError` for unreachable hosts
+/// #[pyfunction]
+/// fn ping(py: Python, host: String) -> PyResult<&PyAny> {
+///     pyo3_asyncio::async_std::future_into_py_with_error_map(
+///         py,
+///         async move {
+///             async_std::task::sleep(Duration::from_millis(10)).await;
+///             Err::<(), _>(Unreachable(host))
+///         },
+///         |e| PyConnectionError::new_err(e.to_string()),
+///     )
 /// }
-/// # #[cfg(not(all(feature = "async-std-runtime", feature = "attributes")))]
-/// # fn main() {}
 /// ```
-#[deprecated(
-    since = "0.15.0",
-    note = "Use pyo3_asyncio::async_std::local_future_into_py instead"
-)]
-#[allow(deprecated)]
-pub fn local_cancellable_future_into_py<F>(py: Python, fut: F) -> PyResult<&PyAny>
+pub fn future_into_py_with_error_map<F, T, E, M>(py: Python, fut: F, map_fn: M) -> PyResult<&PyAny>
 where
-    F: Future<Output = PyResult<PyObject>> + 'static,
+    F: Future<Output = Result<T, E>> + Send + 'static,
+    T: IntoPy<PyObject>,
+    M: FnOnce(E) -> PyErr + Send + 'static,
 {
-    generic::local_cancellable_future_into_py::<AsyncStdRuntime, _>(py, fut)
+    generic::future_into_py_with_error_map::<AsyncStdRuntime, F, T, E, M>(py, fut, map_fn)
 }
 
-/// Convert a Python `awaitable` into a Rust Future
+/// Convert a Rust Future into a Python awaitable that is only scheduled once it is awaited
 ///
-/// This function converts the `awaitable` into a Python Task using `run_coroutine_threadsafe`. A
-/// completion handler sends the result of this Task through a
-/// `futures::channel::oneshot::Sender<PyResult<PyObject>>` and the future returned by this function
-/// simply awaits the result through the `futures::channel::oneshot::Receiver<PyResult<PyObject>>`.
+/// Unlike [`future_into_py`], this does not need a running event loop, so awaitables can be built
+/// before the event loop starts. See
+/// [`generic::future_into_py_deferred`](`crate::generic::future_into_py_deferred`) for more
+/// details.
 ///
 /// # Arguments
-/// * `awaitable` - The Python `awaitable` to be converted
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
 ///
 /// # Examples
 ///
@@ -752,34 +1077,1844 @@ where
 /// const PYTHON_CODE: &'static str = r#"
 /// import asyncio
 ///
-/// async def py_sleep(duration):
-///     await asyncio.sleep(duration)
+/// async def main(aws):
+///     return await asyncio.gather(*aws)
 /// "#;
 ///
-/// async fn py_sleep(seconds: f32) -> PyResult<()> {
-///     let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
-///         Ok(
-///             PyModule::from_code(
-///                 py,
-///                 PYTHON_CODE,
-///                 "test_into_future/test_mod.py",
-///                 "test_mod"
-///             )?
-///             .into()
-///         )
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+///
+///     // build the awaitables before any event loop is running
+///     let sleeps = (1..=3u64)
+///         .map(|i| {
+///             pyo3_asyncio::async_std::future_into_py_deferred(py, async move {
+///                 async_std::task::sleep(Duration::from_millis(i * 10)).await;
+///                 Ok(i)
+///             })
+///         })
+///         .collect::<PyResult<Vec<_>>>()?;
+///
+///     let main = PyModule::from_code(py, PYTHON_CODE, "main.py", "main")?
+///         .call_method1("main", (sleeps,))?;
+///     let results = event_loop.call_method1("run_until_complete", (main,))?;
+///     assert_eq!(results.extract::<Vec<u64>>()?, vec![1, 2, 3]);
+///
+///     event_loop.call_method0("close")?;
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn future_into_py_deferred<F, T>(py: Python, fut: F) -> PyResult<&PyAny>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::future_into_py_deferred::<AsyncStdRuntime, F, T>(py, fut)
+}
+
+/// Convert a Rust Future into a self-contained Python awaitable
+///
+/// This function simply forwards the future to
+/// [`generic::future_into_py_portable`](`crate::generic::future_into_py_portable`). See
+/// [`generic::future_into_py_portable`](`crate::generic::future_into_py_portable`) for more
+/// details, including the tradeoffs compared to [`future_into_py`].
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// const PYTHON_CODE: &'static str = r#"
+/// import time
+///
+/// def drive(awaitable):
+///     it = awaitable.__await__()
+///     while True:
+///         try:
+///             next(it)
+///         except StopIteration as e:
+///             return e.value
+///         time.sleep(0.001)
+/// "#;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let answer = pyo3_asyncio::async_std::future_into_py_portable(py, async {
+///         async_std::task::sleep(Duration::from_millis(10)).await;
+///         Ok(42)
 ///     })?;
 ///
-///     Python::with_gil(|py| {
-///         pyo3_asyncio::async_std::into_future(
-///             test_mod
-///                 .call_method1(py, "py_sleep", (seconds.into_py(py),))?
-///                 .as_ref(py),
-///         )
-///     })?
-///     .await?;
-///     Ok(())    
-/// }
+///     // drive the awaitable by hand, without any event loop
+///     let driver = PyModule::from_code(py, PYTHON_CODE, "driver.py", "driver")?;
+///     assert_eq!(driver.call_method1("drive", (answer,))?.extract::<i32>()?, 42);
+///     Ok(())
+/// })
+/// .unwrap();
 /// ```
-pub fn into_future(awaitable: &PyAny) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send> {
-    generic::into_future::<AsyncStdRuntime>(awaitable)
+pub fn future_into_py_portable<F, T>(py: Python, fut: F) -> PyResult<&PyAny>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::future_into_py_portable::<AsyncStdRuntime, F, T>(py, fut)
+}
+
+/// Convert a Rust Future that produces a sequence of values into an `asyncio.Queue`
+///
+/// This function simply forwards `f` to
+/// [`generic::future_into_py_with_sink`](`crate::generic::future_into_py_with_sink`). See
+/// [`generic::future_into_py_with_sink`](`crate::generic::future_into_py_with_sink`) for more
+/// details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `f` - Called with the sink to build the Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Count to `n` asynchronously, i.e. `async for i in count_to(3): ...`
+/// #[pyfunction]
+/// fn count_to(py: Python, n: u64) -> PyResult<&PyAny> {
+///     pyo3_asyncio::async_std::future_into_py_with_sink(py, move |sink| async move {
+///         for i in 0..n {
+///             async_std::task::sleep(Duration::from_millis(100)).await;
+///             sink.send(i)?;
+///         }
+///         Ok(())
+///     })
+/// }
+/// ```
+pub fn future_into_py_with_sink<F, Fut>(py: Python, f: F) -> PyResult<&PyAny>
+where
+    F: FnOnce(generic::QueueSink) -> Fut,
+    Fut: Future<Output = PyResult<()>> + Send + 'static,
+{
+    generic::future_into_py_with_sink::<AsyncStdRuntime, F, Fut>(py, f)
+}
+
+/// Feed the values received from a channel into a bounded `asyncio.Queue`
+///
+/// This function simply forwards the receiver to
+/// [`generic::stream_into_py_queue`](`crate::generic::stream_into_py_queue`). See
+/// [`generic::stream_into_py_queue`](`crate::generic::stream_into_py_queue`) for more details.
+///
+/// Values are only received from the channel once the Python consumer has taken the previous one,
+/// so the senders wait whenever both the channel and the queue are full. The queue is closed once
+/// every sender has been dropped, which ends the consumer's `async for` loop.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `receiver` - The receiving half of the channel
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Stream events from a background task, i.e. `async for event in events(): ...`
+/// #[pyfunction]
+/// fn events(py: Python) -> PyResult<&PyAny> {
+///     let (tx, rx) = async_std::channel::bounded(16);
+///
+///     async_std::task::spawn(async move {
+///         for i in 0..10u32 {
+///             if tx.send(format!("event {}", i)).await.is_err() {
+///                 break;
+///             }
+///         }
+///     });
+///
+///     pyo3_asyncio::async_std::channel_to_py_queue(py, rx)
+/// }
+/// ```
+pub fn channel_to_py_queue<T>(
+    py: Python,
+    receiver: async_std::channel::Receiver<T>,
+) -> PyResult<&PyAny>
+where
+    T: IntoPy<PyObject> + Send + 'static,
+{
+    generic::stream_into_py_queue::<AsyncStdRuntime, _, T>(py, receiver)
+}
+
+/// Drive a Rust Future and report its outcome to Python callbacks
+///
+/// This function simply forwards the future and the callbacks to
+/// [`generic::future_into_callback`](`crate::generic::future_into_callback`). See
+/// [`generic::future_into_callback`](`crate::generic::future_into_callback`) for more details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be driven
+/// * `on_result` - Called with the value produced by `fut`
+/// * `on_error` - Called with the exception raised by `fut`
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Sleep for `secs`, then call `done(secs)`
+/// #[pyfunction]
+/// fn sleep_then(py: Python, secs: u64, done: &PyAny, failed: &PyAny) -> PyResult<()> {
+///     pyo3_asyncio::async_std::future_into_callback(
+///         py,
+///         async move {
+///             async_std::task::sleep(Duration::from_secs(secs)).await;
+///             Ok(secs)
+///         },
+///         done,
+///         failed,
+///     )
+/// }
+/// ```
+pub fn future_into_callback<F, T>(
+    py: Python,
+    fut: F,
+    on_result: &PyAny,
+    on_error: &PyAny,
+) -> PyResult<()>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::future_into_callback::<AsyncStdRuntime, F, T>(py, fut, on_result, on_error)
+}
+
+/// Convert a Rust async function into a Python callable that returns a new awaitable every time
+/// it is called
+///
+/// This is effectively an `async def` implemented in Rust. Each call extracts the positional
+/// arguments as `A` and converts the future returned by `f` with [`future_into_py`]. If the
+/// arguments cannot be extracted, the call raises a `TypeError` before any future is created.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `f` - The Rust async function to be converted. Its arguments are extracted from the tuple of
+///   positional arguments passed to the Python callable.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// #[pymodule]
+/// fn my_mod(py: Python, m: &PyModule) -> PyResult<()> {
+///     // Python can call `await my_mod.sleep_for(1)` as many times as it likes
+///     m.add(
+///         "sleep_for",
+///         pyo3_asyncio::async_std::async_fn_into_py(py, |(secs,): (u64,)| async move {
+///             async_std::task::sleep(Duration::from_secs(secs)).await;
+///             Ok(())
+///         })?,
+///     )?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn async_fn_into_py<F, A, Fut, T>(py: Python, f: F) -> PyResult<&PyAny>
+where
+    F: Fn(A) -> Fut + Send + 'static,
+    A: for<'p> FromPyObject<'p>,
+    Fut: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::async_fn_into_py::<AsyncStdRuntime, F, A, Fut, T>(py, f)
+}
+
+/// Build a Python async context manager from a pair of Rust async closures
+///
+/// The returned object implements `__aenter__` and `__aexit__`, so Python can use it with
+/// `async with`. Every time the block is entered, the future returned by `enter` is converted with
+/// [`future_into_py`] and its output is bound by `async with ... as`. When the block is exited, the
+/// future returned by `exit` is converted the same way. `exit` receives the exception raised inside
+/// of the block, if any, and returns whether that exception should be suppressed.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `enter` - Creates the future that is awaited by `__aenter__`
+/// * `exit` - Creates the future that is awaited by `__aexit__`. It returns `true` to suppress the
+///   exception that was raised inside of the `async with` block.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// #[pymodule]
+/// fn my_mod(py: Python, m: &PyModule) -> PyResult<()> {
+///     // async with my_mod.connection as conn: ...
+///     m.add(
+///         "connection",
+///         pyo3_asyncio::async_std::async_context_manager_into_py(
+///             py,
+///             || async move {
+///                 async_std::task::sleep(Duration::from_millis(10)).await;
+///                 Ok("connected")
+///             },
+///             |_exc| async move {
+///                 async_std::task::sleep(Duration::from_millis(10)).await;
+///                 Ok(false)
+///             },
+///         )?,
+///     )?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn async_context_manager_into_py<En, EnFut, T, Ex, ExFut>(
+    py: Python,
+    enter: En,
+    exit: Ex,
+) -> PyResult<&PyAny>
+where
+    En: Fn() -> EnFut + Send + 'static,
+    EnFut: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+    Ex: Fn(Option<PyErr>) -> ExFut + Send + 'static,
+    ExFut: Future<Output = PyResult<bool>> + Send + 'static,
+{
+    generic::async_context_manager_into_py::<AsyncStdRuntime, En, EnFut, T, Ex, ExFut>(
+        py, enter, exit,
+    )
+}
+
+/// Convert a Rust Stream into a Python async iterator
+///
+/// Every `__anext__` polls the stream for its next item, and `StopAsyncIteration` is raised once
+/// the stream returns `None`. See [`generic::stream_into_py`](`crate::generic::stream_into_py`) for
+/// more details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `stream` - The Rust stream to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use futures::{stream, StreamExt};
+/// use pyo3::prelude::*;
+///
+/// /// Async iterator over the numbers from 0 to `count`, one every 10ms
+/// #[pyfunction]
+/// fn ticks(py: Python, count: u64) -> PyResult<&PyAny> {
+///     let ticks = stream::iter(0..count).then(|i| async move {
+///         async_std::task::sleep(Duration::from_millis(10)).await;
+///         Ok(i)
+///     });
+///
+///     pyo3_asyncio::async_std::stream_into_py(py, ticks)
+/// }
+/// ```
+pub fn stream_into_py<S, T>(py: Python, stream: S) -> PyResult<&PyAny>
+where
+    S: Stream<Item = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject> + Send + 'static,
+{
+    generic::stream_into_py::<AsyncStdRuntime, S, T>(py, stream)
+}
+
+/// Convert a stream of Rust futures into a Python async iterator that yields their results as
+/// they complete
+///
+/// Up to `concurrency` of the futures are driven at once, like `StreamExt::buffer_unordered`. See
+/// [`generic::buffer_unordered_into_py`](`crate::generic::buffer_unordered_into_py`) for more
+/// details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `stream` - Stream of the futures to run
+/// * `concurrency` - Maximum number of futures to drive at once
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use futures::{stream, StreamExt};
+/// use pyo3::prelude::*;
+///
+/// /// Async iterator over `count` numbers, the largest (and slowest) ones first
+/// #[pyfunction]
+/// fn countdown(py: Python, count: u64) -> PyResult<&PyAny> {
+///     let jobs = stream::iter((0..count).rev()).map(|i| async move {
+///         async_std::task::sleep(Duration::from_millis(i * 10)).await;
+///         Ok(i)
+///     });
+///
+///     pyo3_asyncio::async_std::buffer_unordered_into_py(py, jobs, 4)
+/// }
+/// ```
+pub fn buffer_unordered_into_py<S, F, T>(
+    py: Python,
+    stream: S,
+    concurrency: usize,
+) -> PyResult<&PyAny>
+where
+    S: Stream<Item = F> + Send + 'static,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject> + Send + 'static,
+{
+    generic::buffer_unordered_into_py::<AsyncStdRuntime, S, F, T>(py, stream, concurrency)
+}
+
+/// Convert a Rust Future into a Python awaitable
+///
+/// __This function was deprecated in favor of [`future_into_py`] in `v0.15` because
+/// it became the default behaviour. In `v0.15`, any calls to this function can be seamlessly
+/// replaced with [`future_into_py`].__
+///
+/// __This function will be removed in `v0.16`__
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: &'p PyAny) -> PyResult<&'p PyAny> {
+///     let secs = secs.extract()?;
+///     pyo3_asyncio::async_std::cancellable_future_into_py(py, async move {
+///         async_std::task::sleep(Duration::from_secs(secs)).await;
+///         Python::with_gil(|py| Ok(py.None()))
+///     })
+/// }
+/// ```
+#[deprecated(
+    since = "0.15.0",
+    note = "Use pyo3_asyncio::async_std::future_into_py instead"
+)]
+#[allow(deprecated)]
+pub fn cancellable_future_into_py<F>(py: Python, fut: F) -> PyResult<&PyAny>
+where
+    F: Future<Output = PyResult<PyObject>> + Send + 'static,
+{
+    generic::cancellable_future_into_py::<AsyncStdRuntime, _>(py, fut)
+}
+
+/// Convert a `!Send` Rust Future into a Python awaitable
+///
+/// __This function will be removed in `v0.16`__
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop that the awaitable should be attached to
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::{rc::Rc, time::Duration};
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable non-send sleep function
+/// #[pyfunction]
+/// fn sleep_for(py: Python, secs: u64) -> PyResult<&PyAny> {
+///     // Rc is non-send so it cannot be passed into pyo3_asyncio::async_std::future_into_py
+///     let secs = Rc::new(secs);
+///     Ok(pyo3_asyncio::async_std::local_future_into_py_with_loop(
+///         pyo3_asyncio::async_std::get_current_loop(py)?,
+///         async move {
+///             async_std::task::sleep(Duration::from_secs(*secs)).await;
+///             Ok(Python::with_gil(|py| py.None()))
+///         }
+///     )?.into())
+/// }
+///
+/// # #[cfg(all(feature = "async-std-runtime", feature = "attributes"))]
+/// #[pyo3_asyncio::async_std::main]
+/// async fn main() -> PyResult<()> {
+///     Python::with_gil(|py| {
+///         let py_future = sleep_for(py, 1)?;
+///         pyo3_asyncio::async_std::into_future(py_future)
+///     })?
+///     .await?;
+///
+///     Ok(())
+/// }
+/// # #[cfg(not(all(feature = "async-std-runtime", feature = "attributes")))]
+/// # fn main() {}
+/// ```
+#[deprecated(
+    since = "0.15.0",
+    note = "Use pyo3_asyncio::async_std::local_future_into_py_with_locals instead"
+)]
+#[allow(deprecated)]
+pub fn local_future_into_py_with_loop<F>(event_loop: &PyAny, fut: F) -> PyResult<&PyAny>
+where
+    F: Future<Output = PyResult<PyObject>> + 'static,
+{
+    generic::local_future_into_py_with_loop::<AsyncStdRuntime, _>(event_loop, fut)
+}
+
+/// Convert a `!Send` Rust Future into a Python awaitable
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
+///
+/// The future runs on the thread that spawned it, which doesn't need to be the thread running the
+/// Python event loop. Its result is handed over to the event loop with `call_soon_threadsafe`, so
+/// the `asyncio.Future` is always resolved on the event loop's own thread, just like with the
+/// `Send` conversions.
+///
+/// Python `contextvars` are preserved when calling async Python functions within the Rust future
+/// via [`into_future`] (new behaviour in `v0.15`).
+///
+/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
+/// unfortunately fail to resolve them when called within the Rust future. This is because the
+/// function is being called from a Rust thread, not inside an actual Python coroutine context.
+/// >
+/// > As a workaround, you can get the `contextvars` from the current task locals using
+/// [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
+/// synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
+/// synchronous function, and restore the previous context when it returns or raises an exception.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::{rc::Rc, time::Duration};
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable non-send sleep function
+/// #[pyfunction]
+/// fn sleep_for(py: Python, secs: u64) -> PyResult<&PyAny> {
+///     // Rc is non-send so it cannot be passed into pyo3_asyncio::async_std::future_into_py
+///     let secs = Rc::new(secs);
+///     Ok(pyo3_asyncio::async_std::local_future_into_py_with_locals(
+///         py,
+///         pyo3_asyncio::async_std::get_current_locals(py)?,
+///         async move {
+///             async_std::task::sleep(Duration::from_secs(*secs)).await;
+///             Python::with_gil(|py| Ok(py.None()))
+///         }
+///     )?.into())
+/// }
+///
+/// # #[cfg(all(feature = "async-std-runtime", feature = "attributes"))]
+/// #[pyo3_asyncio::async_std::main]
+/// async fn main() -> PyResult<()> {
+///     Python::with_gil(|py| {
+///         let py_future = sleep_for(py, 1)?;
+///         pyo3_asyncio::async_std::into_future(py_future)
+///     })?
+///     .await?;
+///
+///     Ok(())
+/// }
+/// # #[cfg(not(all(feature = "async-std-runtime", feature = "attributes")))]
+/// # fn main() {}
+/// ```
+pub fn local_future_into_py_with_locals<F, T>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<&PyAny>
+where
+    F: Future<Output = PyResult<T>> + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::local_future_into_py_with_locals::<AsyncStdRuntime, _, T>(py, locals, fut)
+}
+
+/// Convert a `!Send` Rust Future into a Python awaitable
+///
+/// __This function was deprecated in favor of [`local_future_into_py_with_locals`] in `v0.15` because
+/// it became the default behaviour. In `v0.15`, any calls to this function should be
+/// replaced with [`local_future_into_py_with_locals`].__
+///
+/// __This function will be removed in `v0.16`__
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop that the awaitable should be attached to
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::{rc::Rc, time::Duration};
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable non-send sleep function
+/// #[pyfunction]
+/// fn sleep_for(py: Python, secs: u64) -> PyResult<&PyAny> {
+///     // Rc is non-send so it cannot be passed into pyo3_asyncio::async_std::future_into_py
+///     let secs = Rc::new(secs);
+///     Ok(pyo3_asyncio::async_std::local_cancellable_future_into_py_with_loop(
+///         pyo3_asyncio::async_std::get_current_loop(py)?,
+///         async move {
+///             async_std::task::sleep(Duration::from_secs(*secs)).await;
+///             Python::with_gil(|py| Ok(py.None()))
+///         }
+///     )?.into())
+/// }
+///
+/// # #[cfg(all(feature = "async-std-runtime", feature = "attributes"))]
+/// #[pyo3_asyncio::async_std::main]
+/// async fn main() -> PyResult<()> {
+///     Python::with_gil(|py| {
+///         let py_future = sleep_for(py, 1)?;
+///         pyo3_asyncio::async_std::into_future(py_future)
+///     })?
+///     .await?;
+///
+///     Ok(())
+/// }
+/// # #[cfg(not(all(feature = "async-std-runtime", feature = "attributes")))]
+/// # fn main() {}
+/// ```
+#[deprecated(
+    since = "0.15.0",
+    note = "Use pyo3_asyncio::async_std::local_future_into_py_with_locals instead"
+)]
+#[allow(deprecated)]
+pub fn local_cancellable_future_into_py_with_loop<F>(event_loop: &PyAny, fut: F) -> PyResult<&PyAny>
+where
+    F: Future<Output = PyResult<PyObject>> + 'static,
+{
+    generic::local_cancellable_future_into_py_with_loop::<AsyncStdRuntime, _>(event_loop, fut)
+}
+
+/// Convert a `!Send` Rust Future into a Python awaitable
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
+///
+/// Python `contextvars` are preserved when calling async Python functions within the Rust future
+/// via [`into_future`] (new behaviour in `v0.15`).
+///
+/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
+/// unfortunately fail to resolve them when called within the Rust future. This is because the
+/// function is being called from a Rust thread, not inside an actual Python coroutine context.
+/// >
+/// > As a workaround, you can get the `contextvars` from the current task locals using
+/// [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
+/// synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
+/// synchronous function, and restore the previous context when it returns or raises an exception.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::{rc::Rc, time::Duration};
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable non-send sleep function
+/// #[pyfunction]
+/// fn sleep_for(py: Python, secs: u64) -> PyResult<&PyAny> {
+///     // Rc is non-send so it cannot be passed into pyo3_asyncio::async_std::future_into_py
+///     let secs = Rc::new(secs);
+///     pyo3_asyncio::async_std::local_future_into_py(py, async move {
+///         async_std::task::sleep(Duration::from_secs(*secs)).await;
+///         Ok(())
+///     })
+/// }
+///
+/// # #[cfg(all(feature = "async-std-runtime", feature = "attributes"))]
+/// #[pyo3_asyncio::async_std::main]
+/// async fn main() -> PyResult<()> {
+///     Python::with_gil(|py| {
+///         let py_future = sleep_for(py, 1)?;
+///         pyo3_asyncio::async_std::into_future(py_future)
+///     })?
+///     .await?;
+///
+///     Ok(())
+/// }
+/// # #[cfg(not(all(feature = "async-std-runtime", feature = "attributes")))]
+/// # fn main() {}
+/// ```
+pub fn local_future_into_py<F, T>(py: Python, fut: F) -> PyResult<&PyAny>
+where
+    F: Future<Output = PyResult<T>> + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::local_future_into_py::<AsyncStdRuntime, _, T>(py, fut)
+}
+
+/// Convert a `!Send` Rust Future into a Python awaitable
+///
+/// __This function was deprecated in favor of [`local_future_into_py`] in `v0.15` because
+/// it became the default behaviour. In `v0.15`, any calls to this function can be seamlessly
+/// replaced with [`local_future_into_py`].__
+///
+/// __This function will be removed in `v0.16`__
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::{rc::Rc, time::Duration};
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable non-send sleep function
+/// #[pyfunction]
+/// fn sleep_for(py: Python, secs: u64) -> PyResult<&PyAny> {
+///     // Rc is non-send so it cannot be passed into pyo3_asyncio::async_std::future_into_py
+///     let secs = Rc::new(secs);
+///     pyo3_asyncio::async_std::local_cancellable_future_into_py(py, async move {
+///         async_std::task::sleep(Duration::from_secs(*secs)).await;
+///         Python::with_gil(|py| Ok(py.None()))
+///     })
+/// }
+///
+/// # #[cfg(all(feature = "async-std-runtime", feature = "attributes"))]
+/// #[pyo3_asyncio::async_std::main]
+/// async fn main() -> PyResult<()> {
+///     Python::with_gil(|py| {
+///         let py_future = sleep_for(py, 1)?;
+///         pyo3_asyncio::async_std::into_future(py_future)
+///     })?
+///     .await?;
+///
+///     Ok(())
+/// }
+/// # #[cfg(not(all(feature = "async-std-runtime", feature = "attributes")))]
+/// # fn main() {}
+/// ```
+#[deprecated(
+    since = "0.15.0",
+    note = "Use pyo3_asyncio::async_std::local_future_into_py instead"
+)]
+#[allow(deprecated)]
+pub fn local_cancellable_future_into_py<F>(py: Python, fut: F) -> PyResult<&PyAny>
+where
+    F: Future<Output = PyResult<PyObject>> + 'static,
+{
+    generic::local_cancellable_future_into_py::<AsyncStdRuntime, _>(py, fut)
+}
+
+/// Convert a Python `awaitable` into a Rust Future
+///
+/// This function converts the `awaitable` into a Python Task using `run_coroutine_threadsafe`. A
+/// completion handler sends the result of this Task through a
+/// `futures::channel::oneshot::Sender<PyResult<PyObject>>` and the future returned by this function
+/// simply awaits the result through the `futures::channel::oneshot::Receiver<PyResult<PyObject>>`.
+///
+/// The returned [`PyFuture`](`crate::PyFuture`) can also abort the Python Task and check whether
+/// it has finished, like a `JoinHandle`.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// const PYTHON_CODE: &'static str = r#"
+/// import asyncio
+///
+/// async def py_sleep(duration):
+///     await asyncio.sleep(duration)
+/// "#;
+///
+/// async fn py_sleep(seconds: f32) -> PyResult<()> {
+///     let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+///         Ok(
+///             PyModule::from_code(
+///                 py,
+///                 PYTHON_CODE,
+///                 "test_into_future/test_mod.py",
+///                 "test_mod"
+///             )?
+///             .into()
+///         )
+///     })?;
+///
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::into_future(
+///             test_mod
+///                 .call_method1(py, "py_sleep", (seconds.into_py(py),))?
+///                 .as_ref(py),
+///         )
+///     })?
+///     .await?;
+///     Ok(())    
+/// }
+/// ```
+pub fn into_future(awaitable: &PyAny) -> PyResult<PyFuture> {
+    generic::into_future::<AsyncStdRuntime>(awaitable)
+}
+
+/// Convert a Python `awaitable` into a boxed Rust Future
+///
+/// This function simply forwards the awaitable to
+/// [`generic::into_future_boxed`](`crate::generic::into_future_boxed`). See
+/// [`PyFutureResult`](`crate::PyFutureResult`) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+/// use pyo3_asyncio::PyFutureResult;
+///
+/// /// Bridge a batch of Python requests, waiting on some of them with a timer
+/// fn bridge_all(
+///     py: Python,
+///     requests: &[PyObject],
+///     timed: &[PyObject],
+/// ) -> PyResult<Vec<PyFutureResult>> {
+///     let mut futs = Vec::new();
+///
+///     for request in requests {
+///         futs.push(pyo3_asyncio::async_std::into_future_boxed(request.as_ref(py))?);
+///     }
+///     for request in timed {
+///         let fut = pyo3_asyncio::async_std::into_future_timed(request.as_ref(py))?;
+///         futs.push(Box::pin(async move { Ok(fut.await?.0) }) as PyFutureResult);
+///     }
+///
+///     Ok(futs)
+/// }
+/// ```
+pub fn into_future_boxed(awaitable: &PyAny) -> PyResult<PyFutureResult> {
+    generic::into_future_boxed::<AsyncStdRuntime>(awaitable)
+}
+
+/// Convert a Python `awaitable` into a Rust Future that runs in the given contextvars `Context`
+///
+/// This function simply forwards the awaitable, the context and the task locals returned by
+/// [`get_current_locals`] to
+/// [`into_future_in_context_with_locals`](`crate::into_future_in_context_with_locals`). See
+/// [`into_future_in_context_with_locals`](`crate::into_future_in_context_with_locals`) for more
+/// details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `context` - The `contextvars.Context` the awaitable should run in
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Run `handler(request)` in the context that belongs to the request
+/// async fn handle_request(
+///     handler: PyObject,
+///     request: PyObject,
+///     context: PyObject,
+/// ) -> PyResult<PyObject> {
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::into_future_in_context(
+///             handler.as_ref(py).call1((request,))?,
+///             context.as_ref(py),
+///         )
+///     })?
+///     .await
+/// }
+/// ```
+pub fn into_future_in_context(awaitable: &PyAny, context: &PyAny) -> PyResult<PyFuture> {
+    generic::into_future_in_context::<AsyncStdRuntime>(awaitable, context)
+}
+
+/// Convert a Python `awaitable` into a Rust Future that is bounded by `asyncio.timeout(delay)`
+///
+/// This function simply forwards the awaitable, the delay and the task locals returned by
+/// [`get_current_locals`] to
+/// [`into_future_with_asyncio_timeout_with_locals`](`crate::into_future_with_asyncio_timeout_with_locals`).
+/// See
+/// [`into_future_with_asyncio_timeout_with_locals`](`crate::into_future_with_asyncio_timeout_with_locals`)
+/// for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `delay` - How long the awaitable may run before it is cancelled
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::{exceptions::PyTimeoutError, prelude::*};
+///
+/// /// Fetch a page, giving up after 5s like `async with asyncio.timeout(5)` would
+/// async fn fetch(fetch_page: PyObject) -> PyResult<Option<PyObject>> {
+///     let result = Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::into_future_with_asyncio_timeout(
+///             fetch_page.as_ref(py).call0()?,
+///             Duration::from_secs(5),
+///         )
+///     })?
+///     .await;
+///
+///     match result {
+///         Ok(page) => Ok(Some(page)),
+///         Err(e) if Python::with_gil(|py| e.is_instance_of::<PyTimeoutError>(py)) => Ok(None),
+///         Err(e) => Err(e),
+///     }
+/// }
+/// ```
+pub fn into_future_with_asyncio_timeout(awaitable: &PyAny, delay: Duration) -> PyResult<PyFuture> {
+    generic::into_future_with_asyncio_timeout::<AsyncStdRuntime>(awaitable, delay)
+}
+
+/// Convert a Python `awaitable` into a Rust Future and an `AbortRegistration` that is aborted if
+/// the awaitable's Python Task is cancelled
+///
+/// This function simply forwards the awaitable and the task locals returned by
+/// [`get_current_locals`] to
+/// [`into_future_abortable_with_locals`](`crate::into_future_abortable_with_locals`). Wrap any
+/// downstream Rust work in `futures::future::Abortable` with the returned registration to have it
+/// cancelled along with the Python Task.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use futures::future::Abortable;
+/// use pyo3::prelude::*;
+///
+/// async fn sleep_and_notify(py_sleep: PyObject) -> PyResult<()> {
+///     let (py_sleep, registration) = Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::into_future_abortable(py_sleep.as_ref(py))
+///     })?;
+///
+///     // If Python cancels the sleep, the notification is aborted as well
+///     let notify = Abortable::new(async_std::task::sleep(Duration::from_secs(1)), registration);
+///     let (result, _) = futures::join!(py_sleep, notify);
+///     result?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn into_future_abortable(
+    awaitable: &PyAny,
+) -> PyResult<(
+    impl Future<Output = PyResult<PyObject>> + Send,
+    AbortRegistration,
+)> {
+    generic::into_future_abortable::<AsyncStdRuntime>(awaitable)
+}
+
+/// Convert a Python `awaitable` into a Rust Future that calls `on_beat` every `interval` while it
+/// is pending
+///
+/// The heartbeats are timed with async-std's timer, the first one firing `interval` after the future is
+/// first polled. See
+/// [`into_future_with_heartbeat_with_locals`](`crate::into_future_with_heartbeat_with_locals`)
+/// for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `interval` - Time between heartbeats
+/// * `on_beat` - Called once for every heartbeat
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// async fn train(py_train: PyObject) -> PyResult<PyObject> {
+///     let mut elapsed = 0;
+///
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::into_future_with_heartbeat(
+///             py_train.as_ref(py),
+///             Duration::from_secs(1),
+///             move || {
+///                 elapsed += 1;
+///                 println!("still training after {}s", elapsed);
+///             },
+///         )
+///     })?
+///     .await
+/// }
+/// ```
+pub fn into_future_with_heartbeat<B>(
+    awaitable: &PyAny,
+    interval: Duration,
+    on_beat: B,
+) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send>
+where
+    B: FnMut() + Send,
+{
+    let ticks = futures::stream::unfold((), move |_| async move {
+        task::sleep(interval).await;
+        Some(((), ()))
+    });
+
+    crate::into_future_with_heartbeat_with_locals(
+        &get_current_locals(awaitable.py())?,
+        awaitable,
+        ticks,
+        on_beat,
+    )
+}
+
+/// Convert a Python `awaitable` into a Rust Future that runs `on_finish` once it is done
+///
+/// This function simply forwards the awaitable, the finalizer and the task locals returned by
+/// [`get_current_locals`] to
+/// [`into_future_finally_with_locals`](`crate::into_future_finally_with_locals`). See
+/// [`into_future_finally_with_locals`](`crate::into_future_finally_with_locals`) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `on_finish` - Called exactly once with the result of the awaitable
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::{
+///     atomic::{AtomicUsize, Ordering},
+///     Arc,
+/// };
+///
+/// use pyo3::prelude::*;
+///
+/// /// Run `query` while it counts against the number of open connections
+/// async fn run_query(query: PyObject, open: Arc<AtomicUsize>) -> PyResult<PyObject> {
+///     open.fetch_add(1, Ordering::SeqCst);
+///
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::into_future_finally(query.as_ref(py), move |_py, _result| {
+///             // runs even if the caller drops this future early
+///             open.fetch_sub(1, Ordering::SeqCst);
+///         })
+///     })?
+///     .await
+/// }
+/// ```
+pub fn into_future_finally<F>(
+    awaitable: &PyAny,
+    on_finish: F,
+) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send>
+where
+    F: FnOnce(Python, &PyResult<PyObject>) + Send,
+{
+    generic::into_future_finally::<AsyncStdRuntime, F>(awaitable, on_finish)
+}
+
+/// Convert a Python `awaitable` into a Rust Future whose result is transformed by `f` under the GIL
+///
+/// This function simply forwards the awaitable, the transform and the task locals returned by
+/// [`get_current_locals`] to
+/// [`into_future_then_with_locals`](`crate::into_future_then_with_locals`). See
+/// [`into_future_then_with_locals`](`crate::into_future_then_with_locals`) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `f` - Transforms the result of the awaitable
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Fetch a JSON document and decode it without acquiring the GIL a second time
+/// async fn fetch_json(fetch: PyObject) -> PyResult<PyObject> {
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::into_future_then(fetch.as_ref(py).call0()?, |py, body| {
+///             Ok(py.import("json")?.call_method1("loads", (body,))?.into())
+///         })
+///     })?
+///     .await
+/// }
+/// ```
+pub fn into_future_then<F, T>(
+    awaitable: &PyAny,
+    f: F,
+) -> PyResult<impl Future<Output = PyResult<T>> + Send>
+where
+    F: FnOnce(Python, PyObject) -> PyResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    generic::into_future_then::<AsyncStdRuntime, F, T>(awaitable, f)
+}
+
+/// Convert a Python `awaitable` into a Rust Future that waits for it for at most `timeout` without
+/// cancelling it
+///
+/// The returned future resolves to [`Peek::Ready`] if the awaitable completes within `timeout`, or
+/// to [`Peek::Pending`] with a [`PyFuture`] that is still bridged to the running Python Task
+/// otherwise. See [`PyFuture::peek`] for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `timeout` - How long to wait for the awaitable
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+/// use pyo3_asyncio::Peek;
+///
+/// /// Await the training coroutine, reporting every 10s that it is still running
+/// async fn train(py_train: PyObject) -> PyResult<PyObject> {
+///     let mut peek = Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::peek_with_timeout(py_train.as_ref(py), Duration::from_secs(10))
+///     })?
+///     .await;
+///
+///     loop {
+///         match peek {
+///             Peek::Ready(result) => return result,
+///             Peek::Pending(fut) => {
+///                 println!("still training...");
+///                 peek = fut.peek(async_std::task::sleep(Duration::from_secs(10))).await;
+///             }
+///         }
+///     }
+/// }
+/// ```
+pub fn peek_with_timeout(
+    awaitable: &PyAny,
+    timeout: Duration,
+) -> PyResult<impl Future<Output = Peek> + Send> {
+    Ok(into_future(awaitable)?.peek(task::sleep(timeout)))
+}
+
+/// Convert a Python `awaitable` into a Rust Future that cancels it after `timeout`
+///
+/// If the awaitable doesn't complete within `timeout`, its Python Task is cancelled and the
+/// returned future resolves to an `asyncio.TimeoutError`, like `asyncio.wait_for` would. See
+/// [`PyFuture::with_deadline`] for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `timeout` - How long to wait for the awaitable
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Fetch a value from a Python cache, giving up after 100ms
+/// async fn fetch(py_get: PyObject) -> PyResult<Option<PyObject>> {
+///     let result = Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::into_future_with_timeout(
+///             py_get.as_ref(py),
+///             Duration::from_millis(100),
+///         )
+///     })?
+///     .await;
+///
+///     match result {
+///         Ok(value) => Ok(Some(value)),
+///         Err(e) if Python::with_gil(|py| -> PyResult<bool> {
+///             Ok(e.matches(py, py.import("asyncio")?.getattr("TimeoutError")?))
+///         })? => Ok(None),
+///         Err(e) => Err(e),
+///     }
+/// }
+/// ```
+pub fn into_future_with_timeout(
+    awaitable: &PyAny,
+    timeout: Duration,
+) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send> {
+    Ok(into_future(awaitable)?.with_deadline(task::sleep(timeout)))
+}
+
+/// Convert a Python `awaitable` into a Rust Future that resolves to `None` after `timeout`
+///
+/// If the awaitable completes within `timeout`, the returned future resolves to `Some` with its
+/// result. Otherwise its Python Task is cancelled and the returned future resolves to `Ok(None)`
+/// rather than an `asyncio.TimeoutError` like [`into_future_with_timeout`] does. See
+/// [`PyFuture::or_deadline`] for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `timeout` - How long to wait for the awaitable
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Fetch a value from a Python cache, giving up after 100ms
+/// async fn fetch(py_get: PyObject) -> PyResult<Option<PyObject>> {
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::into_future_or_timeout(py_get.as_ref(py), Duration::from_millis(100))
+///     })?
+///     .await
+/// }
+/// ```
+pub fn into_future_or_timeout(
+    awaitable: &PyAny,
+    timeout: Duration,
+) -> PyResult<impl Future<Output = PyResult<Option<PyObject>>> + Send> {
+    Ok(into_future(awaitable)?.or_deadline(task::sleep(timeout)))
+}
+
+/// Convert a Python `awaitable` into a Rust Future that also resolves to the time it took
+///
+/// This function simply forwards the awaitable and the task locals returned by
+/// [`get_current_locals`] to
+/// [`into_future_timed_with_locals`](`crate::into_future_timed_with_locals`). See
+/// [`into_future_timed_with_locals`](`crate::into_future_timed_with_locals`) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Run a Python query, logging how long it took
+/// async fn run_query(query: PyObject) -> PyResult<PyObject> {
+///     let (rows, elapsed) =
+///         Python::with_gil(|py| pyo3_asyncio::async_std::into_future_timed(query.as_ref(py)))?.await?;
+///
+///     println!("query completed in {:?}", elapsed);
+///     Ok(rows)
+/// }
+/// ```
+pub fn into_future_timed(
+    awaitable: &PyAny,
+) -> PyResult<impl Future<Output = PyResult<(PyObject, Duration)>> + Send> {
+    generic::into_future_timed::<AsyncStdRuntime>(awaitable)
+}
+
+/// Convert a Python `awaitable` into a Rust Future that waits for a free slot of `bridge`
+///
+/// This function simply forwards the awaitable and the task locals returned by
+/// [`get_current_locals`] to
+/// [`RateLimitedBridge::into_future_with_locals`](`crate::RateLimitedBridge::into_future_with_locals`).
+/// See [`RateLimitedBridge`](`crate::RateLimitedBridge`) for more details.
+///
+/// # Arguments
+/// * `bridge` - Limits how many awaitables are in flight at once
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use once_cell::sync::Lazy;
+/// use pyo3::prelude::*;
+/// use pyo3_asyncio::RateLimitedBridge;
+///
+/// /// Shared by every call into the Python API client
+/// static API_LIMIT: Lazy<RateLimitedBridge> = Lazy::new(|| RateLimitedBridge::new(4));
+///
+/// async fn call_api(request: PyObject) -> PyResult<PyObject> {
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::into_future_rate_limited(&API_LIMIT, request.as_ref(py))
+///     })?
+///     .await
+/// }
+/// ```
+pub fn into_future_rate_limited(
+    bridge: &RateLimitedBridge,
+    awaitable: &PyAny,
+) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send> {
+    generic::into_future_rate_limited::<AsyncStdRuntime>(bridge, awaitable)
+}
+
+/// Convert a Python `awaitable` into a Rust Future that discards its result
+///
+/// This function simply forwards the awaitable and the task locals returned by
+/// [`get_current_locals`] to
+/// [`into_future_ignore_result_with_locals`](`crate::into_future_ignore_result_with_locals`). It
+/// is cheaper than [`into_future`] for awaiting many small awaitables whose values are not needed.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// async fn yield_to_python(times: usize) -> PyResult<()> {
+///     for _ in 0..times {
+///         Python::with_gil(|py| {
+///             pyo3_asyncio::async_std::into_future_ignore_result(
+///                 py.import("asyncio")?.call_method1("sleep", (0,))?,
+///             )
+///         })?
+///         .await?;
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub fn into_future_ignore_result(
+    awaitable: &PyAny,
+) -> PyResult<impl Future<Output = PyResult<()>> + Send> {
+    generic::into_future_ignore_result::<AsyncStdRuntime>(awaitable)
+}
+
+/// Convert a Python async iterator into a Rust Stream
+///
+/// This function simply forwards the async iterator and the task locals returned by
+/// [`get_current_locals`] to [`into_stream_with_locals`](`crate::into_stream_with_locals`). See
+/// [`into_stream_with_locals`](`crate::into_stream_with_locals`) for more details.
+///
+/// # Arguments
+/// * `gen` - The Python async iterable to be converted
+///
+/// # Examples
+///
+/// ```
+/// use futures::StreamExt;
+/// use pyo3::prelude::*;
+///
+/// /// Take the first row from an `async def` generator, leaving the rest unconsumed
+/// async fn first_row(rows: PyObject) -> PyResult<Option<PyObject>> {
+///     let mut rows = Python::with_gil(|py| pyo3_asyncio::async_std::into_stream(rows.as_ref(py)))?;
+///
+///     // dropping the stream afterwards schedules `rows.aclose()` on the event loop
+///     rows.next().await.transpose()
+/// }
+/// ```
+pub fn into_stream(gen: &PyAny) -> PyResult<impl Stream<Item = PyResult<PyObject>> + Send> {
+    generic::into_stream::<AsyncStdRuntime>(gen)
+}
+
+/// Convert a collection of Python awaitables into a Rust Stream of their results in completion
+/// order
+///
+/// This function simply forwards the awaitables and the task locals returned by
+/// [`get_current_locals`] to
+/// [`as_completed_stream_with_locals`](`crate::as_completed_stream_with_locals`). See
+/// [`as_completed_stream_with_locals`](`crate::as_completed_stream_with_locals`) for more details.
+///
+/// # Arguments
+/// * `awaitables` - A Python iterable of awaitables
+///
+/// # Examples
+///
+/// ```
+/// use futures::StreamExt;
+/// use pyo3::prelude::*;
+///
+/// /// Collect the results of `requests` as they finish, skipping the ones that failed
+/// async fn fetch_all(requests: PyObject) -> PyResult<Vec<PyObject>> {
+///     let results = Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::as_completed_stream(requests.as_ref(py))
+///     })?;
+///
+///     Ok(results.filter_map(|res| async move { res.ok() }).collect().await)
+/// }
+/// ```
+pub fn as_completed_stream(
+    awaitables: &PyAny,
+) -> PyResult<impl Stream<Item = PyResult<PyObject>> + Send> {
+    generic::as_completed_stream::<AsyncStdRuntime>(awaitables)
+}
+
+/// Convert a Python `asyncio.Queue` into a Rust Stream of its items
+///
+/// This function simply forwards the queue, the sentinel and the task locals returned by
+/// [`get_current_locals`] to
+/// [`py_queue_to_stream_with_locals`](`crate::py_queue_to_stream_with_locals`). See
+/// [`py_queue_to_stream_with_locals`](`crate::py_queue_to_stream_with_locals`) for more details.
+///
+/// # Arguments
+/// * `queue` - The Python `asyncio.Queue` to consume
+/// * `sentinel` - The item that ends the stream, or `None` for an infinite stream
+///
+/// # Examples
+///
+/// ```
+/// use futures::TryStreamExt;
+/// use pyo3::prelude::*;
+///
+/// /// Handle the events that Python puts on `events` until it puts `None` there
+/// async fn handle_events(events: PyObject) -> PyResult<()> {
+///     let events = Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::py_queue_to_stream(events.as_ref(py), Some(py.None().into_ref(py)))
+///     })?;
+///
+///     events
+///         .try_for_each(|event| async move {
+///             Python::with_gil(|py| println!("got {}", event.as_ref(py)));
+///             Ok(())
+///         })
+///         .await
+/// }
+/// ```
+pub fn py_queue_to_stream(
+    queue: &PyAny,
+    sentinel: Option<&PyAny>,
+) -> PyResult<impl Stream<Item = PyResult<PyObject>> + Send> {
+    generic::py_queue_to_stream::<AsyncStdRuntime>(queue, sentinel)
+}
+
+/// Create an empty [`PyAsyncExitStack`](`crate::PyAsyncExitStack`) on the current event loop
+///
+/// This function simply forwards the task locals returned by [`get_current_locals`] to
+/// [`PyAsyncExitStack::with_locals`](`crate::PyAsyncExitStack::with_locals`). See
+/// [`PyAsyncExitStack`](`crate::PyAsyncExitStack`) for more details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Hold a lock and a connection, exiting both in reverse order when done
+/// async fn with_resources(lock: PyObject, conn: PyObject) -> PyResult<()> {
+///     let stack = Python::with_gil(pyo3_asyncio::async_std::async_exit_stack)?;
+///
+///     Python::with_gil(|py| stack.enter_async_context(lock.as_ref(py)))?.await?;
+///     let conn = Python::with_gil(|py| stack.enter_async_context(conn.as_ref(py)))?.await?;
+///
+///     // ... use the connection ...
+///
+///     Python::with_gil(|py| stack.aclose(py))?.await?;
+///     Ok(())
+/// }
+/// ```
+pub fn async_exit_stack(py: Python) -> PyResult<PyAsyncExitStack> {
+    generic::async_exit_stack::<AsyncStdRuntime>(py)
+}
+
+/// Run `body` inside the Python async context manager `cm`, like `async with cm as value: ...`
+///
+/// This function simply forwards the context manager, the body and the task locals returned by
+/// [`get_current_locals`] to [`async_with_with_locals`](`crate::async_with_with_locals`). See
+/// [`async_with_with_locals`](`crate::async_with_with_locals`) for more details.
+///
+/// # Arguments
+/// * `cm` - The Python async context manager
+/// * `body` - Called with the value returned by `cm.__aenter__()`
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Run `query` on a connection checked out of `pool`, which is returned to the pool even if
+/// /// the query fails
+/// async fn fetch(pool: PyObject, query: String) -> PyResult<Option<PyObject>> {
+///     Python::with_gil(|py| {
+///         let conn = pool.as_ref(py).call_method0("acquire")?;
+///         pyo3_asyncio::async_std::async_with(conn, |conn| async move {
+///             Python::with_gil(|py| {
+///                 let rows = conn.as_ref(py).call_method1("fetch", (query,))?;
+///                 pyo3_asyncio::async_std::into_future(rows)
+///             })?
+///             .await
+///         })
+///     })?
+///     .await
+/// }
+/// ```
+pub fn async_with<B, Fut, T>(
+    cm: &PyAny,
+    body: B,
+) -> PyResult<impl Future<Output = PyResult<Option<T>>> + Send>
+where
+    B: FnOnce(PyObject) -> Fut + Send,
+    Fut: Future<Output = PyResult<T>> + Send,
+    T: Send,
+{
+    generic::async_with::<AsyncStdRuntime, B, Fut, T>(cm, body)
+}
+
+/// Run a Rust Future to completion on a thread of the current Python event loop's default executor
+///
+/// This function simply forwards the future and the task locals returned by
+/// [`get_current_locals`] to
+/// [`run_on_python_executor_with_locals`](`crate::run_on_python_executor_with_locals`). See
+/// [`run_on_python_executor_with_locals`](`crate::run_on_python_executor_with_locals`) for more
+/// details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to run
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Hash `data` on one of Python's executor threads
+/// #[pyfunction]
+/// fn checksum(py: Python, data: Vec<u8>) -> PyResult<&PyAny> {
+///     let sum = pyo3_asyncio::async_std::run_on_python_executor(py, async move {
+///         data.iter().map(|b| *b as u64).sum::<u64>()
+///     })?;
+///
+///     pyo3_asyncio::async_std::future_into_py(py, sum)
+/// }
+/// ```
+pub fn run_on_python_executor<F, T>(
+    py: Python,
+    fut: F,
+) -> PyResult<impl Future<Output = PyResult<T>> + Send>
+where
+    F: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    generic::run_on_python_executor::<AsyncStdRuntime, F, T>(py, fut)
+}
+
+/// Send `value` into a Python async generator and convert the step into a Rust Future
+///
+/// This function simply forwards the generator, the value and the task locals returned by
+/// [`get_current_locals`] to [`agen_send_with_locals`](`crate::agen_send_with_locals`). See
+/// [`agen_send_with_locals`](`crate::agen_send_with_locals`) for more details.
+///
+/// # Arguments
+/// * `agen` - The Python async generator
+/// * `value` - The value that the pending `yield` expression evaluates to
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Feed `values` to an accumulator written as an `async def` generator, returning its totals
+/// async fn accumulate(acc: PyObject, values: Vec<i64>) -> PyResult<Vec<i64>> {
+///     let mut totals = vec![];
+///
+///     // prime the generator up to its first `yield`
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::agen_send(acc.as_ref(py), py.None().as_ref(py))
+///     })?
+///     .await?;
+///
+///     for value in values {
+///         let total = Python::with_gil(|py| {
+///             pyo3_asyncio::async_std::agen_send(acc.as_ref(py), value.into_py(py).as_ref(py))
+///         })?
+///         .await?;
+///
+///         match total {
+///             Some(total) => totals.push(Python::with_gil(|py| total.extract(py))?),
+///             // the generator returned
+///             None => break,
+///         }
+///     }
+///
+///     Ok(totals)
+/// }
+/// ```
+pub fn agen_send(
+    agen: &PyAny,
+    value: &PyAny,
+) -> PyResult<impl Future<Output = PyResult<Option<PyObject>>> + Send> {
+    generic::agen_send::<AsyncStdRuntime>(agen, value)
+}
+
+/// Throw `exc` into a Python async generator and convert the step into a Rust Future
+///
+/// This function simply forwards the generator, the exception and the task locals returned by
+/// [`get_current_locals`] to [`agen_throw_with_locals`](`crate::agen_throw_with_locals`). See
+/// [`agen_throw_with_locals`](`crate::agen_throw_with_locals`) for more details.
+///
+/// # Arguments
+/// * `agen` - The Python async generator
+/// * `exc` - The exception to raise inside the generator
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::{exceptions::PyTimeoutError, prelude::*};
+///
+/// /// Tell a producer that the consumer gave up, returning what it yields in response
+/// async fn give_up(producer: PyObject) -> PyResult<Option<PyObject>> {
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::agen_throw(
+///             producer.as_ref(py),
+///             PyTimeoutError::new_err("the consumer timed out"),
+///         )
+///     })?
+///     .await
+/// }
+/// ```
+pub fn agen_throw(
+    agen: &PyAny,
+    exc: PyErr,
+) -> PyResult<impl Future<Output = PyResult<Option<PyObject>>> + Send> {
+    generic::agen_throw::<AsyncStdRuntime>(agen, exc)
+}
+
+/// Get a Rust Stream of the exceptions reported to the current event loop's exception handler
+///
+/// This function simply forwards the task locals returned by [`get_current_locals`] to
+/// [`loop_exception_stream_with_locals`](`crate::loop_exception_stream_with_locals`). See
+/// [`loop_exception_stream_with_locals`](`crate::loop_exception_stream_with_locals`) for more
+/// details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+///
+/// # Examples
+///
+/// ```
+/// use futures::StreamExt;
+/// use pyo3::prelude::*;
+///
+/// /// Report every asyncio-level error (i.e. `Task exception was never retrieved`)
+/// fn watch_loop_exceptions(py: Python) -> PyResult<()> {
+///     let mut exceptions = pyo3_asyncio::async_std::loop_exception_stream(py)?;
+///
+///     async_std::task::spawn(async move {
+///         while let Some(context) = exceptions.next().await {
+///             Python::with_gil(|py| {
+///                 eprintln!("asyncio error: {}", context.as_ref(py));
+///             });
+///         }
+///     });
+///
+///     Ok(())
+/// }
+/// ```
+pub fn loop_exception_stream(py: Python) -> PyResult<impl Stream<Item = PyObject> + Send> {
+    generic::loop_exception_stream::<AsyncStdRuntime>(py)
+}
+
+/// Cancel a Python task and wait for it to actually finish
+///
+/// This function simply forwards the task and the task locals returned by [`get_current_locals`]
+/// to [`cancel_and_wait_with_locals`](`crate::cancel_and_wait_with_locals`). The returned future
+/// resolves to `true` once the task has finished unwinding, or `false` if it is still running when
+/// the `timeout` elapses.
+///
+/// # Arguments
+/// * `task` - The Python task or future to cancel
+/// * `timeout` - How long to wait for the task to finish, or `None` to wait indefinitely
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// async fn stop_worker(worker: PyObject) -> PyResult<bool> {
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::async_std::cancel_and_wait(worker.as_ref(py), Some(Duration::from_secs(5)))
+///     })?
+///     .await
+/// }
+/// ```
+pub fn cancel_and_wait(
+    task: &PyAny,
+    timeout: Option<Duration>,
+) -> PyResult<impl Future<Output = PyResult<bool>> + Send> {
+    generic::cancel_and_wait::<AsyncStdRuntime>(task, timeout)
+}
+
+/// Await the coroutine produced by `factory`, restarting it whenever it fails
+///
+/// This function forwards `factory` and `policy` along with the task locals returned by
+/// [`get_current_locals`] to [`supervise_with_locals`](`crate::supervise_with_locals`). Failed
+/// coroutines are replaced by a fresh one from `factory` until `policy` runs out of restarts, but
+/// cancellation is never retried.
+///
+/// # Arguments
+/// * `factory` - Produces a new coroutine (or any other awaitable) for every attempt
+/// * `policy` - How often and how quickly to restart
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+/// use pyo3_asyncio::RestartPolicy;
+///
+/// async fn run_consumer(consumer: PyObject) -> PyResult<PyObject> {
+///     pyo3_asyncio::async_std::supervise(
+///         move |py| consumer.call_method0(py, "run"),
+///         RestartPolicy::new(5).with_backoff(Duration::from_secs(1), Duration::from_secs(30)),
+///     )
+///     .await
+/// }
+/// ```
+pub fn supervise<F>(
+    factory: F,
+    policy: RestartPolicy,
+) -> impl Future<Output = PyResult<PyObject>> + Send
+where
+    F: FnMut(Python) -> PyResult<PyObject> + Send + 'static,
+{
+    generic::supervise::<AsyncStdRuntime, F>(factory, policy)
+}
+
+/// Await many Python awaitables with at most `limit` of them in flight at once
+///
+/// This function forwards the awaitables along with the task locals returned by
+/// [`get_current_locals`] to
+/// [`for_each_concurrent_with_locals`](`crate::for_each_concurrent_with_locals`). Awaitables are
+/// only scheduled on the event loop once a slot is free, and `f` is called with each result as it
+/// completes.
+///
+/// # Arguments
+/// * `awaitables` - The Python awaitables to run
+/// * `limit` - The maximum number of awaitables in flight, must be greater than 0
+/// * `f` - Called with the result of each awaitable as it completes
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// async fn sum_slowly(values: Vec<u64>) -> PyResult<u64> {
+///     let awaitables = Python::with_gil(|py| -> PyResult<Vec<PyObject>> {
+///         let asyncio = py.import("asyncio")?;
+///         values
+///             .into_iter()
+///             .map(|value| Ok(asyncio.call_method1("sleep", (0.1, value))?.into()))
+///             .collect()
+///     })?;
+///
+///     let mut total = 0;
+///     // at most 8 sleeps are in flight at any given time
+///     pyo3_asyncio::async_std::for_each_concurrent(awaitables, 8, |result| {
+///         total += Python::with_gil(|py| result?.extract::<u64>(py))?;
+///         Ok(())
+///     })
+///     .await?;
+///
+///     Ok(total)
+/// }
+/// ```
+pub fn for_each_concurrent<I, F>(
+    awaitables: I,
+    limit: usize,
+    f: F,
+) -> impl Future<Output = PyResult<()>> + Send
+where
+    I: IntoIterator<Item = PyObject>,
+    I::IntoIter: Send,
+    F: FnMut(PyResult<PyObject>) -> PyResult<()> + Send,
+{
+    generic::for_each_concurrent::<AsyncStdRuntime, I, F>(awaitables, limit, f)
+}
+
+/// Block the current thread until a Python `awaitable` completes
+///
+/// This function simply forwards the awaitable and the task locals returned by
+/// [`get_current_locals`] to
+/// [`block_on_awaitable_with_locals`](`crate::block_on_awaitable_with_locals`). The GIL is
+/// released while blocking so the event loop can make progress on its own thread.
+///
+/// > This function must not be called from the event loop's own thread since the loop can't run
+/// > while its thread is blocked. Doing so raises a `RuntimeError` instead of deadlocking.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `awaitable` - The Python `awaitable` to wait on
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Synchronous function meant to be called with `loop.run_in_executor`
+/// #[pyfunction]
+/// fn wait_for(py: Python, awaitable: &PyAny) -> PyResult<PyObject> {
+///     pyo3_asyncio::async_std::block_on_awaitable(py, awaitable)
+/// }
+/// ```
+pub fn block_on_awaitable(py: Python, awaitable: &PyAny) -> PyResult<PyObject> {
+    generic::block_on_awaitable::<AsyncStdRuntime>(py, awaitable)
+}
+
+/// Helpers to synchronize with Python's `asyncio` primitives
+pub mod sync {
+    use std::future::Future;
+
+    use pyo3::prelude::*;
+
+    use super::AsyncStdRuntime;
+    use crate::generic;
+
+    /// Wait for a Python `asyncio.Event` to be set from Rust
+    ///
+    /// This function simply forwards the event and the task locals returned by
+    /// [`get_current_locals`](`super::get_current_locals`) to
+    /// [`await_py_event_with_locals`](`crate::await_py_event_with_locals`). See
+    /// [`await_py_event_with_locals`](`crate::await_py_event_with_locals`) for more details.
+    ///
+    /// # Arguments
+    /// * `event` - The `asyncio.Event` to wait for
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pyo3::prelude::*;
+    ///
+    /// /// Wait until Python signals that the model is loaded, then tell it to start serving
+    /// async fn wait_until_loaded(loaded: PyObject, serving: PyObject) -> PyResult<()> {
+    ///     Python::with_gil(|py| pyo3_asyncio::async_std::sync::await_py_event(loaded.as_ref(py)))?
+    ///         .await?;
+    ///
+    ///     Python::with_gil(|py| pyo3_asyncio::async_std::sync::set_py_event_threadsafe(serving.as_ref(py)))
+    /// }
+    /// ```
+    pub fn await_py_event(event: &PyAny) -> PyResult<impl Future<Output = PyResult<()>> + Send> {
+        generic::await_py_event::<AsyncStdRuntime>(event)
+    }
+
+    /// Set a Python `asyncio.Event` from Rust
+    ///
+    /// This function simply forwards the event and the task locals returned by
+    /// [`get_current_locals`](`super::get_current_locals`) to
+    /// [`set_py_event_threadsafe_with_locals`](`crate::set_py_event_threadsafe_with_locals`).
+    /// See [`set_py_event_threadsafe_with_locals`](`crate::set_py_event_threadsafe_with_locals`)
+    /// for more details, including how to set the event from a thread that has no task locals.
+    ///
+    /// # Arguments
+    /// * `event` - The `asyncio.Event` to set
+    pub fn set_py_event_threadsafe(event: &PyAny) -> PyResult<()> {
+        generic::set_py_event_threadsafe::<AsyncStdRuntime>(event)
+    }
 }
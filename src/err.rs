@@ -1,9 +1,249 @@
 // FIXME - is there a way to document custom PyO3 exceptions?
 #[allow(missing_docs)]
 mod exceptions {
-    use pyo3::{create_exception, exceptions::PyException};
+    use pyo3::{
+        create_exception,
+        exceptions::{PyException, PyRuntimeError},
+    };
 
     create_exception!(pyo3_asyncio, RustPanic, PyException);
+    create_exception!(pyo3_asyncio, NoRunningLoop, PyRuntimeError);
 }
 
-pub use exceptions::RustPanic;
+pub use exceptions::{NoRunningLoop, RustPanic};
+
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::HashMap,
+    fmt::Debug,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex},
+};
+#[cfg(feature = "backtrace")]
+use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    cell::RefCell,
+    sync::Once,
+};
+
+use futures::FutureExt;
+use once_cell::sync::Lazy;
+use pyo3::{
+    exceptions::{PyBaseException, PyRuntimeError},
+    ffi,
+    prelude::*,
+    AsPyPointer, IntoPyPointer,
+};
+
+type ErrorMapper = dyn Fn(Python, Box<dyn Any>) -> PyErr + Send + Sync;
+
+static ERROR_MAPPERS: Lazy<std::sync::Mutex<HashMap<TypeId, Arc<ErrorMapper>>>> =
+    Lazy::new(Default::default);
+
+/// Register how errors of type `E` are converted into Python exceptions
+///
+/// The mapper is used by the runtimes' `future_into_py_mapped` functions (and [`map_error`]) for
+/// futures that fail with an `E`. This keeps the conversion from a structured Rust error into a
+/// Python exception in one place instead of repeating it in every binding. Registering a mapper
+/// for a type that already has one replaces the previous mapper.
+///
+/// # Arguments
+/// * `mapper` - Converts an `E` into a Python exception
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::{exceptions::{PyKeyError, PyPermissionError}, prelude::*};
+///
+/// #[derive(Debug)]
+/// enum DbError {
+///     NotFound(String),
+///     Forbidden,
+/// }
+///
+/// pyo3_asyncio::err::register_error_mapper(|_py, e: DbError| match e {
+///     DbError::NotFound(key) => PyKeyError::new_err(key),
+///     DbError::Forbidden => PyPermissionError::new_err("forbidden"),
+/// });
+///
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| {
+///     let err = pyo3_asyncio::err::map_error(py, DbError::NotFound("user".into()));
+///     assert!(err.is_instance_of::<PyKeyError>(py));
+/// });
+/// ```
+pub fn register_error_mapper<E, F>(mapper: F)
+where
+    E: 'static,
+    F: Fn(Python, E) -> PyErr + Send + Sync + 'static,
+{
+    let mapper: Arc<ErrorMapper> = Arc::new(move |py, e: Box<dyn Any>| match e.downcast::<E>() {
+        Ok(e) => mapper(py, *e),
+        // Mappers are keyed by the TypeId of E, so the downcast can't fail
+        Err(_) => unreachable!(),
+    });
+
+    ERROR_MAPPERS
+        .lock()
+        .unwrap()
+        .insert(TypeId::of::<E>(), mapper);
+}
+
+/// Convert an error into a Python exception with the mapper registered for `E`
+///
+/// If no mapper has been registered with [`register_error_mapper`], the error is converted into a
+/// `RuntimeError` describing it with its `Debug` implementation.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `e` - The error to convert
+pub fn map_error<E>(py: Python, e: E) -> PyErr
+where
+    E: Debug + 'static,
+{
+    // Clone the mapper out so that it can register mappers itself without deadlocking
+    let mapper = ERROR_MAPPERS
+        .lock()
+        .unwrap()
+        .get(&TypeId::of::<E>())
+        .cloned();
+
+    match mapper {
+        Some(mapper) => mapper(py, Box::new(e)),
+        None => PyRuntimeError::new_err(format!(
+            "unmapped error of type `{}`: {:?}",
+            type_name::<E>(),
+            e
+        )),
+    }
+}
+
+#[cfg(feature = "backtrace")]
+thread_local! {
+    static LAST_PANIC_BACKTRACE: RefCell<Option<Backtrace>> = const { RefCell::new(None) };
+}
+
+#[cfg(feature = "backtrace")]
+static INSTALL_PANIC_HOOK: Once = Once::new();
+
+/// Chain a panic hook that records the backtrace of the panicking thread before the stack unwinds
+#[cfg(feature = "backtrace")]
+fn install_panic_hook() {
+    INSTALL_PANIC_HOOK.call_once(|| {
+        let prev_hook = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            LAST_PANIC_BACKTRACE.with(|backtrace| {
+                *backtrace.borrow_mut() = Some(Backtrace::capture());
+            });
+            prev_hook(info);
+        }));
+    });
+}
+
+/// Convert a Python exception instance into a `PyErr` that keeps its `__traceback__`
+///
+/// `PyErr::from_value` drops the traceback, so printing the error (i.e. with
+/// `print_and_set_sys_last_vars`) would no longer show the Python frames it was raised from.
+pub(crate) fn from_exception(exc: &PyAny) -> PyErr {
+    if !exc.is_instance_of::<PyBaseException>().unwrap_or(false) {
+        return PyErr::from_value(exc);
+    }
+
+    // PyErr_Restore steals the references, and PyException_GetTraceback returns a new one
+    unsafe {
+        ffi::PyErr_Restore(
+            exc.get_type().into_ptr(),
+            exc.into_ptr(),
+            ffi::PyException_GetTraceback(exc.as_ptr()),
+        );
+    }
+
+    PyErr::fetch(exc.py())
+}
+
+/// Get the message of a panic if its payload is a `&str` or a `String` (i.e. from `panic!`)
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> Option<&str> {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+}
+
+/// Format the message of the [`RustPanic`] for a panic, including its payload if it is a string
+pub(crate) fn panic_err_message(msg: &str, payload: Option<&str>) -> String {
+    match payload {
+        Some(payload) => format!("{}: {}", msg, payload),
+        None => msg.to_string(),
+    }
+}
+
+/// Carries the panic message and backtrace of a panicking Rust future over to the [`RustPanic`] it
+/// is converted into
+///
+/// Without the `backtrace` feature, the [`RustPanic`] only carries the message.
+#[derive(Clone, Default)]
+pub(crate) struct PanicBacktrace {
+    message: Arc<Mutex<Option<String>>>,
+    #[cfg(feature = "backtrace")]
+    backtrace: Arc<Mutex<Option<Backtrace>>>,
+}
+
+impl PanicBacktrace {
+    /// Record the panic message and backtrace if `fut` panics. The panic still propagates
+    /// afterwards.
+    pub(crate) fn track<F>(&self, fut: F) -> impl Future<Output = F::Output>
+    where
+        F: Future,
+    {
+        #[cfg(feature = "backtrace")]
+        install_panic_hook();
+
+        let this = self.clone();
+
+        async move {
+            match AssertUnwindSafe(fut).catch_unwind().await {
+                Ok(output) => output,
+                Err(payload) => {
+                    *this.message.lock().unwrap() = panic_message(&*payload).map(String::from);
+                    #[cfg(feature = "backtrace")]
+                    {
+                        *this.backtrace.lock().unwrap() =
+                            LAST_PANIC_BACKTRACE.with(|backtrace| backtrace.borrow_mut().take());
+                    }
+                    panic::resume_unwind(payload)
+                }
+            }
+        }
+    }
+
+    /// Build the [`RustPanic`] for a panicked future
+    ///
+    /// The panic payload is appended to `msg` if it is a string. If a backtrace was captured (i.e.
+    /// `RUST_BACKTRACE=1`), it is attached as a note on Python 3.11+ (PEP 678) and appended to the
+    /// message on older versions.
+    #[cfg_attr(not(feature = "backtrace"), allow(unused_variables))]
+    pub(crate) fn into_err(self, py: Python, msg: &str) -> PyErr {
+        let msg = panic_err_message(msg, self.message.lock().unwrap().as_deref());
+        let err = RustPanic::new_err(msg.clone());
+
+        #[cfg(feature = "backtrace")]
+        if let Some(backtrace) = self.backtrace.lock().unwrap().take() {
+            if backtrace.status() == BacktraceStatus::Captured {
+                let note = format!("Rust backtrace:\n{}", backtrace);
+                let value = err.value(py);
+
+                if value.hasattr("add_note").unwrap_or(false)
+                    && value.call_method1("add_note", (note.as_str(),)).is_ok()
+                {
+                    return err;
+                }
+
+                return RustPanic::new_err(format!("{}\n\n{}", msg, note));
+            }
+        }
+
+        err
+    }
+}
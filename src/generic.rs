@@ -1,18 +1,45 @@
 use std::{
+    fmt,
     future::Future,
+    panic::{self, AssertUnwindSafe},
     pin::Pin,
     sync::{Arc, Mutex},
     task::{Context, Poll},
+    thread,
+    time::Duration,
 };
 
-use futures::channel::oneshot;
+use futures::{
+    channel::oneshot,
+    future::{self, AbortHandle, AbortRegistration, Abortable, Either, FutureExt},
+    stream::{Stream, StreamExt},
+};
 use pin_project_lite::pin_project;
-use pyo3::prelude::*;
+use pyo3::{
+    exceptions::{
+        asyncio::CancelledError, PyRuntimeError, PyStopAsyncIteration, PyTypeError, PyValueError,
+    },
+    prelude::*,
+    pyclass::IterNextOutput,
+    types::{PyDict, PyTuple},
+};
 
 #[allow(deprecated)]
 use crate::{
-    asyncio, call_soon_threadsafe, close, create_future, dump_err, err::RustPanic,
-    get_running_loop, into_future_with_locals, TaskLocals,
+    agen_send_with_locals, agen_throw_with_locals, as_completed_stream_with_locals,
+    async_with_with_locals, asyncio, await_py_event_with_locals, block_on_awaitable_with_locals,
+    cached, call_soon_threadsafe, cancel_and_wait_with_locals, check_running_loop, create_future,
+    debug_check_gil_released, debug_enabled, dump_err,
+    err::{from_exception, map_error, PanicBacktrace},
+    for_each_concurrent_with_locals, get_running_loop, into_future_abortable_with_locals,
+    into_future_finally_with_locals, into_future_ignore_result_with_locals,
+    into_future_in_context_with_locals, into_future_then_with_locals,
+    into_future_timed_with_locals, into_future_with_asyncio_timeout_with_locals,
+    into_future_with_locals, into_stream_with_locals, is_running_loop,
+    loop_exception_stream_with_locals, py_id, py_queue_to_stream_with_locals, register_bridge,
+    run_on_python_executor_with_locals, set_py_event_threadsafe_with_locals, supervise_with_locals,
+    try_close, warmup_caches, with_completion_gil, CancellationToken, PyAsyncExitStack, PyCache,
+    PyFuture, PyFutureBridge, PyFutureResult, RateLimitedBridge, RestartPolicy, TaskLocals,
 };
 
 /// Generic utilities for a JoinError
@@ -193,6 +220,176 @@ where
     Ok(result)
 }
 
+/// Run the event loop until the given Future completes or `token` is cancelled
+///
+/// This works just like [`run_until_complete`], except that cancelling `token` from any thread
+/// drops `fut` and stops the event loop gracefully, without stopping the loop from the outside. In
+/// that case an `asyncio.CancelledError` is returned. Like [`run_until_complete`], the event loop
+/// can be reused after this function returns.
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop that should run the future
+/// * `fut` - The future to drive to completion
+/// * `token` - Cancels `fut` when cancelled
+pub fn run_until_complete_with_cancel<R, F, T>(
+    event_loop: &PyAny,
+    fut: F,
+    token: CancellationToken,
+) -> PyResult<T>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    run_until_complete::<R, _, T>(event_loop, async move {
+        match future::select(Box::pin(fut), Box::pin(token.cancelled())).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(CancelledError::new_err(
+                "run_until_complete was cancelled by its token",
+            )),
+        }
+    })
+}
+
+/// Run the event loop until all of the given Futures complete, one after another
+///
+/// The futures are awaited in order within a single call to [`run_until_complete`], so the event
+/// loop is only entered once. If a future fails, the remaining futures are dropped without being
+/// polled and its error is returned.
+///
+/// Like [`run_until_complete`], the event loop can be reused after this function returns.
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop that should run the futures
+/// * `futs` - The futures to drive to completion, in order
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use pyo3::prelude::*;
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # Python::with_gil(|py| -> PyResult<()> {
+/// # let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+/// let results = pyo3_asyncio::generic::run_until_complete_all::<MyCustomRuntime, _, _, _>(
+///     event_loop,
+///     (1..=3u64).map(|secs| async move {
+///         MyCustomRuntime::sleep(Duration::from_secs(secs)).await;
+///         Ok(secs)
+///     }),
+/// )?;
+///
+/// assert_eq!(results, vec![1, 2, 3]);
+/// # Ok(())
+/// # }).unwrap();
+/// ```
+pub fn run_until_complete_all<R, I, F, T>(event_loop: &PyAny, futs: I) -> PyResult<Vec<T>>
+where
+    R: Runtime + ContextExt,
+    I: IntoIterator<Item = F>,
+    I::IntoIter: Send + 'static,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    let futs = futs.into_iter();
+
+    run_until_complete::<R, _, Vec<T>>(event_loop, async move {
+        let mut results = Vec::new();
+
+        for fut in futs {
+            results.push(fut.await?);
+        }
+
+        Ok(results)
+    })
+}
+
+/// Pay the one-time costs of the bridge up front
+///
+/// The first conversion in either direction imports `asyncio`, `inspect` and `contextvars`, starts
+/// the runtime `R` and runs code paths that Python hasn't executed yet, which shows up as latency
+/// on the first request a service handles. This function imports and caches those modules, then
+/// drives a throwaway round trip (a Rust future awaiting `asyncio.sleep(0)` through
+/// [`into_future`]) on a temporary event loop, which is closed afterwards.
+///
+/// Since it runs an event loop of its own, this must be called while no event loop is running on
+/// the current thread, i.e. at startup before calling [`run`].
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+pub fn warmup<R>(py: Python) -> PyResult<()>
+where
+    R: Runtime + ContextExt,
+{
+    warmup_caches(py)?;
+
+    let event_loop = asyncio(py)?.call_method0("new_event_loop")?;
+    let result = run_until_complete::<R, _, _>(event_loop, async move {
+        Python::with_gil(|py| into_future::<R>(asyncio(py)?.call_method1("sleep", (0,))?))?.await?;
+        Ok(())
+    });
+
+    // the loop is closed either way, but a failed round trip is the error worth reporting
+    let closed = event_loop.call_method0("close").map(drop);
+    result.and(closed)
+}
+
 /// Run the event loop until the given Future completes
 ///
 /// # Arguments
@@ -281,48 +478,142 @@ where
 
     let result = run_until_complete::<R, F, T>(event_loop, fut);
 
-    close(event_loop)?;
+    try_close(event_loop)?;
 
     result
 }
 
-fn cancelled(future: &PyAny) -> PyResult<bool> {
-    future.getattr("cancelled")?.call0()?.is_true()
-}
+/// Run the event loop until the given Future completes, using `executor` as the event loop's
+/// default executor
+///
+/// This works like [`run`], except that `executor` is installed with
+/// `loop.set_default_executor` before the future is driven, so `loop.run_in_executor(None, ...)`
+/// and `asyncio.to_thread` run on it instead of a `ThreadPoolExecutor` created by asyncio. This is
+/// useful to bound the number of worker threads. Like the default one, the executor is shut down
+/// along with the event loop on Python 3.9+.
+///
+/// > Since Python 3.11, the default executor must be a `concurrent.futures.ThreadPoolExecutor`.
+/// > Other executors (i.e. a `ProcessPoolExecutor`) are rejected with a `TypeError`, so they have
+/// > to be passed to `loop.run_in_executor` explicitly instead.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `executor` - The `concurrent.futures.Executor` to use as the default executor
+/// * `fut` - The future to drive to completion
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # use std::time::Duration;
+/// # async fn custom_sleep(_duration: Duration) { }
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// fn main() {
+///     Python::with_gil(|py| -> PyResult<()> {
+///         let executor = py
+///             .import("concurrent.futures")?
+///             .call_method1("ThreadPoolExecutor", (4,))?;
+///
+///         pyo3_asyncio::generic::run_with_executor::<MyCustomRuntime, _, _>(
+///             py,
+///             executor,
+///             async move {
+///                 custom_sleep(Duration::from_secs(1)).await;
+///                 Ok(())
+///             },
+///         )
+///     })
+///     .map_err(|e| Python::with_gil(|py| e.print_and_set_sys_last_vars(py)))
+///     .unwrap();
+/// }
+/// ```
+pub fn run_with_executor<R, F, T>(py: Python, executor: &PyAny, fut: F) -> PyResult<T>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    let event_loop = asyncio(py)?.call_method0("new_event_loop")?;
 
-fn set_result(event_loop: &PyAny, future: &PyAny, result: PyResult<PyObject>) -> PyResult<()> {
-    let py = event_loop.py();
-    let none = py.None().into_ref(py);
+    let result = event_loop
+        .call_method1("set_default_executor", (executor,))
+        .and_then(|_| run_until_complete::<R, F, T>(event_loop, fut));
 
-    match result {
-        Ok(val) => {
-            let set_result = future.getattr("set_result")?;
-            call_soon_threadsafe(event_loop, none, (set_result, val))?;
-        }
-        Err(err) => {
-            let set_exception = future.getattr("set_exception")?;
-            call_soon_threadsafe(event_loop, none, (set_exception, err))?;
-        }
-    }
+    try_close(event_loop)?;
 
-    Ok(())
+    result
 }
 
-/// Convert a Python `awaitable` into a Rust Future
+/// Block the current thread until the given Future completes on the runtime
 ///
-/// This function simply forwards the future and the task locals returned by [`get_current_locals`]
-/// to [`into_future_with_locals`](`crate::into_future_with_locals`). See
-/// [`into_future_with_locals`](`crate::into_future_with_locals`) for more details.
+/// The future is spawned onto the runtime with [`Runtime::spawn`] and the current thread is parked
+/// until its output is available, so every [`Runtime`] gets a blocking entry point for free.
+/// Unlike [`run`], this does not involve Python at all. If the future panics, the panic is resumed
+/// on the current thread.
+///
+/// > Don't call this from a thread that drives the runtime (i.e. inside a future running on a
+/// > single-threaded runtime), since the future could never make progress. Similarly, don't hold
+/// > the GIL while blocking if the future needs to acquire it.
 ///
 /// # Arguments
-/// * `awaitable` - The Python `awaitable` to be converted
+/// * `fut` - The future to drive to completion
 ///
 /// # Examples
 ///
 /// ```no_run
-/// # use std::{pin::Pin, future::Future, task::{Context, Poll}, time::Duration};
-/// #
-/// # use pyo3::prelude::*;
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
 /// #
 /// # use pyo3_asyncio::{
 /// #     TaskLocals,
@@ -379,28 +670,260 @@ fn set_result(event_loop: &PyAny, future: &PyAny, result: PyResult<PyObject>) ->
 /// #     }
 /// # }
 /// #
-/// const PYTHON_CODE: &'static str = r#"
-/// import asyncio
+/// # use std::time::Duration;
+/// # async fn custom_sleep(_duration: Duration) { }
+/// #
+/// let answer = pyo3_asyncio::generic::block_on::<MyCustomRuntime, _, _>(async {
+///     custom_sleep(Duration::from_millis(10)).await;
+///     42
+/// });
+/// assert_eq!(answer, 42);
+/// ```
+pub fn block_on<R, F, T>(fut: F) -> T
+where
+    R: Runtime,
+    F: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+
+    R::spawn(async move {
+        let _ = tx.send(AssertUnwindSafe(fut).catch_unwind().await);
+    });
+
+    match futures::executor::block_on(rx) {
+        Ok(Ok(output)) => output,
+        Ok(Err(payload)) => panic::resume_unwind(payload),
+        Err(_) => panic!("the runtime dropped the future passed to block_on"),
+    }
+}
+
+/// Run the event loop until the given Future completes on a dedicated thread
 ///
-/// async def py_sleep(duration):
-///     await asyncio.sleep(duration)
-/// "#;
+/// This works just like [`run`], except that the event loop is created and driven on a new thread
+/// instead of the calling thread, which stays free for other work (i.e. a GUI toolkit's own event
+/// loop). The GIL is acquired on the new thread for as long as [`run`] needs it, so Python must
+/// already be initialized (i.e. with `pyo3::prepare_freethreaded_python`).
 ///
-/// async fn py_sleep(seconds: f32) -> PyResult<()> {
-///     let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
-///         Ok(
-///             PyModule::from_code(
-///                 py,
-///                 PYTHON_CODE,
-///                 "test_into_future/test_mod.py",
-///                 "test_mod"
-///             )?
-///             .into()
-///         )
-///     })?;
+/// Because the event loop does not run on the main thread, it cannot install signal handlers.
+/// `loop.add_signal_handler` raises an error there and `KeyboardInterrupt` is only ever delivered
+/// to the main thread, so Ctrl-C will not interrupt the future.
 ///
-///     Python::with_gil(|py| {
-///         pyo3_asyncio::generic::into_future::<MyCustomRuntime>(
+/// > Don't hold the GIL while joining the returned handle, the event loop thread needs it to make
+/// > progress.
+///
+/// # Arguments
+/// * `fut` - The future to drive to completion
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # use std::time::Duration;
+/// # async fn custom_sleep(_duration: Duration) { }
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// fn main() -> PyResult<()> {
+///     pyo3::prepare_freethreaded_python();
+///
+///     let handle = pyo3_asyncio::generic::run_on_thread::<MyCustomRuntime, _, _>(async move {
+///         custom_sleep(Duration::from_secs(1)).await;
+///         Ok(())
+///     });
+///
+///     // the main thread is free to do other work in the meantime
+///
+///     handle.join().unwrap()
+/// }
+/// ```
+pub fn run_on_thread<R, F, T>(fut: F) -> thread::JoinHandle<PyResult<T>>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    thread::Builder::new()
+        .name("pyo3-asyncio-event-loop".into())
+        .spawn(move || Python::with_gil(|py| run::<R, F, T>(py, fut)))
+        .expect("failed to spawn the event loop thread")
+}
+
+fn cancelled(future: &PyAny) -> PyResult<bool> {
+    future.getattr("cancelled")?.call0()?.is_true()
+}
+
+fn done(future: &PyAny) -> PyResult<bool> {
+    future.getattr("done")?.call0()?.is_true()
+}
+
+fn set_result(event_loop: &PyAny, future: &PyAny, result: PyResult<PyObject>) -> PyResult<()> {
+    let py = event_loop.py();
+    let none = py.None().into_ref(py);
+
+    match result {
+        Ok(val) => {
+            let set_result = future.getattr("set_result")?;
+            call_soon_threadsafe(event_loop, none, (set_result, val))?;
+        }
+        Err(err) => {
+            let set_exception = future.getattr("set_exception")?;
+            call_soon_threadsafe(event_loop, none, (set_exception, err))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert a Python `awaitable` into a Rust Future
+///
+/// This function simply forwards the future and the task locals returned by [`get_current_locals`]
+/// to [`into_future_with_locals`](`crate::into_future_with_locals`). See
+/// [`into_future_with_locals`](`crate::into_future_with_locals`) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::{pin::Pin, future::Future, task::{Context, Poll}, time::Duration};
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// const PYTHON_CODE: &'static str = r#"
+/// import asyncio
+///
+/// async def py_sleep(duration):
+///     await asyncio.sleep(duration)
+/// "#;
+///
+/// async fn py_sleep(seconds: f32) -> PyResult<()> {
+///     let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+///         Ok(
+///             PyModule::from_code(
+///                 py,
+///                 PYTHON_CODE,
+///                 "test_into_future/test_mod.py",
+///                 "test_mod"
+///             )?
+///             .into()
+///         )
+///     })?;
+///
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::generic::into_future::<MyCustomRuntime>(
 ///             test_mod
 ///                 .call_method1(py, "py_sleep", (seconds.into_py(py),))?
 ///                 .as_ref(py),
@@ -410,36 +933,2653 @@ fn set_result(event_loop: &PyAny, future: &PyAny, result: PyResult<PyObject>) ->
 ///     Ok(())    
 /// }
 /// ```
-pub fn into_future<R>(
-    awaitable: &PyAny,
-) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send>
+pub fn into_future<R>(awaitable: &PyAny) -> PyResult<PyFuture>
 where
     R: Runtime + ContextExt,
 {
     into_future_with_locals(&get_current_locals::<R>(awaitable.py())?, awaitable)
 }
 
-/// Convert a Rust Future into a Python awaitable with a generic runtime
+/// Convert a Python `awaitable` into a boxed Rust Future
 ///
-/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
-/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
+/// This is [`into_future`] with the resulting [`PyFuture`] boxed into a
+/// [`PyFutureResult`](`crate::PyFutureResult`), for storing it alongside futures returned by
+/// other conversions.
 ///
-/// Python `contextvars` are preserved when calling async Python functions within the Rust future
-/// via [`into_future`] (new behaviour in `v0.15`).
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_boxed<R>(awaitable: &PyAny) -> PyResult<PyFutureResult>
+where
+    R: Runtime + ContextExt,
+{
+    Ok(Box::pin(into_future::<R>(awaitable)?))
+}
+
+/// Convert a Python `awaitable` into a Rust Future that runs in the given contextvars `Context`
 ///
-/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
-/// unfortunately fail to resolve them when called within the Rust future. This is because the
-/// function is being called from a Rust thread, not inside an actual Python coroutine context.
-/// >
-/// > As a workaround, you can get the `contextvars` from the current task locals using
-/// [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
-/// synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
-/// synchronous function, and restore the previous context when it returns or raises an exception.
+/// This function simply forwards the awaitable, the context and the task locals returned by
+/// [`get_current_locals`] to
+/// [`into_future_in_context_with_locals`](`crate::into_future_in_context_with_locals`). See
+/// [`into_future_in_context_with_locals`](`crate::into_future_in_context_with_locals`) for more
+/// details.
 ///
 /// # Arguments
-/// * `py` - PyO3 GIL guard
-/// * `locals` - The task-local data for Python
-/// * `fut` - The Rust future to be converted
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `context` - The `contextvars.Context` the awaitable should run in
+pub fn into_future_in_context<R>(awaitable: &PyAny, context: &PyAny) -> PyResult<PyFuture>
+where
+    R: Runtime + ContextExt,
+{
+    into_future_in_context_with_locals(
+        &get_current_locals::<R>(awaitable.py())?,
+        awaitable,
+        context,
+    )
+}
+
+/// Convert a Python `awaitable` into a Rust Future that is bounded by `asyncio.timeout(delay)`
+///
+/// This function simply forwards the awaitable, the delay and the task locals returned by
+/// [`get_current_locals`] to
+/// [`into_future_with_asyncio_timeout_with_locals`](`crate::into_future_with_asyncio_timeout_with_locals`).
+/// See
+/// [`into_future_with_asyncio_timeout_with_locals`](`crate::into_future_with_asyncio_timeout_with_locals`)
+/// for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `delay` - How long the awaitable may run before it is cancelled
+pub fn into_future_with_asyncio_timeout<R>(awaitable: &PyAny, delay: Duration) -> PyResult<PyFuture>
+where
+    R: Runtime + ContextExt,
+{
+    into_future_with_asyncio_timeout_with_locals(
+        &get_current_locals::<R>(awaitable.py())?,
+        awaitable,
+        delay,
+    )
+}
+
+/// Convert a Python `awaitable` into a Rust Future and an `AbortRegistration` that is aborted if
+/// the awaitable's Python Task is cancelled
+///
+/// This function simply forwards the awaitable and the task locals returned by
+/// [`get_current_locals`] to
+/// [`into_future_abortable_with_locals`](`crate::into_future_abortable_with_locals`). See
+/// [`into_future_abortable_with_locals`](`crate::into_future_abortable_with_locals`) for more
+/// details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// use futures::future::Abortable;
+/// use pyo3::prelude::*;
+///
+/// async fn sleep_and_notify(py_sleep: PyObject) -> PyResult<()> {
+///     let (py_sleep, registration) = Python::with_gil(|py| {
+///         pyo3_asyncio::generic::into_future_abortable::<MyCustomRuntime>(py_sleep.as_ref(py))
+///     })?;
+///
+///     // If Python cancels the sleep, the notification is aborted as well
+///     let notify = Abortable::new(MyCustomRuntime::sleep(Duration::from_secs(1)), registration);
+///     let (result, _) = futures::join!(py_sleep, notify);
+///     result?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn into_future_abortable<R>(
+    awaitable: &PyAny,
+) -> PyResult<(
+    impl Future<Output = PyResult<PyObject>> + Send,
+    AbortRegistration,
+)>
+where
+    R: Runtime + ContextExt,
+{
+    into_future_abortable_with_locals(&get_current_locals::<R>(awaitable.py())?, awaitable)
+}
+
+/// Convert a Python `awaitable` into a Rust Future that runs `on_finish` once it is done
+///
+/// This function simply forwards the awaitable, the finalizer and the task locals returned by
+/// [`get_current_locals`] to
+/// [`into_future_finally_with_locals`](`crate::into_future_finally_with_locals`). See
+/// [`into_future_finally_with_locals`](`crate::into_future_finally_with_locals`) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `on_finish` - Called exactly once with the result of the awaitable
+pub fn into_future_finally<R, F>(
+    awaitable: &PyAny,
+    on_finish: F,
+) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send>
+where
+    R: Runtime + ContextExt,
+    F: FnOnce(Python, &PyResult<PyObject>) + Send,
+{
+    into_future_finally_with_locals(
+        &get_current_locals::<R>(awaitable.py())?,
+        awaitable,
+        on_finish,
+    )
+}
+
+/// Convert a Python `awaitable` into a Rust Future whose result is transformed by `f` under the GIL
+///
+/// This function simply forwards the awaitable, the transform and the task locals returned by
+/// [`get_current_locals`] to
+/// [`into_future_then_with_locals`](`crate::into_future_then_with_locals`). See
+/// [`into_future_then_with_locals`](`crate::into_future_then_with_locals`) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `f` - Transforms the result of the awaitable
+pub fn into_future_then<R, F, T>(
+    awaitable: &PyAny,
+    f: F,
+) -> PyResult<impl Future<Output = PyResult<T>> + Send>
+where
+    R: Runtime + ContextExt,
+    F: FnOnce(Python, PyObject) -> PyResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    into_future_then_with_locals(&get_current_locals::<R>(awaitable.py())?, awaitable, f)
+}
+
+/// Convert a Python `awaitable` into a Rust Future that also resolves to the time it took
+///
+/// This function simply forwards the awaitable and the task locals returned by
+/// [`get_current_locals`] to
+/// [`into_future_timed_with_locals`](`crate::into_future_timed_with_locals`). See
+/// [`into_future_timed_with_locals`](`crate::into_future_timed_with_locals`) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_timed<R>(
+    awaitable: &PyAny,
+) -> PyResult<impl Future<Output = PyResult<(PyObject, Duration)>> + Send>
+where
+    R: Runtime + ContextExt,
+{
+    into_future_timed_with_locals(&get_current_locals::<R>(awaitable.py())?, awaitable)
+}
+
+/// Convert a Python `awaitable` into a Rust Future that waits for a free slot of `bridge`
+///
+/// This function simply forwards the awaitable and the task locals returned by
+/// [`get_current_locals`] to
+/// [`RateLimitedBridge::into_future_with_locals`](`crate::RateLimitedBridge::into_future_with_locals`).
+/// See [`RateLimitedBridge`](`crate::RateLimitedBridge`) for more details.
+///
+/// # Arguments
+/// * `bridge` - Limits how many awaitables are in flight at once
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_rate_limited<R>(
+    bridge: &RateLimitedBridge,
+    awaitable: &PyAny,
+) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send>
+where
+    R: Runtime + ContextExt,
+{
+    bridge.into_future_with_locals(&get_current_locals::<R>(awaitable.py())?, awaitable)
+}
+
+/// Convert a Python `awaitable` into a Rust Future that discards its result
+///
+/// This function simply forwards the awaitable and the task locals returned by
+/// [`get_current_locals`] to
+/// [`into_future_ignore_result_with_locals`](`crate::into_future_ignore_result_with_locals`). See
+/// [`into_future_ignore_result_with_locals`](`crate::into_future_ignore_result_with_locals`) for
+/// more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_ignore_result<R>(
+    awaitable: &PyAny,
+) -> PyResult<impl Future<Output = PyResult<()>> + Send>
+where
+    R: Runtime + ContextExt,
+{
+    into_future_ignore_result_with_locals(&get_current_locals::<R>(awaitable.py())?, awaitable)
+}
+
+/// Convert a Python async iterator into a Rust Stream
+///
+/// This function simply forwards the async iterator and the task locals returned by
+/// [`get_current_locals`] to [`into_stream_with_locals`](`crate::into_stream_with_locals`). See
+/// [`into_stream_with_locals`](`crate::into_stream_with_locals`) for more details.
+///
+/// # Arguments
+/// * `gen` - The Python async iterable to be converted
+pub fn into_stream<R>(gen: &PyAny) -> PyResult<impl Stream<Item = PyResult<PyObject>> + Send>
+where
+    R: Runtime + ContextExt,
+{
+    into_stream_with_locals(get_current_locals::<R>(gen.py())?, gen)
+}
+
+/// Convert a collection of Python awaitables into a Rust Stream of their results in completion
+/// order
+///
+/// This function simply forwards the awaitables and the task locals returned by
+/// [`get_current_locals`] to
+/// [`as_completed_stream_with_locals`](`crate::as_completed_stream_with_locals`). See
+/// [`as_completed_stream_with_locals`](`crate::as_completed_stream_with_locals`) for more details.
+///
+/// # Arguments
+/// * `awaitables` - A Python iterable of awaitables
+pub fn as_completed_stream<R>(
+    awaitables: &PyAny,
+) -> PyResult<impl Stream<Item = PyResult<PyObject>> + Send>
+where
+    R: Runtime + ContextExt,
+{
+    as_completed_stream_with_locals(get_current_locals::<R>(awaitables.py())?, awaitables)
+}
+
+/// Convert a Python `asyncio.Queue` into a Rust Stream of its items
+///
+/// This function simply forwards the queue, the sentinel and the task locals returned by
+/// [`get_current_locals`] to
+/// [`py_queue_to_stream_with_locals`](`crate::py_queue_to_stream_with_locals`). See
+/// [`py_queue_to_stream_with_locals`](`crate::py_queue_to_stream_with_locals`) for more details.
+///
+/// # Arguments
+/// * `queue` - The Python `asyncio.Queue` to consume
+/// * `sentinel` - The item that ends the stream, or `None` for an infinite stream
+pub fn py_queue_to_stream<R>(
+    queue: &PyAny,
+    sentinel: Option<&PyAny>,
+) -> PyResult<impl Stream<Item = PyResult<PyObject>> + Send>
+where
+    R: Runtime + ContextExt,
+{
+    py_queue_to_stream_with_locals(get_current_locals::<R>(queue.py())?, queue, sentinel)
+}
+
+/// Wait for a Python `asyncio.Event` to be set from Rust
+///
+/// This function simply forwards the event and the task locals returned by [`get_current_locals`]
+/// to [`await_py_event_with_locals`](`crate::await_py_event_with_locals`). See
+/// [`await_py_event_with_locals`](`crate::await_py_event_with_locals`) for more details.
+///
+/// # Arguments
+/// * `event` - The `asyncio.Event` to wait for
+pub fn await_py_event<R>(event: &PyAny) -> PyResult<impl Future<Output = PyResult<()>> + Send>
+where
+    R: Runtime + ContextExt,
+{
+    await_py_event_with_locals(&get_current_locals::<R>(event.py())?, event)
+}
+
+/// Set a Python `asyncio.Event` from Rust
+///
+/// This function simply forwards the event and the task locals returned by [`get_current_locals`]
+/// to [`set_py_event_threadsafe_with_locals`](`crate::set_py_event_threadsafe_with_locals`). See
+/// [`set_py_event_threadsafe_with_locals`](`crate::set_py_event_threadsafe_with_locals`) for more
+/// details, including how to set the event from a thread that has no task locals.
+///
+/// # Arguments
+/// * `event` - The `asyncio.Event` to set
+pub fn set_py_event_threadsafe<R>(event: &PyAny) -> PyResult<()>
+where
+    R: Runtime + ContextExt,
+{
+    set_py_event_threadsafe_with_locals(&get_current_locals::<R>(event.py())?, event)
+}
+
+/// Create an empty [`PyAsyncExitStack`](`crate::PyAsyncExitStack`) on the current event loop
+///
+/// This function simply forwards the task locals returned by [`get_current_locals`] to
+/// [`PyAsyncExitStack::with_locals`](`crate::PyAsyncExitStack::with_locals`). See
+/// [`PyAsyncExitStack`](`crate::PyAsyncExitStack`) for more details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+pub fn async_exit_stack<R>(py: Python) -> PyResult<PyAsyncExitStack>
+where
+    R: Runtime + ContextExt,
+{
+    PyAsyncExitStack::with_locals(py, get_current_locals::<R>(py)?)
+}
+
+/// Run `body` inside the Python async context manager `cm`, like `async with cm as value: ...`
+///
+/// This function simply forwards the context manager, the body and the task locals returned by
+/// [`get_current_locals`] to [`async_with_with_locals`](`crate::async_with_with_locals`). See
+/// [`async_with_with_locals`](`crate::async_with_with_locals`) for more details.
+///
+/// # Arguments
+/// * `cm` - The Python async context manager
+/// * `body` - Called with the value returned by `cm.__aenter__()`
+pub fn async_with<R, B, Fut, T>(
+    cm: &PyAny,
+    body: B,
+) -> PyResult<impl Future<Output = PyResult<Option<T>>> + Send>
+where
+    R: Runtime + ContextExt,
+    B: FnOnce(PyObject) -> Fut + Send,
+    Fut: Future<Output = PyResult<T>> + Send,
+    T: Send,
+{
+    async_with_with_locals(&get_current_locals::<R>(cm.py())?, cm, body)
+}
+
+/// Run a Rust Future to completion on a thread of the current Python event loop's default executor
+///
+/// This function simply forwards the future and the task locals returned by
+/// [`get_current_locals`] to
+/// [`run_on_python_executor_with_locals`](`crate::run_on_python_executor_with_locals`). See
+/// [`run_on_python_executor_with_locals`](`crate::run_on_python_executor_with_locals`) for more
+/// details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to run
+pub fn run_on_python_executor<R, F, T>(
+    py: Python,
+    fut: F,
+) -> PyResult<impl Future<Output = PyResult<T>> + Send>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    run_on_python_executor_with_locals(&get_current_locals::<R>(py)?, fut)
+}
+
+/// Send `value` into a Python async generator and convert the step into a Rust Future
+///
+/// This function simply forwards the generator, the value and the task locals returned by
+/// [`get_current_locals`] to [`agen_send_with_locals`](`crate::agen_send_with_locals`). See
+/// [`agen_send_with_locals`](`crate::agen_send_with_locals`) for more details.
+///
+/// # Arguments
+/// * `agen` - The Python async generator
+/// * `value` - The value that the pending `yield` expression evaluates to
+pub fn agen_send<R>(
+    agen: &PyAny,
+    value: &PyAny,
+) -> PyResult<impl Future<Output = PyResult<Option<PyObject>>> + Send>
+where
+    R: Runtime + ContextExt,
+{
+    agen_send_with_locals(&get_current_locals::<R>(agen.py())?, agen, value)
+}
+
+/// Throw `exc` into a Python async generator and convert the step into a Rust Future
+///
+/// This function simply forwards the generator, the exception and the task locals returned by
+/// [`get_current_locals`] to [`agen_throw_with_locals`](`crate::agen_throw_with_locals`). See
+/// [`agen_throw_with_locals`](`crate::agen_throw_with_locals`) for more details.
+///
+/// # Arguments
+/// * `agen` - The Python async generator
+/// * `exc` - The exception to raise inside the generator
+pub fn agen_throw<R>(
+    agen: &PyAny,
+    exc: PyErr,
+) -> PyResult<impl Future<Output = PyResult<Option<PyObject>>> + Send>
+where
+    R: Runtime + ContextExt,
+{
+    agen_throw_with_locals(&get_current_locals::<R>(agen.py())?, agen, exc)
+}
+
+/// Get a Rust Stream of the exceptions reported to the current event loop's exception handler
+///
+/// This function simply forwards the task locals returned by [`get_current_locals`] to
+/// [`loop_exception_stream_with_locals`](`crate::loop_exception_stream_with_locals`). See
+/// [`loop_exception_stream_with_locals`](`crate::loop_exception_stream_with_locals`) for more
+/// details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+pub fn loop_exception_stream<R>(py: Python) -> PyResult<impl Stream<Item = PyObject> + Send>
+where
+    R: Runtime + ContextExt,
+{
+    loop_exception_stream_with_locals(py, &get_current_locals::<R>(py)?)
+}
+
+/// Cancel a Python task and wait for it to actually finish
+///
+/// This function simply forwards the task and the task locals returned by [`get_current_locals`]
+/// to [`cancel_and_wait_with_locals`](`crate::cancel_and_wait_with_locals`). See
+/// [`cancel_and_wait_with_locals`](`crate::cancel_and_wait_with_locals`) for more details.
+///
+/// # Arguments
+/// * `task` - The Python task or future to cancel
+/// * `timeout` - How long to wait for the task to finish, or `None` to wait indefinitely
+pub fn cancel_and_wait<R>(
+    task: &PyAny,
+    timeout: Option<Duration>,
+) -> PyResult<impl Future<Output = PyResult<bool>> + Send>
+where
+    R: Runtime + ContextExt,
+{
+    cancel_and_wait_with_locals(&get_current_locals::<R>(task.py())?, task, timeout)
+}
+
+/// Await the coroutine produced by `factory`, restarting it whenever it fails
+///
+/// This function forwards `factory` and `policy` along with the task locals returned by
+/// [`get_current_locals`] to [`supervise_with_locals`](`crate::supervise_with_locals`). See
+/// [`supervise_with_locals`](`crate::supervise_with_locals`) for more details.
+///
+/// # Arguments
+/// * `factory` - Produces a new coroutine (or any other awaitable) for every attempt
+/// * `policy` - How often and how quickly to restart
+pub async fn supervise<R, F>(factory: F, policy: RestartPolicy) -> PyResult<PyObject>
+where
+    R: Runtime + ContextExt,
+    F: FnMut(Python) -> PyResult<PyObject> + Send + 'static,
+{
+    let locals = Python::with_gil(get_current_locals::<R>)?;
+    supervise_with_locals(locals, factory, policy).await
+}
+
+/// Await many Python awaitables with at most `limit` of them in flight at once
+///
+/// This function forwards the awaitables along with the task locals returned by
+/// [`get_current_locals`] to
+/// [`for_each_concurrent_with_locals`](`crate::for_each_concurrent_with_locals`). See
+/// [`for_each_concurrent_with_locals`](`crate::for_each_concurrent_with_locals`) for more details.
+///
+/// # Arguments
+/// * `awaitables` - The Python awaitables to run
+/// * `limit` - The maximum number of awaitables in flight, must be greater than 0
+/// * `f` - Called with the result of each awaitable as it completes
+pub fn for_each_concurrent<R, I, F>(
+    awaitables: I,
+    limit: usize,
+    f: F,
+) -> impl Future<Output = PyResult<()>> + Send
+where
+    R: Runtime + ContextExt,
+    I: IntoIterator<Item = PyObject>,
+    I::IntoIter: Send,
+    F: FnMut(PyResult<PyObject>) -> PyResult<()> + Send,
+{
+    let awaitables = awaitables.into_iter();
+
+    async move {
+        let locals = Python::with_gil(get_current_locals::<R>)?;
+        for_each_concurrent_with_locals(locals, awaitables, limit, f).await
+    }
+}
+
+/// Block the current thread until a Python `awaitable` completes
+///
+/// This function simply forwards the awaitable and the task locals returned by
+/// [`get_current_locals`] to
+/// [`block_on_awaitable_with_locals`](`crate::block_on_awaitable_with_locals`). See
+/// [`block_on_awaitable_with_locals`](`crate::block_on_awaitable_with_locals`) for more details.
+///
+/// > This function must not be called from the event loop's own thread.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `awaitable` - The Python `awaitable` to wait on
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// use pyo3::prelude::*;
+///
+/// /// Synchronous function meant to be called with `loop.run_in_executor`
+/// #[pyfunction]
+/// fn wait_for(py: Python, awaitable: &PyAny) -> PyResult<PyObject> {
+///     pyo3_asyncio::generic::block_on_awaitable::<MyCustomRuntime>(py, awaitable)
+/// }
+/// ```
+pub fn block_on_awaitable<R>(py: Python, awaitable: &PyAny) -> PyResult<PyObject>
+where
+    R: Runtime + ContextExt,
+{
+    block_on_awaitable_with_locals(py, &get_current_locals::<R>(py)?, awaitable)
+}
+
+/// Convert a Rust Future into a Python awaitable with a generic runtime
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
+///
+/// Python `contextvars` are preserved when calling async Python functions within the Rust future
+/// via [`into_future`] (new behaviour in `v0.15`).
+///
+/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
+/// unfortunately fail to resolve them when called within the Rust future. This is because the
+/// function is being called from a Rust thread, not inside an actual Python coroutine context.
+/// >
+/// > As a workaround, you can get the `contextvars` from the current task locals using
+/// [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
+/// synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
+/// synchronous function, and restore the previous context when it returns or raises an exception.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task-local data for Python
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: &'p PyAny) -> PyResult<&'p PyAny> {
+///     let secs = secs.extract()?;
+///     pyo3_asyncio::generic::future_into_py_with_locals::<MyCustomRuntime, _, _>(
+///         py,
+///         pyo3_asyncio::generic::get_current_locals::<MyCustomRuntime>(py)?,
+///         async move {
+///             MyCustomRuntime::sleep(Duration::from_secs(secs)).await;
+///             Ok(())
+///         }
+///     )
+/// }
+/// ```
+pub fn future_into_py_with_locals<R, F, T>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<&PyAny>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    structured_future_into_py_with_locals::<R, DropOnCancel<F>, T>(
+        py,
+        locals,
+        DropOnCancel::new(fut),
+    )
+}
+
+/// Convert a [`Cancellable`] Rust Future into a Python awaitable with a generic runtime
+///
+/// This works just like [`future_into_py_with_locals`], except that [`Cancellable::cancel`] is
+/// called when the Python awaitable is cancelled, giving the future a chance to clean up
+/// asynchronously before it is dropped.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `fut` - The Rust future to be converted
+pub fn structured_future_into_py_with_locals<R, F, T>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<&PyAny>
+where
+    R: Runtime + ContextExt,
+    F: Cancellable<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    spawn_into_py::<R, F, T>(py, locals, fut).map(|(py_fut, _)| py_fut)
+}
+
+/// Spawn `fut` on `R` and return the Python future it completes, along with a handle that aborts
+/// the spawned task without completing the Python future
+fn spawn_into_py<R, F, T>(py: Python, locals: TaskLocals, fut: F) -> PyResult<(&PyAny, AbortHandle)>
+where
+    R: Runtime + ContextExt,
+    F: Cancellable<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    if debug_enabled() {
+        check_running_loop(py, &locals)?;
+    }
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    let (abort, registration) = AbortHandle::new_pair();
+
+    let py_fut = create_future(locals.event_loop.clone().into_ref(py))?;
+    py_fut.call_method1(
+        "add_done_callback",
+        (PyDoneCallback {
+            cancel_tx: Some(cancel_tx),
+        },),
+    )?;
+
+    bridge_debug!(
+        "future_into_py: created future {:#x} on loop {:#x}",
+        py_id(py_fut),
+        py_id(locals.event_loop(py))
+    );
+
+    let future_tx1 = PyObject::from(py_fut);
+    let future_tx2 = future_tx1.clone();
+
+    // cancel_all_bridges can reach the future until the task below completes
+    let bridge = Arc::new(PyFutureBridge(future_tx1.clone()));
+    register_bridge(&bridge);
+
+    let panic_backtrace = PanicBacktrace::default();
+    let tracker = panic_backtrace.clone();
+
+    let fut = CancellableFuture::new_with_cancel_rx(fut, cancel_rx);
+    #[cfg(feature = "tracing")]
+    let fut = tracing::Instrument::instrument(fut, crate::instrument::current_span(py));
+
+    R::spawn(async move {
+        let _bridge = bridge;
+        let locals2 = locals.clone();
+
+        if let Err(e) = R::spawn(async move {
+            let complete = async move {
+                let result = tracker
+                    .track(R::scope(locals2.clone(), debug_check_gil_released(fut)))
+                    .await;
+
+                with_completion_gil(move |py| {
+                    if done(future_tx1.as_ref(py))
+                        .map_err(dump_err(py))
+                        .unwrap_or(false)
+                    {
+                        return;
+                    }
+
+                    bridge_debug!(
+                        "future_into_py: rust future for {:#x} {}",
+                        py_id(future_tx1.as_ref(py)),
+                        if result.is_ok() {
+                            "completed"
+                        } else {
+                            "failed"
+                        }
+                    );
+
+                    let _ = set_result(
+                        locals2.event_loop(py),
+                        future_tx1.as_ref(py),
+                        result.map(|val| val.into_py(py)),
+                    )
+                    .map_err(dump_err(py));
+                });
+            };
+
+            // an aborted task never reaches the completion above
+            let _ = Abortable::new(complete, registration).await;
+        })
+        .await
+        {
+            if e.is_panic() {
+                with_completion_gil(move |py| {
+                    if done(future_tx2.as_ref(py))
+                        .map_err(dump_err(py))
+                        .unwrap_or(false)
+                    {
+                        return;
+                    }
+
+                    bridge_debug!(
+                        "future_into_py: rust future for {:#x} panicked",
+                        py_id(future_tx2.as_ref(py))
+                    );
+
+                    let _ = set_result(
+                        locals.event_loop.as_ref(py),
+                        future_tx2.as_ref(py),
+                        Err(panic_backtrace.into_err(py, "rust future panicked")),
+                    )
+                    .map_err(dump_err(py));
+                });
+            }
+        }
+    });
+
+    Ok((py_fut, abort))
+}
+
+/// Convert a Rust Future into a Python awaitable with a generic runtime
+///
+/// __This function will be removed in `v0.16`__
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop that the awaitable should be attached to
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: &'p PyAny) -> PyResult<&'p PyAny> {
+///     let secs = secs.extract()?;
+///     pyo3_asyncio::generic::future_into_py_with_loop::<MyCustomRuntime, _>(
+///         pyo3_asyncio::generic::get_current_loop::<MyCustomRuntime>(py)?,
+///         async move {
+///             MyCustomRuntime::sleep(Duration::from_secs(secs)).await;
+///             Python::with_gil(|py| Ok(py.None()))
+///         }
+///     )
+/// }
+/// ```
+#[deprecated(
+    since = "0.15.0",
+    note = "Use pyo3_asyncio::generic::future_into_py_with_locals instead"
+)]
+pub fn future_into_py_with_loop<R, F>(event_loop: &PyAny, fut: F) -> PyResult<&PyAny>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<PyObject>> + Send + 'static,
+{
+    let py = event_loop.py();
+    future_into_py_with_locals::<R, F, PyObject>(
+        py,
+        TaskLocals::new(event_loop).copy_context(py)?,
+        fut,
+    )
+}
+
+/// A Rust future that is notified when the Python awaitable it was converted into is cancelled
+///
+/// By default, a future converted with [`future_into_py`] is simply dropped when Python cancels its
+/// awaitable. Futures that need to release resources asynchronously (i.e. roll back a transaction
+/// or notify a remote peer) can't do that from `Drop`, so they can implement this trait and be
+/// converted with [`structured_future_into_py`] instead.
+///
+/// Plain futures can be wrapped in [`DropOnCancel`] to keep the default drop behaviour.
+pub trait Cancellable: Future {
+    /// Called once, before the future is dropped, when its Python awaitable is cancelled
+    ///
+    /// Return `true` to keep polling the future until it completes so it can finish cleaning up
+    /// (its output is discarded since the Python awaitable has already been cancelled), or `false`
+    /// to drop it right away.
+    fn cancel(self: Pin<&mut Self>) -> bool;
+}
+
+pin_project! {
+    /// Wraps a future so that it is simply dropped when its Python awaitable is cancelled
+    ///
+    /// This is the [`Cancellable`] implementation used by [`future_into_py`].
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    #[derive(Debug)]
+    pub struct DropOnCancel<F> {
+        #[pin]
+        future: F,
+    }
+}
+
+impl<F> DropOnCancel<F> {
+    /// Wrap `future` so that it is dropped on cancellation
+    pub fn new(future: F) -> Self {
+        Self { future }
+    }
+}
+
+impl<F> Future for DropOnCancel<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().future.poll(cx)
+    }
+}
+
+impl<F> Cancellable for DropOnCancel<F>
+where
+    F: Future,
+{
+    fn cancel(self: Pin<&mut Self>) -> bool {
+        false
+    }
+}
+
+pin_project! {
+    /// Future returned by [`timeout`](timeout) and [`timeout_at`](timeout_at).
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    #[derive(Debug)]
+    struct CancellableFuture<T> {
+        #[pin]
+        future: T,
+        #[pin]
+        cancel_rx: oneshot::Receiver<()>,
+
+        poll_cancel_rx: bool
+    }
+}
+
+impl<T> CancellableFuture<T> {
+    fn new_with_cancel_rx(future: T, cancel_rx: oneshot::Receiver<()>) -> Self {
+        Self {
+            future,
+            cancel_rx,
+
+            poll_cancel_rx: true,
+        }
+    }
+}
+
+impl<F, T> Future for CancellableFuture<F>
+where
+    F: Cancellable<Output = PyResult<T>>,
+    T: IntoPy<PyObject>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        // First, try polling the future
+        if let Poll::Ready(v) = this.future.as_mut().poll(cx) {
+            return Poll::Ready(v);
+        }
+
+        // Now check for cancellation
+        if *this.poll_cancel_rx {
+            match this.cancel_rx.poll(cx) {
+                Poll::Ready(Ok(())) => {
+                    *this.poll_cancel_rx = false;
+
+                    if this.future.as_mut().cancel() {
+                        // Keep polling the future until it has finished cleaning up. The python
+                        // future has already been cancelled, so its output will never be used.
+                        return this.future.poll(cx);
+                    }
+
+                    // The python future has already been cancelled, so this return value will never
+                    // be used.
+                    Poll::Ready(Err(pyo3::exceptions::PyBaseException::new_err(
+                        "unreachable",
+                    )))
+                }
+                Poll::Ready(Err(_)) => {
+                    *this.poll_cancel_rx = false;
+                    Poll::Pending
+                }
+                Poll::Pending => Poll::Pending,
+            }
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[pyclass]
+struct PyDoneCallback {
+    cancel_tx: Option<oneshot::Sender<()>>,
+}
+
+#[pymethods]
+impl PyDoneCallback {
+    pub fn __call__(&mut self, fut: &PyAny) -> PyResult<()> {
+        let py = fut.py();
+
+        if cancelled(fut).map_err(dump_err(py)).unwrap_or(false) {
+            bridge_debug!("future_into_py: future {:#x} was cancelled", py_id(fut));
+        } else {
+            bridge_debug!("future_into_py: future {:#x} is done", py_id(fut));
+        }
+
+        // If the Rust future is still running, the future was resolved without it (i.e. cancelled
+        // or shut down) and its output will never be used
+        let _ = self.cancel_tx.take().unwrap().send(());
+
+        Ok(())
+    }
+}
+
+/// Convert a Rust Future into a Python awaitable with a generic runtime
+///
+/// __This function was deprecated in favor of [`future_into_py_with_locals`] in `v0.15` because
+/// it became the default behaviour. In `v0.15`, any calls to this function should be
+/// replaced with [`future_into_py_with_locals`].__
+///
+/// __In `v0.16` this function will be removed__
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop that the awaitable should be attached to
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: &'p PyAny) -> PyResult<&'p PyAny> {
+///     let secs = secs.extract()?;
+///     pyo3_asyncio::generic::cancellable_future_into_py_with_loop::<MyCustomRuntime, _>(
+///         pyo3_asyncio::generic::get_current_loop::<MyCustomRuntime>(py)?,
+///         async move {
+///             MyCustomRuntime::sleep(Duration::from_secs(secs)).await;
+///             Python::with_gil(|py| Ok(py.None()))
+///         }
+///     )
+/// }
+/// ```
+
+#[deprecated(
+    since = "0.15.0",
+    note = "Use pyo3_asyncio::generic::future_into_py_with_locals instead"
+)]
+#[allow(deprecated)]
+pub fn cancellable_future_into_py_with_loop<R, F>(event_loop: &PyAny, fut: F) -> PyResult<&PyAny>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<PyObject>> + Send + 'static,
+{
+    // cancellable futures became the default behaviour in 0.15
+    future_into_py_with_loop::<R, _>(event_loop, fut)
+}
+
+/// Convert a Rust Future into a Python awaitable with a generic runtime
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
+///
+/// Python `contextvars` are preserved when calling async Python functions within the Rust future
+/// via [`into_future`] (new behaviour in `v0.15`).
+///
+/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
+/// unfortunately fail to resolve them when called within the Rust future. This is because the
+/// function is being called from a Rust thread, not inside an actual Python coroutine context.
+/// >
+/// > As a workaround, you can get the `contextvars` from the current task locals using
+/// [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
+/// synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
+/// synchronous function, and restore the previous context when it returns or raises an exception.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: &'p PyAny) -> PyResult<&'p PyAny> {
+///     let secs = secs.extract()?;
+///     pyo3_asyncio::generic::future_into_py::<MyCustomRuntime, _, _>(py, async move {
+///         MyCustomRuntime::sleep(Duration::from_secs(secs)).await;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub fn future_into_py<R, F, T>(py: Python, fut: F) -> PyResult<&PyAny>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    future_into_py_with_locals::<R, F, T>(py, get_current_locals::<R>(py)?, fut)
+}
+
+/// Convert a Rust Future into the iterator that a Python `__await__` method returns with a generic
+/// runtime
+///
+/// Implementing `__await__` on a `#[pyclass]` makes the class itself awaitable, so it can expose
+/// other methods and attributes alongside its result (unlike the `asyncio.Future` returned by
+/// [`future_into_py`]). `__await__` has to return an iterator rather than an awaitable though, so
+/// this function converts `fut` with [`future_into_py`] and returns the iterator of the resulting
+/// future. The future is bound to the current task locals like any other future converted with
+/// [`future_into_py`], and cancelling the awaiting task drops `fut`.
+///
+/// Every call creates a new future, so each `await` on the `#[pyclass]` runs its own Rust future.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future driven by `__await__`
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # use std::time::Duration;
+/// # async fn custom_sleep(_duration: Duration) { }
+/// #
+/// use pyo3::prelude::*;
+///
+/// /// A timer that can be awaited and also reports its duration
+/// #[pyclass]
+/// struct Timer {
+///     #[pyo3(get)]
+///     secs: u64,
+/// }
+///
+/// #[pymethods]
+/// impl Timer {
+///     fn __await__<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+///         let secs = self.secs;
+///         pyo3_asyncio::generic::future_into_await::<MyCustomRuntime, _, _>(py, async move {
+///             custom_sleep(Duration::from_secs(secs)).await;
+///             Ok(secs)
+///         })
+///     }
+/// }
+/// ```
+pub fn future_into_await<R, F, T>(py: Python, fut: F) -> PyResult<&PyAny>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    future_into_py::<R, F, T>(py, fut)?.call_method0("__await__")
+}
+
+const AWAIT_FUTURE_CODE: &str = r#"
+async def await_future(fut):
+    return await fut
+"#;
+
+static AWAIT_FUTURE: PyCache<PyObject> = PyCache::new();
+
+fn await_future(py: Python) -> PyResult<&PyAny> {
+    cached(py, &AWAIT_FUTURE, || {
+        Ok(PyModule::from_code(
+            py,
+            AWAIT_FUTURE_CODE,
+            "pyo3_asyncio/await_future.py",
+            "pyo3_asyncio_await_future",
+        )?
+        .getattr("await_future")?
+        .into())
+    })
+    .map(|await_future| await_future.into_ref(py))
+}
+
+/// Convert a Rust Future into a named Python task with a generic runtime
+///
+/// The `asyncio.Future` returned by [`future_into_py_with_locals`] is not a task, so it is missing
+/// from `asyncio.all_tasks()` and can't be given a name. This function converts `fut` the same way,
+/// then wraps the resulting future in an `asyncio.Task` named `name` (via `Task.set_name`, which
+/// requires Python 3.8+) so that it shows up in task dumps and in its `repr`. Cancelling the task
+/// cancels the Rust future, just like cancelling the future returned by
+/// [`future_into_py_with_locals`].
+///
+/// The task is created with `loop.create_task`, which is not thread-safe, so this function must be
+/// called on the thread running the event loop in `locals`, i.e. from a `#[pyfunction]` that is
+/// called by a coroutine. Otherwise it raises a `RuntimeError`. On Python 3.11+, the task runs in
+/// the context of `locals`.
+///
+/// An asyncio task has to run a coroutine, so the future is awaited by a small coroutine of its own.
+/// This costs a task and a coroutine frame on top of [`future_into_py_with_locals`], which is
+/// preferable when the name isn't needed.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `name` - The name of the task
+/// * `fut` - The Rust future to be converted
+pub fn future_into_py_named_with_locals<'p, R, F, T>(
+    py: Python<'p>,
+    locals: TaskLocals,
+    name: &str,
+    fut: F,
+) -> PyResult<&'p PyAny>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    let event_loop = locals.event_loop(py);
+    let context = locals.context(py);
+    if !is_running_loop(event_loop)? {
+        return Err(PyRuntimeError::new_err(
+            "future_into_py_named: the event loop is not running on this thread, call it from the \
+             thread running the event loop, i.e. from a #[pyfunction] called by a coroutine",
+        ));
+    }
+
+    let py_fut = future_into_py_with_locals::<R, F, T>(py, locals, fut)?;
+    let coro = await_future(py)?.call1((py_fut,))?;
+
+    // tasks run in a copy of the current context unless they are given one explicitly, which is
+    // only supported by Python 3.11+
+    let task = if !context.is_none() && py.version_info() >= (3, 11) {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("context", context)?;
+        event_loop.call_method("create_task", (coro,), Some(kwargs))?
+    } else {
+        event_loop.call_method1("create_task", (coro,))?
+    };
+
+    // task names are only supported by Python 3.8+
+    if task.hasattr("set_name")? {
+        task.call_method1("set_name", (name,))?;
+    }
+
+    Ok(task)
+}
+
+/// Convert a Rust Future into a named Python task with a generic runtime
+///
+/// This function simply forwards the future and the task locals returned by
+/// [`get_current_locals`] to [`future_into_py_named_with_locals`]. See
+/// [`future_into_py_named_with_locals`] for more details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `name` - The name of the task
+/// * `fut` - The Rust future to be converted
+pub fn future_into_py_named<'p, R, F, T>(py: Python<'p>, name: &str, fut: F) -> PyResult<&'p PyAny>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    future_into_py_named_with_locals::<R, F, T>(py, get_current_locals::<R>(py)?, name, fut)
+}
+
+/// Convert a Rust Future into a Python awaitable and a handle that aborts the Rust future with a
+/// generic runtime
+///
+/// The Rust future is normally only cancelled from Python by cancelling the awaitable. The returned
+/// `AbortHandle` lets Rust code stop it as well, e.g. on shutdown or when a sibling task fails,
+/// without going through the event loop.
+///
+/// Aborting drops the Rust future but __does not complete the awaitable__: it stays pending, so a
+/// coroutine awaiting it will wait forever unless the awaitable is also cancelled (via
+/// `loop.call_soon_threadsafe(fut.cancel)` from another thread). Cancelling the awaitable from
+/// Python drops the Rust future without needing the handle.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `fut` - The Rust future to be converted
+pub fn future_into_py_with_handle_with_locals<R, F, T>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<(&PyAny, AbortHandle)>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    spawn_into_py::<R, DropOnCancel<F>, T>(py, locals, DropOnCancel::new(fut))
+}
+
+/// Convert a Rust Future into a Python awaitable and a handle that aborts the Rust future with a
+/// generic runtime
+///
+/// This function simply forwards the future and the task locals returned by
+/// [`get_current_locals`] to [`future_into_py_with_handle_with_locals`]. See
+/// [`future_into_py_with_handle_with_locals`] for more details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+pub fn future_into_py_with_handle<R, F, T>(py: Python, fut: F) -> PyResult<(&PyAny, AbortHandle)>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    future_into_py_with_handle_with_locals::<R, F, T>(py, get_current_locals::<R>(py)?, fut)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tracing</code></span>
+/// Convert a Rust Future into a Python awaitable instrumented with a `tracing` span with a generic
+/// runtime
+///
+/// This works just like [`future_into_py_with_locals`], except that `span` is entered whenever
+/// `fut` is polled instead of the span picked by default (the `py_await` span of the `into_future`
+/// call being awaited by Python, or else the current span).
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `span` - The span to instrument `fut` with
+/// * `fut` - The Rust future to be converted
+#[cfg(feature = "tracing")]
+pub fn future_into_py_instrumented_with_locals<R, F, T>(
+    py: Python,
+    locals: TaskLocals,
+    span: tracing::Span,
+    fut: F,
+) -> PyResult<&PyAny>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    future_into_py_with_locals::<R, _, T>(py, locals, tracing::Instrument::instrument(fut, span))
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tracing</code></span>
+/// Convert a Rust Future into a Python awaitable instrumented with a `tracing` span with a generic
+/// runtime
+///
+/// This function simply forwards the future and the task locals returned by
+/// [`get_current_locals`] to [`future_into_py_instrumented_with_locals`]. See
+/// [`future_into_py_instrumented_with_locals`] for more details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `span` - The span to instrument `fut` with
+/// * `fut` - The Rust future to be converted
+#[cfg(feature = "tracing")]
+pub fn future_into_py_instrumented<R, F, T>(
+    py: Python,
+    span: tracing::Span,
+    fut: F,
+) -> PyResult<&PyAny>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    future_into_py_instrumented_with_locals::<R, F, T>(py, get_current_locals::<R>(py)?, span, fut)
+}
+
+/// Convert a [`Cancellable`] Rust Future into a Python awaitable with a generic runtime
+///
+/// This function simply forwards the future and the task locals returned by
+/// [`get_current_locals`] to [`structured_future_into_py_with_locals`]. See
+/// [`structured_future_into_py_with_locals`] for more details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+pub fn structured_future_into_py<R, F, T>(py: Python, fut: F) -> PyResult<&PyAny>
+where
+    R: Runtime + ContextExt,
+    F: Cancellable<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    structured_future_into_py_with_locals::<R, F, T>(py, get_current_locals::<R>(py)?, fut)
+}
+
+/// Convert a Rust Future built from borrowed Python arguments into a Python awaitable with a
+/// generic runtime
+///
+/// The future passed to [`future_into_py`] must be `'static`, so it can't hold on to GIL-bound
+/// references like the `&PyAny` arguments of a `#[pyfunction]`. The recommended pattern is to
+/// resolve those borrows into owned Rust data (or `PyObject`s) before the future is created, and
+/// `build` is the place to do it. It is called immediately with the GIL held, can borrow anything
+/// the caller can, and returns the `'static` future that is then converted with
+/// [`future_into_py`]. An error returned by `build` (i.e. a failed extraction) is raised right
+/// away instead of from the awaitable.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `build` - Creates the Rust future to be converted while the GIL is held
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # use std::time::Duration;
+/// # async fn custom_sleep(_duration: Duration) { }
+/// #
+/// use pyo3::{prelude::*, types::PyDict};
+///
+/// /// Awaitable that sleeps for `config["delay"]` seconds, then returns `config["name"]`
+/// #[pyfunction]
+/// fn delayed_name<'p>(py: Python<'p>, config: &'p PyDict) -> PyResult<&'p PyAny> {
+///     pyo3_asyncio::generic::future_into_py_borrowed::<MyCustomRuntime, _, _, _>(py, |_py| {
+///         // borrows of `config` end here, only owned data moves into the future
+///         let delay: u64 = config.get_item("delay").unwrap().extract()?;
+///         let name: String = config.get_item("name").unwrap().extract()?;
+///
+///         Ok(async move {
+///             custom_sleep(Duration::from_secs(delay)).await;
+///             Ok(name)
+///         })
+///     })
+/// }
+/// ```
+pub fn future_into_py_borrowed<'p, R, B, F, T>(py: Python<'p>, build: B) -> PyResult<&'p PyAny>
+where
+    R: Runtime + ContextExt,
+    B: FnOnce(Python<'p>) -> PyResult<F>,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    let fut = build(py)?;
+    future_into_py::<R, F, T>(py, fut)
+}
+
+/// Convert a Rust Future that fails with a custom error type into a Python awaitable with a
+/// generic runtime
+///
+/// Errors returned by `fut` are converted into Python exceptions with the mapper registered for
+/// `E` through [`register_error_mapper`](crate::err::register_error_mapper) (see
+/// [`map_error`](crate::err::map_error) for the fallback when no mapper is registered). Otherwise
+/// this behaves exactly like [`future_into_py`].
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+pub fn future_into_py_mapped<R, F, T, E>(py: Python, fut: F) -> PyResult<&PyAny>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = Result<T, E>> + Send + 'static,
+    T: IntoPy<PyObject>,
+    E: std::fmt::Debug + Send + 'static,
+{
+    future_into_py::<R, _, T>(py, async move {
+        fut.await
+            .map_err(|e| Python::with_gil(|py| map_error(py, e)))
+    })
+}
+
+/// Convert a Rust Future that fails with a custom error type into a Python awaitable with a
+/// generic runtime, converting the error with `map_fn`
+///
+/// This works just like [`future_into_py_mapped`], except that the error is converted by `map_fn`
+/// instead of the mapper registered for `E`, which is handy when the same error type should be
+/// raised as different exceptions in different places. `map_fn` is only called if `fut` fails.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+/// * `map_fn` - Converts the error returned by `fut` into a Python exception
+pub fn future_into_py_with_error_map<R, F, T, E, M>(
+    py: Python,
+    fut: F,
+    map_fn: M,
+) -> PyResult<&PyAny>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = Result<T, E>> + Send + 'static,
+    T: IntoPy<PyObject>,
+    M: FnOnce(E) -> PyErr + Send + 'static,
+{
+    future_into_py::<R, _, T>(py, async move { fut.await.map_err(map_fn) })
+}
+
+/// Raised when pickling (or copying) one of the Rust-backed awaitables, whose state is a Rust
+/// future that can't cross a process boundary
+fn pickle_error() -> PyErr {
+    PyTypeError::new_err("Rust-backed awaitables cannot be pickled")
+}
+
+type DeferredCreate = dyn FnOnce(Python) -> PyResult<PyObject> + Send;
+
+#[pyclass]
+struct PyDeferredFuture {
+    create: Option<Box<DeferredCreate>>,
+}
+
+#[pymethods]
+impl PyDeferredFuture {
+    fn __await__(&mut self, py: Python) -> PyResult<PyObject> {
+        match self.create.take() {
+            Some(create) => create(py)?.call_method0(py, "__await__"),
+            None => Err(PyRuntimeError::new_err(
+                "cannot reuse already awaited deferred future",
+            )),
+        }
+    }
+
+    fn __reduce__(&self) -> PyResult<PyObject> {
+        Err(pickle_error())
+    }
+}
+
+/// Convert a Rust Future into a Python awaitable that is only scheduled once it is awaited with a
+/// generic runtime
+///
+/// [`future_into_py`] needs the running event loop (see [`get_current_locals`]), so it fails when
+/// the awaitables are built before the event loop starts, i.e. to construct a graph of coroutines
+/// that is then passed to `loop.run_until_complete`. The awaitable returned by this function
+/// defers the call to [`future_into_py`] until it is awaited, by which point it is running on the
+/// event loop that awaits it.
+///
+/// Like a coroutine, the awaitable can only be awaited once and `fut` is dropped without being
+/// polled if it is never awaited. It cannot be pickled either, which raises a `TypeError`.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # use std::time::Duration;
+/// # async fn custom_sleep(_duration: Duration) { }
+/// #
+/// use pyo3::prelude::*;
+///
+/// fn main() -> PyResult<()> {
+///     pyo3::prepare_freethreaded_python();
+///
+///     Python::with_gil(|py| {
+///         let asyncio = py.import("asyncio")?;
+///         let event_loop = asyncio.call_method0("new_event_loop")?;
+///
+///         // no event loop is running yet
+///         let sleep = pyo3_asyncio::generic::future_into_py_deferred::<MyCustomRuntime, _, _>(
+///             py,
+///             async move {
+///                 custom_sleep(Duration::from_secs(1)).await;
+///                 Ok(())
+///             },
+///         )?;
+///
+///         event_loop.call_method1("run_until_complete", (sleep,))?;
+///         event_loop.call_method0("close")?;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub fn future_into_py_deferred<R, F, T>(py: Python, fut: F) -> PyResult<&PyAny>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    let deferred = PyDeferredFuture {
+        create: Some(Box::new(move |py| {
+            future_into_py::<R, F, T>(py, fut).map(|awaitable| awaitable.into())
+        })),
+    };
+
+    Ok(PyCell::new(py, deferred)?.as_ref())
+}
+
+type PortableResult = Arc<Mutex<Option<PyResult<PyObject>>>>;
+
+#[pyclass]
+struct PyPortableFuture {
+    result: PortableResult,
+    abort: AbortHandle,
+}
+
+#[pymethods]
+impl PyPortableFuture {
+    fn __await__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&self, py: Python) -> PyResult<IterNextOutput<PyObject, PyObject>> {
+        match &*self.result.lock().unwrap() {
+            // A bare yield asks the awaiting task to be resumed on the next loop iteration
+            None => Ok(IterNextOutput::Yield(py.None())),
+            Some(Ok(val)) => Ok(IterNextOutput::Return(val.clone_ref(py))),
+            Some(Err(e)) => Err(e.clone_ref(py)),
+        }
+    }
+
+    fn __reduce__(&self) -> PyResult<PyObject> {
+        Err(pickle_error())
+    }
+}
+
+impl Drop for PyPortableFuture {
+    fn drop(&mut self) {
+        self.abort.abort();
+    }
+}
+
+/// Convert a Rust Future into a self-contained Python awaitable with a generic runtime
+///
+/// Unlike [`future_into_py`], the returned awaitable is not an `asyncio.Future` created with the
+/// event loop's `create_future`. Its `__await__` is a plain generator-style iterator that yields
+/// until the Rust future has completed and then returns its result, so it can be awaited without
+/// the full asyncio future machinery (i.e. by minimal or alternative Python builds, or by event
+/// loops that don't provide `create_future`). Like an `asyncio.Future`, it can be awaited several
+/// times and always returns the same result.
+///
+/// This portability comes at a cost:
+/// * The awaiting task is not woken up by the Rust future. It is resumed on every iteration of the
+///   event loop instead, which burns CPU for as long as the future is pending.
+/// * The awaitable can't be cancelled from Python with `Future.cancel` or passed to functions that
+///   expect an `asyncio.Future` (i.e. `add_done_callback`). Cancelling the awaiting task stops the
+///   wait, and the Rust future is dropped once the awaitable is garbage collected.
+/// * The future is only scoped with the task locals if it is created while an event loop is
+///   running (see [`get_current_locals`]).
+/// * The awaitable cannot be pickled, which raises a `TypeError`.
+///
+/// Prefer [`future_into_py`] whenever the standard asyncio machinery is available.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # use std::time::Duration;
+/// # async fn custom_sleep(_duration: Duration) { }
+/// #
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function that doesn't need `loop.create_future`
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: &'p PyAny) -> PyResult<&'p PyAny> {
+///     let secs = secs.extract()?;
+///     pyo3_asyncio::generic::future_into_py_portable::<MyCustomRuntime, _, _>(py, async move {
+///         custom_sleep(Duration::from_secs(secs)).await;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub fn future_into_py_portable<R, F, T>(py: Python, fut: F) -> PyResult<&PyAny>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    let result = PortableResult::default();
+    let (abort, registration) = AbortHandle::new_pair();
+    let locals = get_current_locals::<R>(py).ok();
+
+    let result_tx1 = Arc::clone(&result);
+    let result_tx2 = Arc::clone(&result);
+
+    let panic_backtrace = PanicBacktrace::default();
+    let tracker = panic_backtrace.clone();
+
+    R::spawn(async move {
+        let fut = async move {
+            let output = match locals {
+                Some(locals) => tracker.track(R::scope(locals, fut)).await,
+                None => tracker.track(fut).await,
+            };
+
+            let output = with_completion_gil(|py| output.map(|val| val.into_py(py)));
+            *result_tx1.lock().unwrap() = Some(output);
+        };
+
+        if let Err(e) = R::spawn(async move {
+            // Ok(()) unless the awaitable has been dropped
+            let _ = Abortable::new(fut, registration).await;
+        })
+        .await
+        {
+            if e.is_panic() {
+                with_completion_gil(|py| {
+                    *result_tx2.lock().unwrap() =
+                        Some(Err(panic_backtrace.into_err(py, "rust future panicked")));
+                });
+            }
+        }
+    });
+
+    Ok(PyCell::new(py, PyPortableFuture { result, abort })?.as_ref())
+}
+
+const SINK_QUEUE_CODE: &str = r#"
+import asyncio
+
+_CLOSED = object()
+
+class SinkQueue(asyncio.Queue):
+    _exc = None
+
+    def _close(self, exc):
+        self._exc = exc
+        self.put_nowait(_CLOSED)
+
+    async def _aclose(self, exc):
+        # waits for room in bounded queues, where put_nowait could fail
+        self._exc = exc
+        await self.put(_CLOSED)
+
+    def __aiter__(self):
+        return self
+
+    async def __anext__(self):
+        item = await self.get()
+        if item is _CLOSED:
+            # leave the marker in place for the other consumers
+            self.put_nowait(_CLOSED)
+            if self._exc is not None:
+                raise self._exc
+            raise StopAsyncIteration
+        return item
+"#;
+
+static SINK_QUEUE: PyCache<PyObject> = PyCache::new();
+
+fn sink_queue(py: Python) -> PyResult<&PyAny> {
+    cached(py, &SINK_QUEUE, || {
+        Ok(PyModule::from_code(
+            py,
+            SINK_QUEUE_CODE,
+            "pyo3_asyncio/sink_queue.py",
+            "pyo3_asyncio_sink_queue",
+        )?
+        .getattr("SinkQueue")?
+        .into())
+    })
+    .map(|queue| queue.into_ref(py))
+}
+
+/// Drop the cached Python objects of this module, see `crate::reset_caches`
+#[cfg(feature = "testing")]
+pub(crate) fn reset_caches(py: Python) {
+    crate::reset_cache(py, &AWAIT_FUTURE);
+    crate::reset_cache(py, &SINK_QUEUE);
+}
+
+/// Pushes values to the `asyncio.Queue` returned by [`future_into_py_with_sink`]
+#[derive(Clone)]
+pub struct QueueSink {
+    locals: TaskLocals,
+    queue: PyObject,
+}
+
+impl QueueSink {
+    /// Push `value` to the queue
+    ///
+    /// The value is put on the queue by the event loop, so it is not visible to the Python consumer
+    /// right away. This only fails if the event loop has been closed.
+    pub fn send<T>(&self, value: T) -> PyResult<()>
+    where
+        T: IntoPy<PyObject>,
+    {
+        Python::with_gil(|py| {
+            call_soon_threadsafe(
+                self.locals.event_loop(py),
+                py.None().as_ref(py),
+                (self.queue.getattr(py, "put_nowait")?, value.into_py(py)),
+            )
+        })
+    }
+
+    fn close(&self, err: Option<PyErr>) {
+        with_completion_gil(|py| {
+            bridge_debug!(
+                "future_into_py_with_sink: closing queue {:#x}",
+                py_id(self.queue.as_ref(py))
+            );
+
+            let _ = self
+                .queue
+                .getattr(py, "_close")
+                .and_then(|close| {
+                    call_soon_threadsafe(
+                        self.locals.event_loop(py),
+                        py.None().as_ref(py),
+                        (close, err),
+                    )
+                })
+                .map_err(dump_err(py));
+        });
+    }
+}
+
+impl fmt::Debug for QueueSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueueSink")
+            .field("locals", &self.locals)
+            .finish()
+    }
+}
+
+/// Convert a Rust Future that produces a sequence of values into an `asyncio.Queue` with a generic
+/// runtime
+///
+/// `f` is called with a [`QueueSink`], and every value passed to [`QueueSink::send`] is pushed to
+/// the returned queue. The queue is closed once the future returned by `f` completes, so the
+/// Python caller can consume it with `async for` while the Rust side is still producing:
+/// * when the future returns `Ok(())`, the iteration stops once the queue has been drained.
+/// * when the future returns an error (or panics), the iteration raises it once the queue has been
+///   drained.
+///
+/// The queue is an `asyncio.Queue`, so the values can also be consumed with `get()`. However, the
+/// end of the sequence is marked by a private sentinel value, so `async for` is the recommended way
+/// to consume it.
+///
+/// The queue is unbounded and the future keeps running even if nobody consumes the queue, so `f`
+/// should not produce an unbounded number of values.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `f` - Called with the sink to build the Rust future to be converted
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # use std::time::Duration;
+/// # async fn custom_sleep(_duration: Duration) { }
+/// #
+/// use pyo3::prelude::*;
+///
+/// /// Count to `n` asynchronously, i.e. `async for i in count_to(3): ...`
+/// #[pyfunction]
+/// fn count_to(py: Python, n: u64) -> PyResult<&PyAny> {
+///     pyo3_asyncio::generic::future_into_py_with_sink::<MyCustomRuntime, _, _>(py, move |sink| {
+///         async move {
+///             for i in 0..n {
+///                 custom_sleep(Duration::from_millis(100)).await;
+///                 sink.send(i)?;
+///             }
+///             Ok(())
+///         }
+///     })
+/// }
+/// ```
+pub fn future_into_py_with_sink<R, F, Fut>(py: Python, f: F) -> PyResult<&PyAny>
+where
+    R: Runtime + ContextExt,
+    F: FnOnce(QueueSink) -> Fut,
+    Fut: Future<Output = PyResult<()>> + Send + 'static,
+{
+    let locals = get_current_locals::<R>(py)?;
+    let queue = sink_queue(py)?.call0()?;
+
+    let sink = QueueSink {
+        locals: locals.clone(),
+        queue: queue.into(),
+    };
+    let closer1 = sink.clone();
+    let closer2 = sink.clone();
+    let fut = f(sink);
+
+    let panic_backtrace = PanicBacktrace::default();
+    let tracker = panic_backtrace.clone();
+
+    R::spawn(async move {
+        if let Err(e) = R::spawn(async move {
+            let result = tracker.track(R::scope(locals, fut)).await;
+            closer1.close(result.err());
+        })
+        .await
+        {
+            if e.is_panic() {
+                let err =
+                    Python::with_gil(|py| panic_backtrace.into_err(py, "rust future panicked"));
+                closer2.close(Some(err));
+            }
+        }
+    });
+
+    Ok(queue)
+}
+
+/// Feed the values of a Rust Stream into a bounded `asyncio.Queue` with a generic runtime
+///
+/// Unlike [`future_into_py_with_sink`], the returned queue holds a single value at a time and each
+/// value is put on it with `await queue.put(value)`, so the stream is only polled again once the
+/// Python consumer has taken the previous value. Slow consumers therefore apply backpressure to the
+/// Rust producer (i.e. the senders of a bounded channel wait for room in the channel).
+///
+/// The queue is closed once the stream ends, so the Python caller can consume it with
+/// `async for`, which stops once the last value has been received. If the event loop is closed
+/// before the stream ends, the stream is dropped.
+///
+/// > The stream is not polled while the queue is full, so a queue that is never consumed keeps the
+/// > stream (and the task feeding it) alive until the event loop is closed.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `stream` - The Rust stream providing the values
+pub fn stream_into_py_queue<R, S, T>(py: Python, stream: S) -> PyResult<&PyAny>
+where
+    R: Runtime + ContextExt,
+    S: Stream<Item = T> + Send + 'static,
+    T: IntoPy<PyObject> + Send + 'static,
+{
+    let locals = get_current_locals::<R>(py)?;
+    let queue = sink_queue(py)?.call1((1,))?;
+    let producer_queue = PyObject::from(queue);
+
+    R::spawn(R::scope(locals.clone(), async move {
+        let put = |method: &'static str, value: PyObject| {
+            Python::with_gil(|py| {
+                into_future_with_locals(
+                    &locals,
+                    producer_queue
+                        .call_method1(py, method, (value,))?
+                        .as_ref(py),
+                )
+            })
+        };
+
+        let result = async {
+            futures::pin_mut!(stream);
+
+            while let Some(value) = stream.next().await {
+                let value = Python::with_gil(|py| value.into_py(py));
+                put("put", value)?.await?;
+            }
+
+            bridge_debug!("stream_into_py_queue: stream ended, closing queue");
+            put("_aclose", Python::with_gil(|py| py.None()))?.await?;
+
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            Python::with_gil(|py| dump_err(py)(e));
+        }
+    }));
+
+    Ok(queue)
+}
+
+/// Drive a Rust Future with a generic runtime and report its outcome to Python callbacks
+///
+/// This is an alternative to [`future_into_py`] for callback-based Python APIs (i.e. some GUI
+/// frameworks) that expect `on_result` / `on_error` callbacks rather than an awaitable. Once `fut`
+/// completes, either `on_result(value)` or `on_error(exc)` is scheduled on the current event loop
+/// with `call_soon_threadsafe`, so the callbacks always run on the event loop's thread within the
+/// current contextvars. A panic in `fut` is reported to `on_error` as a
+/// [`RustPanic`](crate::err::RustPanic).
+///
+/// Exactly one of the callbacks is called. Nothing is called if the event loop has already been
+/// closed by the time `fut` completes.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be driven
+/// * `on_result` - Called with the value produced by `fut`
+/// * `on_error` - Called with the exception raised by `fut`
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::{task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_asyncio::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {    
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # use std::time::Duration;
+/// # async fn custom_sleep(_duration: Duration) { }
+/// #
+/// use pyo3::prelude::*;
+///
+/// /// Sleep for `secs`, then call `done(secs)`
+/// #[pyfunction]
+/// fn sleep_then(py: Python, secs: u64, done: &PyAny, failed: &PyAny) -> PyResult<()> {
+///     pyo3_asyncio::generic::future_into_callback::<MyCustomRuntime, _, _>(
+///         py,
+///         async move {
+///             custom_sleep(Duration::from_secs(secs)).await;
+///             Ok(secs)
+///         },
+///         done,
+///         failed,
+///     )
+/// }
+/// ```
+pub fn future_into_callback<R, F, T>(
+    py: Python,
+    fut: F,
+    on_result: &PyAny,
+    on_error: &PyAny,
+) -> PyResult<()>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    let locals = get_current_locals::<R>(py)?;
+    let callback_locals = locals.clone();
+    let on_result = PyObject::from(on_result);
+    let on_error1 = PyObject::from(on_error);
+    let on_error2 = on_error1.clone_ref(py);
+
+    let panic_backtrace = PanicBacktrace::default();
+    let tracker = panic_backtrace.clone();
+
+    R::spawn(async move {
+        if let Err(e) = R::spawn(async move {
+            let result = tracker.track(R::scope(locals.clone(), fut)).await;
+
+            with_completion_gil(move |py| {
+                let _ = match result {
+                    Ok(val) => call_soon_threadsafe(
+                        locals.event_loop(py),
+                        locals.context(py),
+                        (on_result, val.into_py(py)),
+                    ),
+                    Err(e) => call_soon_threadsafe(
+                        locals.event_loop(py),
+                        locals.context(py),
+                        (on_error1, e.into_py(py)),
+                    ),
+                }
+                .map_err(dump_err(py));
+            });
+        })
+        .await
+        {
+            if e.is_panic() {
+                with_completion_gil(move |py| {
+                    let err = panic_backtrace.into_err(py, "rust future panicked");
+                    let _ = call_soon_threadsafe(
+                        callback_locals.event_loop(py),
+                        callback_locals.context(py),
+                        (on_error2, err.into_py(py)),
+                    )
+                    .map_err(dump_err(py));
+                });
+            }
+        }
+    });
+
+    Ok(())
+}
+
+type AsyncFnCall = dyn Fn(&PyTuple) -> PyResult<PyObject> + Send;
+
+#[pyclass]
+struct PyAsyncFn {
+    call: Box<AsyncFnCall>,
+}
+
+#[pymethods]
+impl PyAsyncFn {
+    #[args(args = "*")]
+    pub fn __call__(&self, args: &PyTuple) -> PyResult<PyObject> {
+        (self.call)(args)
+    }
+}
+
+/// Convert a Rust async function into a Python callable that returns a new awaitable every time
+/// it is called with a generic runtime
+///
+/// This is effectively an `async def` implemented in Rust. Each call extracts the positional
+/// arguments as `A` and converts the future returned by `f` with [`future_into_py`]. If the
+/// arguments cannot be extracted, the call raises a `TypeError` before any future is created.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `f` - The Rust async function to be converted. Its arguments are extracted from the tuple of
+///   positional arguments passed to the Python callable.
 ///
 /// # Examples
 ///
@@ -505,101 +3645,95 @@ where
 ///
 /// use pyo3::prelude::*;
 ///
-/// /// Awaitable sleep function
-/// #[pyfunction]
-/// fn sleep_for<'p>(py: Python<'p>, secs: &'p PyAny) -> PyResult<&'p PyAny> {
-///     let secs = secs.extract()?;
-///     pyo3_asyncio::generic::future_into_py_with_locals::<MyCustomRuntime, _, _>(
-///         py,
-///         pyo3_asyncio::generic::get_current_locals::<MyCustomRuntime>(py)?,
-///         async move {
-///             MyCustomRuntime::sleep(Duration::from_secs(secs)).await;
-///             Ok(())
-///         }
-///     )
+/// #[pymodule]
+/// fn my_mod(py: Python, m: &PyModule) -> PyResult<()> {
+///     // Python can call `await my_mod.sleep_for(1)` as many times as it likes
+///     m.add(
+///         "sleep_for",
+///         pyo3_asyncio::generic::async_fn_into_py::<MyCustomRuntime, _, _, _, _>(
+///             py,
+///             |(secs,): (u64,)| async move {
+///                 MyCustomRuntime::sleep(Duration::from_secs(secs)).await;
+///                 Ok(())
+///             },
+///         )?,
+///     )?;
+///
+///     Ok(())
 /// }
 /// ```
-pub fn future_into_py_with_locals<R, F, T>(
-    py: Python,
-    locals: TaskLocals,
-    fut: F,
-) -> PyResult<&PyAny>
+pub fn async_fn_into_py<R, F, A, Fut, T>(py: Python, f: F) -> PyResult<&PyAny>
 where
     R: Runtime + ContextExt,
-    F: Future<Output = PyResult<T>> + Send + 'static,
+    F: Fn(A) -> Fut + Send + 'static,
+    A: for<'p> FromPyObject<'p>,
+    Fut: Future<Output = PyResult<T>> + Send + 'static,
     T: IntoPy<PyObject>,
 {
-    let (cancel_tx, cancel_rx) = oneshot::channel();
-
-    let py_fut = create_future(locals.event_loop.clone().into_ref(py))?;
-    py_fut.call_method1(
-        "add_done_callback",
-        (PyDoneCallback {
-            cancel_tx: Some(cancel_tx),
-        },),
-    )?;
-
-    let future_tx1 = PyObject::from(py_fut);
-    let future_tx2 = future_tx1.clone();
+    let async_fn = PyAsyncFn {
+        call: Box::new(move |args| {
+            let py = args.py();
+            // like calling a Python function with the wrong arguments, i.e. extracting a tuple of
+            // the wrong length raises a ValueError otherwise
+            let args = args.extract().map_err(|e| {
+                let err = PyTypeError::new_err(e.value(py).to_string());
+                err.set_cause(py, Some(e));
+                err
+            })?;
+            let fut = f(args);
+            future_into_py::<R, Fut, T>(py, fut).map(|awaitable| awaitable.into())
+        }),
+    };
 
-    R::spawn(async move {
-        let locals2 = locals.clone();
+    Ok(PyCell::new(py, async_fn)?.as_ref())
+}
 
-        if let Err(e) = R::spawn(async move {
-            let result = R::scope(
-                locals2.clone(),
-                Cancellable::new_with_cancel_rx(fut, cancel_rx),
-            )
-            .await;
+type AsyncEnter = dyn Fn(Python) -> PyResult<PyObject> + Send;
+type AsyncExit = dyn Fn(Python, Option<PyErr>) -> PyResult<PyObject> + Send;
 
-            Python::with_gil(move |py| {
-                if cancelled(future_tx1.as_ref(py))
-                    .map_err(dump_err(py))
-                    .unwrap_or(false)
-                {
-                    return;
-                }
+#[pyclass]
+struct PyAsyncContextManager {
+    enter: Box<AsyncEnter>,
+    exit: Box<AsyncExit>,
+}
 
-                let _ = set_result(
-                    locals2.event_loop(py),
-                    future_tx1.as_ref(py),
-                    result.map(|val| val.into_py(py)),
-                )
-                .map_err(dump_err(py));
-            });
-        })
-        .await
-        {
-            if e.is_panic() {
-                Python::with_gil(move |py| {
-                    if cancelled(future_tx2.as_ref(py))
-                        .map_err(dump_err(py))
-                        .unwrap_or(false)
-                    {
-                        return;
-                    }
+#[pymethods]
+impl PyAsyncContextManager {
+    pub fn __aenter__(&self, py: Python) -> PyResult<PyObject> {
+        (self.enter)(py)
+    }
 
-                    let _ = set_result(
-                        locals.event_loop.as_ref(py),
-                        future_tx2.as_ref(py),
-                        Err(RustPanic::new_err("rust future panicked")),
-                    )
-                    .map_err(dump_err(py));
-                });
-            }
-        }
-    });
+    #[args(_exc_type, exc, _traceback)]
+    pub fn __aexit__(
+        &self,
+        py: Python,
+        _exc_type: &PyAny,
+        exc: &PyAny,
+        _traceback: &PyAny,
+    ) -> PyResult<PyObject> {
+        let exc = if exc.is_none() {
+            None
+        } else {
+            Some(from_exception(exc))
+        };
 
-    Ok(py_fut)
+        (self.exit)(py, exc)
+    }
 }
 
-/// Convert a Rust Future into a Python awaitable with a generic runtime
+/// Build a Python async context manager from a pair of Rust async closures with a generic runtime
 ///
-/// __This function will be removed in `v0.16`__
+/// The returned object implements `__aenter__` and `__aexit__`, so Python can use it with
+/// `async with`. Every time the block is entered, the future returned by `enter` is converted with
+/// [`future_into_py`] and its output is bound by `async with ... as`. When the block is exited, the
+/// future returned by `exit` is converted the same way. `exit` receives the exception raised inside
+/// of the block, if any, and returns whether that exception should be suppressed.
 ///
 /// # Arguments
-/// * `event_loop` - The Python event loop that the awaitable should be attached to
-/// * `fut` - The Rust future to be converted
+/// * `py` - The current PyO3 GIL guard
+/// * `enter` - Creates the future that is awaited by `__aenter__`
+/// * `exit` - Creates the future that is awaited by `__aexit__`. It returns `true` to suppress the
+///   exception that was raised inside of the `async with` block.
 ///
 /// # Examples
 ///
@@ -665,128 +3799,90 @@ where
 ///
 /// use pyo3::prelude::*;
 ///
-/// /// Awaitable sleep function
-/// #[pyfunction]
-/// fn sleep_for<'p>(py: Python<'p>, secs: &'p PyAny) -> PyResult<&'p PyAny> {
-///     let secs = secs.extract()?;
-///     pyo3_asyncio::generic::future_into_py_with_loop::<MyCustomRuntime, _>(
-///         pyo3_asyncio::generic::get_current_loop::<MyCustomRuntime>(py)?,
-///         async move {
-///             MyCustomRuntime::sleep(Duration::from_secs(secs)).await;
-///             Python::with_gil(|py| Ok(py.None()))
-///         }
-///     )
+/// #[pymodule]
+/// fn my_mod(py: Python, m: &PyModule) -> PyResult<()> {
+///     // async with my_mod.connection as conn: ...
+///     m.add(
+///         "connection",
+///         pyo3_asyncio::generic::async_context_manager_into_py::<MyCustomRuntime, _, _, _, _, _>(
+///             py,
+///             || async move {
+///                 MyCustomRuntime::sleep(Duration::from_millis(10)).await;
+///                 Ok("connected")
+///             },
+///             |_exc| async move {
+///                 MyCustomRuntime::sleep(Duration::from_millis(10)).await;
+///                 Ok(false)
+///             },
+///         )?,
+///     )?;
+///
+///     Ok(())
 /// }
 /// ```
-#[deprecated(
-    since = "0.15.0",
-    note = "Use pyo3_asyncio::generic::future_into_py_with_locals instead"
-)]
-pub fn future_into_py_with_loop<R, F>(event_loop: &PyAny, fut: F) -> PyResult<&PyAny>
+pub fn async_context_manager_into_py<R, En, EnFut, T, Ex, ExFut>(
+    py: Python,
+    enter: En,
+    exit: Ex,
+) -> PyResult<&PyAny>
 where
     R: Runtime + ContextExt,
-    F: Future<Output = PyResult<PyObject>> + Send + 'static,
-{
-    let py = event_loop.py();
-    future_into_py_with_locals::<R, F, PyObject>(
-        py,
-        TaskLocals::new(event_loop).copy_context(py)?,
-        fut,
-    )
-}
-
-pin_project! {
-    /// Future returned by [`timeout`](timeout) and [`timeout_at`](timeout_at).
-    #[must_use = "futures do nothing unless you `.await` or poll them"]
-    #[derive(Debug)]
-    struct Cancellable<T> {
-        #[pin]
-        future: T,
-        #[pin]
-        cancel_rx: oneshot::Receiver<()>,
-
-        poll_cancel_rx: bool
-    }
-}
-
-impl<T> Cancellable<T> {
-    fn new_with_cancel_rx(future: T, cancel_rx: oneshot::Receiver<()>) -> Self {
-        Self {
-            future,
-            cancel_rx,
-
-            poll_cancel_rx: true,
-        }
-    }
-}
-
-impl<F, T> Future for Cancellable<F>
-where
-    F: Future<Output = PyResult<T>>,
+    En: Fn() -> EnFut + Send + 'static,
+    EnFut: Future<Output = PyResult<T>> + Send + 'static,
     T: IntoPy<PyObject>,
+    Ex: Fn(Option<PyErr>) -> ExFut + Send + 'static,
+    ExFut: Future<Output = PyResult<bool>> + Send + 'static,
 {
-    type Output = F::Output;
-
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.project();
-
-        // First, try polling the future
-        if let Poll::Ready(v) = this.future.poll(cx) {
-            return Poll::Ready(v);
-        }
+    let context_manager = PyAsyncContextManager {
+        enter: Box::new(move |py| {
+            future_into_py::<R, EnFut, T>(py, enter()).map(|awaitable| awaitable.into())
+        }),
+        exit: Box::new(move |py, exc| {
+            future_into_py::<R, ExFut, bool>(py, exit(exc)).map(|awaitable| awaitable.into())
+        }),
+    };
 
-        // Now check for cancellation
-        if *this.poll_cancel_rx {
-            match this.cancel_rx.poll(cx) {
-                Poll::Ready(Ok(())) => {
-                    *this.poll_cancel_rx = false;
-                    // The python future has already been cancelled, so this return value will never
-                    // be used.
-                    Poll::Ready(Err(pyo3::exceptions::PyBaseException::new_err(
-                        "unreachable",
-                    )))
-                }
-                Poll::Ready(Err(_)) => {
-                    *this.poll_cancel_rx = false;
-                    Poll::Pending
-                }
-                Poll::Pending => Poll::Pending,
-            }
-        } else {
-            Poll::Pending
-        }
-    }
+    Ok(PyCell::new(py, context_manager)?.as_ref())
 }
 
+type AsyncIterMethod = dyn Fn(Python) -> PyResult<PyObject> + Send;
+
 #[pyclass]
-struct PyDoneCallback {
-    cancel_tx: Option<oneshot::Sender<()>>,
+struct PyAsyncIter {
+    next: Box<AsyncIterMethod>,
+    close: Box<AsyncIterMethod>,
 }
 
 #[pymethods]
-impl PyDoneCallback {
-    pub fn __call__(&mut self, fut: &PyAny) -> PyResult<()> {
-        let py = fut.py();
+impl PyAsyncIter {
+    fn __aiter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
 
-        if cancelled(fut).map_err(dump_err(py)).unwrap_or(false) {
-            let _ = self.cancel_tx.take().unwrap().send(());
-        }
+    fn __anext__(&self, py: Python) -> PyResult<Option<PyObject>> {
+        (self.next)(py).map(Some)
+    }
 
-        Ok(())
+    /// Drop the Rust stream, so that the iteration ends like `aclose()` ends an async generator
+    fn aclose(&self, py: Python) -> PyResult<PyObject> {
+        (self.close)(py)
     }
 }
 
-/// Convert a Rust Future into a Python awaitable with a generic runtime
+/// Convert a Rust Stream into a Python async iterator with a generic runtime
 ///
-/// __This function was deprecated in favor of [`future_into_py_with_locals`] in `v0.15` because
-/// it became the default behaviour. In `v0.15`, any calls to this function should be
-/// replaced with [`future_into_py_with_locals`].__
+/// Every `__anext__` polls the stream for its next item on the Rust runtime and is converted with
+/// [`future_into_py`], so `async for` can iterate over the stream without a coroutine call per
+/// item. An error item is raised from the iteration that receives it, and `StopAsyncIteration` is
+/// raised once the stream returns `None`.
 ///
-/// __In `v0.16` this function will be removed__
+/// The stream is only polled while Python is awaiting the next item. Like an async generator, the
+/// iterator also has an `aclose()` method (i.e. for `contextlib.aclosing`) that drops the stream,
+/// after which the iteration is over. `aclose()` waits for a pending `__anext__` to complete first.
 ///
 /// # Arguments
-/// * `event_loop` - The Python event loop that the awaitable should be attached to
-/// * `fut` - The Rust future to be converted
+/// * `py` - The current PyO3 GIL guard
+/// * `stream` - The Rust stream to be converted
 ///
 /// # Examples
 ///
@@ -848,58 +3944,83 @@ impl PyDoneCallback {
 /// #     }
 /// # }
 /// #
-/// use std::time::Duration;
-///
+/// # use std::time::Duration;
+/// # async fn custom_sleep(_duration: Duration) { }
+/// #
+/// use futures::{stream, StreamExt};
 /// use pyo3::prelude::*;
 ///
-/// /// Awaitable sleep function
+/// /// Async iterator over the numbers from 0 to `count`, one every 10ms
 /// #[pyfunction]
-/// fn sleep_for<'p>(py: Python<'p>, secs: &'p PyAny) -> PyResult<&'p PyAny> {
-///     let secs = secs.extract()?;
-///     pyo3_asyncio::generic::cancellable_future_into_py_with_loop::<MyCustomRuntime, _>(
-///         pyo3_asyncio::generic::get_current_loop::<MyCustomRuntime>(py)?,
-///         async move {
-///             MyCustomRuntime::sleep(Duration::from_secs(secs)).await;
-///             Python::with_gil(|py| Ok(py.None()))
-///         }
-///     )
+/// fn ticks(py: Python, count: u64) -> PyResult<&PyAny> {
+///     let ticks = stream::iter(0..count).then(|i| async move {
+///         custom_sleep(Duration::from_millis(10)).await;
+///         Ok(i)
+///     });
+///
+///     pyo3_asyncio::generic::stream_into_py::<MyCustomRuntime, _, _>(py, ticks)
 /// }
 /// ```
-
-#[deprecated(
-    since = "0.15.0",
-    note = "Use pyo3_asyncio::generic::future_into_py_with_locals instead"
-)]
-#[allow(deprecated)]
-pub fn cancellable_future_into_py_with_loop<R, F>(event_loop: &PyAny, fut: F) -> PyResult<&PyAny>
+pub fn stream_into_py<R, S, T>(py: Python, stream: S) -> PyResult<&PyAny>
 where
     R: Runtime + ContextExt,
-    F: Future<Output = PyResult<PyObject>> + Send + 'static,
+    S: Stream<Item = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject> + Send + 'static,
 {
-    // cancellable futures became the default behaviour in 0.15
-    future_into_py_with_loop::<R, _>(event_loop, fut)
+    let stream = Arc::new(futures::lock::Mutex::new(Some(Box::pin(stream))));
+    let close_stream = Arc::clone(&stream);
+
+    let iter = PyAsyncIter {
+        next: Box::new(move |py| {
+            let stream = Arc::clone(&stream);
+
+            future_into_py::<R, _, T>(py, async move {
+                let next = match stream.lock().await.as_mut() {
+                    Some(stream) => stream.next().await,
+                    None => None,
+                };
+
+                match next {
+                    Some(result) => result,
+                    None => Err(PyStopAsyncIteration::new_err(())),
+                }
+            })
+            .map(|awaitable| awaitable.into())
+        }),
+        close: Box::new(move |py| {
+            let stream = Arc::clone(&close_stream);
+
+            future_into_py::<R, _, ()>(py, async move {
+                stream.lock().await.take();
+                Ok(())
+            })
+            .map(|awaitable| awaitable.into())
+        }),
+    };
+
+    Ok(PyCell::new(py, iter)?.as_ref())
 }
 
-/// Convert a Rust Future into a Python awaitable with a generic runtime
+/// Convert a stream of Rust futures into a Python async iterator that yields their results as
+/// they complete with a generic runtime
 ///
-/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
-/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
+/// This is the bridge equivalent of `StreamExt::buffer_unordered`. Up to `concurrency` of the
+/// futures produced by `stream` are driven at once and Python receives their results in
+/// completion order, not in the order of the stream. Each `__anext__` is converted with
+/// [`future_into_py`], so an error returned by one of the futures is raised from that iteration.
 ///
-/// Python `contextvars` are preserved when calling async Python functions within the Rust future
-/// via [`into_future`] (new behaviour in `v0.15`).
+/// The futures are driven on demand, i.e. while Python is awaiting the next item, so abandoning
+/// the iteration simply stops the pipeline. Like an async generator, the iterator also has an
+/// `aclose()` method (i.e. for `contextlib.aclosing`) that drops the stream along with any futures
+/// still in flight, after which the iteration is over. `aclose()` waits for a pending `__anext__`
+/// to complete first.
 ///
-/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
-/// unfortunately fail to resolve them when called within the Rust future. This is because the
-/// function is being called from a Rust thread, not inside an actual Python coroutine context.
-/// >
-/// > As a workaround, you can get the `contextvars` from the current task locals using
-/// [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
-/// synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
-/// synchronous function, and restore the previous context when it returns or raises an exception.
+/// A `ValueError` is raised if `concurrency` is zero.
 ///
 /// # Arguments
 /// * `py` - The current PyO3 GIL guard
-/// * `fut` - The Rust future to be converted
+/// * `stream` - Stream of the futures to run
+/// * `concurrency` - Maximum number of futures to drive at once
 ///
 /// # Examples
 ///
@@ -961,27 +4082,41 @@ where
 /// #     }
 /// # }
 /// #
-/// use std::time::Duration;
-///
+/// # use std::time::Duration;
+/// # async fn custom_sleep(_duration: Duration) { }
+/// #
+/// use futures::{stream, StreamExt};
 /// use pyo3::prelude::*;
 ///
-/// /// Awaitable sleep function
+/// /// Async iterator over `count` numbers, the largest (and slowest) ones first
 /// #[pyfunction]
-/// fn sleep_for<'p>(py: Python<'p>, secs: &'p PyAny) -> PyResult<&'p PyAny> {
-///     let secs = secs.extract()?;
-///     pyo3_asyncio::generic::future_into_py::<MyCustomRuntime, _, _>(py, async move {
-///         MyCustomRuntime::sleep(Duration::from_secs(secs)).await;
-///         Ok(())
-///     })
+/// fn countdown(py: Python, count: u64) -> PyResult<&PyAny> {
+///     let jobs = stream::iter((0..count).rev()).map(|i| async move {
+///         custom_sleep(Duration::from_millis(i * 10)).await;
+///         Ok(i)
+///     });
+///
+///     pyo3_asyncio::generic::buffer_unordered_into_py::<MyCustomRuntime, _, _, _>(py, jobs, 4)
 /// }
 /// ```
-pub fn future_into_py<R, F, T>(py: Python, fut: F) -> PyResult<&PyAny>
+pub fn buffer_unordered_into_py<R, S, F, T>(
+    py: Python,
+    stream: S,
+    concurrency: usize,
+) -> PyResult<&PyAny>
 where
     R: Runtime + ContextExt,
+    S: Stream<Item = F> + Send + 'static,
     F: Future<Output = PyResult<T>> + Send + 'static,
-    T: IntoPy<PyObject>,
+    T: IntoPy<PyObject> + Send + 'static,
 {
-    future_into_py_with_locals::<R, F, T>(py, get_current_locals::<R>(py)?, fut)
+    if concurrency == 0 {
+        return Err(PyValueError::new_err(
+            "concurrency must be greater than zero",
+        ));
+    }
+
+    stream_into_py::<R, _, T>(py, stream.buffer_unordered(concurrency))
 }
 
 /// Convert a Rust Future into a Python awaitable with a generic runtime
@@ -1088,6 +4223,11 @@ where
 /// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
 /// the Rust future will be cancelled as well (new behaviour in `v0.15`).
 ///
+/// The future runs on the thread that spawned it (i.e. the thread driving the `LocalSet`), which
+/// doesn't need to be the thread running the Python event loop. Its result is handed over to the
+/// event loop with `call_soon_threadsafe`, so the `asyncio.Future` is always resolved on the
+/// event loop's own thread, just like with the `Send` conversions.
+///
 /// Python `contextvars` are preserved when calling async Python functions within the Rust future
 /// via [`into_future`] (new behaviour in `v0.15`).
 ///
@@ -1213,6 +4353,9 @@ where
     F: Future<Output = PyResult<T>> + 'static,
     T: IntoPy<PyObject>,
 {
+    if debug_enabled() {
+        check_running_loop(py, &locals)?;
+    }
     let (cancel_tx, cancel_rx) = oneshot::channel();
 
     let py_fut = create_future(locals.event_loop.clone().into_ref(py))?;
@@ -1223,27 +4366,55 @@ where
         },),
     )?;
 
+    bridge_debug!(
+        "local_future_into_py: created future {:#x} on loop {:#x}",
+        py_id(py_fut),
+        py_id(locals.event_loop(py))
+    );
+
     let future_tx1 = PyObject::from(py_fut);
     let future_tx2 = future_tx1.clone();
 
+    // cancel_all_bridges can reach the future until the task below completes
+    let bridge = Arc::new(PyFutureBridge(future_tx1.clone()));
+    register_bridge(&bridge);
+
+    let panic_backtrace = PanicBacktrace::default();
+    let tracker = panic_backtrace.clone();
+
     R::spawn_local(async move {
+        let _bridge = bridge;
         let locals2 = locals.clone();
 
         if let Err(e) = R::spawn_local(async move {
-            let result = R::scope_local(
-                locals2.clone(),
-                Cancellable::new_with_cancel_rx(fut, cancel_rx),
-            )
-            .await;
+            let result = tracker
+                .track(R::scope_local(
+                    locals2.clone(),
+                    debug_check_gil_released(CancellableFuture::new_with_cancel_rx(
+                        DropOnCancel::new(fut),
+                        cancel_rx,
+                    )),
+                ))
+                .await;
 
-            Python::with_gil(move |py| {
-                if cancelled(future_tx1.as_ref(py))
+            with_completion_gil(move |py| {
+                if done(future_tx1.as_ref(py))
                     .map_err(dump_err(py))
                     .unwrap_or(false)
                 {
                     return;
                 }
 
+                bridge_debug!(
+                    "local_future_into_py: rust future for {:#x} {}",
+                    py_id(future_tx1.as_ref(py)),
+                    if result.is_ok() {
+                        "completed"
+                    } else {
+                        "failed"
+                    }
+                );
+
                 let _ = set_result(
                     locals2.event_loop.as_ref(py),
                     future_tx1.as_ref(py),
@@ -1255,18 +4426,23 @@ where
         .await
         {
             if e.is_panic() {
-                Python::with_gil(move |py| {
-                    if cancelled(future_tx2.as_ref(py))
+                with_completion_gil(move |py| {
+                    if done(future_tx2.as_ref(py))
                         .map_err(dump_err(py))
                         .unwrap_or(false)
                     {
                         return;
                     }
 
+                    bridge_debug!(
+                        "local_future_into_py: rust future for {:#x} panicked",
+                        py_id(future_tx2.as_ref(py))
+                    );
+
                     let _ = set_result(
                         locals.event_loop.as_ref(py),
                         future_tx2.as_ref(py),
-                        Err(RustPanic::new_err("Rust future panicked")),
+                        Err(panic_backtrace.into_err(py, "Rust future panicked")),
                     )
                     .map_err(dump_err(py));
                 });
@@ -0,0 +1,75 @@
+use pyo3::{prelude::*, types::IntoPyDict};
+use tracing::Span;
+
+use crate::{cached, contextvars, PyCache};
+
+/// Carries a `tracing` span through the Python code awaited by `into_future`
+#[pyclass]
+struct PySpan(Span);
+
+static SPAN_VAR: PyCache<Option<PyObject>> = PyCache::new();
+
+/// The `contextvars.ContextVar` holding the span of the `into_future` call that started the current
+/// Python task, if contextvars are supported (Python 3.7+)
+fn span_var(py: Python) -> PyResult<Option<&PyAny>> {
+    cached(py, &SPAN_VAR, || {
+        contextvars(py)
+            .map(|contextvars| {
+                let kwargs = [("default", py.None())].into_py_dict(py);
+                Ok(contextvars
+                    .getattr("ContextVar")?
+                    .call(("pyo3_asyncio_span",), Some(kwargs))?
+                    .into())
+            })
+            .transpose()
+    })
+    .map(|var| var.map(|var| var.into_ref(py)))
+}
+
+/// Drop the cached Python objects of this module, see `crate::reset_caches`
+#[cfg(feature = "testing")]
+pub(crate) fn reset_caches(py: Python) {
+    crate::reset_cache(py, &SPAN_VAR);
+}
+
+/// The span that a Rust future converted with `future_into_py` is instrumented with
+///
+/// Python code awaited by `into_future` carries the `py_await` span of that call in its context, so
+/// that span is preferred. Otherwise the future inherits the current span of the calling thread.
+pub(crate) fn current_span(py: Python) -> Span {
+    let span = span_var(py)
+        .ok()
+        .flatten()
+        .and_then(|var| var.call_method0("get").ok())
+        .and_then(|span| {
+            span.extract::<PyRef<PySpan>>()
+                .ok()
+                .map(|span| span.0.clone())
+        });
+
+    span.unwrap_or_else(Span::current)
+}
+
+/// Copy `context` (or the current context if it is `None`) with `span` set as the span of the
+/// Python code that runs in it
+///
+/// The context is left alone if `span` is disabled or contextvars are not supported.
+pub(crate) fn context_with_span<'p>(
+    py: Python<'p>,
+    context: &'p PyAny,
+    span: &Span,
+) -> PyResult<&'p PyAny> {
+    let var = match span_var(py)? {
+        Some(var) if !span.is_disabled() => var,
+        _ => return Ok(context),
+    };
+
+    let context = if context.is_none() {
+        contextvars(py).unwrap().call_method0("copy_context")?
+    } else {
+        context.call_method0("copy")?
+    };
+    context.call_method1("run", (var.getattr("set")?, PySpan(span.clone())))?;
+
+    Ok(context)
+}
@@ -288,6 +288,66 @@
 //! > _In the future, we may implement first class support for more Rust runtimes. Contributions are
 //! welcome as well!_
 //!
+//! ### Mixing Runtimes
+//!
+//! The bridge doesn't depend on the Rust runtime that started the Python event loop. The event loop
+//! is only reachable through [`TaskLocals`], so when both the `tokio-runtime` and
+//! `async-std-runtime` features are enabled, any combination works as long as the locals are
+//! passed along:
+//!
+//! - The `*_with_locals` functions (i.e. [`into_future_with_locals`]) target the event loop in the
+//!   given locals from any Rust runtime, or from no runtime at all.
+//! - A runtime's `scope` function sets the task locals for that runtime, so after
+//!   `pyo3_asyncio::async_std::scope(locals, fut)`, `pyo3_asyncio::async_std::into_future` inside
+//!   `fut` targets the event loop in `locals` even if that loop was started with
+//!   `pyo3_asyncio::tokio::run` (and vice versa).
+//! - The functions without locals only see the task locals of their own runtime (or the running
+//!   event loop on the current thread). A tokio task doesn't inherit the task locals of the
+//!   async-std task that spawned it, so get the locals with the spawning runtime's
+//!   `get_current_locals` and pass them explicitly.
+//!
+//! ```
+//! # #[cfg(all(feature = "tokio-runtime", feature = "async-std-runtime"))]
+//! # fn main() -> pyo3::PyResult<()> {
+//! use pyo3::prelude::*;
+//!
+//! pyo3::prepare_freethreaded_python();
+//!
+//! Python::with_gil(|py| {
+//!     // the Python event loop is driven by tokio
+//!     pyo3_asyncio::tokio::run(py, async move {
+//!         let locals = Python::with_gil(pyo3_asyncio::tokio::get_current_locals)?;
+//!
+//!         // but an async-std task can still await Python coroutines on it
+//!         async_std::task::spawn(pyo3_asyncio::async_std::scope(locals, async move {
+//!             Python::with_gil(|py| {
+//!                 pyo3_asyncio::async_std::into_future(
+//!                     py.import("asyncio")?.call_method1("sleep", (0.1,))?,
+//!                 )
+//!             })?
+//!             .await?;
+//!
+//!             Ok::<_, PyErr>(())
+//!         }))
+//!         .await
+//!     })
+//! })
+//! # }
+//! # #[cfg(not(all(feature = "tokio-runtime", feature = "async-std-runtime")))]
+//! # fn main() {}
+//! ```
+//!
+//! ### Multiple Event Loops
+//!
+//! There is no global event loop to initialize: every conversion targets the event loop in its
+//! [`TaskLocals`], so any number of event loops can coexist as long as each one runs on its own
+//! thread. For instance, each call to `pyo3_asyncio::tokio::run_on_thread` (or its async-std
+//! counterpart) drives a separate event loop, and futures scoped with the locals of one loop never
+//! end up on another. Use [`TaskLocals::same_loop`] to check which loop a set of locals targets.
+//!
+//! > The cached references to Python modules (i.e. `asyncio`) are shared by the whole process, so
+//! > multiple Python sub-interpreters are not supported. This is also a limitation of PyO3 itself.
+//!
 //! ## Features
 //!
 //! Items marked with
@@ -333,6 +393,19 @@
 //! <span
 //!   class="module-item stab portability"
 //!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>smol-runtime</code></span>
+//! are only available when the `smol-runtime` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio]
+//! version = "0.15"
+//! features = ["smol-runtime"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
 //! ><code>testing</code></span>
 //! are only available when the `testing` Cargo feature is enabled:
 //!
@@ -341,6 +414,67 @@
 //! version = "0.15"
 //! features = ["testing"]
 //! ```
+//!
+//! When a Rust future converted with `future_into_py` panics, its Python future is completed with a
+//! [`RustPanic`](err::RustPanic) exception instead of being left pending. If the panic payload is a
+//! string (i.e. the message passed to `panic!`), it is included in the exception's message.
+//!
+//! Enabling the `backtrace` Cargo feature attaches the Rust backtrace of a panicking future to the
+//! [`RustPanic`](err::RustPanic) raised on the Python side whenever `RUST_BACKTRACE=1` is set. On
+//! Python 3.11+ the backtrace is added as a note (PEP 678), otherwise it is appended to the
+//! message. This feature requires Rust 1.65 or newer:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio]
+//! version = "0.15"
+//! features = ["backtrace"]
+//! ```
+//!
+//! Enabling the `metrics` Cargo feature times how long bridged futures wait for the GIL before
+//! their result can be handed back to Python. The counters are available through
+//! [`metrics::stats`](metrics::stats):
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio]
+//! version = "0.15"
+//! features = ["metrics"]
+//! ```
+//!
+//! Enabling the `signal` Cargo feature adds [`run_forever_until_signal`], which stops the event loop
+//! on `SIGINT` or `SIGTERM` (Unix only):
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio]
+//! version = "0.15"
+//! features = ["signal"]
+//! ```
+//!
+//! Enabling the `tracing` Cargo feature instruments the Rust futures converted with
+//! `future_into_py` with the current [`tracing`](https://docs.rs/tracing) span, and wraps every
+//! `into_future` call in a `py_await` span that is carried through the Python code it awaits. Rust
+//! futures converted by that Python code become children of the `py_await` span, so the span tree
+//! follows the calls across the language boundary. Explicit spans can be given with the
+//! `future_into_py_instrumented` functions:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio]
+//! version = "0.15"
+//! features = ["tracing"]
+//! ```
+
+/// Log a bridge crossing along with the current thread if [`set_debug`] is enabled
+macro_rules! bridge_debug {
+    ($($arg:tt)+) => {
+        if $crate::debug_enabled() {
+            log::debug!(
+                target: "pyo3_asyncio",
+                "[{:?}] {}",
+                std::thread::current().id(),
+                format_args!($($arg)+)
+            );
+        }
+    };
+}
 
 /// Re-exported for #[test] attributes
 #[cfg(all(feature = "attributes", feature = "testing"))]
@@ -358,12 +492,26 @@ pub mod async_std;
 #[cfg(feature = "tokio-runtime")]
 pub mod tokio;
 
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>smol-runtime</code></span> PyO3 Asyncio functions specific to the smol runtime
+#[cfg(feature = "smol-runtime")]
+pub mod smol;
+
 /// Errors and exceptions related to PyO3 Asyncio
 pub mod err;
 
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>metrics</code></span> Counters for the time spent bridging futures
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+#[cfg(feature = "tracing")]
+mod instrument;
+
 /// Generic implementations of PyO3 Asyncio utilities that can be used for any Rust runtime
 pub mod generic;
 
+/// Low-level building blocks for custom bridges
+pub mod raw;
+
 /// Test README
 #[doc(hidden)]
 pub mod doc_test {
@@ -390,365 +538,4309 @@ pub mod doc_test {
     doctest!("../README.md", readme_md);
 }
 
-use std::future::Future;
+use std::{
+    cell::UnsafeCell,
+    collections::HashMap,
+    fmt,
+    future::Future,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Weak,
+    },
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
 
-use futures::channel::oneshot;
-use once_cell::sync::OnceCell;
+use futures::{
+    channel::{mpsc, oneshot},
+    future::{self, AbortHandle, AbortRegistration, FutureExt},
+    stream::{self, Stream, StreamExt, TryStreamExt},
+    task::AtomicWaker,
+};
+use once_cell::sync::{Lazy, OnceCell};
+use pin_project_lite::pin_project;
 use pyo3::{
+    exceptions::{
+        asyncio::CancelledError, PyGeneratorExit, PyKeyboardInterrupt, PyRuntimeError,
+        PyStopAsyncIteration, PyTypeError, PyValueError,
+    },
     prelude::*,
     types::{PyDict, PyTuple},
+    AsPyPointer,
 };
 
-static ASYNCIO: OnceCell<PyObject> = OnceCell::new();
-static CONTEXTVARS: OnceCell<Option<PyObject>> = OnceCell::new();
-static ENSURE_FUTURE: OnceCell<PyObject> = OnceCell::new();
-static GET_RUNNING_LOOP: OnceCell<PyObject> = OnceCell::new();
-
-fn ensure_future<'p>(py: Python<'p>, awaitable: &'p PyAny) -> PyResult<&'p PyAny> {
-    ENSURE_FUTURE
-        .get_or_try_init(|| -> PyResult<PyObject> {
-            Ok(asyncio(py)?.getattr("ensure_future")?.into())
-        })?
-        .as_ref(py)
-        .call1((awaitable,))
+/// Acquire the GIL to hand the result of a bridged future back to Python, timing the wait if the
+/// `metrics` feature is enabled
+#[inline]
+fn with_completion_gil<F, T>(f: F) -> T
+where
+    F: for<'py> FnOnce(Python<'py>) -> T,
+{
+    #[cfg(feature = "metrics")]
+    {
+        metrics::with_completion_gil(f)
+    }
+    #[cfg(not(feature = "metrics"))]
+    {
+        Python::with_gil(f)
+    }
 }
 
-fn create_future(event_loop: &PyAny) -> PyResult<&PyAny> {
-    event_loop.call_method0("create_future")
+/// The Rust runtimes that PyO3 Asyncio provides first-class support for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RuntimeKind {
+    /// The tokio runtime, enabled by the `tokio-runtime` feature
+    Tokio,
+    /// The async-std runtime, enabled by the `async-std-runtime` feature
+    AsyncStd,
+    /// The smol runtime, enabled by the `smol-runtime` feature
+    Smol,
+    /// No runtime feature is enabled, so only the [`generic`] module is available
+    Generic,
 }
 
-fn close(event_loop: &PyAny) -> PyResult<()> {
-    event_loop.call_method1(
-        "run_until_complete",
-        (event_loop.call_method0("shutdown_asyncgens")?,),
-    )?;
-
-    // how to do this prior to 3.9?
-    if event_loop.hasattr("shutdown_default_executor")? {
-        event_loop.call_method1(
-            "run_until_complete",
-            (event_loop.call_method0("shutdown_default_executor")?,),
-        )?;
+impl RuntimeKind {
+    /// The name of the Cargo feature that enables this runtime, if any
+    pub fn feature_name(&self) -> Option<&'static str> {
+        match self {
+            RuntimeKind::Tokio => Some("tokio-runtime"),
+            RuntimeKind::AsyncStd => Some("async-std-runtime"),
+            RuntimeKind::Smol => Some("smol-runtime"),
+            RuntimeKind::Generic => None,
+        }
     }
+}
 
-    event_loop.call_method0("close")?;
-
-    Ok(())
+/// Get the runtime that this build of PyO3 Asyncio is configured for
+///
+/// This reflects the runtime features enabled at compile time, so libraries can log their
+/// configuration or guard runtime-specific code paths.
+///
+/// > Both `tokio-runtime` and `async-std-runtime` can be enabled at the same time since the runtime
+/// > modules are independent of each other. In that case, [`RuntimeKind::Tokio`] takes precedence.
+/// > Use [`enabled_runtimes`] if you need to know about every enabled runtime.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "tokio-runtime")]
+/// assert_eq!(pyo3_asyncio::active_runtime(), pyo3_asyncio::RuntimeKind::Tokio);
+/// ```
+pub fn active_runtime() -> RuntimeKind {
+    enabled_runtimes()
+        .first()
+        .copied()
+        .unwrap_or(RuntimeKind::Generic)
 }
 
-fn asyncio(py: Python) -> PyResult<&PyAny> {
-    ASYNCIO
-        .get_or_try_init(|| Ok(py.import("asyncio")?.into()))
-        .map(|asyncio| asyncio.as_ref(py))
+/// Get every runtime enabled in this build of PyO3 Asyncio in order of precedence
+pub fn enabled_runtimes() -> &'static [RuntimeKind] {
+    &[
+        #[cfg(feature = "tokio-runtime")]
+        RuntimeKind::Tokio,
+        #[cfg(feature = "async-std-runtime")]
+        RuntimeKind::AsyncStd,
+        #[cfg(feature = "smol-runtime")]
+        RuntimeKind::Smol,
+    ]
 }
 
-/// Get a reference to the Python Event Loop from Rust
+static DEBUG: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable logging of every crossing between Python and Rust futures
 ///
-/// Equivalent to `asyncio.get_running_loop()` in Python 3.7+.
-/// > For Python 3.6, this function falls back to `asyncio.get_event_loop()` which has slightly
-/// different behaviour. See the [`asyncio.get_event_loop`](https://docs.python.org/3/library/asyncio-eventloop.html#asyncio.get_event_loop)
-/// docs to better understand the differences.
-pub fn get_running_loop(py: Python) -> PyResult<&PyAny> {
-    // Ideally should call get_running_loop, but calls get_event_loop for compatibility when
-    // get_running_loop is not available.
-    GET_RUNNING_LOOP
-        .get_or_try_init(|| -> PyResult<PyObject> {
-            let asyncio = asyncio(py)?;
+/// When enabled, the creation, completion and cancellation of every conversion made with
+/// [`into_future_with_locals`], [`into_future_ignore_result_with_locals`] and the runtimes'
+/// `future_into_py` functions is logged at the `debug` level with the `pyo3_asyncio` target
+/// through the [`log`](https://docs.rs/log) crate. Each entry includes the current thread and the
+/// ids of the Python objects involved (matching Python's `id()`), which makes it possible to
+/// reconstruct a timeline of a hang or deadlock.
+///
+/// Logging is disabled by default. A logger implementation (i.e. `env_logger`) must be installed
+/// to see the output.
+///
+/// Debug mode also checks that an `asyncio.Future` or Task converted with
+/// [`into_future_with_locals`] is attached to the event loop in the given `TaskLocals`. Awaiting a
+/// future on another event loop otherwise fails deep inside asyncio with "got Future attached to a
+/// different loop", while this check raises a `RuntimeError` naming both event loops as soon as the
+/// future is bridged. Likewise, the runtimes' `future_into_py_with_locals` functions raise if the
+/// `TaskLocals` target another event loop than the one running on the calling thread, since the
+/// coroutine awaiting the returned future would fail the same way.
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+/// use pyo3_asyncio::TaskLocals;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let asyncio = py.import("asyncio")?;
+///     let loop_a = asyncio.call_method0("new_event_loop")?;
+///     let loop_b = asyncio.call_method0("new_event_loop")?;
+///
+///     pyo3_asyncio::set_debug(true);
+///
+///     // a future created on loop A can't be awaited on loop B
+///     let fut = loop_a.call_method0("create_future")?;
+///     let err = pyo3_asyncio::into_future_with_locals(&TaskLocals::new(loop_b), fut).unwrap_err();
+///     assert!(err.to_string().contains("attached to a different event loop"));
+///
+///     pyo3_asyncio::set_debug(false);
+///     loop_a.call_method0("close")?;
+///     loop_b.call_method0("close")?;
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn set_debug(enabled: bool) {
+    DEBUG.store(enabled, Ordering::Relaxed);
+}
 
-            if asyncio.hasattr("get_running_loop")? {
-                // correct behaviour with Python 3.7+
-                Ok(asyncio.getattr("get_running_loop")?.into())
-            } else {
-                // Python 3.6 compatibility mode
-                Ok(asyncio.getattr("get_event_loop")?.into())
-            }
-        })?
-        .as_ref(py)
-        .call0()
+/// Check whether bridge crossings are logged, see [`set_debug`]
+pub fn debug_enabled() -> bool {
+    DEBUG.load(Ordering::Relaxed)
 }
 
-/// Returns None only if contextvars cannot be imported (Python 3.6 fallback)
-fn contextvars(py: Python) -> Option<&PyAny> {
-    CONTEXTVARS
-        .get_or_init(|| match py.import("contextvars") {
-            Ok(contextvars) => Some(contextvars.into()),
-            Err(_) => None,
-        })
-        .as_ref()
-        .map(|contextvars| contextvars.as_ref(py))
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable validating every awaitable up front, even on the fast paths
+///
+/// Most conversions already check their argument with [`normalize_awaitable`] before scheduling
+/// it, but [`into_future_ignore_result_with_locals`] skips that check to stay cheap and reports an
+/// invalid awaitable from the event loop's thread instead. In strict mode it validates its
+/// argument too, so a value that isn't awaitable is always rejected with a `TypeError` at the
+/// callsite. This is useful for libraries that bridge awaitables received from untrusted code.
+///
+/// Strict mode is disabled by default.
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+/// use pyo3_asyncio::TaskLocals;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// pyo3_asyncio::set_strict(true);
+///
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+///     let locals = TaskLocals::new(event_loop);
+///
+///     let not_awaitable = 42.to_object(py);
+///     let err = pyo3_asyncio::into_future_ignore_result_with_locals(&locals, not_awaitable.as_ref(py))
+///         .err()
+///         .unwrap();
+///     assert!(err.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+///
+///     event_loop.call_method0("close")?;
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn set_strict(enabled: bool) {
+    STRICT.store(enabled, Ordering::Relaxed);
 }
 
-/// Returns Ok(None) only if contextvars cannot be imported (Python 3.6 fallback)
-fn copy_context(py: Python) -> PyResult<Option<&PyAny>> {
-    if let Some(contextvars) = contextvars(py) {
-        Ok(Some(contextvars.call_method0("copy_context")?))
-    } else {
-        Ok(None)
-    }
+/// Check whether every awaitable is validated up front, see [`set_strict`]
+pub fn strict_enabled() -> bool {
+    STRICT.load(Ordering::Relaxed)
 }
 
-/// Task-local data to store for Python conversions.
-#[derive(Debug, Clone)]
-pub struct TaskLocals {
-    /// Track the event loop of the Python task
-    event_loop: PyObject,
-    /// Track the contextvars of the Python task
-    context: PyObject,
+/// Check whether the current thread holds the GIL
+fn gil_held() -> bool {
+    unsafe { pyo3::ffi::PyGILState_Check() == 1 }
 }
 
-impl TaskLocals {
-    /// At a minimum, TaskLocals must store the event loop.
-    pub fn new(event_loop: &PyAny) -> Self {
-        Self {
-            event_loop: event_loop.into(),
-            context: event_loop.py().None(),
-        }
+pin_project! {
+    /// Future returned by [`check_gil_released`]
+    struct GilReleaseCheck<F> {
+        #[pin]
+        fut: F,
     }
+}
 
-    /// Construct TaskLocals with the event loop returned by `get_running_loop`
-    pub fn with_running_loop(py: Python) -> PyResult<Self> {
-        Ok(Self::new(get_running_loop(py)?))
-    }
+impl<F> Future for GilReleaseCheck<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
 
-    /// Manually provide the contextvars for the current task.
-    pub fn with_context(self, context: &PyAny) -> Self {
-        Self {
-            context: context.into(),
-            ..self
-        }
-    }
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let held_before = gil_held();
+        let poll = self.project().fut.poll(cx);
 
-    /// Capture the current task's contextvars
-    pub fn copy_context(self, py: Python) -> PyResult<Self> {
-        // No-op if context cannot be copied (Python 3.6 fallback)
-        if let Some(cx) = copy_context(py)? {
-            Ok(self.with_context(cx))
-        } else {
-            Ok(self)
+        if poll.is_pending() && !held_before && gil_held() {
+            panic!(
+                "a Rust future held the GIL across an await point, which blocks every other thread \
+                 (including the Python event loop) until it is polled again. Release the GIL \
+                 before awaiting, i.e. by ending the `Python::with_gil` closure and keeping only \
+                 `Py<T>`/`PyObject` handles across `.await`."
+            );
         }
-    }
 
-    /// Get a reference to the event loop
-    pub fn event_loop<'p>(&self, py: Python<'p>) -> &'p PyAny {
-        self.event_loop.clone().into_ref(py)
+        poll
     }
+}
 
-    /// Get a reference to the python context
-    pub fn context<'p>(&self, py: Python<'p>) -> &'p PyAny {
-        self.context.clone().into_ref(py)
-    }
+/// Panic if `fut` still holds the GIL when it yields to the runtime
+///
+/// A future that acquires the GIL and keeps it across an `.await` (i.e. a `!Send` future that keeps
+/// a `GILGuard` alive, or one that acquires the GIL through `pyo3::ffi`) blocks every other thread
+/// that needs the GIL, including the Python event loop, so the program hangs or deadlocks. The
+/// borrow checker catches most of these, but not all of them.
+///
+/// The returned future checks the GIL whenever `fut` returns `Poll::Pending`: if the GIL wasn't
+/// held before `fut` was polled but is held afterwards, `fut` kept it across an await point, and
+/// the returned future panics with an explanation. The runtimes' `future_into_py` functions apply
+/// this check automatically in debug builds, so the panic surfaces in Python as a
+/// [`RustPanic`](err::RustPanic).
+///
+/// The safe pattern is to only keep `Py<T>`/`PyObject` handles across `.await` and acquire the GIL
+/// in short `Python::with_gil` blocks in between.
+///
+/// # Arguments
+/// * `fut` - The future to check
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # async fn some_rust_io() {}
+/// async fn call_later(callback: PyObject) -> PyResult<PyObject> {
+///     // wrong: keeping a `GILGuard` from `Python::acquire_gil()` alive across this await
+///     some_rust_io().await;
+///
+///     // right: only acquire the GIL between await points
+///     Python::with_gil(|py| callback.call0(py))
+/// }
+///
+/// async fn call_later_checked(callback: PyObject) -> PyResult<PyObject> {
+///     pyo3_asyncio::check_gil_released(call_later(callback)).await
+/// }
+/// ```
+pub fn check_gil_released<F>(fut: F) -> impl Future<Output = F::Output>
+where
+    F: Future,
+{
+    GilReleaseCheck { fut }
 }
 
-#[pyclass]
-struct PyTaskCompleter {
-    tx: Option<oneshot::Sender<PyResult<PyObject>>>,
+/// Apply [`check_gil_released`] in debug builds only
+#[cfg(debug_assertions)]
+fn debug_check_gil_released<F>(fut: F) -> impl Future<Output = F::Output>
+where
+    F: Future,
+{
+    check_gil_released(fut)
 }
 
-#[pymethods]
-impl PyTaskCompleter {
-    #[args(task)]
-    pub fn __call__(&mut self, task: &PyAny) -> PyResult<()> {
-        debug_assert!(task.call_method0("done")?.extract()?);
+#[cfg(not(debug_assertions))]
+fn debug_check_gil_released<F>(fut: F) -> F
+where
+    F: Future,
+{
+    fut
+}
 
-        let result = match task.call_method0("result") {
-            Ok(val) => Ok(val.into()),
-            Err(e) => Err(e),
-        };
+/// The id of a Python object, as returned by Python's `id()`
+fn py_id(obj: &PyAny) -> usize {
+    obj.as_ptr() as usize
+}
 
-        // unclear to me whether or not this should be a panic or silent error.
-        //
-        // calling PyTaskCompleter twice should not be possible, but I don't think it really hurts
-        // anything if it happens.
-        if let Some(tx) = self.tx.take() {
-            if tx.send(result).is_err() {
-                // cancellation is not an error
-            }
-        }
+/// A lazily initialized Python object, guarded by the GIL
+///
+/// [`cached`] hands out new references to the value instead of borrowing it, so the value can be
+/// dropped by [`testing::reset_globals`](`crate::testing::reset_globals`) while those references
+/// are still in use.
+struct PyCache<T>(UnsafeCell<Option<T>>);
 
-        Ok(())
+// Safety: the value is only accessed while holding the GIL, see `PyCache::with`
+unsafe impl<T: Send> Sync for PyCache<T> {}
+
+impl<T> PyCache<T> {
+    const fn new() -> Self {
+        PyCache(UnsafeCell::new(None))
     }
-}
 
-#[pyclass]
-struct PyEnsureFuture {
-    awaitable: PyObject,
-    tx: Option<oneshot::Sender<PyResult<PyObject>>>,
+    /// Access the value while holding the GIL
+    ///
+    /// `f` must not run any Python code, which could access the cache again.
+    fn with<R>(&self, _py: Python, f: impl FnOnce(&mut Option<T>) -> R) -> R {
+        // Safety: the GIL is held, and `f` doesn't run Python code that could release it or reenter
+        unsafe { f(&mut *self.0.get()) }
+    }
 }
 
-#[pymethods]
-impl PyEnsureFuture {
-    pub fn __call__(&mut self) -> PyResult<()> {
-        Python::with_gil(|py| {
-            let task = ensure_future(py, self.awaitable.as_ref(py))?;
-            let on_complete = PyTaskCompleter { tx: self.tx.take() };
-            task.call_method1("add_done_callback", (on_complete,))?;
+static AS_COMPLETED: PyCache<PyObject> = PyCache::new();
+static ASYNCIO: PyCache<PyObject> = PyCache::new();
+static CONTEXTVARS: PyCache<Option<PyObject>> = PyCache::new();
+static DRAIN_TASKS: PyCache<PyObject> = PyCache::new();
+static ENSURE_FUTURE: PyCache<PyObject> = PyCache::new();
+static GET_RUNNING_LOOP: PyCache<PyObject> = PyCache::new();
+static PEEK_RUNNING_LOOP: PyCache<Option<PyObject>> = PyCache::new();
+static INSPECT: PyCache<PyObject> = PyCache::new();
+static THEN: PyCache<PyObject> = PyCache::new();
+static WITH_TIMEOUT: PyCache<PyObject> = PyCache::new();
 
-            Ok(())
-        })
+/// Get the value of `cache`, initializing it with `init` first if needed
+///
+/// The cache is not accessed while initializing, since importing a module can release the GIL.
+fn cached<T, F>(py: Python, cache: &PyCache<T>, init: F) -> PyResult<T>
+where
+    T: Clone,
+    F: FnOnce() -> PyResult<T>,
+{
+    if let Some(value) = cache.with(py, |value| value.clone()) {
+        return Ok(value);
     }
+
+    // another thread may have initialized the cache while the GIL was released, in which case its
+    // value is kept and `value` is dropped outside of `with`
+    let value = init()?;
+    let existing = cache.with(py, |cached| match cached {
+        Some(existing) => Some(existing.clone()),
+        None => {
+            *cached = Some(value.clone());
+            None
+        }
+    });
+    Ok(existing.unwrap_or(value))
 }
 
-fn call_soon_threadsafe(
-    event_loop: &PyAny,
-    context: &PyAny,
-    args: impl IntoPy<Py<PyTuple>>,
-) -> PyResult<()> {
-    let py = event_loop.py();
+/// Drop the value of `cache`, so that it is initialized again on next use
+#[cfg(feature = "testing")]
+fn reset_cache<T>(py: Python, cache: &PyCache<T>) {
+    // dropped outside of `with`, since dropping a Python object can run arbitrary code
+    drop(cache.with(py, Option::take));
+}
 
-    let kwargs = PyDict::new(py);
+/// Drop the cached Python objects so that they are initialized again on next use
+#[cfg(feature = "testing")]
+fn reset_caches(py: Python) {
+    reset_cache(py, &AS_COMPLETED);
+    reset_cache(py, &ASYNCIO);
+    reset_cache(py, &CONTEXTVARS);
+    reset_cache(py, &DRAIN_TASKS);
+    reset_cache(py, &ENSURE_FUTURE);
+    reset_cache(py, &GET_RUNNING_LOOP);
+    reset_cache(py, &PEEK_RUNNING_LOOP);
+    reset_cache(py, &INSPECT);
+    reset_cache(py, &THEN);
+    reset_cache(py, &WITH_TIMEOUT);
+    generic::reset_caches(py);
+    #[cfg(feature = "tracing")]
+    instrument::reset_caches(py);
+}
 
-    // Accommodate for the Python 3.6 fallback
-    // (call_soon_threadsafe does not support the context kwarg in 3.6)
-    if !context.is_none() {
-        kwargs.set_item("context", context)?;
-    }
+/// Import the Python modules used by the bridge ahead of time, see `warmup`
+fn warmup_caches(py: Python) -> PyResult<()> {
+    asyncio(py)?;
+    inspect(py)?;
+    contextvars(py);
 
-    event_loop.call_method("call_soon_threadsafe", args, Some(kwargs))?;
     Ok(())
 }
 
-/// Convert a Python `awaitable` into a Rust Future
-///
-/// This function converts the `awaitable` into a Python Task using `run_coroutine_threadsafe`. A
-/// completion handler sends the result of this Task through a
-/// `futures::channel::oneshot::Sender<PyResult<PyObject>>` and the future returned by this function
-/// simply awaits the result through the `futures::channel::oneshot::Receiver<PyResult<PyObject>>`.
+fn ensure_future<'p>(py: Python<'p>, awaitable: &'p PyAny) -> PyResult<&'p PyAny> {
+    cached(py, &ENSURE_FUTURE, || {
+        Ok(asyncio(py)?.getattr("ensure_future")?.into())
+    })?
+    .into_ref(py)
+    .call1((awaitable,))
+}
+
+fn create_future(event_loop: &PyAny) -> PyResult<&PyAny> {
+    event_loop.call_method0("create_future")
+}
+
+type CleanupFn = dyn FnOnce(Python) + Send;
+
+static CLEANUPS: Lazy<Mutex<Vec<Box<CleanupFn>>>> = Lazy::new(Default::default);
+
+/// Register a cleanup to run when the event loop managed by `run` shuts down
+///
+/// Cleanups run in registration order at the start of the shutdown sequence, before the async
+/// generators and the default executor are shut down and the event loop is closed. This gives
+/// libraries a hook to release resources that depend on asyncio (i.e. cached Python objects that
+/// need an explicit `close()`) at the right moment.
+///
+/// Each cleanup runs only once, during the first shutdown after it was registered.
+///
+/// # Arguments
+/// * `cleanup` - Called with the GIL held during shutdown
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// # Python::with_gil(|py| -> PyResult<()> {
+/// let session: PyObject = py.eval("open(__import__('os').devnull)", None, None)?.into();
+///
+/// pyo3_asyncio::register_cleanup(move |py| {
+///     if let Err(e) = session.call_method0(py, "close") {
+///         e.print(py);
+///     }
+/// });
+/// # Ok(())
+/// # }).unwrap();
+/// ```
+pub fn register_cleanup<F>(cleanup: F)
+where
+    F: FnOnce(Python) + Send + 'static,
+{
+    CLEANUPS.lock().unwrap().push(Box::new(cleanup));
+}
+
+fn run_cleanups(py: Python) {
+    // Take the queue first so that cleanups can register more cleanups without deadlocking
+    let cleanups = std::mem::take(&mut *CLEANUPS.lock().unwrap());
+
+    for cleanup in cleanups {
+        cleanup(py);
+    }
+}
+
+/// What [`try_close_opts`] does with the event loop once it has been shut down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClosePolicy {
+    /// Shut down the async generators and the default executor, then close the event loop
+    Close,
+    /// Shut down the default executor but leave the event loop open so that it can be run again
+    StopOnly,
+}
+
+// `#[default]` on enum variants requires Rust 1.62, above the MSRV
+#[allow(clippy::derivable_impls)]
+impl Default for ClosePolicy {
+    fn default() -> Self {
+        ClosePolicy::Close
+    }
+}
+
+/// Shut down and close an event loop that is not running
+///
+/// This is the shutdown sequence used by `run`: the cleanups registered with [`register_cleanup`]
+/// run first, then the async generators and the default executor (Python 3.9+) are shut down and
+/// the event loop is closed. It is equivalent to [`try_close_opts`] with [`ClosePolicy::Close`].
+///
+/// # Arguments
+/// * `event_loop` - The stopped Python event loop to shut down
+pub fn try_close(event_loop: &PyAny) -> PyResult<()> {
+    try_close_opts(event_loop, ClosePolicy::Close)
+}
+
+/// Shut down an event loop that is not running, optionally leaving it open for reuse
+///
+/// With [`ClosePolicy::Close`], this works just like [`try_close`]. With [`ClosePolicy::StopOnly`],
+/// the registered cleanups run and the default executor is shut down, but the async generators are
+/// left alone and `close()` is not called, so the same event loop can be driven again later (i.e.
+/// to pause and resume asyncio work) without "Event loop is closed" errors. Note that the default
+/// executor can't be used once it has been shut down, so set a new one with
+/// `loop.set_default_executor` before calling `loop.run_in_executor(None, ...)` again.
+///
+/// # Arguments
+/// * `event_loop` - The stopped Python event loop to shut down
+/// * `policy` - Whether to close the event loop afterwards
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+/// use pyo3_asyncio::ClosePolicy;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let asyncio = py.import("asyncio")?;
+///     let event_loop = asyncio.call_method0("new_event_loop")?;
+///
+///     event_loop.call_method1("run_until_complete", (asyncio.call_method1("sleep", (0,))?,))?;
+///     pyo3_asyncio::try_close_opts(event_loop, ClosePolicy::StopOnly)?;
+///
+///     // the event loop can still be used
+///     event_loop.call_method1("run_until_complete", (asyncio.call_method1("sleep", (0,))?,))?;
+///     pyo3_asyncio::try_close(event_loop)?;
+///     assert!(event_loop.call_method0("is_closed")?.is_true()?);
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn try_close_opts(event_loop: &PyAny, policy: ClosePolicy) -> PyResult<()> {
+    run_cleanups(event_loop.py());
+
+    if policy == ClosePolicy::Close {
+        event_loop.call_method1(
+            "run_until_complete",
+            (event_loop.call_method0("shutdown_asyncgens")?,),
+        )?;
+    }
+
+    // how to do this prior to 3.9?
+    if event_loop.hasattr("shutdown_default_executor")? {
+        event_loop.call_method1(
+            "run_until_complete",
+            (event_loop.call_method0("shutdown_default_executor")?,),
+        )?;
+    }
+
+    if policy == ClosePolicy::Close {
+        event_loop.call_method0("close")?;
+    }
+
+    Ok(())
+}
+
+const DRAIN_TASKS_CODE: &str = r#"
+import asyncio
+
+async def drain_tasks(timeout):
+    loop = asyncio.get_running_loop()
+    tasks = asyncio.all_tasks(loop) - {asyncio.current_task(loop)}
+    if not tasks:
+        return
+
+    for task in tasks:
+        task.cancel()
+
+    done, _ = await asyncio.wait(tasks, timeout=timeout)
+    for task in done:
+        if not task.cancelled() and task.exception() is not None:
+            loop.call_exception_handler({
+                "message": "unhandled exception while draining tasks on shutdown",
+                "exception": task.exception(),
+                "task": task,
+            })
+"#;
+
+/// Cancel the pending tasks of an event loop that is not running and wait for them to finish,
+/// then shut it down and close it
+///
+/// Closing an event loop with [`try_close`] while tasks are still pending on it drops those tasks
+/// without running them to completion, so their `finally` blocks and `except CancelledError`
+/// handlers never run and Python warns "Task was destroyed but it is pending!" when they're
+/// garbage collected. This function cancels every task in `asyncio.all_tasks(loop)` first and runs
+/// the event loop until they have all finished or `timeout` has elapsed, the same way
+/// `asyncio.run` cleans up after the main coroutine. An exception raised by a task while it winds
+/// down is passed to the event loop's exception handler rather than returned. Rust futures that
+/// were converted with `future_into_py` are cancelled along with the tasks awaiting them.
+///
+/// Tasks that are still running when the timeout expires are left as they are and the event loop
+/// is closed anyway, so a task that suppresses cancellation can't hold up the shutdown forever.
+///
+/// # Arguments
+/// * `event_loop` - The stopped Python event loop to shut down
+/// * `timeout` - How long to wait for the cancelled tasks to finish
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let asyncio = py.import("asyncio")?;
+///     let event_loop = asyncio.call_method0("new_event_loop")?;
+///
+///     // a task that is still pending when the event loop stops
+///     let task = event_loop.call_method1("create_task", (asyncio.call_method1("sleep", (60,))?,))?;
+///     event_loop.call_method1("run_until_complete", (asyncio.call_method1("sleep", (0,))?,))?;
+///
+///     pyo3_asyncio::try_close_graceful(event_loop, Duration::from_secs(1))?;
+///     assert!(task.call_method0("cancelled")?.is_true()?);
+///     assert!(event_loop.call_method0("is_closed")?.is_true()?);
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn try_close_graceful(event_loop: &PyAny, timeout: Duration) -> PyResult<()> {
+    let py = event_loop.py();
+    let drain_tasks = cached(py, &DRAIN_TASKS, || {
+        Ok(PyModule::from_code(
+            py,
+            DRAIN_TASKS_CODE,
+            "pyo3_asyncio/drain_tasks.py",
+            "pyo3_asyncio_drain_tasks",
+        )?
+        .getattr("drain_tasks")?
+        .into())
+    })?;
+
+    event_loop.call_method1(
+        "run_until_complete",
+        (drain_tasks.as_ref(py).call1((timeout.as_secs_f64(),))?,),
+    )?;
+
+    try_close(event_loop)
+}
+
+fn asyncio(py: Python) -> PyResult<&PyAny> {
+    cached(py, &ASYNCIO, || Ok(py.import("asyncio")?.into())).map(|asyncio| asyncio.into_ref(py))
+}
+
+/// Get a reference to the Python Event Loop from Rust
+///
+/// Equivalent to `asyncio.get_running_loop()` in Python 3.7+. If no event loop is running on the
+/// current OS thread, the `RuntimeError` raised by Python is converted into an
+/// [`err::NoRunningLoop`], which subclasses `RuntimeError` so existing handlers still catch it.
+/// See [`get_event_loop`] for a fallback that doesn't require the loop to be running.
+///
+/// > For Python 3.6, this function falls back to `asyncio.get_event_loop()` which has slightly
+/// different behaviour. See the [`asyncio.get_event_loop`](https://docs.python.org/3/library/asyncio-eventloop.html#asyncio.get_event_loop)
+/// docs to better understand the differences.
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| {
+///     // nothing is running on this thread
+///     let err = pyo3_asyncio::get_running_loop(py).unwrap_err();
+///     assert!(err.is_instance_of::<pyo3_asyncio::err::NoRunningLoop>(py));
+///     assert!(err.is_instance_of::<pyo3::exceptions::PyRuntimeError>(py));
+///
+///     // the error raised by asyncio is kept as the cause
+///     assert!(err.cause(py).unwrap().is_instance_of::<pyo3::exceptions::PyRuntimeError>(py));
+/// });
+/// ```
+pub fn get_running_loop(py: Python) -> PyResult<&PyAny> {
+    // Ideally should call get_running_loop, but calls get_event_loop for compatibility when
+    // get_running_loop is not available.
+    cached(py, &GET_RUNNING_LOOP, || {
+        let asyncio = asyncio(py)?;
+
+        if asyncio.hasattr("get_running_loop")? {
+            // correct behaviour with Python 3.7+
+            Ok(asyncio.getattr("get_running_loop")?.into())
+        } else {
+            // Python 3.6 compatibility mode
+            Ok(asyncio.getattr("get_event_loop")?.into())
+        }
+    })?
+    .into_ref(py)
+    .call0()
+    .map_err(|e| no_running_loop(py, e))
+}
+
+/// Get the running event loop, or the event loop set for the current OS thread if none is running
+///
+/// This first tries [`get_running_loop`] and, if no event loop is running, falls back to the event
+/// loop policy's `get_event_loop()`, which returns the loop installed with
+/// `asyncio.set_event_loop`. This mirrors `asyncio.get_event_loop()` without its deprecation
+/// warnings, so it tells you which loop a future created outside of a coroutine would attach to.
+/// Note that on the main thread, the default policy of Python versions before 3.14 creates and
+/// installs a new event loop if none was set. If there is no event loop for the current thread
+/// either, an [`err::NoRunningLoop`] is raised.
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let asyncio = py.import("asyncio")?;
+///     let event_loop = asyncio.call_method0("new_event_loop")?;
+///     asyncio.call_method1("set_event_loop", (event_loop,))?;
+///
+///     // the loop isn't running, but it's the current loop of this thread
+///     assert!(pyo3_asyncio::get_running_loop(py).is_err());
+///     assert!(pyo3_asyncio::get_event_loop(py)?.is(event_loop));
+///
+///     asyncio.call_method1("set_event_loop", (py.None(),))?;
+///     event_loop.call_method0("close")?;
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn get_event_loop(py: Python) -> PyResult<&PyAny> {
+    match get_running_loop(py) {
+        Ok(event_loop) => Ok(event_loop),
+        Err(e) if e.is_instance_of::<err::NoRunningLoop>(py) => asyncio(py)?
+            .call_method0("get_event_loop_policy")?
+            .call_method0("get_event_loop")
+            .map_err(|e| no_running_loop(py, e)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Get the event loop running on the current thread, if any
+///
+/// Unlike [`get_running_loop`], this doesn't raise (and build an exception) when no event loop is
+/// running, which keeps it cheap enough to check on every conversion.
+fn peek_running_loop(py: Python) -> PyResult<Option<&PyAny>> {
+    // asyncio._get_running_loop returns None instead of raising, it is missing from Python 3.6
+    let peek = cached(py, &PEEK_RUNNING_LOOP, || {
+        let asyncio = asyncio(py)?;
+
+        if asyncio.hasattr("_get_running_loop")? {
+            Ok(Some(asyncio.getattr("_get_running_loop")?.into()))
+        } else {
+            Ok(None)
+        }
+    })?;
+
+    match peek {
+        Some(peek) => {
+            let running = peek.into_ref(py).call0()?;
+            Ok(if running.is_none() {
+                None
+            } else {
+                Some(running)
+            })
+        }
+        None => Ok(None),
+    }
+}
+
+/// Whether `event_loop` is the event loop running on the current thread
+///
+/// Like [`peek_running_loop`], this doesn't raise when no event loop is running.
+pub(crate) fn is_running_loop(event_loop: &PyAny) -> PyResult<bool> {
+    Ok(peek_running_loop(event_loop.py())?.is_some_and(|running| running.is(event_loop)))
+}
+
+/// Convert the `RuntimeError` asyncio raises when there is no event loop into a `NoRunningLoop`
+fn no_running_loop(py: Python, e: PyErr) -> PyErr {
+    if !e.get_type(py).is(py.get_type::<PyRuntimeError>()) {
+        return e;
+    }
+
+    // keep the frames the original error was raised from, and the original error as the cause
+    let converted = err::NoRunningLoop::new_err(e.value(py).to_string());
+    let value = converted.value(py);
+    if let Some(traceback) = e.traceback(py) {
+        if let Err(e) = value.setattr("__traceback__", traceback) {
+            return e;
+        }
+    }
+    converted.set_cause(py, Some(e));
+    err::from_exception(value)
+}
+
+fn inspect(py: Python) -> PyResult<&PyAny> {
+    cached(py, &INSPECT, || Ok(py.import("inspect")?.into())).map(|inspect| inspect.into_ref(py))
+}
+
+/// Validate that `obj` can be bridged as a Python awaitable
+///
+/// Coroutines, futures, tasks, and any other object accepted by `inspect.isawaitable` are returned
+/// unchanged, ready to be passed to `asyncio.ensure_future`. Anything else raises a `TypeError`
+/// describing what was passed instead. Common mistakes like passing a coroutine function instead of
+/// calling it, or passing an async iterator, get a specific hint.
+///
+/// This check is performed by the conversions from a Python awaitable to a Rust future, so an
+/// invalid awaitable fails at the callsite instead of inside the event loop. See [`set_strict`] for
+/// the conversions that skip it.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `obj` - The object to validate
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let asyncio = py.import("asyncio")?;
+///
+///     let coro = asyncio.call_method1("sleep", (0,))?;
+///     assert!(pyo3_asyncio::normalize_awaitable(py, coro)?.is(coro));
+///     # coro.call_method0("close")?;
+///
+///     // forgot to call asyncio.sleep
+///     assert!(pyo3_asyncio::normalize_awaitable(py, asyncio.getattr("sleep")?).is_err());
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn normalize_awaitable<'p>(py: Python<'p>, obj: &'p PyAny) -> PyResult<&'p PyAny> {
+    // coroutines, Futures and Tasks implement `__await__`, so the common case is a single lookup on
+    // the type instead of calls into asyncio and inspect
+    if obj.get_type().hasattr("__await__")? {
+        return Ok(obj);
+    }
+
+    let asyncio = asyncio(py)?;
+
+    if asyncio.call_method1("iscoroutine", (obj,))?.is_true()?
+        || asyncio.call_method1("isfuture", (obj,))?.is_true()?
+        || inspect(py)?
+            .call_method1("isawaitable", (obj,))?
+            .is_true()?
+    {
+        return Ok(obj);
+    }
+
+    let type_name = obj.get_type().name()?;
+
+    let msg = if asyncio
+        .call_method1("iscoroutinefunction", (obj,))?
+        .is_true()?
+    {
+        format!(
+            "expected an awaitable, got the coroutine function {} (did you forget to call it?)",
+            obj.getattr("__qualname__")
+                .and_then(|name| name.extract::<String>())
+                .unwrap_or_else(|_| type_name.to_string())
+        )
+    } else if obj.hasattr("__aiter__")? {
+        format!(
+            "expected an awaitable, got an async iterator of type '{}' (await its __anext__() \
+             instead)",
+            type_name
+        )
+    } else {
+        format!(
+            "expected an awaitable, got an object of type '{}'",
+            type_name
+        )
+    };
+
+    Err(PyTypeError::new_err(msg))
+}
+
+/// Returns None only if contextvars cannot be imported (Python 3.6 fallback)
+fn contextvars(py: Python) -> Option<&PyAny> {
+    cached(py, &CONTEXTVARS, || match py.import("contextvars") {
+        Ok(contextvars) => Ok(Some(contextvars.into())),
+        Err(_) => Ok(None),
+    })
+    .ok()
+    .flatten()
+    .map(|contextvars| contextvars.into_ref(py))
+}
+
+/// Returns Ok(None) only if contextvars cannot be imported (Python 3.6 fallback)
+fn copy_context(py: Python) -> PyResult<Option<&PyAny>> {
+    if let Some(contextvars) = contextvars(py) {
+        Ok(Some(contextvars.call_method0("copy_context")?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Task-local data to store for Python conversions.
+///
+/// Two `TaskLocals` are equal if they refer to the very same event loop and context objects. Their
+/// `Debug` output shows the `repr` of the event loop (acquiring the GIL to do so), which helps when
+/// tracking down futures that end up on the wrong event loop.
+#[derive(Clone)]
+pub struct TaskLocals {
+    /// Track the event loop of the Python task
+    event_loop: PyObject,
+    /// Track the contextvars of the Python task
+    context: PyObject,
+}
+
+impl TaskLocals {
+    /// At a minimum, TaskLocals must store the event loop.
+    pub fn new(event_loop: &PyAny) -> Self {
+        Self {
+            event_loop: event_loop.into(),
+            context: event_loop.py().None(),
+        }
+    }
+
+    /// Construct TaskLocals with the event loop returned by `get_running_loop`
+    pub fn with_running_loop(py: Python) -> PyResult<Self> {
+        Ok(Self::new(get_running_loop(py)?))
+    }
+
+    /// Manually provide the contextvars for the current task.
+    pub fn with_context(self, context: &PyAny) -> Self {
+        Self {
+            context: context.into(),
+            ..self
+        }
+    }
+
+    /// Capture the current task's contextvars
+    pub fn copy_context(self, py: Python) -> PyResult<Self> {
+        // No-op if context cannot be copied (Python 3.6 fallback)
+        if let Some(cx) = copy_context(py)? {
+            Ok(self.with_context(cx))
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Run the task under a new, empty `contextvars.Context` instead of the current one
+    ///
+    /// The loop and the context are chosen independently, so this can be combined with any of the
+    /// constructors above to run a future on a given event loop without inheriting the values of
+    /// the caller's context variables.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pyo3::prelude::*;
+    /// use pyo3_asyncio::TaskLocals;
+    ///
+    /// # pyo3::prepare_freethreaded_python();
+    /// Python::with_gil(|py| -> PyResult<()> {
+    ///     let contextvars = py.import("contextvars")?;
+    ///     let var = contextvars.call_method1("ContextVar", ("var",))?;
+    ///     var.call_method1("set", ("outer",))?;
+    ///
+    ///     let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+    ///     let copied = TaskLocals::new(event_loop).copy_context(py)?;
+    ///     let fresh = TaskLocals::new(event_loop).with_new_context(py)?;
+    ///
+    ///     assert!(copied.context(py).call_method1("get", (var,))?.eq("outer")?);
+    ///     assert!(fresh.context(py).call_method1("get", (var,))?.is_none());
+    ///
+    ///     event_loop.call_method0("close")?;
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn with_new_context(self, py: Python) -> PyResult<Self> {
+        // No-op if contextvars is unavailable (Python 3.6 fallback)
+        if let Some(contextvars) = contextvars(py) {
+            Ok(self.with_context(contextvars.call_method0("Context")?))
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Get a reference to the event loop
+    pub fn event_loop<'p>(&self, py: Python<'p>) -> &'p PyAny {
+        self.event_loop.clone().into_ref(py)
+    }
+
+    /// Get a reference to the python context
+    pub fn context<'p>(&self, py: Python<'p>) -> &'p PyAny {
+        self.context.clone().into_ref(py)
+    }
+
+    /// Check whether both TaskLocals refer to the same event loop, regardless of their context
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pyo3::prelude::*;
+    /// use pyo3_asyncio::TaskLocals;
+    ///
+    /// # pyo3::prepare_freethreaded_python();
+    /// Python::with_gil(|py| -> PyResult<()> {
+    ///     let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+    ///     let locals = TaskLocals::new(event_loop);
+    ///
+    ///     let with_context = locals.clone().copy_context(py)?;
+    ///     assert!(locals.same_loop(&with_context));
+    ///     assert_ne!(locals, with_context);
+    ///
+    ///     event_loop.call_method0("close")?;
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn same_loop(&self, other: &TaskLocals) -> bool {
+        self.event_loop.as_ptr() == other.event_loop.as_ptr()
+    }
+}
+
+impl PartialEq for TaskLocals {
+    fn eq(&self, other: &Self) -> bool {
+        self.same_loop(other) && self.context.as_ptr() == other.context.as_ptr()
+    }
+}
+
+impl Eq for TaskLocals {}
+
+impl fmt::Debug for TaskLocals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Python::with_gil(|py| {
+            let event_loop = match self.event_loop.as_ref(py).repr() {
+                Ok(repr) => repr.to_string(),
+                Err(_) => format!("<event loop at {:#x}>", py_id(self.event_loop.as_ref(py))),
+            };
+
+            f.debug_struct("TaskLocals")
+                .field("event_loop", &format_args!("{}", event_loop))
+                .field("has_context", &!self.context.is_none(py))
+                .finish()
+        })
+    }
+}
+
+#[pyclass]
+struct PyTaskCompleter {
+    tx: Option<oneshot::Sender<PyResult<PyObject>>>,
+    abort: Option<AbortHandle>,
+    handle: Arc<TaskHandle>,
+}
+
+#[pymethods]
+impl PyTaskCompleter {
+    #[args(task)]
+    pub fn __call__(&mut self, task: &PyAny) -> PyResult<()> {
+        debug_assert!(task.call_method0("done")?.extract()?);
+
+        if let Some(abort) = self.abort.take() {
+            if task.call_method0("cancelled")?.is_true()? {
+                abort.abort();
+            }
+        }
+
+        let result = match task.call_method0("result") {
+            Ok(val) => Ok(val.into()),
+            // rebuild the error from the exception itself so that its Python traceback survives
+            Err(e) => Err(err::from_exception(e.value(task.py()))),
+        };
+
+        bridge_debug!(
+            "into_future: task {:#x} {}",
+            py_id(task),
+            match &result {
+                Ok(_) => "completed",
+                Err(e) if e.is_instance_of::<CancelledError>(task.py()) => "was cancelled",
+                Err(_) => "failed",
+            }
+        );
+
+        self.handle.finished.store(true, Ordering::Release);
+
+        // unclear to me whether or not this should be a panic or silent error.
+        //
+        // calling PyTaskCompleter twice should not be possible, but I don't think it really hurts
+        // anything if it happens.
+        if let Some(tx) = self.tx.take() {
+            if tx.send(result).is_err() {
+                // cancellation is not an error
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[pyclass]
+struct PyEnsureFuture {
+    awaitable: PyObject,
+    tx: Option<oneshot::Sender<PyResult<PyObject>>>,
+    abort: Option<AbortHandle>,
+    handle: Arc<TaskHandle>,
+    task_context: Option<PyObject>,
+}
+
+impl PyEnsureFuture {
+    fn create_task<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let awaitable = self.awaitable.clone_ref(py).into_ref(py);
+
+        match &self.task_context {
+            // Tasks run in a copy of the current context unless they are given one explicitly,
+            // which is only supported by Python 3.11+
+            Some(context)
+                if py.version_info() >= (3, 11)
+                    && asyncio(py)?
+                        .call_method1("iscoroutine", (awaitable,))?
+                        .is_true()? =>
+            {
+                let kwargs = PyDict::new(py);
+                kwargs.set_item("context", context)?;
+                get_running_loop(py)?.call_method("create_task", (awaitable,), Some(kwargs))
+            }
+            _ => ensure_future(py, awaitable),
+        }
+    }
+}
+
+#[pymethods]
+impl PyEnsureFuture {
+    pub fn __call__(&mut self) -> PyResult<()> {
+        Python::with_gil(|py| {
+            let task = match self.create_task(py) {
+                Ok(task) => task,
+                Err(e) => {
+                    self.handle.finished.store(true, Ordering::Release);
+                    return Err(e);
+                }
+            };
+            bridge_debug!(
+                "into_future: awaitable {:#x} scheduled as task {:#x}",
+                py_id(self.awaitable.as_ref(py)),
+                py_id(task)
+            );
+            *self.handle.task.lock().unwrap() = Some(task.into());
+            let on_complete = PyTaskCompleter {
+                tx: self.tx.take(),
+                abort: self.abort.take(),
+                handle: Arc::clone(&self.handle),
+            };
+            task.call_method1("add_done_callback", (on_complete,))?;
+
+            // PyFuture::abort was called before the task existed
+            if self.handle.aborted.load(Ordering::Acquire) {
+                task.call_method0("cancel")?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Cancels the Python Task behind a [`PyFuture`]
+#[pyclass]
+struct PyTaskCanceller {
+    handle: Arc<TaskHandle>,
+}
+
+#[pymethods]
+impl PyTaskCanceller {
+    pub fn __call__(&self, py: Python) -> PyResult<()> {
+        let task = self
+            .handle
+            .task
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|task| task.clone_ref(py));
+
+        // Otherwise the task is cancelled by PyEnsureFuture as soon as it is created
+        if let Some(task) = task {
+            bridge_debug!("into_future: aborting task {:#x}", py_id(task.as_ref(py)));
+            task.call_method0(py, "cancel")?;
+        }
+
+        Ok(())
+    }
+}
+
+type ScheduleFn = dyn Fn(&PyAny, &PyAny, &PyTuple) -> PyResult<()> + Send + Sync;
+
+static SCHEDULER: OnceCell<Box<ScheduleFn>> = OnceCell::new();
+
+/// Replace how callbacks are scheduled on Python event loops
+///
+/// Every time PyO3 Asyncio needs to wake up an event loop from another thread (i.e. to schedule an
+/// awaitable or to complete a future), it calls `loop.call_soon_threadsafe(callback, *args,
+/// context=context)`. Custom event loop implementations that drive their tasks differently can
+/// supply their own scheduling function here, which is used for every event loop from then on.
+///
+/// The scheduler is called with the event loop, the `contextvars` context to run the callback in
+/// (or `None` if there isn't one) and a tuple of the callback followed by its arguments. It may be
+/// called from any thread, with the GIL held.
+///
+/// The scheduler can only be set once, so this should be called before any conversions are made.
+/// A `RuntimeError` is raised if a scheduler has already been set.
+///
+/// # Arguments
+/// * `scheduler` - Schedules a callback on the given event loop
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # fn main() -> PyResult<()> {
+/// // Schedule callbacks through the loop's own `schedule_threadsafe` method
+/// pyo3_asyncio::set_scheduler(|event_loop, _context, args| {
+///     event_loop.call_method1("schedule_threadsafe", args)?;
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn set_scheduler<F>(scheduler: F) -> PyResult<()>
+where
+    F: Fn(&PyAny, &PyAny, &PyTuple) -> PyResult<()> + Send + Sync + 'static,
+{
+    SCHEDULER
+        .set(Box::new(scheduler))
+        .map_err(|_| PyRuntimeError::new_err("the scheduler has already been set"))
+}
+
+fn call_soon_threadsafe(
+    event_loop: &PyAny,
+    context: &PyAny,
+    args: impl IntoPy<Py<PyTuple>>,
+) -> PyResult<()> {
+    let py = event_loop.py();
+
+    if let Some(scheduler) = SCHEDULER.get() {
+        return scheduler(event_loop, context, args.into_py(py).as_ref(py));
+    }
+
+    let kwargs = PyDict::new(py);
+
+    // Accommodate for the Python 3.6 fallback
+    // (call_soon_threadsafe does not support the context kwarg in 3.6)
+    if !context.is_none() {
+        kwargs.set_item("context", context)?;
+    }
+
+    event_loop.call_method("call_soon_threadsafe", args, Some(kwargs))?;
+    Ok(())
+}
+
+/// Convert a Python `awaitable` into a Rust Future
+///
+/// This function converts the `awaitable` into a Python Task using `run_coroutine_threadsafe`. A
+/// completion handler sends the result of this Task through a
+/// `futures::channel::oneshot::Sender<PyResult<PyObject>>` and the future returned by this function
+/// simply awaits the result through the `futures::channel::oneshot::Receiver<PyResult<PyObject>>`.
+///
+/// When called from the thread running the event loop in `locals` (i.e. from a `#[pyfunction]`
+/// called by a coroutine), the Task is created right away instead of being scheduled with
+/// `call_soon_threadsafe`, which saves a trip through the event loop for every conversion.
+///
+/// The returned [`PyFuture`] can also abort the Python Task and check whether it has finished,
+/// like a `JoinHandle`.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for the provided awaitable
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// const PYTHON_CODE: &'static str = r#"
+/// import asyncio
+///
+/// async def py_sleep(duration):
+///     await asyncio.sleep(duration)
+/// "#;
+///
+/// async fn py_sleep(seconds: f32) -> PyResult<()> {
+///     let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+///         Ok(
+///             PyModule::from_code(
+///                 py,
+///                 PYTHON_CODE,
+///                 "test_into_future/test_mod.py",
+///                 "test_mod"
+///             )?
+///             .into()
+///         )
+///     })?;
+///
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::into_future_with_loop(
+///             pyo3_asyncio::get_running_loop(py)?,
+///             test_mod
+///                 .call_method1(py, "py_sleep", (seconds.into_py(py),))?
+///                 .as_ref(py),
+///         )
+///     })?
+///     .await?;
+///     Ok(())    
+/// }
+/// ```
+pub fn into_future_with_locals(locals: &TaskLocals, awaitable: &PyAny) -> PyResult<PyFuture> {
+    bridge_awaitable(locals, awaitable, None, None, false)
+}
+
+/// Convert a Python `awaitable` into a Rust Future that runs on the current thread's event loop
+///
+/// Some awaitables (i.e. from GUI toolkits or C extensions) may only be driven by the thread that
+/// created them. This function captures the event loop running on the calling thread and always
+/// schedules `awaitable` there, regardless of the task locals of the current runtime. It must be
+/// called from a thread with a running event loop, typically from a `#[pyfunction]` called by
+/// Python code, and raises [`NoRunningLoop`](err::NoRunningLoop) otherwise.
+///
+/// The returned future can then be awaited from any thread or runtime.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// // there is no event loop running on this thread
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let coro = py.import("asyncio")?.call_method1("sleep", (0,))?;
+///     let err = pyo3_asyncio::into_future_pinned(coro).err().unwrap();
+///     assert!(err.is_instance_of::<pyo3_asyncio::err::NoRunningLoop>(py));
+///     # coro.call_method0("close")?;
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn into_future_pinned(awaitable: &PyAny) -> PyResult<PyFuture> {
+    let py = awaitable.py();
+
+    into_future_with_locals(
+        &TaskLocals::with_running_loop(py)?.copy_context(py)?,
+        awaitable,
+    )
+}
+
+/// Convert a Python `awaitable` into a Rust Future that runs in the given contextvars `Context`
+///
+/// [`into_future_with_locals`] schedules the awaitable in the context stored in `locals`, but the
+/// Python Task created for it runs in a *copy* of that context. This function runs the Task in
+/// `context` itself instead, so any `ContextVar` changes made by the coroutine are visible in
+/// `context` afterwards. This is useful to keep request-scoped state isolated per context.
+///
+/// Running a Task in an explicit context requires Python 3.11+. On older versions (or if the
+/// awaitable is not a coroutine) the Task still runs in a copy of `context`: it sees the same
+/// values, but its own changes are not written back.
+///
+/// # Arguments
+/// * `locals` - The Python event loop to be used for the provided awaitable
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `context` - The `contextvars.Context` the awaitable should run in
+pub fn into_future_in_context_with_locals(
+    locals: &TaskLocals,
+    awaitable: &PyAny,
+    context: &PyAny,
+) -> PyResult<PyFuture> {
+    bridge_awaitable(
+        &locals.clone().with_context(context),
+        awaitable,
+        None,
+        None,
+        true,
+    )
+}
+
+const WITH_TIMEOUT_CODE: &str = r#"
+import asyncio
+
+async def with_timeout(awaitable, delay):
+    async with asyncio.timeout(delay):
+        return await awaitable
+"#;
+
+/// Wrap `awaitable` in `asyncio.timeout(delay)`, or `asyncio.wait_for` before Python 3.11
+fn with_asyncio_timeout<'p>(
+    py: Python<'p>,
+    awaitable: &'p PyAny,
+    delay: Duration,
+) -> PyResult<&'p PyAny> {
+    let asyncio = asyncio(py)?;
+    let awaitable = normalize_awaitable(py, awaitable)?;
+
+    if !asyncio.hasattr("timeout")? {
+        return asyncio.call_method1("wait_for", (awaitable, delay.as_secs_f64()));
+    }
+
+    cached(py, &WITH_TIMEOUT, || {
+        Ok(PyModule::from_code(
+            py,
+            WITH_TIMEOUT_CODE,
+            "pyo3_asyncio/with_timeout.py",
+            "pyo3_asyncio_with_timeout",
+        )?
+        .getattr("with_timeout")?
+        .into())
+    })?
+    .into_ref(py)
+    .call1((awaitable, delay.as_secs_f64()))
+}
+
+/// Convert a Python `awaitable` into a Rust Future that is bounded by `asyncio.timeout(delay)`
+///
+/// This works just like [`into_future_with_locals`], except that the awaitable runs inside an
+/// `async with asyncio.timeout(delay):` block on the event loop, so the timeout behaves exactly like
+/// it would in native Python code: once `delay` has elapsed, the awaitable is cancelled at its
+/// current await point (it sees a `CancelledError`, so its `finally` blocks and cleanup handlers
+/// run), and the returned future resolves to a `TimeoutError`.
+///
+/// `asyncio.timeout` was added in Python 3.11. On older versions, `asyncio.wait_for` is used instead,
+/// which also cancels the awaitable and raises `asyncio.TimeoutError`, but runs it in a separate
+/// Task.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for the provided awaitable
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `delay` - How long the awaitable may run before it is cancelled
+pub fn into_future_with_asyncio_timeout_with_locals(
+    locals: &TaskLocals,
+    awaitable: &PyAny,
+    delay: Duration,
+) -> PyResult<PyFuture> {
+    into_future_with_locals(
+        locals,
+        with_asyncio_timeout(awaitable.py(), awaitable, delay)?,
+    )
+}
+
+/// Convert a Python `awaitable` into a Rust Future and an `AbortRegistration` that is aborted if
+/// the awaitable's Python Task is cancelled
+///
+/// This works just like [`into_future_with_locals`], except that cancellation on the Python side
+/// can also be observed by other Rust work. Wrap the downstream work in
+/// `futures::future::Abortable` with the returned registration and it will be aborted as soon as
+/// Python cancels the Task.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for the provided awaitable
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_abortable_with_locals(
+    locals: &TaskLocals,
+    awaitable: &PyAny,
+) -> PyResult<(
+    impl Future<Output = PyResult<PyObject>> + Send,
+    AbortRegistration,
+)> {
+    let (abort, registration) = AbortHandle::new_pair();
+    Ok((
+        bridge_awaitable(locals, awaitable, Some(abort), None, false)?,
+        registration,
+    ))
+}
+
+/// Convert a Python `awaitable` into a Rust Future that calls `on_beat` periodically while it is
+/// pending
+///
+/// This works just like [`into_future_with_locals`], except that `on_beat` is called every time
+/// `ticks` yields an item before the awaitable completes (i.e. to update a progress bar or to reset
+/// a watchdog). Beats stop as soon as the awaitable completes or `ticks` is exhausted, and the
+/// ticks are dropped along with the returned future.
+///
+/// The runtime modules provide `into_future_with_heartbeat`, which produces the ticks from a fixed
+/// interval with the runtime's own timer.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for the provided awaitable
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `ticks` - Stream whose items trigger a heartbeat
+/// * `on_beat` - Called once for every heartbeat
+pub fn into_future_with_heartbeat_with_locals<S, B>(
+    locals: &TaskLocals,
+    awaitable: &PyAny,
+    ticks: S,
+    mut on_beat: B,
+) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send>
+where
+    S: Stream + Send,
+    B: FnMut() + Send,
+{
+    let mut fut = Box::pin(into_future_with_locals(locals, awaitable)?);
+    let mut ticks = Box::pin(ticks.fuse());
+
+    Ok(future::poll_fn(move |cx| {
+        if let Poll::Ready(result) = fut.as_mut().poll(cx) {
+            return Poll::Ready(result);
+        }
+
+        // Drain every tick that is ready so that the ticks register their waker
+        while let Poll::Ready(Some(_)) = ticks.as_mut().poll_next(cx) {
+            on_beat();
+        }
+
+        Poll::Pending
+    }))
+}
+
+/// Runs the finalizer of [`into_future_finally_with_locals`] on completion or drop
+struct FinallyFuture<F>
+where
+    F: FnOnce(Python, &PyResult<PyObject>),
+{
+    fut: PyFuture,
+    on_finish: Option<F>,
+}
+
+// The finalizer is never pinned
+impl<F> Unpin for FinallyFuture<F> where F: FnOnce(Python, &PyResult<PyObject>) {}
+
+impl<F> Future for FinallyFuture<F>
+where
+    F: FnOnce(Python, &PyResult<PyObject>),
+{
+    type Output = PyResult<PyObject>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let result = futures::ready!(Pin::new(&mut self.fut).poll(cx));
+
+        if let Some(on_finish) = self.on_finish.take() {
+            Python::with_gil(|py| on_finish(py, &result));
+        }
+
+        Poll::Ready(result)
+    }
+}
+
+impl<F> Drop for FinallyFuture<F>
+where
+    F: FnOnce(Python, &PyResult<PyObject>),
+{
+    fn drop(&mut self) {
+        if let Some(on_finish) = self.on_finish.take() {
+            Python::with_gil(|py| {
+                bridge_debug!("into_future_finally: dropped before completion");
+                let result = Err(CancelledError::new_err(
+                    "future was dropped before the awaitable completed",
+                ));
+                on_finish(py, &result);
+            });
+        }
+    }
+}
+
+/// Convert a Python `awaitable` into a Rust Future that runs `on_finish` once it is done
+///
+/// This works just like [`into_future_with_locals`], except that `on_finish` is guaranteed to run
+/// exactly once, which makes it a reliable place to release resources held for the duration of the
+/// await:
+/// * when the awaitable completes or raises, `on_finish` gets its result before the returned
+///   future resolves to it.
+/// * when the returned future is dropped before completing, `on_finish` runs in `Drop` with an
+///   `asyncio.CancelledError`. Dropping the future also cancels the Python Task, like dropping the
+///   [`PyFuture`] returned by [`into_future_with_locals`] does, but `on_finish` doesn't wait for
+///   the cancellation to take effect on the event loop.
+///
+/// `on_finish` is called with the GIL held.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for the provided awaitable
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `on_finish` - Called exactly once with the result of the awaitable
+pub fn into_future_finally_with_locals<F>(
+    locals: &TaskLocals,
+    awaitable: &PyAny,
+    on_finish: F,
+) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send>
+where
+    F: FnOnce(Python, &PyResult<PyObject>) + Send,
+{
+    Ok(FinallyFuture {
+        fut: into_future_with_locals(locals, awaitable)?,
+        on_finish: Some(on_finish),
+    })
+}
+
+/// Convert a Python `awaitable` into a Rust Future that also resolves to the time it took
+///
+/// The clock starts before the awaitable is scheduled on the event loop and stops when its result
+/// is received by Rust, so the elapsed time includes the latency of crossing the bridge in both
+/// directions and not just the time the awaitable spent running. Nothing is measured if the
+/// awaitable fails.
+///
+/// # Arguments
+/// * `locals` - The Python event loop to be used for the provided awaitable
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_timed_with_locals(
+    locals: &TaskLocals,
+    awaitable: &PyAny,
+) -> PyResult<impl Future<Output = PyResult<(PyObject, Duration)>> + Send> {
+    let start = Instant::now();
+    let fut = into_future_with_locals(locals, awaitable)?;
+
+    Ok(async move {
+        let value = fut.await?;
+        Ok((value, start.elapsed()))
+    })
+}
+
+type ThenFn = dyn FnOnce(&PyAny) -> PyResult<()> + Send;
+
+/// Transforms the result of an awaitable from the event loop, where the GIL is already held
+#[pyclass]
+struct PyThen {
+    f: Option<Box<ThenFn>>,
+}
+
+#[pymethods]
+impl PyThen {
+    fn __call__(&mut self, value: &PyAny) -> PyResult<()> {
+        match self.f.take() {
+            Some(f) => f(value),
+            None => Err(PyRuntimeError::new_err(
+                "into_future_then transform called twice",
+            )),
+        }
+    }
+}
+
+const THEN_CODE: &str = r#"
+async def then(awaitable, f):
+    return f(await awaitable)
+"#;
+
+/// Convert a Python `awaitable` into a Rust Future whose result is transformed by `f` under the GIL
+///
+/// This works just like [`into_future_with_locals`], except that `f` is called with the result of
+/// the awaitable right after it completes, from the event loop where the GIL is already held. The
+/// returned future resolves to the output of `f` without acquiring the GIL again, which saves an
+/// acquisition compared to awaiting the result and then transforming it with
+/// `Python::with_gil`. An error returned by `f` is raised from the awaitable like any other
+/// exception.
+///
+/// Since `f` runs on the event loop, it should be quick (i.e. extracting or decoding the result).
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for the provided awaitable
+/// * `awaitable` - The Python `awaitable` to be converted
+/// * `f` - Transforms the result of the awaitable
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+/// use pyo3_asyncio::TaskLocals;
+///
+/// /// Fetch a document and decode it on the event loop thread
+/// async fn fetch_len(locals: TaskLocals, fetch: PyObject) -> PyResult<usize> {
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::into_future_then_with_locals(&locals, fetch.as_ref(py).call0()?, |py, doc| {
+///             doc.as_ref(py).len()
+///         })
+///     })?
+///     .await
+/// }
+/// ```
+pub fn into_future_then_with_locals<F, T>(
+    locals: &TaskLocals,
+    awaitable: &PyAny,
+    f: F,
+) -> PyResult<impl Future<Output = PyResult<T>> + Send>
+where
+    F: FnOnce(Python, PyObject) -> PyResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let py = awaitable.py();
+    let output = Arc::new(Mutex::new(None));
+    let slot = Arc::clone(&output);
+
+    let then = PyThen {
+        f: Some(Box::new(move |value: &PyAny| {
+            *slot.lock().unwrap() = Some(f(value.py(), value.into())?);
+            Ok(())
+        })),
+    };
+
+    let then_fn = cached(py, &THEN, || {
+        Ok(
+            PyModule::from_code(py, THEN_CODE, "pyo3_asyncio/then.py", "pyo3_asyncio_then")?
+                .getattr("then")?
+                .into(),
+        )
+    })?;
+    let fut = into_future_with_locals(
+        locals,
+        then_fn
+            .as_ref(py)
+            .call1((normalize_awaitable(py, awaitable)?, then))?,
+    )?;
+
+    Ok(async move {
+        fut.await?;
+
+        // the transform ran on the event loop before the awaitable completed
+        Ok(output
+            .lock()
+            .unwrap()
+            .take()
+            .expect("into_future_then output is missing"))
+    })
+}
+
+/// Shared with the event loop so that the Python Task created for a bridged awaitable can be found
+/// and controlled later on
+#[derive(Default)]
+struct TaskHandle {
+    task: Mutex<Option<PyObject>>,
+    finished: AtomicBool,
+    aborted: AtomicBool,
+}
+
+/// A live conversion between a Python awaitable and a Rust future
+trait Bridge: Send + Sync {
+    /// Request the cancellation of the bridge from any thread
+    fn cancel(&self, py: Python) -> PyResult<()>;
+
+    /// Resolve the bridge because the runtime driving it is going away
+    fn shut_down(&self, py: Python) -> PyResult<()> {
+        self.cancel(py)
+    }
+}
+
+/// Schedule `fut.cancel()` on the event loop of an `asyncio.Future` or Task
+fn cancel_threadsafe(fut: &PyAny) -> PyResult<()> {
+    let py = fut.py();
+    call_soon_threadsafe(
+        fut.call_method0("get_loop")?,
+        py.None().as_ref(py),
+        (fut.getattr("cancel")?,),
+    )
+}
+
+impl Bridge for TaskHandle {
+    fn cancel(&self, py: Python) -> PyResult<()> {
+        // cancels the task as soon as it is created if it doesn't exist yet
+        self.aborted.store(true, Ordering::Release);
+
+        let task = self
+            .task
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|task| task.clone_ref(py));
+        match task {
+            Some(task) if !self.finished.load(Ordering::Acquire) => {
+                cancel_threadsafe(task.as_ref(py))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The `asyncio.Future` returned by the runtimes' `future_into_py` functions
+///
+/// Cancelling it drops the Rust future. It is kept alive by the task that drives the Rust future.
+struct PyFutureBridge(PyObject);
+
+impl Bridge for PyFutureBridge {
+    fn cancel(&self, py: Python) -> PyResult<()> {
+        cancel_threadsafe(self.0.as_ref(py))
+    }
+
+    fn shut_down(&self, py: Python) -> PyResult<()> {
+        let fut = self.0.as_ref(py);
+
+        call_soon_threadsafe(
+            fut.call_method0("get_loop")?,
+            py.None().as_ref(py),
+            (PyShutdownNotifier {
+                future: self.0.clone_ref(py),
+            },),
+        )
+    }
+}
+
+/// Fails a pending `future_into_py` future with a `RuntimeError`, see [`shutdown_bridges`]
+#[pyclass]
+struct PyShutdownNotifier {
+    future: PyObject,
+}
+
+#[pymethods]
+impl PyShutdownNotifier {
+    fn __call__(&self, py: Python) -> PyResult<()> {
+        let fut = self.future.as_ref(py);
+
+        // the Rust future may have completed in the meantime
+        if !fut.call_method0("done")?.is_true()? {
+            fut.call_method1(
+                "set_exception",
+                (PyRuntimeError::new_err("event loop / runtime shut down"),),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct BridgeRegistry {
+    bridges: Vec<Weak<dyn Bridge>>,
+    /// Dead bridges are pruned once the registry grows past this size
+    prune_at: usize,
+}
+
+impl BridgeRegistry {
+    fn prune(&mut self) {
+        self.bridges.retain(|bridge| bridge.strong_count() > 0);
+        self.prune_at = (self.bridges.len() * 2).max(64);
+    }
+}
+
+static BRIDGES: Lazy<Mutex<BridgeRegistry>> = Lazy::new(Default::default);
+
+/// Track a bridge until it is dropped, see [`cancel_all_bridges`]
+fn register_bridge<B>(bridge: &Arc<B>)
+where
+    B: Bridge + 'static,
+{
+    let bridge: Weak<dyn Bridge> = Arc::downgrade(bridge) as Weak<dyn Bridge>;
+    let mut registry = BRIDGES.lock().unwrap();
+
+    if registry.bridges.len() >= registry.prune_at {
+        registry.prune();
+    }
+    registry.bridges.push(bridge);
+}
+
+/// Get the live bridges, pruning the ones that have been dropped
+fn live_bridges() -> Vec<Arc<dyn Bridge>> {
+    let mut registry = BRIDGES.lock().unwrap();
+    registry.prune();
+    registry.bridges.iter().filter_map(Weak::upgrade).collect()
+}
+
+/// Get the number of bridges created by this crate that are still alive
+///
+/// This counts every Python awaitable converted with `into_future` (and its variants) whose Rust
+/// future hasn't been dropped yet, and every Rust future converted with `future_into_py` (and its
+/// variants) that is still running. The bridges are tracked with weak references, so they are
+/// forgotten as soon as they are dropped. This is mostly useful to find leaks in tests.
+pub fn live_bridge_count() -> usize {
+    live_bridges().len()
+}
+
+/// Cancel every bridge created by this crate that is still alive
+///
+/// This is a bigger hammer than closing the event loop, meant for emergency teardown and test
+/// isolation:
+/// * The Python Task of every awaitable converted with `into_future` is cancelled, so the Rust
+///   futures awaiting them resolve to a `CancelledError`.
+/// * The `asyncio.Future` of every Rust future converted with `future_into_py` is cancelled, which
+///   drops the Rust future just like a cancellation from Python would.
+///
+/// The cancellations are scheduled on the event loops that the bridges belong to, so they only take
+/// effect once those loops run again. Bridges whose event loop is already closed are skipped. Note
+/// that this includes the bridge that the caller may be running in, such as the future driven by
+/// `run_until_complete` or `run`.
+/// Returns the number of bridges that were cancelled.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| {
+///     let cancelled = pyo3_asyncio::cancel_all_bridges(py);
+///     println!("cancelled {} bridges", cancelled);
+/// });
+/// ```
+pub fn cancel_all_bridges(py: Python) -> usize {
+    live_bridges()
+        .iter()
+        .filter(|bridge| match bridge.cancel(py) {
+            Ok(()) => true,
+            Err(e) => {
+                bridge_debug!("cancel_all_bridges: unable to cancel bridge: {}", e);
+                false
+            }
+        })
+        .count()
+}
+
+/// Resolve every bridge created by this crate that is still alive because the Rust runtime is
+/// shutting down
+///
+/// A Python awaitable created by `future_into_py` (and its variants) only completes once its Rust
+/// future does, so it hangs forever if the runtime driving that future is torn down first. This
+/// function should be called before shutting a runtime down:
+/// * The `asyncio.Future` of every Rust future that is still running is failed with
+///   `RuntimeError("event loop / runtime shut down")`, so Python awaiters get a clean error. The
+///   Rust futures are dropped afterwards, like they are when the `asyncio.Future` is cancelled.
+/// * The Python Task of every awaitable converted with `into_future` is cancelled, like
+///   [`cancel_all_bridges`] does.
+///
+/// The awaitables are resolved on the event loops that they belong to, so this only takes effect
+/// once those loops run again. Bridges whose event loop is already closed are skipped. Returns the
+/// number of bridges that were resolved.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// // i.e. before dropping the runtime that runs the futures
+/// Python::with_gil(|py| {
+///     let resolved = pyo3_asyncio::shutdown_bridges(py);
+///     println!("resolved {} bridges", resolved);
+/// });
+/// ```
+pub fn shutdown_bridges(py: Python) -> usize {
+    live_bridges()
+        .iter()
+        .filter(|bridge| match bridge.shut_down(py) {
+            Ok(()) => true,
+            Err(e) => {
+                bridge_debug!("shutdown_bridges: unable to shut down bridge: {}", e);
+                false
+            }
+        })
+        .count()
+}
+
+/// A boxed Rust Future resolving to the result of a Python awaitable
+///
+/// [`PyFuture`] can already be stored as is, but the other conversions (i.e.
+/// [`into_future_timed_with_locals`] or [`into_future_then_with_locals`]) return unnamed
+/// `impl Future` types. Boxing them into a `PyFutureResult` makes it possible to keep futures from
+/// different conversions in the same struct field or `Vec`.
+pub type PyFutureResult = Pin<Box<dyn Future<Output = PyResult<PyObject>> + Send>>;
+
+/// A Python awaitable converted into a Rust Future
+///
+/// This is the future returned by [`into_future_with_locals`] and the runtimes' `into_future`
+/// functions. Besides being awaitable, it controls the Python Task it is bridged to, much like a
+/// `JoinHandle` controls a spawned Rust task, so a single value can be stored, polled and aborted.
+///
+/// Unlike a `JoinHandle`, dropping a `PyFuture` before the Task has finished cancels the Task (see
+/// [`PyFuture::abort`]), so Rust side cancellation, such as a `select!` picking another branch or a
+/// timeout elapsing, also reaches the Python code it was waiting on.
+pub struct PyFuture {
+    rx: oneshot::Receiver<PyResult<PyObject>>,
+    locals: TaskLocals,
+    handle: Arc<TaskHandle>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl PyFuture {
+    /// Cancel the Python Task
+    ///
+    /// The cancellation is scheduled on the Task's event loop, so the Task may take a while to
+    /// finish unwinding. Awaiting the future afterwards resolves to a `CancelledError` unless the
+    /// Task completed (or suppressed the cancellation) in the meantime. Aborting a finished Task
+    /// does nothing.
+    pub fn abort(&self) {
+        self.handle.aborted.store(true, Ordering::Release);
+
+        Python::with_gil(|py| {
+            if let Err(e) = call_soon_threadsafe(
+                self.locals.event_loop(py),
+                self.locals.context(py),
+                (PyTaskCanceller {
+                    handle: Arc::clone(&self.handle),
+                },),
+            ) {
+                // The event loop is closed, so the task won't run anymore anyway
+                bridge_debug!("into_future: unable to abort task: {}", e);
+            }
+        });
+    }
+
+    /// Check whether the Python Task has finished, even if its result hasn't been awaited yet
+    pub fn is_finished(&self) -> bool {
+        self.handle.finished.load(Ordering::Acquire)
+    }
+
+    /// Wait for the Python Task until `deadline` completes, without cancelling it
+    ///
+    /// Resolves to [`Peek::Ready`] with the result of the Task if it finishes first. Otherwise it
+    /// resolves to [`Peek::Pending`] with this future, which is still bridged to the running Task
+    /// and can be awaited (or peeked at) again later. Unlike a timeout, reaching the deadline
+    /// leaves the Task untouched.
+    ///
+    /// The runtimes' `peek_with_timeout` functions use this with their own timer.
+    ///
+    /// # Arguments
+    /// * `deadline` - Future that completes when the wait should stop
+    pub async fn peek<D>(mut self, deadline: D) -> Peek
+    where
+        D: Future<Output = ()>,
+    {
+        futures::pin_mut!(deadline);
+
+        let result = future::poll_fn(|cx| {
+            if let Poll::Ready(result) = Pin::new(&mut self).poll(cx) {
+                return Poll::Ready(Some(result));
+            }
+
+            deadline.as_mut().poll(cx).map(|_| None)
+        })
+        .await;
+
+        match result {
+            Some(result) => Peek::Ready(result),
+            None => Peek::Pending(self),
+        }
+    }
+
+    /// Wait for the Python Task until `deadline` completes, cancelling it if it is still running
+    ///
+    /// Resolves to the result of the Task if it finishes first. Otherwise the Task is cancelled
+    /// (see [`PyFuture::abort`]) and this resolves to an `asyncio.TimeoutError` right away, without
+    /// waiting for the Task to finish unwinding. This is the Rust side equivalent of
+    /// `asyncio.wait_for`.
+    ///
+    /// The runtimes' `into_future_with_timeout` functions use this with their own timer.
+    ///
+    /// # Arguments
+    /// * `deadline` - Future that completes when the Task should be cancelled
+    pub async fn with_deadline<D>(self, deadline: D) -> PyResult<PyObject>
+    where
+        D: Future<Output = ()>,
+    {
+        match self.peek(deadline).await {
+            Peek::Ready(result) => result,
+            Peek::Pending(fut) => {
+                fut.abort();
+
+                Python::with_gil(|py| {
+                    Err(PyErr::from_value(
+                        asyncio(py)?
+                            .getattr("TimeoutError")?
+                            .call1(("the awaitable did not complete before the deadline",))?,
+                    ))
+                })
+            }
+        }
+    }
+
+    /// Wait for the Python Task until `deadline` completes, cancelling it if it is still running
+    ///
+    /// Like [`PyFuture::with_deadline`], except that reaching the deadline resolves to `Ok(None)`
+    /// instead of an `asyncio.TimeoutError`. This fits callers for which running out of time is
+    /// an expected outcome rather than an error. The Task is still cancelled (see
+    /// [`PyFuture::abort`]) when the deadline is reached.
+    ///
+    /// The runtimes' `into_future_or_timeout` functions use this with their own timer.
+    ///
+    /// # Arguments
+    /// * `deadline` - Future that completes when the Task should be cancelled
+    pub async fn or_deadline<D>(self, deadline: D) -> PyResult<Option<PyObject>>
+    where
+        D: Future<Output = ()>,
+    {
+        match self.peek(deadline).await {
+            Peek::Ready(result) => result.map(Some),
+            Peek::Pending(fut) => {
+                fut.abort();
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// The outcome of [`PyFuture::peek`]
+#[derive(Debug)]
+pub enum Peek {
+    /// The Python Task finished before the deadline
+    Ready(PyResult<PyObject>),
+    /// The deadline was reached first, the Python Task is still running
+    Pending(PyFuture),
+}
+
+impl Future for PyFuture {
+    type Output = PyResult<PyObject>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        #[cfg(feature = "tracing")]
+        let _entered = this.span.enter();
+
+        match futures::ready!(Pin::new(&mut this.rx).poll(cx)) {
+            Ok(item) => Poll::Ready(item),
+            Err(_) => Poll::Ready(Python::with_gil(|py| {
+                bridge_debug!("into_future: completion handler dropped, treating as cancelled");
+                Err(PyErr::from_value(
+                    asyncio(py)?.call_method0("CancelledError")?,
+                ))
+            })),
+        }
+    }
+}
+
+impl Drop for PyFuture {
+    fn drop(&mut self) {
+        if !self.is_finished() && !self.handle.aborted.load(Ordering::Acquire) {
+            bridge_debug!("into_future: dropped before completion, aborting task");
+            self.abort();
+        }
+    }
+}
+
+impl fmt::Debug for PyFuture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PyFuture")
+            .field("locals", &self.locals)
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+/// Raise early if a Python future attached to the event loop in `locals` is created while another
+/// event loop is running on the current thread, since awaiting it there would fail
+pub(crate) fn check_running_loop(py: Python, locals: &TaskLocals) -> PyResult<()> {
+    let running = match peek_running_loop(py)? {
+        Some(running) if !running.is(locals.event_loop(py)) => running,
+        _ => return Ok(()),
+    };
+
+    Err(PyRuntimeError::new_err(format!(
+        "future_into_py: the future would be attached to a different event loop ({}) than the one \
+         running on this thread ({}), so awaiting it here would fail. Pass the TaskLocals of the \
+         running event loop, i.e. the ones returned by get_current_locals",
+        locals.event_loop(py).repr()?,
+        running.repr()?,
+    )))
+}
+
+/// Raise early if `awaitable` is a future attached to another event loop than the one in `locals`
+fn check_same_loop(locals: &TaskLocals, awaitable: &PyAny) -> PyResult<()> {
+    let py = awaitable.py();
+
+    if !asyncio(py)?
+        .call_method1("isfuture", (awaitable,))?
+        .is_true()?
+        || !awaitable.hasattr("get_loop")?
+    {
+        // coroutines aren't attached to any event loop until they are scheduled
+        return Ok(());
+    }
+
+    let attached = awaitable.call_method0("get_loop")?;
+    if attached.is(locals.event_loop(py)) {
+        return Ok(());
+    }
+
+    Err(PyRuntimeError::new_err(format!(
+        "into_future: {} is attached to a different event loop ({}) than the one it is being \
+         awaited on ({}). Futures created by future_into_py are attached to the event loop of the \
+         TaskLocals they were created with, so pass the same TaskLocals to both conversions",
+        awaitable.repr()?,
+        attached.repr()?,
+        locals.event_loop(py).repr()?,
+    )))
+}
+
+/// Run `ensure_future` in `context` right away, returns `false` if `context` can't be entered
+fn ensure_future_now(context: &PyAny, ensure_future: &PyCell<PyEnsureFuture>) -> PyResult<bool> {
+    if context.is_none() {
+        ensure_future.call0()?;
+        return Ok(true);
+    }
+
+    match context.call_method1("run", (ensure_future,)) {
+        Ok(_) => Ok(true),
+        Err(e) => {
+            let this = ensure_future.borrow();
+
+            // A context that is already entered (i.e. by the caller) can't be run again, so let
+            // the event loop run the callback instead. Otherwise the error comes from the callback.
+            if this.tx.is_some() && !this.handle.finished.load(Ordering::Acquire) {
+                Ok(false)
+            } else {
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Schedule `awaitable` on the event loop in `locals` and return the Rust future awaiting it
+///
+/// A new handle is created and registered for `cancel_all_bridges` unless a `shared` one is given,
+/// in which case its owner registers it once for every awaitable it is reused for.
+fn bridge_awaitable(
+    locals: &TaskLocals,
+    awaitable: &PyAny,
+    abort: Option<AbortHandle>,
+    shared: Option<Arc<TaskHandle>>,
+    exact_context: bool,
+) -> PyResult<PyFuture> {
+    let py = awaitable.py();
+    let registered = shared.is_some();
+    let handle = shared.unwrap_or_default();
+    let awaitable = normalize_awaitable(py, awaitable)?;
+    if debug_enabled() {
+        check_same_loop(locals, awaitable)?;
+    }
+    // The channel, the handle and the callbacks are not pooled: the pending Task holds on to its
+    // own sender and callbacks, and a oneshot can't be reset for reuse. They take a handful of
+    // allocations per conversion (see `benches/into_future.rs`), next to the asyncio Task that
+    // every conversion creates anyway.
+    let (tx, rx) = oneshot::channel();
+
+    bridge_debug!(
+        "into_future: created for awaitable {:#x} on loop {:#x}",
+        py_id(awaitable),
+        py_id(locals.event_loop(py))
+    );
+
+    #[cfg(feature = "tracing")]
+    let span = tracing::debug_span!(target: "pyo3_asyncio", "py_await");
+    // the exact context is shared with the caller, so it can't carry the span
+    #[cfg(feature = "tracing")]
+    let context = if exact_context {
+        locals.context(py)
+    } else {
+        instrument::context_with_span(py, locals.context(py), &span)?
+    };
+    #[cfg(not(feature = "tracing"))]
+    let context = locals.context(py);
+
+    let ensure_future = PyCell::new(
+        py,
+        PyEnsureFuture {
+            awaitable: awaitable.into(),
+            tx: Some(tx),
+            abort,
+            handle: Arc::clone(&handle),
+            task_context: if exact_context {
+                Some(locals.context.clone())
+            } else {
+                None
+            },
+        },
+    )?;
+
+    // Already on the thread running the event loop, so the Task can be created right away instead
+    // of waiting for the next iteration of the event loop
+    if SCHEDULER.get().is_none()
+        && is_running_loop(locals.event_loop(py))?
+        && ensure_future_now(context, ensure_future)?
+    {
+        bridge_debug!("into_future: scheduled on the event loop thread");
+    } else {
+        call_soon_threadsafe(locals.event_loop(py), context, (ensure_future,))?;
+    }
+
+    if !registered {
+        register_bridge(&handle);
+    }
+
+    Ok(PyFuture {
+        rx,
+        locals: locals.clone(),
+        handle,
+        #[cfg(feature = "tracing")]
+        span,
+    })
+}
+
+/// Closes an abandoned async generator once its in-flight `__anext__` (if any) has finished
+#[pyclass]
+struct PyAsyncGenCloser {
+    gen: PyObject,
+    handle: Arc<TaskHandle>,
+}
+
+#[pymethods]
+impl PyAsyncGenCloser {
+    #[args(_task = "None")]
+    fn __call__(slf: &PyCell<Self>, _task: Option<&PyAny>) -> PyResult<()> {
+        let py = slf.py();
+        let this = slf.borrow();
+
+        // aclose() raises a RuntimeError while the generator is still running, so wait for the
+        // pending __anext__ to complete first
+        if let Some(task) = this.handle.task.lock().unwrap().take() {
+            let task = task.as_ref(py);
+            if !task.call_method0("done")?.is_true()? {
+                task.call_method1("add_done_callback", (slf,))?;
+                return Ok(());
+            }
+        }
+
+        let gen = this.gen.as_ref(py);
+        if gen.hasattr("aclose")? {
+            bridge_debug!("into_stream: closing async generator {:#x}", py_id(gen));
+            ensure_future(py, gen.call_method0("aclose")?)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct AsyncGenStream {
+    locals: TaskLocals,
+    gen: PyObject,
+    anext: Option<PyFuture>,
+    handle: Arc<TaskHandle>,
+    done: bool,
+}
+
+impl Stream for AsyncGenStream {
+    type Item = PyResult<PyObject>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        if this.anext.is_none() {
+            // the handle is shared by every __anext__, so it only tracks the one in flight
+            this.handle.finished.store(false, Ordering::Release);
+
+            let anext = Python::with_gil(|py| {
+                bridge_awaitable(
+                    &this.locals,
+                    this.gen.as_ref(py).call_method0("__anext__")?,
+                    None,
+                    Some(Arc::clone(&this.handle)),
+                    false,
+                )
+            });
+
+            match anext {
+                Ok(anext) => this.anext = Some(anext),
+                Err(e) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+
+        let item = match this.anext.as_mut() {
+            Some(anext) => futures::ready!(Pin::new(anext).poll(cx)),
+            None => unreachable!(),
+        };
+        this.anext = None;
+
+        match item {
+            Ok(item) => Poll::Ready(Some(Ok(item))),
+            Err(e) => {
+                // the generator is finished either way
+                this.done = true;
+
+                // GeneratorExit means the generator was closed from the Python side, which is a
+                // clean shutdown rather than an error
+                if Python::with_gil(|py| {
+                    e.is_instance_of::<PyStopAsyncIteration>(py)
+                        || e.is_instance_of::<PyGeneratorExit>(py)
+                }) {
+                    Poll::Ready(None)
+                } else {
+                    Poll::Ready(Some(Err(e)))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for AsyncGenStream {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+
+        // Best-effort, the generator's cleanup is skipped if the event loop is already closed
+        Python::with_gil(|py| {
+            let closer = PyAsyncGenCloser {
+                gen: self.gen.clone_ref(py),
+                handle: Arc::clone(&self.handle),
+            };
+
+            if let Err(e) = call_soon_threadsafe(
+                self.locals.event_loop(py),
+                self.locals.context(py),
+                (closer,),
+            ) {
+                bridge_debug!("into_stream: unable to close async generator: {}", e);
+            }
+        });
+    }
+}
+
+/// Convert a Python async iterator (i.e. an `async def` generator) into a Rust Stream
+///
+/// Each item is produced by awaiting `__anext__()` on the event loop in `locals` the same way
+/// [`into_future_with_locals`] does, and the stream ends when `StopAsyncIteration` is raised (so
+/// an iterator that raises it on the first `__anext__()` is simply an empty stream). A
+/// `GeneratorExit` (i.e. when the generator is closed by Python while the stream is waiting on it)
+/// also ends the stream cleanly. Any other exception is yielded as the last item of the stream.
+///
+/// If the stream is dropped before the iterator is exhausted, `aclose()` is scheduled on the event
+/// loop so that the generator's `finally` blocks run and the resources it holds are released
+/// promptly. When an `__anext__()` is still in flight, it is cancelled and `aclose()` is deferred
+/// until it completes. This is best-effort: nothing is run if the event loop has already been closed.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for the async iterator
+/// * `gen` - The Python async iterable to be converted
+pub fn into_stream_with_locals(
+    locals: TaskLocals,
+    gen: &PyAny,
+) -> PyResult<impl Stream<Item = PyResult<PyObject>> + Send> {
+    let handle = Arc::default();
+    // the handle is reused by every __anext__, so it is only registered once
+    register_bridge(&handle);
+
+    Ok(AsyncGenStream {
+        locals,
+        gen: gen.call_method0("__aiter__")?.into(),
+        anext: None,
+        handle,
+        done: false,
+    })
+}
+
+const AS_COMPLETED_CODE: &str = r#"
+import asyncio
+
+async def as_completed(awaitables):
+    pending = {asyncio.ensure_future(aw) for aw in awaitables}
+    while pending:
+        done, pending = await asyncio.wait(pending, return_when=asyncio.FIRST_COMPLETED)
+        for fut in done:
+            if fut.cancelled():
+                yield False, asyncio.CancelledError()
+            elif fut.exception() is not None:
+                yield False, fut.exception()
+            else:
+                yield True, fut.result()
+"#;
+
+/// Convert a collection of Python awaitables into a Rust Stream of their results in completion
+/// order
+///
+/// This is the Rust counterpart to iterating over `asyncio.as_completed(awaitables)`: the
+/// awaitables are scheduled as tasks on the event loop in `locals` and awaited there with
+/// `asyncio.wait(..., return_when=FIRST_COMPLETED)`, so they run concurrently with each other and
+/// nothing is polled from Rust except the stream itself. Each item is the result of one awaitable,
+/// and an awaitable that raises (or is cancelled) yields its exception as an `Err` item without
+/// ending the stream, so the remaining results are still delivered.
+///
+/// Every element of `awaitables` is checked with [`normalize_awaitable`] up front, so a
+/// non-awaitable element is reported before anything is scheduled. Dropping the stream stops
+/// waiting on the awaitables, but the tasks created for them are left running on the event loop.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for the awaitables
+/// * `awaitables` - A Python iterable of awaitables
+pub fn as_completed_stream_with_locals(
+    locals: TaskLocals,
+    awaitables: &PyAny,
+) -> PyResult<impl Stream<Item = PyResult<PyObject>> + Send> {
+    let py = awaitables.py();
+    let awaitables = awaitables
+        .iter()?
+        .map(|aw| {
+            aw.and_then(|aw| normalize_awaitable(py, aw))
+                .map(PyObject::from)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+
+    let gen = cached(py, &AS_COMPLETED, || {
+        Ok(PyModule::from_code(
+            py,
+            AS_COMPLETED_CODE,
+            "pyo3_asyncio/as_completed.py",
+            "pyo3_asyncio_as_completed",
+        )?
+        .getattr("as_completed")?
+        .into())
+    })?
+    .into_ref(py)
+    .call1((awaitables,))?;
+
+    Ok(into_stream_with_locals(locals, gen)?.map(|item| {
+        Python::with_gil(|py| {
+            let (ok, value): (bool, PyObject) = item?.extract(py)?;
+
+            if ok {
+                Ok(value)
+            } else {
+                Err(err::from_exception(value.into_ref(py)))
+            }
+        })
+    }))
+}
+
+/// Convert a Python `asyncio.Queue` into a Rust Stream of its items
+///
+/// Each item is produced by awaiting `queue.get()` on the event loop in `locals` the same way
+/// [`into_future_with_locals`] does. When a `sentinel` is given, the stream ends as soon as the
+/// sentinel object itself (compared by identity, not with `==`) is taken off the queue, and the
+/// sentinel is not yielded. An exception raised by
+/// `queue.get()` is yielded as the last item of the stream.
+///
+/// Without a `sentinel` the stream never ends on its own: the stream keeps a reference to the
+/// queue, so the queue can't be garbage collected out from under it either. In that case the
+/// consumer decides when to stop, e.g. with `StreamExt::take_while`, and dropping the stream
+/// cancels the pending `queue.get()`.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for `queue.get()`
+/// * `queue` - The Python `asyncio.Queue` to consume
+/// * `sentinel` - The item that ends the stream, or `None` for an infinite stream
+pub fn py_queue_to_stream_with_locals(
+    locals: TaskLocals,
+    queue: &PyAny,
+    sentinel: Option<&PyAny>,
+) -> PyResult<impl Stream<Item = PyResult<PyObject>> + Send> {
+    let get = PyObject::from(queue.getattr("get")?);
+    let sentinel = sentinel.map(PyObject::from);
+
+    Ok(stream::unfold(
+        Some((locals, get, sentinel)),
+        |state| async move {
+            let (locals, get, sentinel) = state?;
+
+            let item = match Python::with_gil(|py| {
+                into_future_with_locals(&locals, get.as_ref(py).call0()?)
+            }) {
+                Ok(fut) => fut.await,
+                Err(e) => Err(e),
+            };
+
+            if let (Ok(item), Some(sentinel)) = (&item, &sentinel) {
+                if Python::with_gil(|py| item.as_ref(py).is(sentinel.as_ref(py))) {
+                    return None;
+                }
+            }
+
+            // an error ends the stream
+            let state = item.is_ok().then_some((locals, get, sentinel));
+            Some((item, state))
+        },
+    ))
+}
+
+/// Raise a `TypeError` unless `event` is an `asyncio.Event`
+fn check_py_event(event: &PyAny) -> PyResult<()> {
+    if event.is_instance(asyncio(event.py())?.getattr("Event")?.downcast()?)? {
+        Ok(())
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "expected an asyncio.Event, got an object of type '{}'",
+            event.get_type().name()?
+        )))
+    }
+}
+
+/// Wait for a Python `asyncio.Event` to be set from Rust
+///
+/// This converts `event.wait()` into a Rust Future with [`into_future_with_locals`], after checking
+/// that `event` is actually an `asyncio.Event` so that passing the wrong object (i.e. a
+/// `threading.Event`) raises a `TypeError` right away.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for `event.wait()`
+/// * `event` - The `asyncio.Event` to wait for
+pub fn await_py_event_with_locals(
+    locals: &TaskLocals,
+    event: &PyAny,
+) -> PyResult<impl Future<Output = PyResult<()>> + Send> {
+    check_py_event(event)?;
+    let wait = into_future_with_locals(locals, event.call_method0("wait")?)?;
+
+    Ok(async move { wait.await.map(|_| ()) })
+}
+
+/// Set a Python `asyncio.Event` from any thread
+///
+/// `asyncio.Event` is not thread-safe, so `event.set()` is scheduled with `call_soon_threadsafe` on
+/// the event loop in `locals`, which must be the event loop that the tasks waiting for the event
+/// run on. They are woken up on that loop. `TaskLocals` can be cloned and moved to another thread,
+/// so capturing them beforehand makes this usable from plain Rust threads as well.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to set the event on
+/// * `event` - The `asyncio.Event` to set
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+/// use pyo3_asyncio::TaskLocals;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let asyncio = py.import("asyncio")?;
+///     let event_loop = asyncio.call_method0("new_event_loop")?;
+///     // before Python 3.10, an asyncio.Event is bound to the current event loop when it is created
+///     asyncio.call_method1("set_event_loop", (event_loop,))?;
+///     let event: PyObject = asyncio.call_method0("Event")?.into();
+///
+///     // set the event from a thread that knows nothing about the event loop
+///     let setter = {
+///         let locals = TaskLocals::new(event_loop);
+///         let event = event.clone_ref(py);
+///         std::thread::spawn(move || {
+///             Python::with_gil(|py| {
+///                 pyo3_asyncio::set_py_event_threadsafe_with_locals(&locals, event.as_ref(py))
+///             })
+///         })
+///     };
+///     py.allow_threads(|| setter.join()).unwrap()?;
+///
+///     event_loop.call_method1("run_until_complete", (event.as_ref(py).call_method0("wait")?,))?;
+///     asyncio.call_method1("set_event_loop", (py.None(),))?;
+///     event_loop.call_method0("close")?;
+///
+///     // not an asyncio.Event
+///     let event = py.import("threading")?.call_method0("Event")?;
+///     let locals = TaskLocals::new(event_loop);
+///     assert!(pyo3_asyncio::set_py_event_threadsafe_with_locals(&locals, event).is_err());
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn set_py_event_threadsafe_with_locals(locals: &TaskLocals, event: &PyAny) -> PyResult<()> {
+    let py = event.py();
+    check_py_event(event)?;
+
+    call_soon_threadsafe(
+        locals.event_loop(py),
+        locals.context(py),
+        (event.getattr("set")?,),
+    )
+}
+
+/// Resolve a step of an async generator, mapping `StopAsyncIteration` to `None`
+async fn agen_step(step: PyFuture) -> PyResult<Option<PyObject>> {
+    match step.await {
+        Ok(item) => Ok(Some(item)),
+        Err(e) if Python::with_gil(|py| e.is_instance_of::<PyStopAsyncIteration>(py)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Send `value` into a Python async generator with `asend()` and convert the step into a Rust
+/// Future
+///
+/// The returned future resolves to `Some` with the next value yielded by the generator, or to
+/// `None` if the generator finished (i.e. `StopAsyncIteration` was raised). Any other exception is
+/// returned as an error. Sending `None` is equivalent to advancing the generator with `__anext__()`.
+///
+/// Dropping the returned future before it completes cancels the Python Task running `asend()`, so
+/// a `CancelledError` is raised inside the generator at the point where it is suspended. Unless the
+/// generator handles it, this finishes the generator. Otherwise the generator is not closed when
+/// the future is dropped, unlike with [`into_stream_with_locals`].
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to drive the generator on
+/// * `agen` - The Python async generator
+/// * `value` - The value that the pending `yield` expression evaluates to
+pub fn agen_send_with_locals(
+    locals: &TaskLocals,
+    agen: &PyAny,
+    value: &PyAny,
+) -> PyResult<impl Future<Output = PyResult<Option<PyObject>>> + Send> {
+    let step = into_future_with_locals(locals, agen.call_method1("asend", (value,))?)?;
+    Ok(agen_step(step))
+}
+
+/// Throw `exc` into a Python async generator with `athrow()` and convert the step into a Rust
+/// Future
+///
+/// The exception is raised at the pending `yield` expression. The returned future resolves to
+/// `Some` with the next value yielded if the generator handles it, or to `None` if the generator
+/// finished. If the generator lets the exception (or any other one) propagate, it is returned as an
+/// error.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to drive the generator on
+/// * `agen` - The Python async generator
+/// * `exc` - The exception to raise inside the generator
+pub fn agen_throw_with_locals(
+    locals: &TaskLocals,
+    agen: &PyAny,
+    exc: PyErr,
+) -> PyResult<impl Future<Output = PyResult<Option<PyObject>>> + Send> {
+    let py = agen.py();
+    let step = into_future_with_locals(locals, agen.call_method1("athrow", (exc.into_py(py),))?)?;
+    Ok(agen_step(step))
+}
+
+type PyItemIter = dyn Iterator<Item = PyResult<PyObject>> + Send;
+
+/// Async iterator over a synchronous Rust iterator
+#[pyclass]
+struct PyIterAsync {
+    iter: Option<Box<PyItemIter>>,
+    yield_every: usize,
+    since_yield: usize,
+}
+
+#[pymethods]
+impl PyIterAsync {
+    fn __aiter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __anext__(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        let item = match self.iter.as_mut().and_then(|iter| iter.next()) {
+            Some(item) => item,
+            None => {
+                // don't poll the iterator again once it's exhausted
+                self.iter = None;
+                return Err(PyStopAsyncIteration::new_err(()));
+            }
+        };
+
+        // yield_every is 0 when the iterator never yields, which since_yield can't be here
+        self.since_yield += 1;
+        let yield_now = self.since_yield == self.yield_every;
+        if yield_now {
+            self.since_yield = 0;
+        }
+
+        match item {
+            Ok(item) if yield_now => {
+                // a sleep(0) suspends the consumer for one pass of the event loop
+                Ok(Some(asyncio(py)?.call_method1("sleep", (0, item))?.into()))
+            }
+            item => {
+                let fut = create_future(get_running_loop(py)?)?;
+                match item {
+                    Ok(item) => fut.call_method1("set_result", (item,))?,
+                    Err(e) => fut.call_method1("set_exception", (e,))?,
+                };
+                Ok(Some(fut.into()))
+            }
+        }
+    }
+
+    /// Drop the Rust iterator, so that the iteration ends like `aclose()` ends an async generator
+    fn aclose(&mut self, py: Python) -> PyResult<PyObject> {
+        self.iter = None;
+
+        let fut = create_future(get_running_loop(py)?)?;
+        fut.call_method1("set_result", (py.None(),))?;
+        Ok(fut.into())
+    }
+}
+
+/// Convert a synchronous Rust iterator into a Python async iterator
+///
+/// The iterator is advanced lazily, one item per `__anext__`, on the thread running the event
+/// loop. Each item is handed back through an `asyncio.Future` that is already done, so `async for`
+/// can consume the iterator without a Rust runtime or a thread hop. An error item is raised from
+/// the iteration that receives it, and `StopAsyncIteration` is raised once the iterator returns
+/// `None`. Like an async generator, the async iterator also has an `aclose()` method that drops
+/// the iterator.
+///
+/// Awaiting a done future doesn't suspend the consumer, so other coroutines on the event loop
+/// don't run until the iteration is over. Use [`iter_into_py_async_with_yield`] if producing the
+/// items takes long enough for this to matter.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `iter` - The Rust iterator to be converted
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Async iterator over the squares of the numbers from 0 to `count`
+/// #[pyfunction]
+/// fn squares(py: Python, count: u64) -> PyResult<&PyAny> {
+///     pyo3_asyncio::iter_into_py_async(
+///         py,
+///         (0..count).map(|i| Ok(Python::with_gil(|py| (i * i).into_py(py)))),
+///     )
+/// }
+/// ```
+pub fn iter_into_py_async<I>(py: Python, iter: I) -> PyResult<&PyAny>
+where
+    I: IntoIterator<Item = PyResult<PyObject>>,
+    I::IntoIter: Send + 'static,
+{
+    Ok(PyCell::new(
+        py,
+        PyIterAsync {
+            iter: Some(Box::new(iter.into_iter())),
+            yield_every: 0,
+            since_yield: 0,
+        },
+    )?
+    .as_ref())
+}
+
+/// Convert a synchronous Rust iterator into a Python async iterator that periodically yields to
+/// the event loop
+///
+/// This works like [`iter_into_py_async`], except that every `yield_every`-th item is handed back
+/// through `asyncio.sleep(0, item)`, which suspends the consumer for one pass of the event loop.
+/// This keeps a CPU-heavy iterator from starving the other coroutines on the event loop.
+///
+/// A `ValueError` is raised if `yield_every` is zero.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `iter` - The Rust iterator to be converted
+/// * `yield_every` - How many items to produce between two passes of the event loop
+pub fn iter_into_py_async_with_yield<I>(py: Python, iter: I, yield_every: usize) -> PyResult<&PyAny>
+where
+    I: IntoIterator<Item = PyResult<PyObject>>,
+    I::IntoIter: Send + 'static,
+{
+    if yield_every == 0 {
+        return Err(PyValueError::new_err(
+            "yield_every must be greater than zero",
+        ));
+    }
+
+    Ok(PyCell::new(
+        py,
+        PyIterAsync {
+            iter: Some(Box::new(iter.into_iter())),
+            yield_every,
+            since_yield: 0,
+        },
+    )?
+    .as_ref())
+}
+
+/// Exception handler installed by [`loop_exception_stream_with_locals`]
+#[pyclass]
+struct PyExceptionForwarder {
+    tx: mpsc::UnboundedSender<PyObject>,
+    previous: Option<PyObject>,
+}
+
+#[pymethods]
+impl PyExceptionForwarder {
+    #[args(event_loop, context)]
+    fn __call__(&self, event_loop: &PyAny, context: &PyAny) -> PyResult<()> {
+        // The stream may have been dropped already, in which case the handler only chains
+        if self.tx.unbounded_send(context.into()).is_err() {
+            bridge_debug!("loop_exception_stream: stream dropped, only chaining");
+        }
+
+        match &self.previous {
+            Some(previous) => previous.call1(event_loop.py(), (event_loop, context))?,
+            None => event_loop
+                .call_method1("default_exception_handler", (context,))?
+                .into(),
+        };
+
+        Ok(())
+    }
+}
+
+/// Get a Rust Stream of the exceptions reported to the event loop's exception handler
+///
+/// This installs an exception handler on the event loop in `locals` with
+/// `loop.set_exception_handler`. Every context dict passed to the handler (i.e. for
+/// `Task exception was never retrieved`) is yielded by the stream before being forwarded to the
+/// previously installed handler, or to `loop.default_exception_handler` if there was none, so
+/// existing logging keeps working.
+///
+/// The stream is unbounded, so it should be polled continuously. Dropping it does not uninstall the
+/// handler, since another handler may have been chained on top of it in the meantime; the handler
+/// keeps forwarding to the previous one and stops sending context dicts.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `locals` - The task locals holding the event loop to be monitored
+pub fn loop_exception_stream_with_locals(
+    py: Python,
+    locals: &TaskLocals,
+) -> PyResult<impl Stream<Item = PyObject> + Send> {
+    let event_loop = locals.event_loop(py);
+    let previous = event_loop.call_method0("get_exception_handler")?;
+    let (tx, rx) = mpsc::unbounded();
+
+    event_loop.call_method1(
+        "set_exception_handler",
+        (PyExceptionForwarder {
+            tx,
+            previous: if previous.is_none() {
+                None
+            } else {
+                Some(previous.into())
+            },
+        },),
+    )?;
+
+    Ok(rx)
+}
+
+struct CompletionSignal {
+    done: AtomicBool,
+    waker: AtomicWaker,
+    err: Mutex<Option<PyErr>>,
+}
+
+impl CompletionSignal {
+    fn complete(&self, err: Option<PyErr>) {
+        if self.done.load(Ordering::Acquire) {
+            return;
+        }
+
+        *self.err.lock().unwrap() = err;
+        self.done.store(true, Ordering::Release);
+        self.waker.wake();
+    }
+}
+
+/// Schedules the awaitable on the event loop, then registers itself as the Task's done callback.
+///
+/// Using a single object for both steps and only recording the error (if any) keeps the
+/// bookkeeping down to one allocation on each side of the bridge.
+#[pyclass]
+struct PyTaskSignal {
+    awaitable: Option<PyObject>,
+    signal: Arc<CompletionSignal>,
+}
+
+#[pymethods]
+impl PyTaskSignal {
+    #[args(task = "None")]
+    pub fn __call__(slf: &PyCell<Self>, task: Option<&PyAny>) -> PyResult<()> {
+        let py = slf.py();
+
+        match task {
+            None => {
+                let awaitable = slf.borrow_mut().awaitable.take();
+
+                if let Some(awaitable) = awaitable {
+                    if let Err(e) = ensure_future(py, awaitable.as_ref(py)).and_then(|task| {
+                        bridge_debug!(
+                            "into_future_ignore_result: awaitable {:#x} scheduled as task {:#x}",
+                            py_id(awaitable.as_ref(py)),
+                            py_id(task)
+                        );
+                        task.call_method1("add_done_callback", (slf,))
+                    }) {
+                        slf.borrow().signal.complete(Some(e));
+                    }
+                }
+            }
+            Some(task) => {
+                // the result itself is discarded, only a raised exception is kept
+                let err = task.call_method0("result").err();
+                bridge_debug!(
+                    "into_future_ignore_result: task {:#x} {}",
+                    py_id(task),
+                    if err.is_some() { "failed" } else { "completed" }
+                );
+                slf.borrow().signal.complete(err);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for PyTaskSignal {
+    fn drop(&mut self) {
+        // The event loop dropped the callback without ever completing the Task
+        self.signal
+            .complete(Some(CancelledError::new_err("Task was dropped")));
+    }
+}
+
+/// Convert a Python `awaitable` into a Rust Future that only reports whether it succeeded
+///
+/// This is a lighter alternative to [`into_future_with_locals`] for hot paths that await many
+/// small awaitables (like `asyncio.sleep(0)`) and have no use for their results. Instead of a
+/// oneshot channel carrying the `PyObject` result, completion is signalled through an
+/// `AtomicWaker` and a flag, and the awaitable is not validated up front unless strict mode is
+/// enabled (see [`set_strict`]). Otherwise, an invalid awaitable is reported through the returned
+/// future instead.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for the provided awaitable
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_ignore_result_with_locals(
+    locals: &TaskLocals,
+    awaitable: &PyAny,
+) -> PyResult<impl Future<Output = PyResult<()>> + Send> {
+    let py = awaitable.py();
+    if strict_enabled() {
+        normalize_awaitable(py, awaitable)?;
+    }
+
+    let signal = Arc::new(CompletionSignal {
+        done: AtomicBool::new(false),
+        waker: AtomicWaker::new(),
+        err: Mutex::new(None),
+    });
+
+    call_soon_threadsafe(
+        locals.event_loop(py),
+        locals.context(py),
+        (PyTaskSignal {
+            awaitable: Some(awaitable.into()),
+            signal: Arc::clone(&signal),
+        },),
+    )?;
+
+    Ok(future::poll_fn(move |cx| {
+        if !signal.done.load(Ordering::Acquire) {
+            signal.waker.register(cx.waker());
+
+            if !signal.done.load(Ordering::Acquire) {
+                return Poll::Pending;
+            }
+        }
+
+        match signal.err.lock().unwrap().take() {
+            Some(e) => Poll::Ready(Err(e)),
+            None => Poll::Ready(Ok(())),
+        }
+    }))
+}
+
+/// Convert a Python `awaitable` into a Rust Future
+///
+/// This function converts the `awaitable` into a Python Task using `run_coroutine_threadsafe`. A
+/// completion handler sends the result of this Task through a
+/// `futures::channel::oneshot::Sender<PyResult<PyObject>>` and the future returned by this function
+/// simply awaits the result through the `futures::channel::oneshot::Receiver<PyResult<PyObject>>`.
+///
+/// __This function will be removed in `v0.16`__
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop that the awaitable should be attached to
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// const PYTHON_CODE: &'static str = r#"
+/// import asyncio
+///
+/// async def py_sleep(duration):
+///     await asyncio.sleep(duration)
+/// "#;
+///
+/// async fn py_sleep(seconds: f32) -> PyResult<()> {
+///     let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+///         Ok(
+///             PyModule::from_code(
+///                 py,
+///                 PYTHON_CODE,
+///                 "test_into_future/test_mod.py",
+///                 "test_mod"
+///             )?
+///             .into()
+///         )
+///     })?;
+///
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::into_future_with_loop(
+///             pyo3_asyncio::get_running_loop(py)?,
+///             test_mod
+///                 .call_method1(py, "py_sleep", (seconds.into_py(py),))?
+///                 .as_ref(py),
+///         )
+///     })?
+///     .await?;
+///     Ok(())    
+/// }
+/// ```
+#[deprecated(
+    since = "0.15.0",
+    note = "Use pyo3_asyncio::into_future_with_locals instead"
+)]
+pub fn into_future_with_loop(event_loop: &PyAny, awaitable: &PyAny) -> PyResult<PyFuture> {
+    into_future_with_locals(
+        &TaskLocals::new(event_loop).copy_context(event_loop.py())?,
+        awaitable,
+    )
+}
+
+/// Cancel a Python task and wait for it to actually finish
+///
+/// `task.cancel()` only requests cancellation. The task may take a while to unwind (i.e. to run
+/// `finally` blocks or `async with` exits) before it is done. This function cancels `task` on its
+/// event loop and the returned future resolves once the task has finished unwinding or `timeout`
+/// has elapsed, whichever comes first. This mirrors the `task.cancel()` followed by `await task`
+/// pattern in Python.
+///
+/// The returned future resolves to `true` if the task is done, or `false` if it was still running
+/// when the timeout elapsed. The task's own result or exception is not propagated.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context that `task` is running on
+/// * `task` - The Python task or future to cancel
+/// * `timeout` - How long to wait for the task to finish, or `None` to wait indefinitely
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// async fn stop_worker(locals: pyo3_asyncio::TaskLocals, worker: PyObject) -> PyResult<()> {
+///     let stopped = Python::with_gil(|py| {
+///         pyo3_asyncio::cancel_and_wait_with_locals(
+///             &locals,
+///             worker.as_ref(py),
+///             Some(Duration::from_secs(5)),
+///         )
+///     })?
+///     .await?;
+///
+///     if !stopped {
+///         println!("worker is still cleaning up");
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub fn cancel_and_wait_with_locals(
+    locals: &TaskLocals,
+    task: &PyAny,
+    timeout: Option<Duration>,
+) -> PyResult<impl Future<Output = PyResult<bool>> + Send> {
+    let py = task.py();
+
+    call_soon_threadsafe(
+        locals.event_loop(py),
+        locals.context(py),
+        (task.getattr("cancel")?,),
+    )?;
+
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("timeout", timeout.map(|timeout| timeout.as_secs_f64()))?;
+    let wait = asyncio(py)?.call_method("wait", ((task,),), Some(kwargs))?;
+
+    let fut = into_future_with_locals(locals, wait)?;
+    let task = PyObject::from(task);
+
+    Ok(async move {
+        fut.await?;
+        Python::with_gil(|py| task.as_ref(py).call_method0("done")?.is_true())
+    })
+}
+
+/// Await the result of an existing Python Task without taking ownership of it
+///
+/// [`into_future_with_locals`] schedules an awaitable as a new Task that belongs to the returned
+/// [`PyFuture`], which cancels it when dropped. This function only observes `task`: a done
+/// callback is added on the Task's own event loop and its result (or exception) is sent back to
+/// the returned future. Dropping the returned future leaves the Task running, so any number of
+/// observers can wait on a Task that is shared with Python code or with other Rust tasks.
+///
+/// If the event loop stops before the Task is done, the returned future resolves to a
+/// `CancelledError`.
+///
+/// # Arguments
+/// * `task` - The `asyncio.Task` or `asyncio.Future` to observe
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// async fn wait_for_shared(task: PyObject) -> PyResult<PyObject> {
+///     // unlike into_future, giving up on the result here does not cancel the task
+///     Python::with_gil(|py| pyo3_asyncio::observe_task(task.as_ref(py)))?.await
+/// }
+/// ```
+pub fn observe_task(task: &PyAny) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send> {
+    let py = task.py();
+
+    if !asyncio(py)?.call_method1("isfuture", (task,))?.is_true()? {
+        return Err(PyTypeError::new_err(format!(
+            "observe_task expected an asyncio.Task or asyncio.Future, got {}",
+            task.get_type().name()?
+        )));
+    }
+
+    let (tx, rx) = oneshot::channel();
+
+    // add_done_callback isn't thread-safe, so it is called from the Task's event loop
+    call_soon_threadsafe(
+        task.call_method0("get_loop")?,
+        py.None().as_ref(py),
+        (
+            task.getattr("add_done_callback")?,
+            PyTaskCompleter {
+                tx: Some(tx),
+                abort: None,
+                handle: Default::default(),
+            },
+        ),
+    )?;
+
+    Ok(async move {
+        match rx.await {
+            Ok(result) => result,
+            Err(_) => Err(CancelledError::new_err(
+                "the event loop stopped before the task was done",
+            )),
+        }
+    })
+}
+
+/// Closes an abandoned `AsyncExitStack` from its event loop
+#[pyclass]
+struct PyExitStackCloser {
+    stack: PyObject,
+}
+
+#[pymethods]
+impl PyExitStackCloser {
+    fn __call__(&self, py: Python) -> PyResult<()> {
+        bridge_debug!(
+            "PyAsyncExitStack: closing exit stack {:#x}",
+            py_id(self.stack.as_ref(py))
+        );
+        ensure_future(py, self.stack.as_ref(py).call_method0("aclose")?)?;
+        Ok(())
+    }
+}
+
+/// A Python `contextlib.AsyncExitStack` managed from Rust
+///
+/// Async context managers entered with [`enter_async_context`](PyAsyncExitStack::enter_async_context)
+/// are exited in reverse order when the stack is closed with [`aclose`](PyAsyncExitStack::aclose),
+/// which makes it easy to manage a dynamic set of async resources. Entering and closing both run
+/// on the event loop in the stack's `TaskLocals`.
+///
+/// If the stack is dropped without being closed, `aclose()` is scheduled on the event loop so that
+/// the context managers are still exited. Like the cleanup of [`into_stream_with_locals`], this is
+/// best-effort: nothing is run if the event loop has already been closed.
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+/// use pyo3_asyncio::{PyAsyncExitStack, TaskLocals};
+///
+/// /// Open every connection, exiting the ones that were opened if any of them fails
+/// async fn open_all(locals: TaskLocals, connections: Vec<PyObject>) -> PyResult<()> {
+///     let stack = Python::with_gil(|py| PyAsyncExitStack::with_locals(py, locals))?;
+///
+///     for conn in connections {
+///         // an early return drops the stack, which exits the connections entered so far
+///         Python::with_gil(|py| stack.enter_async_context(conn.as_ref(py)))?.await?;
+///     }
+///
+///     // ... use the connections ...
+///
+///     Python::with_gil(|py| stack.aclose(py))?.await?;
+///     Ok(())
+/// }
+/// ```
+pub struct PyAsyncExitStack {
+    locals: TaskLocals,
+    stack: PyObject,
+    closed: bool,
+}
+
+impl PyAsyncExitStack {
+    /// Create an empty exit stack that enters and exits its context managers on the event loop in
+    /// `locals`
+    pub fn with_locals(py: Python, locals: TaskLocals) -> PyResult<Self> {
+        Ok(Self {
+            locals,
+            stack: py
+                .import("contextlib")?
+                .call_method0("AsyncExitStack")?
+                .into(),
+            closed: false,
+        })
+    }
+
+    /// Get a reference to the Python `AsyncExitStack`
+    pub fn stack<'p>(&self, py: Python<'p>) -> &'p PyAny {
+        self.stack.clone().into_ref(py)
+    }
+
+    /// Enter the async context manager `cm` and push its `__aexit__` onto the stack
+    ///
+    /// The returned future resolves to the value returned by `cm.__aenter__()`.
+    pub fn enter_async_context(&self, cm: &PyAny) -> PyResult<PyFuture> {
+        into_future_with_locals(
+            &self.locals,
+            self.stack(cm.py())
+                .call_method1("enter_async_context", (cm,))?,
+        )
+    }
+
+    /// Exit every context manager on the stack in reverse order
+    ///
+    /// Exceptions raised by the context managers while exiting are returned by the future.
+    pub fn aclose(mut self, py: Python) -> PyResult<PyFuture> {
+        let fut = into_future_with_locals(&self.locals, self.stack(py).call_method0("aclose")?)?;
+        self.closed = true;
+        Ok(fut)
+    }
+}
+
+impl Drop for PyAsyncExitStack {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        Python::with_gil(|py| {
+            let closer = PyExitStackCloser {
+                stack: self.stack.clone_ref(py),
+            };
+
+            if let Err(e) = call_soon_threadsafe(
+                self.locals.event_loop(py),
+                self.locals.context(py),
+                (closer,),
+            ) {
+                bridge_debug!("PyAsyncExitStack: unable to close exit stack: {}", e);
+            }
+        });
+    }
+}
+
+/// Run `body` inside the Python async context manager `cm`, like `async with cm as value: ...`
+///
+/// Nothing happens until the returned future is first polled. `cm.__aenter__()` is then awaited on
+/// the event loop in `locals` and `body` is called with the value it returns. Once the future
+/// returned by `body` completes, `cm.__aexit__()` is awaited with the exception that `body` failed
+/// with, or with `(None, None, None)` if it succeeded. Just like in Python:
+/// * if `__aexit__` returns a true value, the exception is suppressed and the returned future
+///   resolves to `Ok(None)`. Otherwise the exception is propagated.
+/// * if `__aexit__` raises, its exception replaces the one raised by `body`.
+///
+/// `__aexit__` is not called if the returned future is dropped while `body` is running. Use a
+/// [`PyAsyncExitStack`] to exit the context manager in that case too.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for `__aenter__` and `__aexit__`
+/// * `cm` - The Python async context manager
+/// * `body` - Called with the value returned by `cm.__aenter__()`
+pub fn async_with_with_locals<B, Fut, T>(
+    locals: &TaskLocals,
+    cm: &PyAny,
+    body: B,
+) -> PyResult<impl Future<Output = PyResult<Option<T>>> + Send>
+where
+    B: FnOnce(PyObject) -> Fut + Send,
+    Fut: Future<Output = PyResult<T>> + Send,
+    T: Send,
+{
+    let locals = locals.clone();
+    let cm: PyObject = cm.into();
+
+    Ok(async move {
+        // like `async with`, the special methods are looked up on the type
+        let (enter, aexit) = Python::with_gil(|py| -> PyResult<_> {
+            let cm_type = cm.as_ref(py).get_type();
+            let aenter = cm_type.getattr("__aenter__")?;
+            let aexit: PyObject = cm_type.getattr("__aexit__")?.into();
+            Ok((
+                into_future_with_locals(&locals, aenter.call1((&cm,))?)?,
+                aexit,
+            ))
+        })?;
+        let result = body(enter.await?).await;
+
+        let exit = Python::with_gil(|py| {
+            let exc_info = match &result {
+                Ok(_) => (py.None(), py.None(), py.None()),
+                Err(e) => (
+                    e.get_type(py).into(),
+                    e.value(py).into(),
+                    e.traceback(py).into_py(py),
+                ),
+            };
+            into_future_with_locals(
+                &locals,
+                aexit
+                    .as_ref(py)
+                    .call1((&cm, exc_info.0, exc_info.1, exc_info.2))?,
+            )
+        })?;
+        let suppress = exit.await?;
+
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) => {
+                if Python::with_gil(|py| suppress.as_ref(py).is_true())? {
+                    Ok(None)
+                } else {
+                    Err(e)
+                }
+            }
+        }
+    })
+}
+
+type ExecutorJob = dyn FnOnce() + Send;
+
+/// Runs a Rust job on a thread of the event loop's executor with the GIL released
+#[pyclass]
+struct PyExecutorJob {
+    job: Option<Box<ExecutorJob>>,
+}
+
+#[pymethods]
+impl PyExecutorJob {
+    fn __call__(&mut self, py: Python) {
+        if let Some(job) = self.job.take() {
+            py.allow_threads(job);
+        }
+    }
+}
+
+/// Run a Rust Future to completion on a thread of the Python event loop's default executor
+///
+/// The future is driven with `futures::executor::block_on` inside a job submitted with
+/// `loop.run_in_executor(None, ...)` on the event loop in `locals`, so it runs on the same
+/// Python-managed thread pool as `asyncio.to_thread` instead of a Rust runtime's threads. This
+/// helps embedders that account for every thread through Python's executor. The GIL is released
+/// while the future runs, and a panic in `fut` resolves the returned future to a
+/// [`RustPanic`](err::RustPanic) error.
+///
+/// Since the future occupies an executor thread until it completes, it should mostly do blocking or
+/// CPU-bound work. It can't rely on a Rust runtime (i.e. `tokio::time::sleep`) either, since none
+/// is running on the executor threads. Dropping the returned future does not stop the job. If the
+/// job can't run (i.e. the event loop or its executor was shut down first), the returned future
+/// resolves to a `CancelledError`.
+///
+/// # Arguments
+/// * `locals` - The Python event loop whose executor runs the future
+/// * `fut` - The Rust future to run
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+/// use pyo3_asyncio::TaskLocals;
+///
+/// /// Hash `data` on one of Python's executor threads
+/// async fn checksum(locals: TaskLocals, data: Vec<u8>) -> PyResult<u64> {
+///     pyo3_asyncio::run_on_python_executor_with_locals(&locals, async move {
+///         data.iter().map(|b| *b as u64).sum::<u64>()
+///     })?
+///     .await
+/// }
+/// ```
+pub fn run_on_python_executor_with_locals<F, T>(
+    locals: &TaskLocals,
+    fut: F,
+) -> PyResult<impl Future<Output = PyResult<T>> + Send>
+where
+    F: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+
+    let job = PyExecutorJob {
+        job: Some(Box::new(move || {
+            let result = futures::executor::block_on(AssertUnwindSafe(fut).catch_unwind());
+            let _ = tx.send(result);
+        })),
+    };
+
+    Python::with_gil(|py| {
+        let event_loop = locals.event_loop(py);
+
+        // run_in_executor isn't thread-safe, so it is called from the event loop
+        call_soon_threadsafe(
+            event_loop,
+            locals.context(py),
+            (event_loop.getattr("run_in_executor")?, py.None(), job),
+        )
+    })?;
+
+    Ok(async move {
+        match rx.await {
+            Ok(Ok(output)) => Ok(output),
+            Ok(Err(payload)) => Err(err::RustPanic::new_err(err::panic_err_message(
+                "rust future panicked",
+                err::panic_message(&*payload),
+            ))),
+            Err(_) => Err(CancelledError::new_err(
+                "the Python executor did not run the rust future",
+            )),
+        }
+    })
+}
+
+#[derive(Default)]
+struct CancellationState {
+    cancelled: AtomicBool,
+    waiters: Mutex<CancellationWaiters>,
+}
+
+/// The wakers of the futures waiting on a [`CancellationToken`], keyed so that each future can
+/// remove its own waker when it is dropped
+#[derive(Default)]
+struct CancellationWaiters {
+    next_key: usize,
+    wakers: HashMap<usize, Waker>,
+}
+
+/// Waits until a [`CancellationToken`] is cancelled, see [`CancellationToken::cancelled`]
+struct Cancelled {
+    state: Arc<CancellationState>,
+    key: Option<usize>,
+}
+
+impl Future for Cancelled {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let this = &mut *self;
+
+        if this.state.cancelled.load(Ordering::Acquire) {
+            return Poll::Ready(());
+        }
+
+        let mut waiters = this.state.waiters.lock().unwrap();
+        match this.key.and_then(|key| waiters.wakers.get_mut(&key)) {
+            Some(waker) => {
+                if !waker.will_wake(cx.waker()) {
+                    *waker = cx.waker().clone();
+                }
+            }
+            None => {
+                let key = waiters.next_key;
+                waiters.next_key = waiters.next_key.wrapping_add(1);
+                waiters.wakers.insert(key, cx.waker().clone());
+                this.key = Some(key);
+            }
+        }
+        drop(waiters);
+
+        // the token may have been cancelled before the waker was registered
+        if this.state.cancelled.load(Ordering::Acquire) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Cancelled {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            self.state.waiters.lock().unwrap().wakers.remove(&key);
+        }
+    }
+}
+
+/// A runtime-agnostic token to cancel work from any thread
+///
+/// Clones share the same state, so cancelling any of them cancels all of them. Once cancelled, a
+/// token stays cancelled.
+///
+/// # Examples
+///
+/// ```
+/// use pyo3_asyncio::CancellationToken;
+///
+/// let token = CancellationToken::new();
+/// let child = token.clone();
+///
+/// std::thread::spawn(move || token.cancel()).join().unwrap();
+///
+/// futures::executor::block_on(child.cancelled());
+/// assert!(child.is_cancelled());
+/// ```
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    state: Arc<CancellationState>,
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the token, waking every task waiting on [`cancelled`](CancellationToken::cancelled)
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::Release);
+
+        let wakers = std::mem::take(&mut self.state.waiters.lock().unwrap().wakers);
+        for (_, waker) in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Check whether the token has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Wait until the token is cancelled
+    ///
+    /// Dropping the returned future unregisters it from the token, so a long-lived token can be
+    /// waited on any number of times.
+    pub fn cancelled(&self) -> impl Future<Output = ()> + Send + 'static {
+        Cancelled {
+            state: Arc::clone(&self.state),
+            key: None,
+        }
+    }
+}
+
+struct LimiterState {
+    available: usize,
+    waiters: Vec<Waker>,
+}
+
+/// Releases its slot of a [`RateLimitedBridge`] when dropped
+struct RateLimitPermit {
+    state: Arc<Mutex<LimiterState>>,
+}
+
+impl Drop for RateLimitPermit {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.available += 1;
+
+        // every waiter gets a chance at the slot since some of them may have been dropped since
+        // they registered their waker
+        for waker in state.waiters.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// Limits how many Python awaitables are in flight at once across every call site sharing it
+///
+/// Clones share the same slots, so a single bridge can be handed out to the whole application to
+/// rate-limit all of its outbound Python calls in one place. Awaitables passed to
+/// [`into_future_with_locals`](RateLimitedBridge::into_future_with_locals) are only scheduled on
+/// the event loop once a slot is free, and the slot is given back when the resulting future
+/// completes, fails or is dropped. Waiting callers are not served in any particular order.
+///
+/// See `tokio::into_future_rate_limited` or `async_std::into_future_rate_limited` for versions
+/// that use the task locals of the current runtime.
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+/// use pyo3_asyncio::{RateLimitedBridge, TaskLocals};
+///
+/// /// Call the API from Python, with at most 8 requests in flight across the whole application
+/// async fn fetch(
+///     bridge: &RateLimitedBridge,
+///     locals: &TaskLocals,
+///     request: PyObject,
+/// ) -> PyResult<PyObject> {
+///     Python::with_gil(|py| bridge.into_future_with_locals(locals, request.as_ref(py)))?.await
+/// }
+///
+/// let bridge = RateLimitedBridge::new(8);
+/// assert_eq!(bridge.available_permits(), 8);
+/// ```
+#[derive(Clone)]
+pub struct RateLimitedBridge {
+    state: Arc<Mutex<LimiterState>>,
+}
+
+impl fmt::Debug for RateLimitedBridge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RateLimitedBridge")
+            .field("available_permits", &self.available_permits())
+            .finish()
+    }
+}
+
+impl RateLimitedBridge {
+    /// Create a bridge that lets at most `max_concurrent` awaitables run at once
+    ///
+    /// # Panics
+    /// Panics if `max_concurrent` is 0, since no awaitable could ever be scheduled.
+    pub fn new(max_concurrent: usize) -> Self {
+        assert!(
+            max_concurrent > 0,
+            "the concurrency limit must be greater than 0"
+        );
+
+        Self {
+            state: Arc::new(Mutex::new(LimiterState {
+                available: max_concurrent,
+                waiters: Vec::new(),
+            })),
+        }
+    }
+
+    /// The number of awaitables that could be scheduled right now without waiting
+    pub fn available_permits(&self) -> usize {
+        self.state.lock().unwrap().available
+    }
+
+    fn acquire(&self) -> impl Future<Output = RateLimitPermit> + Send + 'static {
+        let state = Arc::clone(&self.state);
+
+        future::poll_fn(move |cx| {
+            let mut guard = state.lock().unwrap();
+
+            if guard.available > 0 {
+                guard.available -= 1;
+                drop(guard);
+
+                Poll::Ready(RateLimitPermit {
+                    state: Arc::clone(&state),
+                })
+            } else {
+                if !guard
+                    .waiters
+                    .iter()
+                    .any(|waker| waker.will_wake(cx.waker()))
+                {
+                    guard.waiters.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+        })
+    }
+
+    /// Convert a Python `awaitable` into a Rust Future once a slot of the bridge is free
+    ///
+    /// The awaitable is not scheduled on the event loop until the returned future is polled and
+    /// a slot is available. It then behaves like [`into_future_with_locals`], and its slot is
+    /// released as soon as the awaitable completes or errors. Dropping the future releases the
+    /// slot as well, and cancels the awaitable if it was already scheduled.
+    ///
+    /// # Arguments
+    /// * `locals` - The Python event loop and context to be used for the provided awaitable
+    /// * `awaitable` - The Python `awaitable` to be converted
+    pub fn into_future_with_locals(
+        &self,
+        locals: &TaskLocals,
+        awaitable: &PyAny,
+    ) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send> {
+        let locals = locals.clone();
+        let awaitable = PyObject::from(normalize_awaitable(awaitable.py(), awaitable)?);
+        let acquire = self.acquire();
+
+        Ok(async move {
+            let _permit = acquire.await;
+
+            Python::with_gil(|py| into_future_with_locals(&locals, awaitable.as_ref(py)))?.await
+        })
+    }
+}
+
+/// How [`supervise_with_locals`] restarts a failing coroutine
+///
+/// By default a coroutine is restarted up to 3 times, waiting 100ms before the first restart and
+/// doubling the wait after each one, up to 10s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestartPolicy {
+    max_restarts: usize,
+    backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 3,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Restart a failing coroutine at most `max_restarts` times
+    pub fn new(max_restarts: usize) -> Self {
+        Self {
+            max_restarts,
+            ..Self::default()
+        }
+    }
+
+    /// Wait `backoff` before the first restart, doubling the wait after every restart without
+    /// exceeding `max_backoff`. A zero `backoff` restarts immediately.
+    pub fn with_backoff(self, backoff: Duration, max_backoff: Duration) -> Self {
+        Self {
+            backoff,
+            max_backoff,
+            ..self
+        }
+    }
+
+    /// The maximum number of restarts
+    pub fn max_restarts(&self) -> usize {
+        self.max_restarts
+    }
+
+    /// The wait before the restart that follows `restarts` previous restarts
+    fn backoff(&self, restarts: usize) -> Duration {
+        let mut backoff = self.backoff;
+
+        for _ in 0..restarts {
+            if backoff >= self.max_backoff {
+                break;
+            }
+            backoff *= 2;
+        }
+
+        backoff.min(self.max_backoff)
+    }
+}
+
+/// Await the coroutine produced by `factory`, restarting it with a fresh coroutine whenever it
+/// fails
+///
+/// The coroutine is bridged with [`into_future_with_locals`]. If it raises (or `factory` itself
+/// fails), `factory` is called again for a new coroutine according to `policy`, after waiting out
+/// the policy's backoff with `asyncio.sleep` on the event loop. The returned future resolves to the
+/// result of the first coroutine that succeeds, or to the last error once `policy` runs out of
+/// restarts.
+///
+/// Cancellation is never retried: if the coroutine is cancelled, its `CancelledError` is returned
+/// immediately. Dropping the returned future cancels the coroutine that is currently running.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for the coroutines
+/// * `factory` - Produces a new coroutine (or any other awaitable) for every attempt
+/// * `policy` - How often and how quickly to restart
+pub async fn supervise_with_locals<F>(
+    locals: TaskLocals,
+    mut factory: F,
+    policy: RestartPolicy,
+) -> PyResult<PyObject>
+where
+    F: FnMut(Python) -> PyResult<PyObject> + Send + 'static,
+{
+    let mut restarts = 0;
+
+    loop {
+        let attempt =
+            Python::with_gil(|py| into_future_with_locals(&locals, factory(py)?.as_ref(py)));
+        let result = match attempt {
+            Ok(attempt) => attempt.await,
+            Err(e) => Err(e),
+        };
+
+        let e = match result {
+            Ok(item) => return Ok(item),
+            Err(e) => e,
+        };
+
+        if restarts >= policy.max_restarts
+            || Python::with_gil(|py| e.is_instance_of::<CancelledError>(py))
+        {
+            return Err(e);
+        }
+
+        let backoff = policy.backoff(restarts);
+        restarts += 1;
+        bridge_debug!(
+            "supervise: restarting after failure ({}/{}) in {:?}: {}",
+            restarts,
+            policy.max_restarts,
+            backoff,
+            e
+        );
+
+        if backoff > Duration::from_secs(0) {
+            Python::with_gil(|py| {
+                into_future_with_locals(
+                    &locals,
+                    asyncio(py)?.call_method1("sleep", (backoff.as_secs_f64(),))?,
+                )
+            })?
+            .await?;
+        }
+    }
+}
+
+/// Await many Python awaitables with at most `limit` of them in flight at once
+///
+/// Awaitables are pulled from `awaitables` lazily and only converted with
+/// [`into_future_with_locals`] (and therefore scheduled on the event loop) once one of the `limit`
+/// slots is free, so a large batch of coroutines never floods the event loop. Each result is passed
+/// to `f` as soon as it completes, which means results are not necessarily processed in the order
+/// of `awaitables`.
+///
+/// Awaitables that fail to convert are reported to `f` like any other error. If `f` returns an
+/// error, no more awaitables are scheduled and the error is returned. Awaitables that are already
+/// in flight at that point are no longer awaited, but they keep running on the event loop.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for the awaitables
+/// * `awaitables` - The Python awaitables to run
+/// * `limit` - The maximum number of awaitables in flight, must be greater than 0
+/// * `f` - Called with the result of each awaitable as it completes
+pub fn for_each_concurrent_with_locals<I, F>(
+    locals: TaskLocals,
+    awaitables: I,
+    limit: usize,
+    mut f: F,
+) -> impl Future<Output = PyResult<()>> + Send
+where
+    I: IntoIterator<Item = PyObject>,
+    I::IntoIter: Send,
+    F: FnMut(PyResult<PyObject>) -> PyResult<()> + Send,
+{
+    let awaitables = awaitables.into_iter();
+
+    async move {
+        if limit == 0 {
+            return Err(PyValueError::new_err(
+                "the concurrency limit must be greater than 0",
+            ));
+        }
+
+        stream::iter(awaitables)
+            .map(|awaitable| {
+                let locals = locals.clone();
+                async move {
+                    Python::with_gil(|py| into_future_with_locals(&locals, awaitable.as_ref(py)))?
+                        .await
+                }
+            })
+            .buffer_unordered(limit)
+            .map(Ok)
+            .try_for_each(|result| future::ready(f(result)))
+            .await
+    }
+}
+
+/// Block the current thread until a Python `awaitable` completes
+///
+/// The `awaitable` is converted with [`into_future_with_locals`] and the current thread blocks on
+/// the resulting future. The GIL is released while blocking so the event loop, which must be
+/// running on a different thread, can make progress. This makes it possible to wait on a coroutine
+/// from a synchronous `#[pyfunction]` that was called off of the event loop's thread (i.e. from
+/// `loop.run_in_executor` or a `threading.Thread`).
+///
+/// > This function must not be called from the event loop's own thread since the loop can't run
+/// > while its thread is blocked. Doing so would deadlock, so a `RuntimeError` is raised instead.
+/// > See [`run_in_nested_loop`] for a way out of this situation.
 ///
 /// # Arguments
+/// * `py` - The current PyO3 GIL guard
 /// * `locals` - The Python event loop and context to be used for the provided awaitable
-/// * `awaitable` - The Python `awaitable` to be converted
+/// * `awaitable` - The Python `awaitable` to wait on
+pub fn block_on_awaitable_with_locals(
+    py: Python,
+    locals: &TaskLocals,
+    awaitable: &PyAny,
+) -> PyResult<PyObject> {
+    if let Ok(running_loop) = get_running_loop(py) {
+        if running_loop.is(locals.event_loop(py)) {
+            return Err(PyRuntimeError::new_err(
+                "cannot block on an awaitable from the thread running its event loop",
+            ));
+        }
+    }
+
+    let fut = into_future_with_locals(locals, awaitable)?;
+
+    py.allow_threads(move || futures::executor::block_on(fut))
+}
+
+/// Drive a Python `awaitable` to completion on a temporary child event loop
+///
+/// This is an escape hatch for synchronous Rust code that needs the result of a coroutine but was
+/// called from the thread that is already running an event loop, where
+/// [`block_on_awaitable_with_locals`] would deadlock. A new event loop is created, the thread's
+/// running loop is cleared so that asyncio allows the child loop to run, and the `awaitable` is run
+/// with `run_until_complete`. Afterwards, the child loop's async generators are shut down, the loop
+/// is closed and the previous running loop is restored, even if the `awaitable` raised.
+///
+/// While the `awaitable` runs, `asyncio.get_running_loop()` returns the child loop, so Rust
+/// conversions like `future_into_py` performed inside of it will target the child loop as well.
+///
+/// > __Use this sparingly__. Nested event loops come with some serious hazards:
+/// >
+/// > - The outer loop is suspended until the `awaitable` completes, so none of its tasks, timers or
+/// >   callbacks make progress in the meantime. Awaiting anything that depends on the outer loop
+/// >   (a future or task created on it, a lock held by one of its tasks, etc.) will deadlock.
+/// > - Futures and tasks are bound to the loop they were created on, so the `awaitable` should be a
+/// >   fresh coroutine rather than a future belonging to the outer loop.
+/// > - Code that is not expecting re-entrancy may observe the outer loop in an inconsistent state
+/// >   when control returns to it.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `awaitable` - The Python `awaitable` to run on the child loop
 ///
 /// # Examples
 ///
 /// ```
-/// use std::time::Duration;
+/// use pyo3::{prelude::*, wrap_pyfunction};
 ///
-/// use pyo3::prelude::*;
+/// /// Called synchronously from a coroutine running on the outer loop
+/// #[pyfunction]
+/// fn answer(py: Python) -> PyResult<PyObject> {
+///     let coro = py.import("asyncio")?.call_method1("sleep", (0, 42))?;
+///     pyo3_asyncio::run_in_nested_loop(py, coro)
+/// }
 ///
-/// const PYTHON_CODE: &'static str = r#"
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let test_mod = PyModule::from_code(
+///         py,
+///         r#"
 /// import asyncio
 ///
-/// async def py_sleep(duration):
-///     await asyncio.sleep(duration)
-/// "#;
+/// async def main(answer):
+///     outer = asyncio.get_running_loop()
+///     assert answer() == 42
+///     assert asyncio.get_running_loop() is outer
+///     return True
 ///
-/// async fn py_sleep(seconds: f32) -> PyResult<()> {
-///     let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
-///         Ok(
-///             PyModule::from_code(
-///                 py,
-///                 PYTHON_CODE,
-///                 "test_into_future/test_mod.py",
-///                 "test_mod"
-///             )?
-///             .into()
-///         )
-///     })?;
+/// def run(answer):
+///     return asyncio.run(main(answer))
+/// "#,
+///         "nested_loop.py",
+///         "nested_loop",
+///     )?;
 ///
-///     Python::with_gil(|py| {
-///         pyo3_asyncio::into_future_with_loop(
-///             pyo3_asyncio::get_running_loop(py)?,
-///             test_mod
-///                 .call_method1(py, "py_sleep", (seconds.into_py(py),))?
-///                 .as_ref(py),
-///         )
-///     })?
-///     .await?;
-///     Ok(())    
-/// }
+///     let answer = wrap_pyfunction!(answer, test_mod)?;
+///     assert!(test_mod.call_method1("run", (answer,))?.extract::<bool>()?);
+///     Ok(())
+/// })
+/// .unwrap();
 /// ```
-pub fn into_future_with_locals(
-    locals: &TaskLocals,
-    awaitable: &PyAny,
-) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send> {
-    let py = awaitable.py();
-    let (tx, rx) = oneshot::channel();
+pub fn run_in_nested_loop(py: Python, awaitable: &PyAny) -> PyResult<PyObject> {
+    let asyncio = asyncio(py)?;
+    let events = asyncio.getattr("events")?;
 
-    call_soon_threadsafe(
-        locals.event_loop(py),
-        locals.context(py),
-        (PyEnsureFuture {
-            awaitable: awaitable.into(),
-            tx: Some(tx),
-        },),
-    )?;
+    let outer_loop = events.call_method0("_get_running_loop")?;
+    let child_loop = asyncio.call_method0("new_event_loop")?;
 
-    Ok(async move {
-        match rx.await {
-            Ok(item) => item,
-            Err(_) => Python::with_gil(|py| {
-                Err(PyErr::from_value(
-                    asyncio(py)?.call_method0("CancelledError")?,
-                ))
-            }),
-        }
-    })
+    events.call_method1("_set_running_loop", (py.None(),))?;
+
+    let result = child_loop.call_method1("run_until_complete", (awaitable,));
+
+    let cleanup = child_loop
+        .call_method0("shutdown_asyncgens")
+        .and_then(|shutdown| child_loop.call_method1("run_until_complete", (shutdown,)))
+        .and_then(|_| child_loop.call_method0("close"));
+
+    events.call_method1("_set_running_loop", (outer_loop,))?;
+
+    let result = result?.into();
+    cleanup?;
+
+    Ok(result)
 }
 
-/// Convert a Python `awaitable` into a Rust Future
+/// What [`run_forever_with_handler`] should do about an exception that escaped the event loop
+#[derive(Debug)]
+pub enum LoopControl {
+    /// Call `run_forever` on the event loop again
+    Resume,
+    /// Return `Ok(())` without restarting the event loop
+    Stop,
+    /// Return the error to the caller
+    Propagate(PyErr),
+}
+
+/// Run the event loop until `stop` is called, returning `Ok(())` on `KeyboardInterrupt`
 ///
-/// This function converts the `awaitable` into a Python Task using `run_coroutine_threadsafe`. A
-/// completion handler sends the result of this Task through a
-/// `futures::channel::oneshot::Sender<PyResult<PyObject>>` and the future returned by this function
-/// simply awaits the result through the `futures::channel::oneshot::Receiver<PyResult<PyObject>>`.
+/// Any other exception that escapes the event loop is returned. Use [`run_forever_with_handler`]
+/// to recover from them instead.
 ///
-/// __This function will be removed in `v0.16`__
+/// # Arguments
+/// * `event_loop` - The Python event loop to run
+pub fn run_forever(event_loop: &PyAny) -> PyResult<()> {
+    run_forever_with_handler(event_loop, |_py, e| LoopControl::Propagate(e))
+}
+
+/// Run the event loop until `stop` is called, letting `on_error` decide what happens when an
+/// exception escapes the event loop
+///
+/// Exceptions raised by callbacks and tasks are normally reported to the loop's exception handler,
+/// but `SystemExit` and errors raised by the exception handler itself interrupt `run_forever`.
+/// `on_error` is called with each of them and can resume the event loop, stop it or propagate the
+/// error. `KeyboardInterrupt` always stops the event loop and returns `Ok(())`, just like
+/// [`run_forever`].
 ///
 /// # Arguments
-/// * `event_loop` - The Python event loop that the awaitable should be attached to
-/// * `awaitable` - The Python `awaitable` to be converted
+/// * `event_loop` - The Python event loop to run
+/// * `on_error` - Decides what to do with an exception that escaped the event loop
 ///
 /// # Examples
 ///
 /// ```
-/// use std::time::Duration;
-///
 /// use pyo3::prelude::*;
+/// use pyo3_asyncio::LoopControl;
 ///
-/// const PYTHON_CODE: &'static str = r#"
-/// import asyncio
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let test_mod = PyModule::from_code(
+///         py,
+///         r#"
+/// import sys
 ///
-/// async def py_sleep(duration):
-///     await asyncio.sleep(duration)
-/// "#;
+/// def boom():
+///     sys.exit(1)
+/// "#,
+///         "run_forever.py",
+///         "run_forever",
+///     )?;
 ///
-/// async fn py_sleep(seconds: f32) -> PyResult<()> {
-///     let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
-///         Ok(
-///             PyModule::from_code(
-///                 py,
-///                 PYTHON_CODE,
-///                 "test_into_future/test_mod.py",
-///                 "test_mod"
-///             )?
-///             .into()
-///         )
+///     let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+///     event_loop.call_method1("call_soon", (test_mod.getattr("boom")?,))?;
+///     event_loop.call_method1("call_soon", (test_mod.getattr("boom")?,))?;
+///
+///     let mut failures = 0;
+///     pyo3_asyncio::run_forever_with_handler(event_loop, |_py, e| {
+///         eprintln!("the event loop failed: {:?}", e);
+///         failures += 1;
+///
+///         // restart the event loop after the first failure
+///         if failures < 2 {
+///             LoopControl::Resume
+///         } else {
+///             LoopControl::Stop
+///         }
 ///     })?;
 ///
+///     assert_eq!(failures, 2);
+///     event_loop.call_method0("close")?;
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn run_forever_with_handler<F>(event_loop: &PyAny, mut on_error: F) -> PyResult<()>
+where
+    F: FnMut(Python, PyErr) -> LoopControl,
+{
+    let py = event_loop.py();
+
+    loop {
+        match event_loop.call_method0("run_forever") {
+            Ok(_) => return Ok(()),
+            Err(e) if e.is_instance_of::<PyKeyboardInterrupt>(py) => return Ok(()),
+            Err(e) => match on_error(py, e) {
+                LoopControl::Resume => continue,
+                LoopControl::Stop => return Ok(()),
+                LoopControl::Propagate(e) => return Err(e),
+            },
+        }
+    }
+}
+
+/// The number of [`run_forever_until_signal`] calls currently waiting for a signal
+#[cfg(all(feature = "signal", unix))]
+static SIGNAL_WAITERS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+#[cfg(all(feature = "signal", unix))]
+static SIGNAL_DEFAULTS: OnceCell<()> = OnceCell::new();
+
+/// Keep the default action (terminating the process) of the signals that had no handler once
+/// [`run_forever_until_signal`] returns
+///
+/// signal-hook never uninstalls its own low-level handler, so without this the signals would simply
+/// be ignored after the first call.
+#[cfg(all(feature = "signal", unix))]
+fn keep_signal_defaults(py: Python, signals: &[i32]) -> PyResult<()> {
+    SIGNAL_DEFAULTS
+        .get_or_try_init(|| {
+            let signal = py.import("signal")?;
+            let sig_dfl = signal.getattr("SIG_DFL")?;
+
+            for &sig in signals {
+                if !signal.call_method1("getsignal", (sig,))?.eq(sig_dfl)? {
+                    continue;
+                }
+
+                // Safety: only async-signal-safe operations are performed by the action
+                unsafe {
+                    signal_hook::low_level::register(sig, move || {
+                        if SIGNAL_WAITERS.load(Ordering::SeqCst) == 0 {
+                            let _ = signal_hook::low_level::emulate_default_handler(sig);
+                        }
+                    })?;
+                }
+            }
+
+            Ok(())
+        })
+        .map(|_| ())
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>signal</code></span>
+/// Run the event loop until `stop` is called or the process receives `SIGINT` or `SIGTERM`
+///
+/// This works like [`run_forever`], except that `SIGINT` (i.e. Ctrl-C) and `SIGTERM` are handled
+/// from Rust with [signal-hook](https://docs.rs/signal-hook): a background thread waits for them and
+/// schedules `loop.stop()` with `call_soon_threadsafe`. The event loop stops promptly whichever
+/// thread the signal is delivered to, and `SIGTERM`, which Python doesn't handle by default, stops
+/// it cleanly instead of killing the process. The handlers are unregistered before this function
+/// returns, so they don't stack up over repeated calls, and signals that had no handler before go
+/// back to terminating the process.
+///
+/// Python's own signal handlers are left in place and still run. Python handles `SIGINT` by raising
+/// `KeyboardInterrupt` on the main thread, which also stops the event loop and is handled like
+/// [`run_forever`] does, so either way this function returns `Ok(())`. Handlers installed from
+/// Python while the event loop is running (i.e. with `signal.signal` or `loop.add_signal_handler`)
+/// replace the ones installed here, so their signals are then handled by Python alone.
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop to run
+///
+/// # Examples
+///
+/// ```no_run
+/// use pyo3::prelude::*;
+///
+/// fn main() -> PyResult<()> {
+///     pyo3::prepare_freethreaded_python();
+///
 ///     Python::with_gil(|py| {
-///         pyo3_asyncio::into_future_with_loop(
-///             pyo3_asyncio::get_running_loop(py)?,
-///             test_mod
-///                 .call_method1(py, "py_sleep", (seconds.into_py(py),))?
-///                 .as_ref(py),
-///         )
-///     })?
-///     .await?;
-///     Ok(())    
+///         let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+///
+///         // serve until the daemon is stopped with Ctrl-C or `kill`
+///         pyo3_asyncio::run_forever_until_signal(event_loop)?;
+///
+///         pyo3_asyncio::try_close(event_loop)
+///     })
 /// }
 /// ```
-#[deprecated(
-    since = "0.15.0",
-    note = "Use pyo3_asyncio::into_future_with_locals instead"
-)]
-pub fn into_future_with_loop(
-    event_loop: &PyAny,
-    awaitable: &PyAny,
-) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send> {
-    into_future_with_locals(
-        &TaskLocals::new(event_loop).copy_context(event_loop.py())?,
-        awaitable,
-    )
+#[cfg(all(feature = "signal", unix))]
+pub fn run_forever_until_signal(event_loop: &PyAny) -> PyResult<()> {
+    use signal_hook::{
+        consts::{SIGINT, SIGTERM},
+        iterator::Signals,
+    };
+
+    let py = event_loop.py();
+    keep_signal_defaults(py, &[SIGINT, SIGTERM])?;
+
+    // counted before registering so that a signal can't terminate the process in between
+    SIGNAL_WAITERS.fetch_add(1, Ordering::SeqCst);
+    let mut signals = match Signals::new([SIGINT, SIGTERM]) {
+        Ok(signals) => signals,
+        Err(e) => {
+            SIGNAL_WAITERS.fetch_sub(1, Ordering::SeqCst);
+            return Err(e.into());
+        }
+    };
+    let handle = signals.handle();
+
+    let target: PyObject = event_loop.into();
+    let listener = std::thread::spawn(move || {
+        // the iterator ends without a signal once the handle is closed
+        if let Some(sig) = signals.forever().next() {
+            bridge_debug!("run_forever_until_signal: received signal {}", sig);
+
+            Python::with_gil(|py| {
+                let event_loop = target.as_ref(py);
+
+                if let Err(e) = event_loop.getattr("stop").and_then(|stop| {
+                    call_soon_threadsafe(event_loop, py.None().as_ref(py), (stop,))
+                }) {
+                    dump_err(py)(e);
+                }
+            });
+        }
+        // dropping the iterator unregisters the handlers
+    });
+
+    let result = run_forever(event_loop);
+
+    handle.close();
+    // the listener may be waiting for the GIL to stop the event loop
+    py.allow_threads(|| listener.join())
+        .expect("the signal listener panicked");
+    SIGNAL_WAITERS.fetch_sub(1, Ordering::SeqCst);
+
+    result
 }
 
 fn dump_err(py: Python<'_>) -> impl FnOnce(PyErr) + '_ {
@@ -0,0 +1,78 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use pyo3::prelude::*;
+
+static COMPLETION_GIL_ACQUISITIONS: AtomicU64 = AtomicU64::new(0);
+static COMPLETION_GIL_WAIT_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// A snapshot of the counters collected since startup (or since the last [`reset_stats`])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of times the GIL was acquired to resolve a bridged future
+    pub completion_gil_acquisitions: u64,
+    /// Total time spent blocked on the GIL before resolving bridged futures
+    pub completion_gil_wait: Duration,
+}
+
+impl Stats {
+    /// Average time a bridged future spent blocked on the GIL before being resolved
+    pub fn mean_completion_gil_wait(&self) -> Duration {
+        (self.completion_gil_wait.as_nanos() as u64)
+            .checked_div(self.completion_gil_acquisitions)
+            .map_or_else(|| Duration::from_secs(0), Duration::from_nanos)
+    }
+}
+
+/// Get a snapshot of the counters
+///
+/// The counters cover the completion path of the Rust futures converted into Python awaitables
+/// (i.e. with `future_into_py`): once a future is done, the GIL has to be acquired before its result
+/// can be handed back to the event loop. A high mean wait means that completions are held up by
+/// other threads holding the GIL, in which case resolving them in batches may pay off.
+///
+/// The counters are updated with relaxed atomics, so a snapshot taken while futures are completing
+/// may be slightly inconsistent.
+///
+/// # Examples
+///
+/// ```
+/// let stats = pyo3_asyncio::metrics::stats();
+///
+/// println!(
+///     "{} completions waited {:?} on average for the GIL",
+///     stats.completion_gil_acquisitions,
+///     stats.mean_completion_gil_wait()
+/// );
+/// ```
+pub fn stats() -> Stats {
+    Stats {
+        completion_gil_acquisitions: COMPLETION_GIL_ACQUISITIONS.load(Ordering::Relaxed),
+        completion_gil_wait: Duration::from_nanos(
+            COMPLETION_GIL_WAIT_NANOS.load(Ordering::Relaxed),
+        ),
+    }
+}
+
+/// Reset the counters, i.e. to measure a single phase of the workload
+pub fn reset_stats() {
+    COMPLETION_GIL_ACQUISITIONS.store(0, Ordering::Relaxed);
+    COMPLETION_GIL_WAIT_NANOS.store(0, Ordering::Relaxed);
+}
+
+pub(crate) fn with_completion_gil<F, T>(f: F) -> T
+where
+    F: for<'py> FnOnce(Python<'py>) -> T,
+{
+    let start = Instant::now();
+
+    Python::with_gil(move |py| {
+        let waited = start.elapsed();
+        COMPLETION_GIL_ACQUISITIONS.fetch_add(1, Ordering::Relaxed);
+        COMPLETION_GIL_WAIT_NANOS.fetch_add(waited.as_nanos() as u64, Ordering::Relaxed);
+
+        f(py)
+    })
+}
@@ -0,0 +1,66 @@
+//! The building blocks of [`into_future_with_locals`](crate::into_future_with_locals) are exposed
+//! here for custom executors and bespoke bridges. They skip the conveniences of the higher level
+//! functions (task locals, cancellation, the bridge registry), so prefer those whenever possible.
+
+use futures::channel::oneshot;
+use pyo3::prelude::*;
+
+use crate::{normalize_awaitable, PyEnsureFuture};
+
+/// Create the Python callback that schedules `awaitable` as a Task, along with the receiver of its
+/// result
+///
+/// Calling the callback with no arguments on the event loop's thread (i.e. with
+/// `loop.call_soon_threadsafe(callback)`) wraps the awaitable in a Task on the running event loop.
+/// Once the Task is done, its result (or exception) is sent through the returned receiver, which
+/// wakes up whatever task is polling it. This makes it possible to wire the completion into a
+/// custom future type instead of a [`PyFuture`](crate::PyFuture).
+///
+/// The callback should only be called once. If the Task can't be created (i.e. because the
+/// awaitable is invalid), the error is raised from the callback and the receiver resolves to
+/// `Canceled` once the callback is dropped. Dropping the receiver does not cancel the Task.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be scheduled
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let asyncio = py.import("asyncio")?;
+///     let event_loop = asyncio.call_method0("new_event_loop")?;
+///
+///     let (mut rx, callback) =
+///         pyo3_asyncio::raw::ensure_future_callback(asyncio.call_method1("sleep", (0, 42))?)?;
+///
+///     event_loop.call_method1("call_soon", (callback,))?;
+///     event_loop.call_method1("run_until_complete", (asyncio.call_method1("sleep", (0.01,))?,))?;
+///
+///     let result = rx.try_recv().unwrap().expect("the task should be done")?;
+///     assert_eq!(result.extract::<i32>(py)?, 42);
+///
+///     event_loop.call_method0("close")?;
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn ensure_future_callback(
+    awaitable: &PyAny,
+) -> PyResult<(oneshot::Receiver<PyResult<PyObject>>, PyObject)> {
+    let py = awaitable.py();
+    let awaitable = normalize_awaitable(py, awaitable)?;
+    let (tx, rx) = oneshot::channel();
+
+    let callback = PyEnsureFuture {
+        awaitable: awaitable.into(),
+        tx: Some(tx),
+        abort: None,
+        handle: Default::default(),
+        task_context: None,
+    };
+
+    Ok((rx, Py::new(py, callback)?.into_py(py)))
+}
@@ -0,0 +1,480 @@
+use std::{
+    cell::RefCell,
+    future::Future,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{channel::oneshot, prelude::*};
+use pin_project_lite::pin_project;
+use pyo3::prelude::*;
+use smol::LocalExecutor;
+
+use crate::{
+    generic::{self, ContextExt, JoinError, LocalContextExt, Runtime, SpawnLocalExt},
+    PyFuture, TaskLocals,
+};
+
+/// Why a detached smol task did not run to completion
+enum SmolJoinErr {
+    /// The task panicked
+    Panicked,
+    /// The executor was dropped before the task finished
+    Cancelled,
+}
+
+impl JoinError for SmolJoinErr {
+    fn is_panic(&self) -> bool {
+        matches!(self, SmolJoinErr::Panicked)
+    }
+}
+
+/// Resolves with the outcome of a detached smol task
+///
+/// Dropping a `smol::Task` cancels it and awaiting it resumes its panic, so the task is detached
+/// instead and reports how it ended through a channel.
+struct SmolJoinHandle(oneshot::Receiver<Result<(), SmolJoinErr>>);
+
+impl Future for SmolJoinHandle {
+    type Output = Result<(), SmolJoinErr>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.0
+            .poll_unpin(cx)
+            .map(|result| result.unwrap_or(Err(SmolJoinErr::Cancelled)))
+    }
+}
+
+/// Run `fut` to completion, catching any panic, and send how it ended to the join handle
+fn detachable<F>(fut: F) -> (impl Future<Output = ()>, SmolJoinHandle)
+where
+    F: Future<Output = ()>,
+{
+    let (tx, rx) = oneshot::channel();
+
+    let task = async move {
+        let result = AssertUnwindSafe(fut)
+            .catch_unwind()
+            .await
+            .map_err(|_| SmolJoinErr::Panicked);
+        let _ = tx.send(result);
+    };
+
+    (task, SmolJoinHandle(rx))
+}
+
+thread_local! {
+    static TASK_LOCALS: RefCell<Option<TaskLocals>> = const { RefCell::new(None) };
+    static LOCAL_EXECUTOR: LocalExecutor<'static> = const { LocalExecutor::new() };
+}
+
+pin_project! {
+    /// smol has no task-local storage, so the locals are swapped into a thread-local while the
+    /// future is being polled
+    struct Scoped<F> {
+        locals: Option<TaskLocals>,
+        #[pin]
+        fut: F,
+    }
+}
+
+/// Puts the locals back into their `Scoped` future, even if polling it panicked
+struct RestoreLocals<'a> {
+    locals: &'a mut Option<TaskLocals>,
+    previous: Option<TaskLocals>,
+}
+
+impl Drop for RestoreLocals<'_> {
+    fn drop(&mut self) {
+        *self.locals = TASK_LOCALS.with(|c| c.replace(self.previous.take()));
+    }
+}
+
+impl<F> Future for Scoped<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let previous = TASK_LOCALS.with(|c| c.replace(this.locals.take()));
+        let _restore = RestoreLocals {
+            locals: this.locals,
+            previous,
+        };
+
+        this.fut.poll(cx)
+    }
+}
+
+struct SmolRuntime;
+
+impl Runtime for SmolRuntime {
+    type JoinError = SmolJoinErr;
+    type JoinHandle = SmolJoinHandle;
+
+    fn spawn<F>(fut: F) -> Self::JoinHandle
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let (task, handle) = detachable(fut);
+        smol::spawn(task).detach();
+        handle
+    }
+}
+
+impl ContextExt for SmolRuntime {
+    fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+    where
+        F: Future<Output = R> + Send + 'static,
+    {
+        Box::pin(Scoped {
+            locals: Some(locals),
+            fut,
+        })
+    }
+
+    fn get_task_locals() -> Option<TaskLocals> {
+        TASK_LOCALS
+            .try_with(|c| c.borrow().clone())
+            .unwrap_or_default()
+    }
+}
+
+impl SpawnLocalExt for SmolRuntime {
+    fn spawn_local<F>(fut: F) -> Self::JoinHandle
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        let (task, handle) = detachable(fut);
+        LOCAL_EXECUTOR.with(|executor| executor.spawn(task).detach());
+        handle
+    }
+}
+
+impl LocalContextExt for SmolRuntime {
+    fn scope_local<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R>>>
+    where
+        F: Future<Output = R> + 'static,
+    {
+        Box::pin(Scoped {
+            locals: Some(locals),
+            fut,
+        })
+    }
+}
+
+/// Set the task local event loop for the given future
+pub async fn scope<F, R>(locals: TaskLocals, fut: F) -> R
+where
+    F: Future<Output = R> + Send + 'static,
+{
+    SmolRuntime::scope(locals, fut).await
+}
+
+/// Set the task local event loop for the given !Send future
+pub async fn scope_local<F, R>(locals: TaskLocals, fut: F) -> R
+where
+    F: Future<Output = R> + 'static,
+{
+    SmolRuntime::scope_local(locals, fut).await
+}
+
+/// Get the current event loop from either Python or Rust async task local context
+///
+/// This function first checks if the runtime has a task-local reference to the Python event loop.
+/// If not, it calls [`get_running_loop`](`crate::get_running_loop`) to get the event loop
+/// associated with the current OS thread.
+pub fn get_current_loop(py: Python) -> PyResult<&PyAny> {
+    generic::get_current_loop::<SmolRuntime>(py)
+}
+
+/// Either copy the task locals from the current task OR get the current running loop and
+/// contextvars from Python.
+pub fn get_current_locals(py: Python) -> PyResult<TaskLocals> {
+    generic::get_current_locals::<SmolRuntime>(py)
+}
+
+/// Run the event loop until the given Future completes
+///
+/// The event loop runs until the given future is complete.
+///
+/// After this function returns, the event loop can be resumed with [`run_until_complete`]
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop that should run the future
+/// * `fut` - The future to drive to completion
+///
+/// # Examples
+///
+/// ```
+/// # use std::time::Duration;
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// # pyo3::prepare_freethreaded_python();
+/// #
+/// # Python::with_gil(|py| -> PyResult<()> {
+/// # let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+/// pyo3_asyncio::smol::run_until_complete(event_loop, async move {
+///     smol::Timer::after(Duration::from_secs(1)).await;
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }).unwrap();
+/// ```
+pub fn run_until_complete<F, T>(event_loop: &PyAny, fut: F) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    generic::run_until_complete::<SmolRuntime, _, T>(event_loop, fut)
+}
+
+/// Run the event loop until the given Future completes
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The future to drive to completion
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// fn main() {
+///     pyo3::prepare_freethreaded_python();
+///
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::smol::run(py, async move {
+///             smol::Timer::after(Duration::from_secs(1)).await;
+///             Ok(())
+///         })
+///         .map_err(|e| {
+///             e.print_and_set_sys_last_vars(py);
+///         })
+///         .unwrap();
+///     })
+/// }
+/// ```
+pub fn run<F, T>(py: Python, fut: F) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    generic::run::<SmolRuntime, F, T>(py, fut)
+}
+
+/// Drive a !Send future to completion on the calling thread
+///
+/// The future is run with [`scope_local`] on a thread-local `smol::LocalExecutor`, so it never
+/// leaves the calling thread, and neither do the futures it spawns with [`local_future_into_py`].
+/// Those futures only make progress while `run_local` is running.
+///
+/// The Python event loop in `locals` keeps running on its own thread. Don't call this from the
+/// thread that runs that event loop (i.e. from Python code), since blocking it would prevent any
+/// Python awaitable from completing.
+///
+/// # Arguments
+/// * `locals` - The task locals for the given future
+/// * `fut` - The !Send future to drive to completion
+pub fn run_local<F, T>(locals: TaskLocals, fut: F) -> T
+where
+    F: Future<Output = T> + 'static,
+{
+    LOCAL_EXECUTOR.with(|executor| smol::block_on(executor.run(scope_local(locals, fut))))
+}
+
+/// Convert a Rust Future into a Python awaitable
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: &'p PyAny) -> PyResult<&'p PyAny> {
+///     let secs = secs.extract()?;
+///     pyo3_asyncio::smol::future_into_py_with_locals(
+///         py,
+///         pyo3_asyncio::smol::get_current_locals(py)?,
+///         async move {
+///             smol::Timer::after(Duration::from_secs(secs)).await;
+///             Python::with_gil(|py| Ok(py.None()))
+///         }
+///     )
+/// }
+/// ```
+pub fn future_into_py_with_locals<F, T>(py: Python, locals: TaskLocals, fut: F) -> PyResult<&PyAny>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::future_into_py_with_locals::<SmolRuntime, F, T>(py, locals, fut)
+}
+
+/// Convert a Rust Future into a Python awaitable
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: &'p PyAny) -> PyResult<&'p PyAny> {
+///     let secs = secs.extract()?;
+///     pyo3_asyncio::smol::future_into_py(py, async move {
+///         smol::Timer::after(Duration::from_secs(secs)).await;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub fn future_into_py<F, T>(py: Python, fut: F) -> PyResult<&PyAny>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::future_into_py::<SmolRuntime, _, T>(py, fut)
+}
+
+/// Convert a `!Send` Rust Future into a Python awaitable
+///
+/// The future is spawned on the calling thread's `smol::LocalExecutor`, so it only makes progress
+/// while that thread is inside [`run_local`].
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `fut` - The Rust future to be converted
+pub fn local_future_into_py_with_locals<F, T>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<&PyAny>
+where
+    F: Future<Output = PyResult<T>> + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::local_future_into_py_with_locals::<SmolRuntime, F, T>(py, locals, fut)
+}
+
+/// Convert a `!Send` Rust Future into a Python awaitable
+///
+/// The future is spawned on the calling thread's `smol::LocalExecutor`, so it only makes progress
+/// while that thread is inside [`run_local`].
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+pub fn local_future_into_py<F, T>(py: Python, fut: F) -> PyResult<&PyAny>
+where
+    F: Future<Output = PyResult<T>> + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::local_future_into_py::<SmolRuntime, F, T>(py, fut)
+}
+
+/// Convert a Python `awaitable` into a Rust Future
+///
+/// This function converts the `awaitable` into a Python Task using `run_coroutine_threadsafe`. A
+/// completion handler sends the result of this Task through a
+/// `futures::channel::oneshot::Sender<PyResult<PyObject>>` and the future returned by this function
+/// simply awaits the result through the `futures::channel::oneshot::Receiver<PyResult<PyObject>>`.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// async fn py_sleep(seconds: f32) -> PyResult<()> {
+///     Python::with_gil(|py| {
+///         pyo3_asyncio::smol::into_future(
+///             py.import("asyncio")?.call_method1("sleep", (seconds,))?,
+///         )
+///     })?
+///     .await?;
+///     Ok(())
+/// }
+/// ```
+pub fn into_future(awaitable: &PyAny) -> PyResult<PyFuture> {
+    generic::into_future::<SmolRuntime>(awaitable)
+}
+
+/// Helpers to synchronize with Python's `asyncio` primitives
+pub mod sync {
+    use std::future::Future;
+
+    use pyo3::prelude::*;
+
+    use super::SmolRuntime;
+    use crate::generic;
+
+    /// Wait for a Python `asyncio.Event` to be set from Rust
+    ///
+    /// This function simply forwards the event and the task locals returned by
+    /// [`get_current_locals`](`super::get_current_locals`) to
+    /// [`await_py_event_with_locals`](`crate::await_py_event_with_locals`). See
+    /// [`await_py_event_with_locals`](`crate::await_py_event_with_locals`) for more details.
+    ///
+    /// # Arguments
+    /// * `event` - The `asyncio.Event` to wait for
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pyo3::prelude::*;
+    ///
+    /// /// Wait until Python signals that the model is loaded, then tell it to start serving
+    /// async fn wait_until_loaded(loaded: PyObject, serving: PyObject) -> PyResult<()> {
+    ///     Python::with_gil(|py| pyo3_asyncio::smol::sync::await_py_event(loaded.as_ref(py)))?
+    ///         .await?;
+    ///
+    ///     Python::with_gil(|py| pyo3_asyncio::smol::sync::set_py_event_threadsafe(serving.as_ref(py)))
+    /// }
+    /// ```
+    pub fn await_py_event(event: &PyAny) -> PyResult<impl Future<Output = PyResult<()>> + Send> {
+        generic::await_py_event::<SmolRuntime>(event)
+    }
+
+    /// Set a Python `asyncio.Event` from Rust
+    ///
+    /// This function simply forwards the event and the task locals returned by
+    /// [`get_current_locals`](`super::get_current_locals`) to
+    /// [`set_py_event_threadsafe_with_locals`](`crate::set_py_event_threadsafe_with_locals`).
+    /// See [`set_py_event_threadsafe_with_locals`](`crate::set_py_event_threadsafe_with_locals`)
+    /// for more details, including how to set the event from a thread that has no task locals.
+    ///
+    /// # Arguments
+    /// * `event` - The `asyncio.Event` to set
+    pub fn set_py_event_threadsafe(event: &PyAny) -> PyResult<()> {
+        generic::set_py_event_threadsafe::<SmolRuntime>(event)
+    }
+}
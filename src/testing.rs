@@ -137,6 +137,73 @@
 //! # fn main() {}
 //! ```
 //!
+//! ### Parameterized Tests
+//!
+//! Table-driven tests can pass their cases to the `#[test]` attribute. Each case is registered as a
+//! separate test named after its index, i.e. `test_add[case0]` and `test_add[case1]` below, and the
+//! values of a case are passed to the leading parameters of the test function:
+//!
+//! ```ignore
+//! use pyo3::prelude::*;
+//!
+//! #[pyo3_asyncio::tokio::test(cases = [(1, 2, 3), (2, 2, 4)])]
+//! async fn test_add(a: i32, b: i32, sum: i32) -> PyResult<()> {
+//!     assert_eq!(a + b, sum);
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ## Event Loop Isolation
+//!
+//! By default, all of the tests share the event loop that runs the harness. Tests that register
+//! exception handlers, change the event loop policy or otherwise modify loop state can leak that
+//! state into other tests, so the harness can also give each test a fresh event loop:
+//!
+//! ```
+//! # #[cfg(all(feature = "tokio-runtime", feature = "attributes"))]
+//! #[pyo3_asyncio::tokio::main]
+//! async fn main() -> pyo3::PyResult<()> {
+//!     pyo3_asyncio::testing::set_event_loop_mode(pyo3_asyncio::testing::EventLoopMode::Isolated);
+//!     pyo3_asyncio::testing::main().await
+//! }
+//! # #[cfg(not(all(feature = "tokio-runtime", feature = "attributes")))]
+//! # fn main() {}
+//! ```
+//!
+//! ## Setup and Teardown
+//!
+//! Setup that every test shares, like starting a server or clearing a database, can be registered
+//! as async hooks that the harness awaits around each test. The teardown hook runs even if the test
+//! fails or panics:
+//!
+//! ```
+//! # #[cfg(all(feature = "tokio-runtime", feature = "attributes"))]
+//! #[pyo3_asyncio::tokio::main]
+//! async fn main() -> pyo3::PyResult<()> {
+//!     pyo3_asyncio::testing::set_setup(|| async {
+//!         println!("preparing the test database");
+//!         Ok(())
+//!     });
+//!     pyo3_asyncio::testing::set_teardown(|| async {
+//!         println!("clearing the test database");
+//!         Ok(())
+//!     });
+//!
+//!     pyo3_asyncio::testing::main().await
+//! }
+//! # #[cfg(not(all(feature = "tokio-runtime", feature = "attributes")))]
+//! # fn main() {}
+//! ```
+//!
+//! ## Output Capture
+//!
+//! Like the default test harness, the output of each test is captured and only printed if the test
+//! fails. Everything written to the process' stdout and stderr is captured, so this covers both
+//! `println!` in Rust and `print` in Python. Capturing is process-wide, so the tests are run one
+//! at a time while it is enabled. Pass `--nocapture` to the test binary
+//! (`cargo test -- --nocapture`) to print the output as it is written and run the tests
+//! concurrently instead.
+//!
 //! ## Lib Tests
 //!
 //! Unfortunately, as we mentioned at the beginning, these utilities will only run in integration
@@ -179,23 +246,351 @@
 //! # fn main() {}
 //! ```
 
-use std::{future::Future, pin::Pin};
+use std::{
+    future::Future,
+    io::Write,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
 
 use clap::{App, Arg};
-use futures::stream::{self, StreamExt};
-use pyo3::prelude::*;
+use futures::{
+    channel::oneshot,
+    future::FutureExt,
+    stream::{self, StreamExt},
+};
+use once_cell::sync::Lazy;
+use pyo3::{exceptions::PyException, prelude::*};
+
+use crate::TaskLocals;
+
+static DEFAULT_TIMEOUT: Lazy<Mutex<Option<Duration>>> = Lazy::new(|| Mutex::new(None));
+
+/// Set the timeout for tests that do not specify their own
+///
+/// Runtime `#[test]` attributes that support a `timeout_ms` argument fall back on this timeout when
+/// the argument is omitted. Tests that exceed it fail with a `TimeoutError` and the harness moves on
+/// to the next test. By default, there is no timeout.
+///
+/// This should be called before the tests are run, i.e. before [`main`] or [`test_harness`].
+pub fn set_default_timeout(timeout: Option<Duration>) {
+    *DEFAULT_TIMEOUT.lock().unwrap() = timeout;
+}
+
+/// Get the timeout for tests that do not specify their own
+pub fn default_timeout() -> Option<Duration> {
+    *DEFAULT_TIMEOUT.lock().unwrap()
+}
+
+/// Drop the Python objects that PyO3 Asyncio caches globally
+///
+/// PyO3 Asyncio lazily imports `asyncio`, `contextvars` and `inspect` and caches some of their
+/// functions for the lifetime of the process. This clears those caches, so they are initialized
+/// again on next use, i.e. to test the initialization and shutdown logic of a crate against several
+/// interpreter and event loop lifecycles.
+///
+/// Only caches that can safely be rebuilt are cleared. Configuration like the scheduler from
+/// [`set_scheduler`](crate::set_scheduler), the registered error mappers and the tokio runtime are
+/// kept, since they can't be initialized again without the user's input.
+///
+/// The caches themselves can't be configured. They only hold objects looked up from the Python
+/// interpreter, so there is nothing to provide up front, and clearing them is all a test needs to
+/// start from a fresh interpreter and event loop.
+///
+/// This should not be called while futures are being converted on other threads.
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+///     pyo3_asyncio::TaskLocals::new(event_loop).copy_context(py)?;
+///     event_loop.call_method0("close")?;
+///     Ok(())
+/// })
+/// .unwrap();
+///
+/// // the next scenario starts without any cached Python objects
+/// pyo3_asyncio::testing::reset_globals();
+/// ```
+pub fn reset_globals() {
+    Python::with_gil(crate::reset_caches);
+}
+
+/// How the test harness provides Python event loops to the tests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventLoopMode {
+    /// Every test runs on the event loop that is running the harness (the default)
+    Shared,
+    /// Every test gets a fresh event loop, running on its own thread, that is closed once the test
+    /// completes. The asyncio event loop policy is restored afterwards as well, so exception
+    /// handlers, policies and other loop state can't leak from one test into the next.
+    Isolated,
+}
+
+static EVENT_LOOP_MODE: Lazy<Mutex<EventLoopMode>> =
+    Lazy::new(|| Mutex::new(EventLoopMode::Shared));
+
+/// Set how the test harness provides Python event loops to the tests
+///
+/// This should be called before the tests are run, i.e. before [`main`] or [`test_harness`].
+pub fn set_event_loop_mode(mode: EventLoopMode) {
+    *EVENT_LOOP_MODE.lock().unwrap() = mode;
+}
+
+/// Get how the test harness provides Python event loops to the tests
+pub fn event_loop_mode() -> EventLoopMode {
+    *EVENT_LOOP_MODE.lock().unwrap()
+}
+
+/// The task created by a test
+pub type TestTask = Pin<Box<dyn Future<Output = PyResult<()>> + Send>>;
+
+type HookFn = dyn Fn() -> TestTask + Send + Sync;
+
+static SETUP: Lazy<Mutex<Option<Arc<HookFn>>>> = Lazy::new(|| Mutex::new(None));
+static TEARDOWN: Lazy<Mutex<Option<Arc<HookFn>>>> = Lazy::new(|| Mutex::new(None));
+
+/// Set an async hook that the harness awaits before each test
+///
+/// The hook runs on the event loop that is running the harness, before the test's fixtures are
+/// provided. If it fails, the test fails with its error without being run, and the teardown hook is
+/// not run either. Setting a new hook replaces the previous one.
+///
+/// This should be called before the tests are run, i.e. before [`main`] or [`test_harness`].
+pub fn set_setup<F, Fut>(setup: F)
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = PyResult<()>> + Send + 'static,
+{
+    *SETUP.lock().unwrap() = Some(Arc::new(move || -> TestTask { Box::pin(setup()) }));
+}
+
+/// Set an async hook that the harness awaits after each test
+///
+/// The hook runs once the test has completed, whether it passed, failed or panicked. A failing
+/// teardown fails a test that passed. Setting a new hook replaces the previous one.
+///
+/// This should be called before the tests are run, i.e. before [`main`] or [`test_harness`].
+pub fn set_teardown<F, Fut>(teardown: F)
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = PyResult<()>> + Send + 'static,
+{
+    *TEARDOWN.lock().unwrap() = Some(Arc::new(move || -> TestTask { Box::pin(teardown()) }));
+}
+
+/// Run a test between the setup and teardown hooks, returning the payload if the test panicked
+async fn run_with_hooks(test: &Test) -> thread::Result<PyResult<()>> {
+    let setup = SETUP.lock().unwrap().clone();
+    let teardown = TEARDOWN.lock().unwrap().clone();
+
+    if let Some(setup) = setup {
+        if let Err(e) = setup().await {
+            return Ok(Err(e));
+        }
+    }
+
+    let result = AssertUnwindSafe(test.task()).catch_unwind().await;
+
+    let torn_down = match teardown {
+        Some(teardown) => teardown().await,
+        None => Ok(()),
+    };
+
+    result.map(|result| result.and(torn_down))
+}
+
+/// A value that the runtime `#[test]` attributes can inject into a test function's parameters
+///
+/// Each parameter of a test function is provided right before the test runs, within the test's
+/// task locals, so `locals` refers to the event loop the test runs on (which is a fresh one with
+/// [`EventLoopMode::Isolated`]). A test fails with the error returned by `provide` without being
+/// run.
+///
+/// Fixtures are implemented for [`TaskLocals`] and for `PyObject`, which is the event loop.
+/// Implement it for your own types to share setup code between tests.
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+/// use pyo3_asyncio::{testing::Fixture, TaskLocals};
+///
+/// /// A scratch module that every test gets a fresh copy of
+/// struct TestModule(PyObject);
+///
+/// impl Fixture for TestModule {
+///     fn provide(py: Python, _locals: &TaskLocals) -> PyResult<Self> {
+///         Ok(TestModule(
+///             PyModule::from_code(py, "answer = 42", "fixture.py", "fixture")?.into(),
+///         ))
+///     }
+/// }
+///
+/// # #[cfg(all(feature = "tokio-runtime", feature = "attributes"))]
+/// #[pyo3_asyncio::tokio::test]
+/// async fn test_with_fixtures(locals: TaskLocals, module: TestModule) -> PyResult<()> {
+///     Python::with_gil(|py| {
+///         assert_eq!(module.0.getattr(py, "answer")?.extract::<i32>(py)?, 42);
+///         assert!(locals.event_loop(py).call_method0("is_running")?.is_true()?);
+///         Ok(())
+///     })
+/// }
+/// # fn main() {}
+/// ```
+pub trait Fixture: Sized + Send {
+    /// Create the value for a test that runs on the event loop in `locals`
+    fn provide(py: Python, locals: &TaskLocals) -> PyResult<Self>;
+}
+
+impl Fixture for TaskLocals {
+    fn provide(_py: Python, locals: &TaskLocals) -> PyResult<Self> {
+        Ok(locals.clone())
+    }
+}
+
+impl Fixture for PyObject {
+    fn provide(py: Python, locals: &TaskLocals) -> PyResult<Self> {
+        Ok(locals.event_loop(py).into())
+    }
+}
+
+/// Create a test's task according to the current [`EventLoopMode`]
+///
+/// This is used by the runtime `#[test]` attributes. With [`EventLoopMode::Shared`], `task` is
+/// simply called. With [`EventLoopMode::Isolated`], a new event loop is started on a separate
+/// thread and `task` is called and awaited within `scope`, which should set the runtime's task
+/// locals to the new loop (i.e. `pyo3_asyncio::tokio::scope`). Once the task completes, the loop is
+/// stopped and closed and the previous event loop policy is restored.
+///
+/// # Arguments
+/// * `task` - Creates the test's task
+/// * `scope` - Runs a future with the given task locals
+pub fn isolate<F, S, SFut>(task: F, scope: S) -> TestTask
+where
+    F: FnOnce() -> TestTask + Send + 'static,
+    S: FnOnce(TaskLocals, TestTask) -> SFut + Send + 'static,
+    SFut: Future<Output = PyResult<()>> + Send + 'static,
+{
+    match event_loop_mode() {
+        EventLoopMode::Shared => task(),
+        EventLoopMode::Isolated => Box::pin(async move {
+            let policy = Python::with_gil(|py| -> PyResult<PyObject> {
+                Ok(py
+                    .import("asyncio")?
+                    .call_method0("get_event_loop_policy")?
+                    .into())
+            })?;
+
+            let isolated = IsolatedLoop::start().await?;
+            let locals = Python::with_gil(|py| {
+                TaskLocals::new(isolated.event_loop.as_ref(py)).copy_context(py)
+            })?;
+
+            let result = scope(locals, Box::pin(async move { task().await })).await;
+
+            isolated.stop().await?;
+            Python::with_gil(|py| -> PyResult<()> {
+                py.import("asyncio")?
+                    .call_method1("set_event_loop_policy", (policy,))?;
+                Ok(())
+            })?;
+
+            result
+        }),
+    }
+}
+
+/// A Python event loop running forever on its own thread
+struct IsolatedLoop {
+    event_loop: PyObject,
+    stopped: oneshot::Receiver<PyResult<()>>,
+}
+
+impl IsolatedLoop {
+    async fn start() -> PyResult<Self> {
+        let (loop_tx, loop_rx) = oneshot::channel();
+        let (stopped_tx, stopped_rx) = oneshot::channel();
+
+        thread::spawn(move || {
+            let result = Python::with_gil(|py| -> PyResult<()> {
+                let asyncio = py.import("asyncio")?;
+                let event_loop = asyncio.call_method0("new_event_loop")?;
+                asyncio.call_method1("set_event_loop", (event_loop,))?;
+
+                let _ = loop_tx.send(PyObject::from(event_loop));
+
+                let result = event_loop.call_method0("run_forever").and_then(|_| {
+                    event_loop.call_method1(
+                        "run_until_complete",
+                        (event_loop.call_method0("shutdown_asyncgens")?,),
+                    )
+                });
+
+                event_loop.call_method0("close")?;
+                asyncio.call_method1("set_event_loop", (py.None(),))?;
+
+                result.map(|_| ())
+            });
+
+            let _ = stopped_tx.send(result);
+        });
+
+        match loop_rx.await {
+            Ok(event_loop) => Ok(Self {
+                event_loop,
+                stopped: stopped_rx,
+            }),
+            // the thread failed before the loop was started
+            Err(_) => match stopped_rx.await {
+                Ok(Err(e)) => Err(e),
+                _ => Err(PyException::new_err(
+                    "failed to start an isolated event loop",
+                )),
+            },
+        }
+    }
+
+    async fn stop(self) -> PyResult<()> {
+        Python::with_gil(|py| -> PyResult<()> {
+            let event_loop = self.event_loop.as_ref(py);
+            event_loop.call_method1("call_soon_threadsafe", (event_loop.getattr("stop")?,))?;
+            Ok(())
+        })?;
+
+        self.stopped
+            .await
+            .unwrap_or_else(|_| Err(PyException::new_err("isolated event loop thread panicked")))
+    }
+}
 
 /// Args that should be provided to the test program
 ///
 /// These args are meant to mirror the default test harness's args.
-/// > Currently only `--filter` is supported.
+/// > Currently only filtering, `--nocapture` and `--list` are supported.
 pub struct Args {
     filter: Option<String>,
+    nocapture: bool,
+    list: bool,
 }
 
 impl Default for Args {
     fn default() -> Self {
-        Self { filter: None }
+        Self {
+            filter: None,
+            nocapture: false,
+            list: false,
+        }
     }
 }
 
@@ -205,8 +600,8 @@ impl Default for Args {
 /// control over how our tests are run.
 ///
 /// Ideally, we should mirror the default test harness's arguments exactly, but
-/// for the sake of simplicity, only filtering is supported for now. If you want
-/// more features, feel free to request them
+/// for the sake of simplicity, only filtering, `--nocapture` and `--list` are supported for now.
+/// If you want more features, feel free to request them
 /// [here](https://github.com/awestlake87/pyo3-asyncio/issues).
 ///
 /// # Examples
@@ -225,8 +620,10 @@ impl Default for Args {
 /// test_example [TESTNAME]
 ///
 /// FLAGS:
-/// -h, --help       Prints help information
-/// -V, --version    Prints version information
+/// -h, --help         Prints help information
+///     --list         List the tests that would be run instead of running them
+///     --nocapture    Don't capture the output of the tests, and run them concurrently
+/// -V, --version      Prints version information
 ///
 /// ARGS:
 /// <TESTNAME>    If specified, only run tests containing this string in their names
@@ -237,14 +634,26 @@ pub fn parse_args() -> Args {
             Arg::with_name("TESTNAME")
                 .help("If specified, only run tests containing this string in their names"),
         )
+        .arg(
+            Arg::with_name("nocapture")
+                .long("nocapture")
+                .help("Don't capture the output of the tests, and run them concurrently"),
+        )
+        .arg(
+            Arg::with_name("list")
+                .long("list")
+                .help("List the tests that would be run instead of running them"),
+        )
         .get_matches();
 
     Args {
         filter: matches.value_of("TESTNAME").map(|name| name.to_string()),
+        nocapture: matches.is_present("nocapture"),
+        list: matches.is_present("list"),
     }
 }
 
-type TestFn = dyn Fn() -> Pin<Box<dyn Future<Output = PyResult<()>> + Send>> + Send + Sync;
+type TestFn = dyn Fn() -> TestTask + Send + Sync;
 
 /// The structure used by the `#[test]` macros to provide a test to the `pyo3-asyncio` test harness.
 #[derive(Clone)]
@@ -266,29 +675,160 @@ impl Test {
 
 inventory::collect!(Test);
 
+const CAPTURE_CODE: &str = r#"
+import os
+import sys
+import tempfile
+
+def _flush():
+    for stream in (sys.stdout, sys.stderr):
+        if stream is not None:
+            stream.flush()
+
+class Capture:
+    def __init__(self):
+        _flush()
+        self._file = tempfile.TemporaryFile()
+        self._saved = []
+        for fd in (1, 2):
+            self._saved.append((fd, os.dup(fd)))
+            os.dup2(self._file.fileno(), fd)
+
+    def finish(self):
+        _flush()
+        for fd, saved in self._saved:
+            os.dup2(saved, fd)
+            os.close(saved)
+
+        self._file.seek(0)
+        output = self._file.read().decode(errors="replace")
+        self._file.close()
+        return output
+"#;
+
+/// Redirects the stdout and stderr file descriptors of the process while a test runs
+struct Capture(PyObject);
+
+impl Capture {
+    fn start() -> PyResult<Self> {
+        let _ = std::io::stdout().flush();
+
+        Python::with_gil(|py| {
+            let capture = PyModule::from_code(
+                py,
+                CAPTURE_CODE,
+                "pyo3_asyncio_capture.py",
+                "pyo3_asyncio_capture",
+            )?
+            .call_method0("Capture")?;
+
+            Ok(Self(capture.into()))
+        })
+    }
+
+    /// Restore stdout and stderr, returning everything that was written to them
+    fn finish(self) -> PyResult<String> {
+        let _ = std::io::stdout().flush();
+
+        Python::with_gil(|py| self.0.call_method0(py, "finish")?.extract(py))
+    }
+}
+
+/// Run a test, returning its result along with its output if it was captured
+async fn run_test(test: &Test, capture: bool) -> (PyResult<()>, Option<String>) {
+    if !capture {
+        match run_with_hooks(test).await {
+            Ok(result) => return (result, None),
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    let capture = match Capture::start() {
+        Ok(capture) => capture,
+        Err(e) => return (Err(e), None),
+    };
+
+    let result = match run_with_hooks(test).await {
+        Ok(result) => result,
+        Err(payload) => {
+            // restore stdout and stderr so the panic and its output can be seen
+            if let Ok(output) = capture.finish() {
+                print!("{}", output);
+            }
+            panic::resume_unwind(payload)
+        }
+    };
+
+    match capture.finish() {
+        Ok(output) => (result, Some(output)),
+        Err(e) => (result.and(Err(e)), None),
+    }
+}
+
+/// Whether the test name matches the filter from the `Args`, if any
+fn is_selected(test: &Test, args: &Args) -> bool {
+    match &args.filter {
+        Some(filter) => test.name.contains(filter),
+        None => true,
+    }
+}
+
 /// Run a sequence of tests while applying any necessary filtering from the `Args`
+///
+/// A failing test does not stop the harness. Its error is printed and the remaining tests still
+/// run, then an error is returned if any of the tests failed.
+///
+/// Unless `--nocapture` was passed, the output of each test is captured and only printed if the
+/// test fails, and the tests are run one at a time.
+///
+/// With `--list`, the names of the tests that would be run are printed in the same format as the
+/// default test harness and none of them are run.
 pub async fn test_harness(tests: Vec<Test>, args: Args) -> PyResult<()> {
+    if args.list {
+        let mut count = 0;
+
+        for test in tests.iter().filter(|test| is_selected(test, &args)) {
+            println!("{}: test", test.name);
+            count += 1;
+        }
+
+        println!("\n{} tests, 0 benchmarks", count);
+        return Ok(());
+    }
+
+    let failures = AtomicUsize::new(0);
+    let capture = !args.nocapture;
+
     stream::iter(tests)
-        .for_each_concurrent(Some(4), |test| {
-            let mut ignore = false;
+        .for_each_concurrent(Some(if capture { 1 } else { 4 }), |test| {
+            let ignore = !is_selected(&test, &args);
 
-            if let Some(filter) = args.filter.as_ref() {
-                if !test.name.contains(filter) {
-                    ignore = true;
-                }
-            }
+            let failures = &failures;
 
             async move {
                 if !ignore {
-                    test.task().await.unwrap();
+                    match run_test(&test, capture).await {
+                        (Ok(()), _) => println!("test {} ... ok", test.name),
+                        (Err(e), output) => {
+                            println!("test {} ... FAILED", test.name);
 
-                    println!("test {} ... ok", test.name);
+                            if let Some(output) = output.filter(|output| !output.is_empty()) {
+                                println!("---- {} output ----\n{}", test.name, output);
+                            }
+
+                            Python::with_gil(|py| e.print_and_set_sys_last_vars(py));
+                            failures.fetch_add(1, Ordering::SeqCst);
+                        }
+                    }
                 }
             }
         })
         .await;
 
-    Ok(())
+    match failures.into_inner() {
+        0 => Ok(()),
+        n => Err(PyException::new_err(format!("{} test(s) failed", n))),
+    }
 }
 
 /// Parses test arguments and passes the tests to the `pyo3-asyncio` test harness
@@ -313,11 +853,48 @@ pub async fn test_harness(tests: Vec<Test>, args: Args) -> PyResult<()> {
 pub async fn main() -> PyResult<()> {
     let args = parse_args();
 
-    test_harness(
-        inventory::iter::<Test>().map(|test| test.clone()).collect(),
-        args,
-    )
-    .await
+    // inventory yields the tests in no particular order, so sort them to keep the cases of a
+    // parameterized test together
+    let mut tests: Vec<Test> = inventory::iter::<Test>().map(|test| test.clone()).collect();
+    tests.sort_by(|a, b| a.name.cmp(&b.name));
+
+    test_harness(tests, args).await
+}
+
+#[cfg(test)]
+mod list_tests {
+    use super::*;
+
+    fn test_not_run() -> TestTask {
+        panic!("--list should not run the tests")
+    }
+
+    fn tests() -> Vec<Test> {
+        ["suite::test_foo", "suite::test_bar"]
+            .iter()
+            .map(|name| Test {
+                name: name.to_string(),
+                test_fn: &test_not_run,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_list_filters_by_name() {
+        let args = Args {
+            filter: Some("foo".into()),
+            list: true,
+            ..Args::default()
+        };
+        let selected: Vec<_> = tests()
+            .into_iter()
+            .filter(|test| is_selected(test, &args))
+            .map(|test| test.name)
+            .collect();
+        assert_eq!(selected, vec!["suite::test_foo".to_string()]);
+
+        futures::executor::block_on(test_harness(tests(), args)).unwrap();
+    }
 }
 
 #[cfg(test)]